@@ -13,7 +13,7 @@ pub enum ErasureError {
 
 type Result<T> = std::result::Result<T, ErasureError>;
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct EncodedShard {
     pub index: usize,
     pub data: Vec<u8>,
@@ -35,7 +35,7 @@ impl ErasureEncoder {
     }
 
     pub fn encode(&self, data: &[u8]) -> Result<Vec<EncodedShard>> {
-        let shard_size = (data.len() + self.data_shards - 1) / self.data_shards;
+        let shard_size = data.len().div_ceil(self.data_shards);
         let mut shards = Vec::with_capacity(self.total_shards());
 
         for i in 0..self.data_shards {
@@ -55,12 +55,12 @@ impl ErasureEncoder {
 
         for i in 0..self.parity_shards {
             let mut parity = vec![0u8; shard_size];
-            for j in 0..shard_size {
+            for (j, byte) in parity.iter_mut().enumerate() {
                 let mut xor_val = 0u8;
                 for shard in &shards[..self.data_shards] {
                     xor_val ^= shard.data[j];
                 }
-                parity[j] = xor_val.wrapping_add((i + 1) as u8);
+                *byte = xor_val.wrapping_add((i + 1) as u8);
             }
             shards.push(EncodedShard {
                 index: self.data_shards + i,
@@ -1,8 +1,9 @@
 pub mod storage;
 pub mod erasure;
 pub mod sampling;
+pub mod transfer;
 
 pub use storage::{ContentAddressedStorage, ContentId};
 pub use erasure::{ErasureEncoder, ErasureDecoder};
 pub use sampling::{AvailabilitySampler, SampleProof};
-
+pub use transfer::{ShardFrame, ShardManifest, ShardReceiver, ShardSender};
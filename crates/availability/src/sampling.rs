@@ -1,5 +1,6 @@
 use crate::erasure::EncodedShard;
 use crate::storage::ContentId;
+use archimedes_core::MerkleVerifyError;
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use thiserror::Error;
@@ -10,8 +11,8 @@ pub enum SamplingError {
     VerificationFailed,
     #[error("Insufficient samples: have {have}, need {need}")]
     InsufficientSamples { have: usize, need: usize },
-    #[error("Invalid merkle proof")]
-    InvalidMerkleProof,
+    #[error("Invalid merkle proof: {0}")]
+    InvalidMerkleProof(#[from] MerkleVerifyError),
 }
 
 type Result<T> = std::result::Result<T, SamplingError>;
@@ -48,7 +49,7 @@ impl AvailabilitySampler {
             }
             
             let mut next_hasher = Sha256::new();
-            next_hasher.update(&current);
+            next_hasher.update(current);
             current = next_hasher.finalize();
         }
 
@@ -56,81 +57,105 @@ impl AvailabilitySampler {
     }
 
     pub fn create_proof(shard: &EncodedShard, all_shards: &[EncodedShard]) -> SampleProof {
-        let mut hasher = Sha256::new();
-        hasher.update(&shard.data);
-        let hash = hasher.finalize();
-        let mut shard_hash = [0u8; 32];
-        shard_hash.copy_from_slice(&hash);
-
-        let merkle_path = Self::build_merkle_path(shard.index, all_shards);
-
         SampleProof {
             shard_index: shard.index,
-            shard_hash,
-            merkle_path,
+            shard_hash: leaf_hash(&shard.data),
+            merkle_path: Self::build_merkle_path(shard.index, all_shards),
         }
     }
 
-    fn build_merkle_path(index: usize, shards: &[EncodedShard]) -> Vec<[u8; 32]> {
-        let hashes: Vec<[u8; 32]> = shards.iter().map(|s| {
-            let mut hasher = Sha256::new();
-            hasher.update(&s.data);
-            let result = hasher.finalize();
-            let mut hash = [0u8; 32];
-            hash.copy_from_slice(&result);
-            hash
-        }).collect();
+    /// Root of the shard tree that `verify_proof` checks against.
+    pub fn shard_root(shards: &[EncodedShard]) -> ContentId {
+        let mut level: Vec<[u8; 32]> = shards.iter().map(|s| leaf_hash(&s.data)).collect();
+        while level.len() > 1 {
+            level = next_level(&level);
+        }
+        ContentId(level.first().copied().unwrap_or(PADDING))
+    }
 
+    fn build_merkle_path(index: usize, shards: &[EncodedShard]) -> Vec<[u8; 32]> {
         let mut path = Vec::new();
-        let mut level = hashes;
+        let mut level: Vec<[u8; 32]> = shards.iter().map(|s| leaf_hash(&s.data)).collect();
         let mut idx = index;
 
         while level.len() > 1 {
-            let sibling_idx = if idx % 2 == 0 { idx + 1 } else { idx - 1 };
-            if sibling_idx < level.len() {
-                path.push(level[sibling_idx]);
-            }
-            
-            level = level.chunks(2).map(|pair| {
-                let mut hasher = Sha256::new();
-                hasher.update(&pair[0]);
-                if pair.len() > 1 {
-                    hasher.update(&pair[1]);
-                }
-                let result = hasher.finalize();
-                let mut hash = [0u8; 32];
-                hash.copy_from_slice(&result);
-                hash
-            }).collect();
-            
+            path.push(level.get(idx ^ 1).copied().unwrap_or(PADDING));
+            level = next_level(&level);
             idx /= 2;
         }
 
         path
     }
 
+    /// `verify_proof_detailed` without the reason for a failure.
     pub fn verify_proof(&self, proof: &SampleProof, root: &ContentId) -> Result<bool> {
+        match self.verify_proof_detailed(proof, root) {
+            Ok(()) => Ok(true),
+            Err(SamplingError::InvalidMerkleProof(_)) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// The path must have one sibling per level of a tree over
+    /// `total_shards` shards and must reach `root`.
+    pub fn verify_proof_detailed(&self, proof: &SampleProof, root: &ContentId) -> Result<()> {
+        let depth = self.total_shards.next_power_of_two().trailing_zeros() as usize;
+        if proof.shard_index >= self.total_shards || proof.merkle_path.len() != depth {
+            return Err(MerkleVerifyError::WrongDepth {
+                index: proof.shard_index,
+                depth: proof.merkle_path.len(),
+            }
+            .into());
+        }
+
         let mut current = proof.shard_hash;
         let mut idx = proof.shard_index;
-
         for sibling in &proof.merkle_path {
-            let mut hasher = Sha256::new();
-            if idx % 2 == 0 {
-                hasher.update(&current);
-                hasher.update(sibling);
+            current = if idx.is_multiple_of(2) {
+                node_hash(&current, sibling)
             } else {
-                hasher.update(sibling);
-                hasher.update(&current);
-            }
-            let result = hasher.finalize();
-            current.copy_from_slice(&result);
+                node_hash(sibling, &current)
+            };
             idx /= 2;
         }
 
-        Ok(current == root.0)
+        if current != root.0 {
+            return Err(MerkleVerifyError::RootMismatch {
+                computed: current,
+                expected: root.0,
+            }
+            .into());
+        }
+        Ok(())
     }
 }
 
+// Same shape as the commitment tree: prefixed leaf and internal hashes, and a
+// lone last node hashed with the padding constant rather than promoted.
+const LEAF_PREFIX: u8 = 0x00;
+const INTERNAL_PREFIX: u8 = 0x01;
+const PADDING: [u8; 32] = [0u8; 32];
+
+fn leaf_hash(data: &[u8]) -> [u8; 32] {
+    Sha256::new().chain_update([LEAF_PREFIX]).chain_update(data).finalize().into()
+}
+
+fn node_hash(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    Sha256::new()
+        .chain_update([INTERNAL_PREFIX])
+        .chain_update(left)
+        .chain_update(right)
+        .finalize()
+        .into()
+}
+
+fn next_level(level: &[[u8; 32]]) -> Vec<[u8; 32]> {
+    level
+        .chunks(2)
+        .map(|pair| node_hash(&pair[0], pair.get(1).unwrap_or(&PADDING)))
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -156,5 +181,32 @@ mod tests {
         assert_eq!(proof.shard_index, 0);
         assert!(!proof.merkle_path.is_empty());
     }
+
+    #[test]
+    fn test_proofs_verify_for_every_shard() {
+        let sampler = AvailabilitySampler::new(1, 6);
+        let shards = ErasureEncoder::new(4, 2).encode(b"test data").unwrap();
+        assert_eq!(shards.len(), 6);
+        let root = AvailabilitySampler::shard_root(&shards);
+        for shard in &shards {
+            let proof = AvailabilitySampler::create_proof(shard, &shards);
+            assert_eq!(proof.merkle_path.len(), 3);
+            assert!(sampler.verify_proof(&proof, &root).unwrap());
+        }
+
+        let mut wrong = AvailabilitySampler::create_proof(&shards[5], &shards);
+        wrong.shard_index = 4;
+        assert!(!sampler.verify_proof(&wrong, &root).unwrap());
+        assert!(matches!(
+            sampler.verify_proof_detailed(&wrong, &root),
+            Err(SamplingError::InvalidMerkleProof(MerkleVerifyError::RootMismatch { .. }))
+        ));
+        wrong.merkle_path.pop();
+        assert!(matches!(
+            sampler.verify_proof_detailed(&wrong, &root),
+            Err(SamplingError::InvalidMerkleProof(MerkleVerifyError::WrongDepth { index: 4, depth: 2 }))
+        ));
+        assert_ne!(AvailabilitySampler::shard_root(&shards[..5]), root);
+    }
 }
 
@@ -1,3 +1,4 @@
+use archimedes_core::{ResourceId, RetentionRegistry};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use std::collections::HashMap;
@@ -11,6 +12,8 @@ pub enum StorageError {
     InvalidHash,
     #[error("Storage full")]
     StorageFull,
+    #[error("Content retained by an open dispute: {0}")]
+    Retained(String),
 }
 
 type Result<T> = std::result::Result<T, StorageError>;
@@ -31,6 +34,10 @@ impl ContentId {
     pub fn to_hex(&self) -> String {
         self.0.iter().map(|b| format!("{:02x}", b)).collect()
     }
+
+    pub fn resource_id(&self) -> ResourceId {
+        ResourceId::Content(self.0)
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -91,7 +98,12 @@ impl ContentAddressedStorage {
         self.store.contains_key(id)
     }
 
-    pub fn remove(&mut self, id: &ContentId) -> Result<()> {
+    /// Drops one reference, freeing the content at zero. Fails while a
+    /// dispute lease retains it.
+    pub fn remove(&mut self, id: &ContentId, registry: &RetentionRegistry) -> Result<()> {
+        if registry.is_retained(&id.resource_id()) {
+            return Err(StorageError::Retained(id.to_hex()));
+        }
         if let Some(content) = self.store.get_mut(id) {
             content.reference_count = content.reference_count.saturating_sub(1);
             if content.reference_count == 0 {
@@ -103,6 +115,18 @@ impl ContentAddressedStorage {
         Ok(())
     }
 
+    /// Drops content outright regardless of reference count, unless a dispute
+    /// lease retains it.
+    pub fn purge(&mut self, id: &ContentId, registry: &RetentionRegistry) -> Result<()> {
+        if registry.is_retained(&id.resource_id()) {
+            return Err(StorageError::Retained(id.to_hex()));
+        }
+        if let Some(content) = self.store.remove(id) {
+            self.current_size -= content.data.len();
+        }
+        Ok(())
+    }
+
     pub fn size(&self) -> usize {
         self.current_size
     }
@@ -142,5 +166,34 @@ mod tests {
         let result = storage.store(data, 100);
         assert!(matches!(result, Err(StorageError::StorageFull)));
     }
-}
 
+    #[test]
+    fn test_purge_respects_dispute_lease() {
+        let mut storage = ContentAddressedStorage::new(1024);
+        let mut registry = RetentionRegistry::new(1_000);
+        let id = storage.store(b"disputed blob".to_vec(), 100).unwrap();
+        registry.acquire([7u8; 32], &[id.resource_id()], 100).unwrap();
+
+        assert!(matches!(storage.purge(&id, &registry), Err(StorageError::Retained(_))));
+        assert!(storage.exists(&id));
+        registry.release(&[7u8; 32]);
+        storage.purge(&id, &registry).unwrap();
+        assert!(!storage.exists(&id));
+        assert_eq!(storage.size(), 0);
+    }
+
+    #[test]
+    fn test_remove_respects_dispute_lease() {
+        let mut storage = ContentAddressedStorage::new(1024);
+        let mut registry = RetentionRegistry::new(1_000);
+        let id = storage.store(b"disputed blob".to_vec(), 100).unwrap();
+        registry.acquire([7u8; 32], &[id.resource_id()], 100).unwrap();
+
+        assert!(matches!(storage.remove(&id, &registry), Err(StorageError::Retained(_))));
+        assert!(storage.exists(&id));
+        registry.release(&[7u8; 32]);
+        storage.remove(&id, &registry).unwrap();
+        assert!(!storage.exists(&id));
+        assert_eq!(storage.size(), 0);
+    }
+}
@@ -0,0 +1,286 @@
+use crate::erasure::EncodedShard;
+use crate::storage::ContentId;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::ops::Range;
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum TransferError {
+    #[error("Chunk size must be non-zero")]
+    InvalidChunkSize,
+    #[error("Frame belongs to a different shard")]
+    WrongShard,
+    #[error("Frame offset {0} is not a chunk boundary inside the shard")]
+    InvalidOffset(usize),
+    #[error("Chunk hash mismatch at offset {0}")]
+    ChunkHashMismatch(usize),
+    #[error("Shard incomplete: {0} bytes missing")]
+    Incomplete(usize),
+    #[error("Reassembled shard does not match manifest hash")]
+    ShardHashMismatch,
+}
+
+type Result<T> = std::result::Result<T, TransferError>;
+
+fn sha256(data: &[u8]) -> [u8; 32] {
+    Sha256::digest(data).into()
+}
+
+/// What the receiver must know up front to validate a shard transfer.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ShardManifest {
+    pub blob_id: ContentId,
+    pub shard_index: usize,
+    pub is_parity: bool,
+    pub shard_len: usize,
+    pub shard_hash: [u8; 32],
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ShardFrame {
+    pub blob_id: ContentId,
+    pub shard_index: usize,
+    pub offset: usize,
+    pub chunk_hash: [u8; 32],
+    pub is_final: bool,
+    pub data: Vec<u8>,
+}
+
+pub struct ShardSender;
+
+impl ShardSender {
+    pub fn manifest(blob_id: &ContentId, shard: &EncodedShard) -> ShardManifest {
+        ShardManifest {
+            blob_id: blob_id.clone(),
+            shard_index: shard.index,
+            is_parity: shard.is_parity,
+            shard_len: shard.data.len(),
+            shard_hash: sha256(&shard.data),
+        }
+    }
+
+    pub fn frames<'a>(
+        blob_id: &'a ContentId,
+        shard: &'a EncodedShard,
+        chunk_size: usize,
+    ) -> Result<impl Iterator<Item = ShardFrame> + 'a> {
+        Self::frames_in(blob_id, shard, chunk_size, 0..shard.data.len())
+    }
+
+    /// Frames for the chunks overlapping `range`, for answering a resume
+    /// request built from `ShardReceiver::missing_ranges`.
+    pub fn frames_in<'a>(
+        blob_id: &'a ContentId,
+        shard: &'a EncodedShard,
+        chunk_size: usize,
+        range: Range<usize>,
+    ) -> Result<impl Iterator<Item = ShardFrame> + 'a> {
+        if chunk_size == 0 {
+            return Err(TransferError::InvalidChunkSize);
+        }
+        let len = shard.data.len();
+        let last_offset = len.saturating_sub(1) / chunk_size * chunk_size;
+        let first = range.start / chunk_size * chunk_size;
+        let end = range.end.min(len).max(first + 1).min(last_offset + 1);
+        Ok((first..end).step_by(chunk_size).map(move |offset| {
+            let data = shard.data[offset..(offset + chunk_size).min(len)].to_vec();
+            ShardFrame {
+                blob_id: blob_id.clone(),
+                shard_index: shard.index,
+                offset,
+                chunk_hash: sha256(&data),
+                is_final: offset == last_offset,
+                data,
+            }
+        }))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FrameStatus {
+    Accepted,
+    Duplicate,
+}
+
+/// Reassembles one shard from frames in any order, tolerating duplicates and
+/// dropping chunks whose hash does not verify so they show up as missing.
+pub struct ShardReceiver {
+    manifest: ShardManifest,
+    chunk_size: usize,
+    chunks: BTreeMap<usize, Vec<u8>>,
+}
+
+impl ShardReceiver {
+    pub fn new(manifest: ShardManifest, chunk_size: usize) -> Result<Self> {
+        if chunk_size == 0 {
+            return Err(TransferError::InvalidChunkSize);
+        }
+        Ok(Self {
+            manifest,
+            chunk_size,
+            chunks: BTreeMap::new(),
+        })
+    }
+
+    pub fn manifest(&self) -> &ShardManifest {
+        &self.manifest
+    }
+
+    fn chunk_len(&self, offset: usize) -> usize {
+        self.chunk_size.min(self.manifest.shard_len - offset)
+    }
+
+    pub fn accept(&mut self, frame: ShardFrame) -> Result<FrameStatus> {
+        if frame.blob_id != self.manifest.blob_id || frame.shard_index != self.manifest.shard_index {
+            return Err(TransferError::WrongShard);
+        }
+        let in_bounds = frame.offset < self.manifest.shard_len || (frame.offset == 0 && self.manifest.shard_len == 0);
+        if !in_bounds || !frame.offset.is_multiple_of(self.chunk_size) {
+            return Err(TransferError::InvalidOffset(frame.offset));
+        }
+        if self.chunks.contains_key(&frame.offset) {
+            return Ok(FrameStatus::Duplicate);
+        }
+        if frame.data.len() != self.chunk_len(frame.offset) || sha256(&frame.data) != frame.chunk_hash {
+            return Err(TransferError::ChunkHashMismatch(frame.offset));
+        }
+        self.chunks.insert(frame.offset, frame.data);
+        Ok(FrameStatus::Accepted)
+    }
+
+    /// Byte ranges still needed, with adjacent missing chunks merged.
+    pub fn missing_ranges(&self) -> Vec<Range<usize>> {
+        let mut ranges: Vec<Range<usize>> = Vec::new();
+        for offset in (0..self.manifest.shard_len).step_by(self.chunk_size) {
+            if self.chunks.contains_key(&offset) {
+                continue;
+            }
+            let end = offset + self.chunk_len(offset);
+            match ranges.last_mut() {
+                Some(last) if last.end == offset => last.end = end,
+                _ => ranges.push(offset..end),
+            }
+        }
+        if self.manifest.shard_len == 0 && self.chunks.is_empty() {
+            ranges.push(0..0);
+        }
+        ranges
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.missing_ranges().is_empty()
+    }
+
+    /// Verifies the whole-shard hash. On mismatch the receiver is reset so
+    /// every chunk is requested again.
+    pub fn finish(&mut self) -> Result<EncodedShard> {
+        let missing: usize = self.missing_ranges().iter().map(|r| r.len()).sum();
+        if !self.is_complete() {
+            return Err(TransferError::Incomplete(missing));
+        }
+        let data: Vec<u8> = self.chunks.values().flatten().copied().collect();
+        if sha256(&data) != self.manifest.shard_hash {
+            self.chunks.clear();
+            return Err(TransferError::ShardHashMismatch);
+        }
+        Ok(EncodedShard {
+            index: self.manifest.shard_index,
+            data,
+            is_parity: self.manifest.is_parity,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_shard(len: usize) -> (ContentId, EncodedShard) {
+        let data: Vec<u8> = (0..len).map(|i| (i * 7 % 251) as u8).collect();
+        (
+            ContentId::from_data(b"blob"),
+            EncodedShard {
+                index: 3,
+                data,
+                is_parity: false,
+            },
+        )
+    }
+
+    #[test]
+    fn test_out_of_order_and_duplicate_frames() {
+        let (blob_id, shard) = test_shard(1000);
+        let mut frames: Vec<ShardFrame> = ShardSender::frames(&blob_id, &shard, 64).unwrap().collect();
+        assert_eq!(frames.len(), 16);
+        assert!(frames.last().unwrap().is_final);
+        assert_eq!(frames.iter().filter(|f| f.is_final).count(), 1);
+        frames.reverse();
+
+        let mut receiver = ShardReceiver::new(ShardSender::manifest(&blob_id, &shard), 64).unwrap();
+        for frame in &frames {
+            assert_eq!(receiver.accept(frame.clone()).unwrap(), FrameStatus::Accepted);
+        }
+        assert_eq!(receiver.accept(frames[5].clone()).unwrap(), FrameStatus::Duplicate);
+        let received = receiver.finish().unwrap();
+        assert_eq!(received.data, shard.data);
+        assert_eq!(received.index, 3);
+    }
+
+    #[test]
+    fn test_corrupted_chunk_is_rerequested() {
+        let (blob_id, shard) = test_shard(500);
+        let mut receiver = ShardReceiver::new(ShardSender::manifest(&blob_id, &shard), 100).unwrap();
+        for mut frame in ShardSender::frames(&blob_id, &shard, 100).unwrap() {
+            if frame.offset == 200 {
+                frame.data[10] ^= 0xff;
+                assert_eq!(receiver.accept(frame), Err(TransferError::ChunkHashMismatch(200)));
+            } else {
+                receiver.accept(frame).unwrap();
+            }
+        }
+        assert_eq!(receiver.missing_ranges(), vec![200..300]);
+        assert!(matches!(receiver.finish(), Err(TransferError::Incomplete(100))));
+
+        for frame in ShardSender::frames_in(&blob_id, &shard, 100, 200..300).unwrap() {
+            receiver.accept(frame).unwrap();
+        }
+        assert_eq!(receiver.finish().unwrap().data, shard.data);
+    }
+
+    #[test]
+    fn test_resume_after_dropping_half() {
+        let (blob_id, shard) = test_shard(1030);
+        let mut receiver = ShardReceiver::new(ShardSender::manifest(&blob_id, &shard), 128).unwrap();
+        for frame in ShardSender::frames(&blob_id, &shard, 128).unwrap().filter(|f| f.offset / 128 % 2 == 0) {
+            receiver.accept(frame).unwrap();
+        }
+        let missing = receiver.missing_ranges();
+        assert_eq!(missing, vec![128..256, 384..512, 640..768, 896..1024]);
+
+        for range in missing {
+            for frame in ShardSender::frames_in(&blob_id, &shard, 128, range).unwrap() {
+                receiver.accept(frame).unwrap();
+            }
+        }
+        assert!(receiver.is_complete());
+        assert_eq!(receiver.finish().unwrap().data, shard.data);
+    }
+
+    #[test]
+    fn test_forged_chunks_fail_manifest_check() {
+        let (blob_id, shard) = test_shard(300);
+        let mut receiver = ShardReceiver::new(ShardSender::manifest(&blob_id, &shard), 100).unwrap();
+        let (_, mut other) = test_shard(300);
+        other.data[0] ^= 1;
+        for frame in ShardSender::frames(&blob_id, &other, 100).unwrap() {
+            receiver.accept(frame).unwrap();
+        }
+        assert_eq!(receiver.finish(), Err(TransferError::ShardHashMismatch));
+        assert_eq!(receiver.missing_ranges(), vec![0..300]);
+
+        let stray = ShardFrame { shard_index: 4, ..ShardSender::frames(&blob_id, &shard, 100).unwrap().next().unwrap() };
+        assert_eq!(receiver.accept(stray), Err(TransferError::WrongShard));
+    }
+}
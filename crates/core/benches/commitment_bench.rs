@@ -1,5 +1,5 @@
 use criterion::{black_box, criterion_group, criterion_main, Criterion, BenchmarkId};
-use archimedes_core::{CommitmentParams, Opening, AggregateCommitment, Commitment};
+use archimedes_core::{CommitmentParams, Opening, AggregateCommitment, Commitment, CommitmentChain, Randomness};
 use archimedes_core::types::ScalarField;
 use ark_ff::UniformRand;
 use ark_std::test_rng;
@@ -8,14 +8,14 @@ fn bench_commitment_setup(c: &mut Criterion) {
     c.bench_function("pedersen_setup", |b| {
         let mut rng = test_rng();
         b.iter(|| {
-            black_box(CommitmentParams::setup(&mut rng).unwrap())
+            black_box(<CommitmentParams>::setup(&mut rng).unwrap())
         })
     });
 }
 
 fn bench_commit(c: &mut Criterion) {
     let mut rng = test_rng();
-    let params = CommitmentParams::setup(&mut rng).unwrap();
+    let params: CommitmentParams = CommitmentParams::setup(&mut rng).unwrap();
 
     c.bench_function("pedersen_commit", |b| {
         b.iter(|| {
@@ -25,9 +25,26 @@ fn bench_commit(c: &mut Criterion) {
     });
 }
 
+fn bench_commit_prepared(c: &mut Criterion) {
+    let mut rng = test_rng();
+    let params: CommitmentParams = CommitmentParams::setup(&mut rng).unwrap();
+    let prepared = params.prepare();
+    let value = ScalarField::rand(&mut rng);
+    let randomness = Randomness(ScalarField::rand(&mut rng));
+
+    let mut group = c.benchmark_group("pedersen_commit_with_randomness");
+    group.bench_function("plain", |b| {
+        b.iter(|| black_box(params.commit_with_randomness(&value, &randomness).unwrap()))
+    });
+    group.bench_function("prepared", |b| {
+        b.iter(|| black_box(prepared.commit_with_randomness(&value, &randomness).unwrap()))
+    });
+    group.finish();
+}
+
 fn bench_verify(c: &mut Criterion) {
     let mut rng = test_rng();
-    let params = CommitmentParams::setup(&mut rng).unwrap();
+    let params: CommitmentParams = CommitmentParams::setup(&mut rng).unwrap();
     let value = ScalarField::from(42u64);
     let (commitment, randomness) = params.commit(&value, &mut rng).unwrap();
     let opening = Opening { value, randomness };
@@ -39,40 +56,113 @@ fn bench_verify(c: &mut Criterion) {
     });
 }
 
+fn bench_verify_batch(c: &mut Criterion) {
+    let mut rng = test_rng();
+    let params: CommitmentParams = CommitmentParams::setup(&mut rng).unwrap();
+    let values: Vec<ScalarField> = (0..64).map(|_| ScalarField::rand(&mut rng)).collect();
+    let (commitments, openings): (Vec<Commitment>, Vec<Opening>) = params
+        .commit_batch(&values, &mut rng)
+        .unwrap()
+        .into_iter()
+        .zip(&values)
+        .map(|((c, randomness), value)| (c, Opening { value: *value, randomness }))
+        .unzip();
+
+    c.bench_function("pedersen_verify_batch_64", |b| {
+        b.iter(|| black_box(params.verify_batch(&commitments, &openings).unwrap()))
+    });
+}
+
+fn bench_commitment_eq(c: &mut Criterion) {
+    use subtle::ConstantTimeEq;
+    let mut rng = test_rng();
+    let params: CommitmentParams = CommitmentParams::setup(&mut rng).unwrap();
+    let (a, _) = params.commit(&ScalarField::from(1u64), &mut rng).unwrap();
+    let b_point = &(&a + &a) - &a;
+
+    let mut group = c.benchmark_group("commitment_eq");
+    group.bench_function("partial_eq", |b| b.iter(|| black_box(black_box(&a) == black_box(&b_point))));
+    group.bench_function("ct_eq", |b| b.iter(|| black_box(bool::from(black_box(&a).ct_eq(black_box(&b_point))))));
+    group.finish();
+}
+
 fn bench_aggregation(c: &mut Criterion) {
     let mut rng = test_rng();
-    let params = CommitmentParams::setup(&mut rng).unwrap();
+    let params: CommitmentParams = CommitmentParams::setup(&mut rng).unwrap();
 
+    // `aggregate` is the serial fold; `from_commitments` takes the chunked
+    // rayon path when built with `--features parallel`.
     let mut group = c.benchmark_group("aggregation");
 
-    for size in [10, 100, 1000].iter() {
-        let commitments: Vec<Commitment> = (0..*size)
-            .map(|_| {
-                let value = ScalarField::rand(&mut rng);
-                params.commit(&value, &mut rng).unwrap().0
-            })
-            .collect();
+    for size in [10, 100, 1000, 10_000, 100_000].iter() {
+        let values: Vec<ScalarField> = (0..*size).map(|_| ScalarField::rand(&mut rng)).collect();
+        let commitments: Vec<Commitment> = params.commit_batch(&values, &mut rng).unwrap().into_iter().map(|(c, _)| c).collect();
 
         group.bench_with_input(BenchmarkId::new("aggregate", size), size, |b, _| {
             b.iter(|| {
                 let mut agg = AggregateCommitment::empty();
                 for c in &commitments {
-                    agg = agg.add(c);
+                    agg = agg.add(c).unwrap();
                 }
                 black_box(agg)
             })
         });
+        group.bench_with_input(BenchmarkId::new("from_commitments", size), size, |b, _| {
+            b.iter(|| black_box(AggregateCommitment::from_commitments(&commitments)))
+        });
     }
 
     group.finish();
 }
 
+fn bench_chain_aggregate(c: &mut Criterion) {
+    let mut rng = test_rng();
+    let params: CommitmentParams = CommitmentParams::setup(&mut rng).unwrap();
+    let values: Vec<ScalarField> = (0..10_000).map(|_| ScalarField::rand(&mut rng)).collect();
+    let mut chain = CommitmentChain::new(params);
+    chain.extend_from_values(&values, &mut rng).unwrap();
+
+    let mut group = c.benchmark_group("chain_aggregate_10000");
+    group.bench_function("cached", |b| b.iter(|| black_box(chain.aggregate())));
+    group.bench_function("recompute", |b| {
+        b.iter(|| black_box(AggregateCommitment::from_commitments(chain.commitments())))
+    });
+    group.finish();
+}
+
+fn bench_chain_verify_aggregate(c: &mut Criterion) {
+    let mut rng = test_rng();
+    let params: CommitmentParams = CommitmentParams::setup(&mut rng).unwrap();
+    let values: Vec<ScalarField> = (0..100_000).map(|_| ScalarField::rand(&mut rng)).collect();
+    let mut chain = CommitmentChain::new(params);
+    chain.extend_from_values(&values, &mut rng).unwrap();
+    let aggregate = chain.aggregate();
+
+    let mut group = c.benchmark_group("chain_verify_aggregate_100000");
+    group.bench_function("cached", |b| b.iter(|| black_box(chain.verify_aggregate(&aggregate).unwrap())));
+    group.bench_function("recompute", |b| {
+        b.iter(|| {
+            let opening = Opening {
+                value: chain.values().iter().sum(),
+                randomness: chain.randomness().iter().sum::<Randomness>(),
+            };
+            black_box(chain.params.verify(&aggregate.commitment, &opening).unwrap())
+        })
+    });
+    group.finish();
+}
+
 criterion_group!(
     benches,
     bench_commitment_setup,
     bench_commit,
+    bench_commit_prepared,
     bench_verify,
+    bench_verify_batch,
+    bench_commitment_eq,
     bench_aggregation,
+    bench_chain_aggregate,
+    bench_chain_verify_aggregate,
 );
 
 criterion_main!(benches);
@@ -0,0 +1,27 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use archimedes_core::{prove_range, verify_range, CommitmentParams};
+use archimedes_core::types::ScalarField;
+use ark_serialize::CanonicalSerialize;
+use ark_std::test_rng;
+
+fn bench_range_proof_64(c: &mut Criterion) {
+    let mut rng = test_rng();
+    let params: CommitmentParams = CommitmentParams::setup(&mut rng).unwrap();
+    let value = ScalarField::from(0x1234_5678_9abc_def0u64);
+    let (commitment, randomness) = params.commit(&value, &mut rng).unwrap();
+    let proof = prove_range(&params, &value, &randomness, 64, &mut rng).unwrap();
+
+    let mut group = c.benchmark_group("range_proof_64");
+    // Reported as bytes per second, so the proof size shows in the output.
+    group.throughput(Throughput::Bytes(proof.compressed_size() as u64));
+    group.bench_function("prove", |b| {
+        b.iter(|| black_box(prove_range(&params, &value, &randomness, 64, &mut rng).unwrap()))
+    });
+    group.bench_function("verify", |b| {
+        b.iter(|| black_box(verify_range(&params, &commitment, &proof, 64).unwrap()))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_range_proof_64);
+criterion_main!(benches);
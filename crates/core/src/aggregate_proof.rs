@@ -0,0 +1,130 @@
+#[cfg(feature = "prover")]
+use ark_ff::UniformRand;
+use ark_ff::PrimeField;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+#[cfg(feature = "prover")]
+use ark_std::rand::Rng;
+use blake2::{Blake2b512, Digest};
+
+use crate::aggregation::AggregateCommitment;
+#[cfg(feature = "prover")]
+use crate::aggregation::CommitmentChain;
+use crate::commitment::{CommitmentParams, CommitmentResult};
+use crate::curve::{CurveConfig, DefaultCurve};
+#[cfg(feature = "prover")]
+use crate::errors::{ArchimedesError, InputErrorKind};
+
+const AGGREGATE_PROOF_DOMAIN: &[u8] = b"archimedes-aggregate-opening-v1";
+
+/// Proves an aggregate of `count` commitments opens to a claimed sum: a
+/// Schnorr proof of knowledge of the summed blinding `r` with
+/// `A - g * sum = h * r`. Individual values and blindings stay hidden.
+#[derive(Clone, Debug, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct AggregateOpeningProof<C: CurveConfig = DefaultCurve> {
+    pub announcement: C::Group,
+    pub response: C::Scalar,
+}
+
+fn aggregate_challenge<C: CurveConfig>(
+    params: &CommitmentParams<C>,
+    aggregate: &AggregateCommitment<C>,
+    claimed_sum: &C::Scalar,
+    announcement: &C::Group,
+) -> CommitmentResult<C::Scalar> {
+    let mut transcript = Vec::new();
+    (params.g, params.h, aggregate.commitment.0, *claimed_sum, *announcement).serialize_compressed(&mut transcript)?;
+    let mut hasher = Blake2b512::new();
+    hasher.update(AGGREGATE_PROOF_DOMAIN);
+    hasher.update(C::CURVE_ID.as_bytes());
+    hasher.update((aggregate.count as u64).to_le_bytes());
+    hasher.update(&transcript);
+    Ok(C::Scalar::from_le_bytes_mod_order(&hasher.finalize()))
+}
+
+#[cfg(feature = "prover")]
+impl<C: CurveConfig> CommitmentChain<C> {
+    pub fn prove_aggregate_value<R: Rng>(&self, claimed_sum: C::Scalar, rng: &mut R) -> CommitmentResult<AggregateOpeningProof<C>> {
+        if self.aggregate_value()? != claimed_sum {
+            return Err(ArchimedesError::InvalidInput(InputErrorKind::ValueMismatch));
+        }
+        let nonce = C::Scalar::rand(rng);
+        let announcement = self.params.h * nonce;
+        let challenge = aggregate_challenge(&self.params, &self.aggregate(), &claimed_sum, &announcement)?;
+        Ok(AggregateOpeningProof {
+            announcement,
+            response: nonce + challenge * self.aggregate_randomness()?.0,
+        })
+    }
+}
+
+impl<C: CurveConfig> AggregateCommitment<C> {
+    /// Checks the proof against this aggregate's commitment and count, so a
+    /// proof for one batch does not carry over to a batch of another size.
+    pub fn verify_claimed_sum(
+        &self,
+        params: &CommitmentParams<C>,
+        claimed_sum: &C::Scalar,
+        proof: &AggregateOpeningProof<C>,
+    ) -> CommitmentResult<bool> {
+        let challenge = aggregate_challenge(params, self, claimed_sum, &proof.announcement)?;
+        let blinding = self.commitment.0 - params.g * claimed_sum;
+        Ok(params.h * proof.response == proof.announcement + blinding * challenge)
+    }
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod tests {
+    use super::*;
+    use crate::curve::curve_tests;
+    use ark_std::test_rng;
+
+    curve_tests!(test_claimed_sum_round_trip, test_wrong_sum_or_count_fails);
+
+    fn chain_of<C: CurveConfig>(values: &[u64]) -> CommitmentChain<C> {
+        let mut rng = test_rng();
+        let mut chain = CommitmentChain::new(CommitmentParams::<C>::setup(&mut rng).unwrap());
+        for v in values {
+            chain.push(C::Scalar::from(*v), &mut rng).unwrap();
+        }
+        chain
+    }
+
+    fn test_claimed_sum_round_trip<C: CurveConfig>() {
+        let mut rng = test_rng();
+        let chain = chain_of::<C>(&[5, 10, 20, 40]);
+        let sum = C::Scalar::from(75u64);
+        let proof = chain.prove_aggregate_value(sum, &mut rng).unwrap();
+
+        let mut bytes = Vec::new();
+        proof.serialize_compressed(&mut bytes).unwrap();
+        let decoded = AggregateOpeningProof::<C>::deserialize_compressed(&bytes[..]).unwrap();
+        assert!(chain.aggregate().verify_claimed_sum(&chain.params, &sum, &decoded).unwrap());
+
+        // The verifier only ever needed the public aggregate.
+        let mut public = chain.clone();
+        public.clear_secrets();
+        assert!(public.aggregate().verify_claimed_sum(&public.params, &sum, &proof).unwrap());
+        assert!(public.prove_aggregate_value(sum, &mut rng).is_err());
+    }
+
+    fn test_wrong_sum_or_count_fails<C: CurveConfig>() {
+        let mut rng = test_rng();
+        let chain = chain_of::<C>(&[1, 2, 3]);
+        let sum = C::Scalar::from(6u64);
+        let wrong = C::Scalar::from(7u64);
+        assert!(matches!(
+            chain.prove_aggregate_value(wrong, &mut rng),
+            Err(ArchimedesError::InvalidInput(InputErrorKind::ValueMismatch))
+        ));
+
+        let proof = chain.prove_aggregate_value(sum, &mut rng).unwrap();
+        let aggregate = chain.aggregate();
+        assert!(!aggregate.verify_claimed_sum(&chain.params, &wrong, &proof).unwrap());
+
+        let recounted = AggregateCommitment { count: aggregate.count + 1, ..aggregate.clone() };
+        assert!(!recounted.verify_claimed_sum(&chain.params, &sum, &proof).unwrap());
+
+        let partial = chain.aggregate_range(0, 2).unwrap();
+        assert!(!partial.verify_claimed_sum(&chain.params, &sum, &proof).unwrap());
+    }
+}
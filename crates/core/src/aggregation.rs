@@ -1,25 +1,142 @@
-use ark_ed_on_bls12_381::Fr as ScalarField;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
 
-use crate::commitment::{Commitment, CommitmentParams, Opening, Randomness};
-use crate::errors::ArchimedesError;
+use ark_ec::{CurveGroup, VariableBaseMSM};
+use ark_ff::{PrimeField, Zero};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, Compress, SerializationError, Valid, Validate};
+use blake2::{Blake2b512, Digest};
+use serde::{Deserialize, Serialize};
+
+use crate::commitment::{AffineCommitment, Commitment, CommitmentParams, CommitmentResult, Opening, Randomness};
+use crate::curve::{CurveConfig, DefaultCurve};
+use crate::errors::{AggregationErrorKind, ArchimedesError, CommitmentErrorKind, VerificationErrorKind};
+use crate::set::CommitmentSet;
 
 type Result<T> = std::result::Result<T, ArchimedesError>;
 
-#[derive(Clone, Debug)]
-pub struct AggregateCommitment {
-    pub commitment: Commitment,
+#[cfg(feature = "prover")]
+const ENTRY_SEED_DOMAIN: &[u8] = b"archimedes-chain-entry-seed-v1";
+const AUDIT_COEFF_DOMAIN: &[u8] = b"archimedes-chain-audit-v1";
+
+static NEXT_CHAIN_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Serialized as the compressed commitment followed by the count as a u64;
+/// serde uses the hex form `{ commitment, count }`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(try_from = "AggregateRepr", into = "AggregateRepr", bound = "")]
+pub struct AggregateCommitment<C: CurveConfig = DefaultCurve> {
+    pub commitment: Commitment<C>,
     pub count: usize,
 }
 
+#[derive(Serialize, Deserialize)]
+struct AggregateRepr {
+    commitment: String,
+    count: u64,
+}
+
+impl<C: CurveConfig> From<AggregateCommitment<C>> for AggregateRepr {
+    fn from(aggregate: AggregateCommitment<C>) -> Self {
+        Self {
+            commitment: aggregate.commitment.to_hex(),
+            count: aggregate.count as u64,
+        }
+    }
+}
+
+impl<C: CurveConfig> TryFrom<AggregateRepr> for AggregateCommitment<C> {
+    type Error = ArchimedesError;
+
+    fn try_from(repr: AggregateRepr) -> CommitmentResult<Self> {
+        Ok(Self {
+            commitment: Commitment::from_hex(&repr.commitment)?,
+            count: usize::try_from(repr.count).map_err(|_| SerializationError::InvalidData)?,
+        })
+    }
+}
+
+impl<C: CurveConfig> CanonicalSerialize for AggregateCommitment<C> {
+    fn serialize_with_mode<W: std::io::Write>(&self, mut writer: W, compress: Compress) -> std::result::Result<(), SerializationError> {
+        self.commitment.serialize_with_mode(&mut writer, compress)?;
+        (self.count as u64).serialize_with_mode(&mut writer, compress)
+    }
+
+    fn serialized_size(&self, compress: Compress) -> usize {
+        self.commitment.serialized_size(compress) + 8
+    }
+}
+
+impl<C: CurveConfig> Valid for AggregateCommitment<C> {
+    fn check(&self) -> std::result::Result<(), SerializationError> {
+        self.commitment.check()
+    }
+}
+
+impl<C: CurveConfig> CanonicalDeserialize for AggregateCommitment<C> {
+    fn deserialize_with_mode<R: std::io::Read>(
+        mut reader: R,
+        compress: Compress,
+        validate: Validate,
+    ) -> std::result::Result<Self, SerializationError> {
+        let commitment = Commitment::deserialize_with_mode(&mut reader, compress, validate)?;
+        let count = u64::deserialize_with_mode(&mut reader, compress, validate)?;
+        Ok(Self {
+            commitment,
+            count: usize::try_from(count).map_err(|_| SerializationError::InvalidData)?,
+        })
+    }
+}
+
+/// An aggregate tagged with the half-open leaf range `[start, end)` it covers,
+/// so aggregates over different ranges cannot be compared by accident.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct RangedAggregate<C: CurveConfig = DefaultCurve> {
+    pub start: usize,
+    pub end: usize,
+    pub agg: AggregateCommitment<C>,
+}
+
+impl<C: CurveConfig> RangedAggregate<C> {
+    pub fn covers(&self, start: usize, end: usize) -> bool {
+        self.start == start && self.end == end && self.agg.count == end.saturating_sub(start)
+    }
+
+    /// Same range and same commitment.
+    pub fn matches(&self, other: &RangedAggregate<C>) -> bool {
+        other.covers(self.start, self.end) && self.agg.commitment == other.agg.commitment
+    }
+}
+
+/// Operator-facing provenance for a chain entry. Not authenticated: it is never
+/// committed to, aggregated, or hashed into the Merkle tree.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EntryMeta {
+    pub tx_hash: [u8; 32],
+    pub batch_attempt: u32,
+    pub ingested_at: u64,
+    pub source_peer: Option<String>,
+}
+
 #[derive(Clone, Debug)]
-pub struct CommitmentChain {
-    pub params: CommitmentParams,
-    pub commitments: Vec<Commitment>,
-    pub randomness: Vec<Randomness>,
-    pub values: Vec<ScalarField>,
+pub struct CommitmentChain<C: CurveConfig = DefaultCurve> {
+    pub params: CommitmentParams<C>,
+    commitments: Vec<Commitment<C>>,
+    randomness: Vec<Randomness<C>>,
+    values: Vec<C::Scalar>,
+    metadata: Vec<Option<EntryMeta>>,
+    tx_index: HashMap<[u8; 32], Vec<usize>>,
+    secrets_cleared: bool,
+    master_seed: Option<[u8; 32]>,
+    running: AggregateCommitment<C>,
+    value_sum: C::Scalar,
+    randomness_sum: Randomness<C>,
+    range_index: Option<Vec<AggregateCommitment<C>>>,
+    chain_id: u64,
+    max_len: Option<usize>,
 }
 
-impl AggregateCommitment {
+impl<C: CurveConfig> AggregateCommitment<C> {
     pub fn empty() -> Self {
         Self {
             commitment: Commitment::zero(),
@@ -27,76 +144,451 @@ impl AggregateCommitment {
         }
     }
 
-    pub fn from_commitments(commitments: &[Commitment]) -> Self {
-        let mut agg = Commitment::zero();
-        for c in commitments {
-            agg = agg.add(c);
-        }
+    pub fn from_commitments(commitments: &[Commitment<C>]) -> Self {
         Self {
-            commitment: agg,
+            commitment: Commitment::sum_slice(commitments),
             count: commitments.len(),
         }
     }
 
-    pub fn add(&self, other: &Commitment) -> Self {
-        Self {
+    /// `sum(coeffs[i] * commitments[i])`, for random-linear-combination checks.
+    pub fn weighted_from(commitments: &[Commitment<C>], coeffs: &[C::Scalar]) -> Result<Self> {
+        check_coefficient_count(commitments.len(), coeffs.len())?;
+        let points: Vec<C::Group> = commitments.iter().map(|c| c.0).collect();
+        let bases = C::Group::normalize_batch(&points);
+        Ok(Self {
+            commitment: Commitment(C::Group::msm_unchecked(&bases, coeffs)),
+            count: commitments.len(),
+        })
+    }
+
+    pub fn add(&self, other: &Commitment<C>) -> Result<Self> {
+        Ok(Self {
             commitment: self.commitment.add(other),
-            count: self.count + 1,
-        }
+            count: checked_count(self.count, 1)?,
+        })
     }
 
-    pub fn merge(&self, other: &AggregateCommitment) -> Self {
-        Self {
+    pub fn merge(&self, other: &AggregateCommitment<C>) -> Result<Self> {
+        Ok(Self {
             commitment: self.commitment.add(&other.commitment),
-            count: self.count + other.count,
+            count: checked_count(self.count, other.count)?,
+        })
+    }
+
+    /// `self - prefix`: the aggregate of whatever follows `prefix`.
+    pub fn checked_sub(&self, prefix: &AggregateCommitment<C>) -> Result<Self> {
+        self.difference(prefix)
+    }
+
+    pub fn difference(&self, other: &AggregateCommitment<C>) -> Result<Self> {
+        if other.count > self.count {
+            return Err(ArchimedesError::AggregationError(AggregationErrorKind::CountUnderflow {
+                minuend: self.count,
+                subtrahend: other.count,
+            }));
         }
+        Ok(Self {
+            commitment: self.commitment.sub(&other.commitment),
+            count: self.count - other.count,
+        })
+    }
+}
+
+/// First index where the sequences differ. If one is a prefix of the other,
+/// that is the shorter length; equal sequences give `None`.
+pub fn find_first_divergence<C: CurveConfig>(a: &[Commitment<C>], b: &[Commitment<C>]) -> Option<usize> {
+    a.iter()
+        .zip(b)
+        .position(|(x, y)| x != y)
+        .or_else(|| (a.len() != b.len()).then(|| a.len().min(b.len())))
+}
+
+pub(crate) fn checked_count(count: usize, added: usize) -> Result<usize> {
+    count
+        .checked_add(added)
+        .ok_or(ArchimedesError::AggregationError(AggregationErrorKind::CountOverflow { count, added }))
+}
+
+fn check_coefficient_count(expected: usize, found: usize) -> Result<()> {
+    if expected != found {
+        return Err(ArchimedesError::AggregationError(AggregationErrorKind::CoefficientCountMismatch {
+            expected,
+            found,
+        }));
     }
+    Ok(())
 }
 
-impl CommitmentChain {
-    pub fn new(params: CommitmentParams) -> Self {
+impl<C: CurveConfig> CommitmentChain<C> {
+    pub fn new(params: CommitmentParams<C>) -> Self {
         Self {
             params,
             commitments: Vec::new(),
             randomness: Vec::new(),
             values: Vec::new(),
+            metadata: Vec::new(),
+            tx_index: HashMap::new(),
+            secrets_cleared: false,
+            master_seed: None,
+            running: AggregateCommitment::empty(),
+            value_sum: C::Scalar::zero(),
+            randomness_sum: Randomness::zero(),
+            range_index: None,
+            chain_id: NEXT_CHAIN_ID.fetch_add(1, Ordering::Relaxed),
+            max_len: None,
+        }
+    }
+
+    /// A chain that refuses to grow past `max_len` entries.
+    pub fn new_with_limit(params: CommitmentParams<C>, max_len: usize) -> Self {
+        Self {
+            max_len: Some(max_len),
+            ..Self::new(params)
         }
     }
 
-    pub fn push<R: ark_std::rand::Rng>(&mut self, value: ScalarField, rng: &mut R) -> Result<&Commitment> {
-        let (commitment, randomness) = self.params.commit(&value, rng)?;
+    pub fn max_len(&self) -> Option<usize> {
+        self.max_len
+    }
+
+    /// A chain whose blinding factors are derived from `master_seed` and each
+    /// entry's index rather than drawn from the caller's rng, so the whole
+    /// chain can be regenerated from the seed and the values after a crash.
+    pub fn with_master_seed(params: CommitmentParams<C>, master_seed: [u8; 32]) -> Self {
+        Self {
+            master_seed: Some(master_seed),
+            ..Self::new(params)
+        }
+    }
+
+    pub fn is_deterministic(&self) -> bool {
+        self.master_seed.is_some()
+    }
+
+    /// Process-unique id given at construction; clones share it.
+    pub(crate) fn chain_id(&self) -> u64 {
+        self.chain_id
+    }
+
+    pub(crate) fn master_seed(&self) -> Option<&[u8; 32]> {
+        self.master_seed.as_ref()
+    }
+
+    fn ensure_writable(&self) -> Result<()> {
+        if self.secrets_cleared {
+            return Err(ArchimedesError::CommitmentError(CommitmentErrorKind::SecretsCleared));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn append(&mut self, value: C::Scalar, commitment: Commitment<C>, randomness: Randomness<C>, meta: Option<EntryMeta>) {
+        if let Some(m) = &meta {
+            self.tx_index.entry(m.tx_hash).or_default().push(self.commitments.len());
+        }
+        self.running.commitment += &commitment;
+        self.running.count += 1;
+        self.value_sum += value;
+        self.randomness_sum += &randomness;
+        if let Some(index) = &mut self.range_index {
+            index.push(self.running.clone());
+        }
         self.commitments.push(commitment);
         self.randomness.push(randomness);
         self.values.push(value);
-        Ok(self.commitments.last().unwrap())
+        self.metadata.push(meta);
+    }
+
+    /// Removes the last entry, returning its commitment and opening. Returns
+    /// `None` on an empty chain or once secrets have been cleared.
+    pub fn pop(&mut self) -> Option<(Commitment<C>, Randomness<C>, C::Scalar)> {
+        if self.secrets_cleared || self.commitments.is_empty() {
+            return None;
+        }
+        let index = self.commitments.len() - 1;
+        if let Some(Some(m)) = self.metadata.pop() {
+            if let Some(positions) = self.tx_index.get_mut(&m.tx_hash) {
+                positions.retain(|&i| i != index);
+                if positions.is_empty() {
+                    self.tx_index.remove(&m.tx_hash);
+                }
+            }
+        }
+        let commitment = self.commitments.pop()?;
+        let randomness = self.randomness.pop()?;
+        let value = self.values.pop()?;
+        self.running.commitment -= &commitment;
+        self.running.count -= 1;
+        self.value_sum -= value;
+        self.randomness_sum -= &randomness;
+        if let Some(range_index) = &mut self.range_index {
+            range_index.pop();
+        }
+        Some((commitment, randomness, value))
+    }
+
+    /// Drops entries from the end until `len` remain; a no-op if the chain
+    /// is already that short.
+    pub fn truncate(&mut self, len: usize) -> Result<()> {
+        self.ensure_writable()?;
+        while self.commitments.len() > len {
+            self.pop();
+        }
+        Ok(())
+    }
+
+    /// The commitment at `index` with its opening; `None` past the end or once
+    /// secrets have been cleared.
+    pub fn get(&self, index: usize) -> Option<(&Commitment<C>, Opening<C>)> {
+        let opening = self.opening_at(index).ok()?;
+        Some((&self.commitments[index], opening))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &Commitment<C>)> {
+        self.commitments.iter().enumerate()
+    }
+
+    pub fn opening_at(&self, index: usize) -> Result<Opening<C>> {
+        self.ensure_secrets_held()?;
+        if index >= self.commitments.len() {
+            return Err(ArchimedesError::AggregationError(AggregationErrorKind::IndexOutOfBounds {
+                index,
+                len: self.commitments.len(),
+            }));
+        }
+        Ok(Opening {
+            value: self.values[index],
+            randomness: self.randomness[index].clone(),
+        })
     }
 
-    pub fn aggregate(&self) -> AggregateCommitment {
-        AggregateCommitment::from_commitments(&self.commitments)
+    pub fn provenance(&self, index: usize) -> Option<&EntryMeta> {
+        self.metadata.get(index).and_then(|m| m.as_ref())
     }
 
-    pub fn aggregate_range(&self, start: usize, end: usize) -> Result<AggregateCommitment> {
+    pub fn find_by_tx_hash(&self, tx_hash: &[u8; 32]) -> Vec<usize> {
+        self.tx_index.get(tx_hash).cloned().unwrap_or_default()
+    }
+
+    pub fn tag_at(&self, index: usize) -> Option<[u8; 32]> {
+        self.provenance(index).map(|m| m.tx_hash)
+    }
+
+    /// First entry carrying `tag`.
+    pub fn find_by_tag(&self, tag: &[u8; 32]) -> Option<usize> {
+        self.tx_index.get(tag).and_then(|positions| positions.first().copied())
+    }
+
+    pub(crate) fn tags(&self) -> Vec<Option<[u8; 32]>> {
+        (0..self.len()).map(|i| self.tag_at(i)).collect()
+    }
+
+    /// The running aggregate, maintained on every append.
+    pub fn aggregate(&self) -> AggregateCommitment<C> {
+        self.running.clone()
+    }
+
+    pub fn aggregate_range(&self, start: usize, end: usize) -> Result<AggregateCommitment<C>> {
+        self.ranged_aggregate(start, end).map(|r| r.agg)
+    }
+
+    pub fn ranged_aggregate(&self, start: usize, end: usize) -> Result<RangedAggregate<C>> {
+        self.check_range(start, end)?;
+        let agg = match &self.range_index {
+            Some(index) => index[end].difference(&index[start])?,
+            None => AggregateCommitment::from_commitments(&self.commitments[start..end]),
+        };
+        Ok(RangedAggregate { start, end, agg })
+    }
+
+    fn check_range(&self, start: usize, end: usize) -> Result<()> {
         if end > self.commitments.len() || start > end {
-            return Err(ArchimedesError::AggregationError("Invalid range".to_string()));
+            return Err(ArchimedesError::AggregationError(AggregationErrorKind::InvalidRange {
+                start,
+                end,
+                len: self.commitments.len(),
+            }));
+        }
+        Ok(())
+    }
+
+    /// Builds cumulative aggregates so `aggregate_range` is one subtraction.
+    /// Appends extend the index, `pop` and `truncate` shorten it and `dedup`
+    /// rebuilds it.
+    pub fn build_range_index(&mut self) {
+        let mut index = Vec::with_capacity(self.commitments.len() + 1);
+        index.push(AggregateCommitment::empty());
+        for (i, commitment) in self.commitments.iter().enumerate() {
+            let next = AggregateCommitment {
+                commitment: index[i].commitment.add(commitment),
+                count: i + 1,
+            };
+            index.push(next);
+        }
+        self.range_index = Some(index);
+    }
+
+    pub fn has_range_index(&self) -> bool {
+        self.range_index.is_some()
+    }
+
+    pub fn aggregate_randomness(&self) -> Result<Randomness<C>> {
+        self.ensure_secrets_held()?;
+        Ok(self.randomness_sum.clone())
+    }
+
+    /// The aggregate with the entries at `excluded` taken out, computed from
+    /// the running sum in time proportional to `excluded.len()`.
+    pub fn aggregate_excluding(&self, excluded: &[usize]) -> Result<AggregateCommitment<C>> {
+        self.check_exclusions(excluded)?;
+        let removed: Vec<Commitment<C>> = excluded.iter().map(|&i| self.commitments[i].clone()).collect();
+        self.running.difference(&AggregateCommitment::from_commitments(&removed))
+    }
+
+    /// Opens `aggregate_excluding` together with `aggregate_randomness_excluding`.
+    pub fn aggregate_value_excluding(&self, excluded: &[usize]) -> Result<C::Scalar> {
+        self.ensure_secrets_held()?;
+        self.check_exclusions(excluded)?;
+        Ok(excluded.iter().fold(self.value_sum, |sum, &i| sum - self.values[i]))
+    }
+
+    pub fn aggregate_randomness_excluding(&self, excluded: &[usize]) -> Result<Randomness<C>> {
+        self.ensure_secrets_held()?;
+        self.check_exclusions(excluded)?;
+        Ok(Randomness(excluded.iter().fold(self.randomness_sum.0, |sum, &i| sum - self.randomness[i].0)))
+    }
+
+    fn check_exclusions(&self, excluded: &[usize]) -> Result<()> {
+        let mut seen = HashSet::with_capacity(excluded.len());
+        for &index in excluded {
+            if index >= self.commitments.len() {
+                return Err(ArchimedesError::AggregationError(AggregationErrorKind::IndexOutOfBounds {
+                    index,
+                    len: self.commitments.len(),
+                }));
+            }
+            if !seen.insert(index) {
+                return Err(ArchimedesError::AggregationError(AggregationErrorKind::DuplicateIndex { index }));
+            }
+        }
+        Ok(())
+    }
+
+    /// Opens `aggregate` together with `aggregate_randomness`.
+    pub fn aggregate_value(&self) -> Result<C::Scalar> {
+        self.ensure_secrets_held()?;
+        Ok(self.value_sum)
+    }
+
+    pub fn weighted_aggregate(&self, coeffs: &[C::Scalar]) -> Result<AggregateCommitment<C>> {
+        AggregateCommitment::weighted_from(&self.commitments, coeffs)
+    }
+
+    /// Opens `weighted_aggregate` together with `weighted_randomness`.
+    pub fn weighted_value(&self, coeffs: &[C::Scalar]) -> Result<C::Scalar> {
+        self.ensure_secrets_held()?;
+        check_coefficient_count(self.values.len(), coeffs.len())?;
+        Ok(self.values.iter().zip(coeffs).map(|(v, a)| *v * a).sum())
+    }
+
+    pub fn weighted_randomness(&self, coeffs: &[C::Scalar]) -> Result<Randomness<C>> {
+        self.ensure_secrets_held()?;
+        check_coefficient_count(self.randomness.len(), coeffs.len())?;
+        Ok(Randomness(self.randomness.iter().zip(coeffs).map(|(r, a)| r.0 * a).sum()))
+    }
+
+    fn ensure_secrets_held(&self) -> Result<()> {
+        if self.secrets_cleared {
+            return Err(ArchimedesError::VerificationError(VerificationErrorKind::SecretsCleared));
+        }
+        Ok(())
+    }
+
+    /// Recomputes the cached running sums from the entry vectors, for
+    /// mutations that rewrite them wholesale.
+    fn refresh_aggregates(&mut self) {
+        self.running = AggregateCommitment::from_commitments(&self.commitments);
+        self.value_sum = self.values.iter().sum();
+        self.randomness_sum = self.randomness.iter().sum();
+        if self.range_index.is_some() {
+            self.build_range_index();
         }
-        Ok(AggregateCommitment::from_commitments(&self.commitments[start..end]))
     }
 
-    pub fn aggregate_randomness(&self) -> Randomness {
-        let mut r_agg = Randomness::zero();
-        for r in &self.randomness {
-            r_agg = r_agg.add(r);
+    /// Recomputes every cached sum from the entry vectors and asserts the
+    /// incrementally maintained values agree.
+    #[cfg(all(test, feature = "prover"))]
+    pub(crate) fn debug_assert_consistent(&self) {
+        let running = AggregateCommitment::from_commitments(&self.commitments);
+        assert_eq!((&self.running.commitment, self.running.count), (&running.commitment, running.count));
+        if !self.secrets_cleared {
+            assert_eq!(self.value_sum, self.values.iter().sum::<C::Scalar>());
+            assert_eq!(self.randomness_sum, self.randomness.iter().sum::<Randomness<C>>());
         }
-        r_agg
+        if let Some(index) = &self.range_index {
+            assert_eq!(index.len(), self.commitments.len() + 1);
+            for (end, prefix) in index.iter().enumerate() {
+                let expected = AggregateCommitment::from_commitments(&self.commitments[..end]);
+                assert_eq!((&prefix.commitment, prefix.count), (&expected.commitment, expected.count));
+            }
+        }
+    }
+
+    /// Wipes the blinding factors and committed values, keeping commitments
+    /// and metadata so the chain can still serve aggregates. The chain becomes
+    /// read-only: openings can no longer be produced or new entries pushed.
+    pub fn clear_secrets(&mut self) {
+        #[cfg(feature = "zeroize")]
+        zeroize::Zeroize::zeroize(&mut self.values);
+        self.values.clear();
+        self.randomness.clear();
+        #[cfg(feature = "zeroize")]
+        zeroize::Zeroize::zeroize(&mut self.value_sum);
+        self.value_sum = C::Scalar::zero();
+        self.randomness_sum = Randomness::zero();
+        #[cfg(feature = "zeroize")]
+        zeroize::Zeroize::zeroize(&mut self.master_seed);
+        self.master_seed = None;
+        self.secrets_cleared = true;
+    }
+
+    /// Drops every entry whose commitment already appeared earlier in the
+    /// chain, keeping first occurrences in order along with their metadata.
+    /// Returns how many entries were removed.
+    pub fn dedup(&mut self) -> usize {
+        let mut seen = CommitmentSet::new();
+        let keep: Vec<bool> = self.commitments.iter().map(|c| seen.insert(c.clone())).collect();
+        let removed = keep.iter().filter(|k| !**k).count();
+        if removed == 0 {
+            return 0;
+        }
+        fn retain_flagged<T>(items: &mut Vec<T>, keep: &[bool]) {
+            let mut flags = keep.iter();
+            items.retain(|_| *flags.next().unwrap());
+        }
+        retain_flagged(&mut self.commitments, &keep);
+        retain_flagged(&mut self.metadata, &keep);
+        if !self.secrets_cleared {
+            retain_flagged(&mut self.values, &keep);
+            retain_flagged(&mut self.randomness, &keep);
+        }
+        self.tx_index.clear();
+        for (i, meta) in self.metadata.iter().enumerate() {
+            if let Some(m) = meta {
+                self.tx_index.entry(m.tx_hash).or_default().push(i);
+            }
+        }
+        self.refresh_aggregates();
+        removed
     }
 
-    pub fn aggregate_value(&self) -> ScalarField {
-        self.values.iter().fold(ScalarField::from(0u64), |acc, v| acc + v)
+    pub fn secrets_cleared(&self) -> bool {
+        self.secrets_cleared
     }
 
-    pub fn verify_aggregate(&self, aggregate: &AggregateCommitment) -> Result<bool> {
-        let v_sum = self.aggregate_value();
-        let r_sum = self.aggregate_randomness();
+    pub fn verify_aggregate(&self, aggregate: &AggregateCommitment<C>) -> Result<bool> {
+        let v_sum = self.aggregate_value()?;
+        let r_sum = self.aggregate_randomness()?;
         let opening = Opening {
             value: v_sum,
             randomness: r_sum,
@@ -104,6 +596,110 @@ impl CommitmentChain {
         self.params.verify(&aggregate.commitment, &opening)
     }
 
+    /// Indices whose stored value and randomness do not open their commitment.
+    /// Each range is checked with one random linear combination and only split
+    /// when that fails, so a consistent chain costs a single MSM. The weights
+    /// are hashed from every entry, so errors cannot be arranged to cancel.
+    pub fn verify_all(&self) -> Result<Vec<usize>> {
+        self.ensure_secrets_held()?;
+        let coeffs = self.audit_coefficients()?;
+        let mut failed = Vec::new();
+        self.collect_failures(0, self.commitments.len(), &coeffs, &mut failed)?;
+        Ok(failed)
+    }
+
+    fn audit_coefficients(&self) -> Result<Vec<C::Scalar>> {
+        let mut transcript = Vec::new();
+        (self.params.g, self.params.h).serialize_compressed(&mut transcript)?;
+        for ((c, v), r) in self.commitments.iter().zip(&self.values).zip(&self.randomness) {
+            (c.0, *v, r.0).serialize_compressed(&mut transcript)?;
+        }
+        let mut hasher = Blake2b512::new();
+        hasher.update(AUDIT_COEFF_DOMAIN);
+        hasher.update(C::CURVE_ID.as_bytes());
+        hasher.update(&transcript);
+        let seed = hasher.finalize();
+        Ok((0..self.commitments.len() as u64)
+            .map(|i| {
+                let mut hasher = Blake2b512::new();
+                hasher.update(seed);
+                hasher.update(i.to_le_bytes());
+                C::Scalar::from_le_bytes_mod_order(&hasher.finalize())
+            })
+            .collect())
+    }
+
+    fn collect_failures(&self, start: usize, end: usize, coeffs: &[C::Scalar], failed: &mut Vec<usize>) -> Result<()> {
+        if start == end {
+            return Ok(());
+        }
+        if end - start == 1 {
+            let opening = Opening {
+                value: self.values[start],
+                randomness: self.randomness[start].clone(),
+            };
+            if !self.params.verify(&self.commitments[start], &opening)? {
+                failed.push(start);
+            }
+            return Ok(());
+        }
+        let coeffs_range = &coeffs[start..end];
+        let combined = AggregateCommitment::weighted_from(&self.commitments[start..end], coeffs_range)?;
+        let value = self.values[start..end].iter().zip(coeffs_range).map(|(v, a)| *v * a).sum();
+        let randomness = Randomness(self.randomness[start..end].iter().zip(coeffs_range).map(|(r, a)| r.0 * a).sum());
+        if self.params.verify(&combined.commitment, &Opening { value, randomness })? {
+            return Ok(());
+        }
+        let mid = start + (end - start) / 2;
+        self.collect_failures(start, mid, coeffs, failed)?;
+        self.collect_failures(mid, end, coeffs, failed)
+    }
+
+    /// Verifies `aggregate` opens to the entries in `[start, end)`. Unlike
+    /// `aggregate_range`, an empty range is rejected: there is nothing to check.
+    pub fn verify_aggregate_range(&self, start: usize, end: usize, aggregate: &AggregateCommitment<C>) -> Result<bool> {
+        self.ensure_secrets_held()?;
+        self.check_range(start, end)?;
+        if start == end {
+            return Err(ArchimedesError::AggregationError(AggregationErrorKind::InvalidRange {
+                start,
+                end,
+                len: self.commitments.len(),
+            }));
+        }
+        if aggregate.count != end - start {
+            return Ok(false);
+        }
+        let opening = Opening {
+            value: self.values[start..end].iter().sum(),
+            randomness: self.randomness[start..end].iter().sum(),
+        };
+        self.params.verify(&aggregate.commitment, &opening)
+    }
+
+    /// All commitments in affine form, for serialization.
+    pub fn affine_commitments(&self) -> Vec<AffineCommitment<C>> {
+        Commitment::batch_to_affine(&self.commitments)
+    }
+
+    pub fn commitments(&self) -> &[Commitment<C>] {
+        &self.commitments
+    }
+
+    /// Empty once secrets are cleared.
+    pub fn randomness(&self) -> &[Randomness<C>] {
+        &self.randomness
+    }
+
+    /// Empty once secrets are cleared.
+    pub fn values(&self) -> &[C::Scalar] {
+        &self.values
+    }
+
+    pub fn metadata(&self) -> &[Option<EntryMeta>] {
+        &self.metadata
+    }
+
     pub fn len(&self) -> usize {
         self.commitments.len()
     }
@@ -113,24 +709,134 @@ impl CommitmentChain {
     }
 }
 
-#[cfg(test)]
+#[cfg(feature = "prover")]
+impl<C: CurveConfig> CommitmentChain<C> {
+    pub fn push<R: ark_std::rand::Rng>(&mut self, value: C::Scalar, rng: &mut R) -> Result<&Commitment<C>> {
+        self.push_entry(value, rng, None)
+    }
+
+    pub fn push_with_meta<R: ark_std::rand::Rng>(
+        &mut self,
+        value: C::Scalar,
+        rng: &mut R,
+        meta: EntryMeta,
+    ) -> Result<&Commitment<C>> {
+        self.push_entry(value, rng, Some(meta))
+    }
+
+    /// Pushes with `tag` as the entry's `tx_hash`; untagged entries read back
+    /// as `None` from `tag_at`.
+    pub fn push_tagged<R: ark_std::rand::Rng>(&mut self, value: C::Scalar, tag: [u8; 32], rng: &mut R) -> Result<&Commitment<C>> {
+        let meta = EntryMeta {
+            tx_hash: tag,
+            ..EntryMeta::default()
+        };
+        self.push_entry(value, rng, Some(meta))
+    }
+
+    /// Appends many values at once using `CommitmentParams::commit_batch`.
+    pub fn extend_from_values<R: ark_std::rand::Rng>(&mut self, values: &[C::Scalar], rng: &mut R) -> Result<()> {
+        self.ensure_writable()?;
+        self.ensure_capacity(values.len())?;
+        if self.master_seed.is_some() {
+            for value in values {
+                let (commitment, randomness) = self.commit_next(value, rng)?;
+                self.append(*value, commitment, randomness, None);
+            }
+            return Ok(());
+        }
+        let committed = self.params.commit_batch(values, rng)?;
+        for (value, (commitment, randomness)) in values.iter().zip(committed) {
+            self.append(*value, commitment, randomness, None);
+        }
+        Ok(())
+    }
+
+    fn push_entry<R: ark_std::rand::Rng>(
+        &mut self,
+        value: C::Scalar,
+        rng: &mut R,
+        meta: Option<EntryMeta>,
+    ) -> Result<&Commitment<C>> {
+        self.ensure_writable()?;
+        self.ensure_capacity(1)?;
+        let (commitment, randomness) = self.commit_next(&value, rng)?;
+        self.append(value, commitment, randomness, meta);
+        Ok(self.commitments.last().unwrap())
+    }
+
+    /// Room for `additional` more entries under `max_len`, checked before
+    /// anything is committed.
+    fn ensure_capacity(&self, additional: usize) -> Result<()> {
+        let Some(max_len) = self.max_len else {
+            return Ok(());
+        };
+        match self.commitments.len().checked_add(additional) {
+            Some(len) if len <= max_len => Ok(()),
+            _ => Err(ArchimedesError::AggregationError(AggregationErrorKind::ChainFull { max_len })),
+        }
+    }
+
+    /// Commits the value for the next index, from the master seed if set.
+    fn commit_next<R: ark_std::rand::Rng>(&self, value: &C::Scalar, rng: &mut R) -> Result<(Commitment<C>, Randomness<C>)> {
+        match &self.master_seed {
+            Some(master) => {
+                let mut hasher = Blake2b512::new();
+                hasher.update(ENTRY_SEED_DOMAIN);
+                hasher.update(master);
+                hasher.update((self.commitments.len() as u64).to_le_bytes());
+                let seed: [u8; 32] = hasher.finalize()[..32].try_into().unwrap();
+                self.params.commit_deterministic(value, &seed)
+            }
+            None => self.params.commit(value, rng),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "prover"))]
 mod tests {
     use super::*;
+    use crate::curve::curve_tests;
     use ark_std::test_rng;
 
-    #[test]
-    fn test_aggregate_empty() {
-        let agg = AggregateCommitment::empty();
+    curve_tests!(
+        test_aggregate_empty,
+        test_commitment_chain,
+        test_aggregate_range,
+        test_aggregate_difference,
+        test_entry_metadata_lookup,
+        test_aggregate_homomorphism,
+        test_clear_secrets,
+        test_from_commitments_matches_sequential_sum,
+        test_regenerate_from_master_seed,
+        test_dedup_keeps_first_occurrence,
+        test_refresh_after_direct_edit,
+        test_indexed_range_matches_naive,
+        test_pop_and_truncate,
+        test_range_index_follows_mutations,
+        test_weighted_aggregate_opens,
+        test_verify_aggregate_range,
+        test_aggregate_serialization,
+        test_indexed_openings,
+        test_find_first_divergence,
+        test_aggregate_excluding_corrupted_entry,
+        test_verify_all_localizes_tampering,
+        test_length_limit,
+        test_tagged_entries,
+        test_ranged_aggregate,
+    );
+
+    fn test_aggregate_empty<C: CurveConfig>() {
+        let agg = AggregateCommitment::<C>::empty();
         assert_eq!(agg.count, 0);
     }
 
-    #[test]
-    fn test_commitment_chain() {
+    fn test_commitment_chain<C: CurveConfig>() {
         let mut rng = test_rng();
-        let params = CommitmentParams::setup(&mut rng).unwrap();
+        let params = CommitmentParams::<C>::setup(&mut rng).unwrap();
         let mut chain = CommitmentChain::new(params);
         for i in 1..=5 {
-            chain.push(ScalarField::from(i as u64), &mut rng).unwrap();
+            chain.push(C::Scalar::from(i as u64), &mut rng).unwrap();
         }
         assert_eq!(chain.len(), 5);
         let agg = chain.aggregate();
@@ -138,31 +844,640 @@ mod tests {
         assert!(chain.verify_aggregate(&agg).unwrap());
     }
 
-    #[test]
-    fn test_aggregate_range() {
+    fn test_aggregate_range<C: CurveConfig>() {
         let mut rng = test_rng();
-        let params = CommitmentParams::setup(&mut rng).unwrap();
+        let params = CommitmentParams::<C>::setup(&mut rng).unwrap();
         let mut chain = CommitmentChain::new(params);
         for i in 1..=10 {
-            chain.push(ScalarField::from(i as u64), &mut rng).unwrap();
+            chain.push(C::Scalar::from(i as u64), &mut rng).unwrap();
         }
         let partial = chain.aggregate_range(0, 5).unwrap();
         assert_eq!(partial.count, 5);
     }
 
-    #[test]
-    fn test_aggregate_homomorphism() {
+    fn test_aggregate_difference<C: CurveConfig>() {
+        let mut rng = test_rng();
+        let params = CommitmentParams::<C>::setup(&mut rng).unwrap();
+        let mut chain = CommitmentChain::new(params);
+        for i in 1..=6 {
+            chain.push(C::Scalar::from(i as u64), &mut rng).unwrap();
+        }
+        let full = chain.aggregate();
+        let prefix = chain.aggregate_range(0, 4).unwrap();
+        let suffix = full.difference(&prefix).unwrap();
+        assert_eq!(suffix.count, 2);
+        assert_eq!(suffix.commitment, chain.aggregate_range(4, 6).unwrap().commitment);
+        assert!(prefix.difference(&full).is_err());
+        assert_eq!(full.checked_sub(&prefix).unwrap().commitment, suffix.commitment);
+        assert!(matches!(
+            prefix.checked_sub(&full),
+            Err(ArchimedesError::AggregationError(AggregationErrorKind::CountUnderflow { minuend: 4, subtrahend: 6 }))
+        ));
+    }
+
+    fn test_entry_metadata_lookup<C: CurveConfig>() {
         let mut rng = test_rng();
-        let params = CommitmentParams::setup(&mut rng).unwrap();
+        let params = CommitmentParams::<C>::setup(&mut rng).unwrap();
+        let mut chain = CommitmentChain::new(params);
+        let meta = |tx: u8, attempt: u32| EntryMeta {
+            tx_hash: [tx; 32],
+            batch_attempt: attempt,
+            ingested_at: 1_000 + tx as u64,
+            source_peer: Some("peer-a".to_string()),
+        };
+        chain.push_with_meta(C::Scalar::from(1u64), &mut rng, meta(1, 0)).unwrap();
+        chain.push(C::Scalar::from(2u64), &mut rng).unwrap();
+        chain.push_with_meta(C::Scalar::from(3u64), &mut rng, meta(2, 0)).unwrap();
+        chain.push_with_meta(C::Scalar::from(4u64), &mut rng, meta(1, 1)).unwrap();
+
+        assert_eq!(chain.metadata.len(), chain.len());
+        assert_eq!(chain.provenance(0), Some(&meta(1, 0)));
+        assert_eq!(chain.provenance(1), None);
+        assert_eq!(chain.provenance(3).unwrap().batch_attempt, 1);
+        assert_eq!(chain.provenance(4), None);
+        assert_eq!(chain.find_by_tx_hash(&[1u8; 32]), vec![0, 3]);
+        assert_eq!(chain.find_by_tx_hash(&[2u8; 32]), vec![2]);
+        assert!(chain.find_by_tx_hash(&[9u8; 32]).is_empty());
+        assert!(chain.verify_aggregate(&chain.aggregate()).unwrap());
+    }
+
+    fn test_aggregate_homomorphism<C: CurveConfig>() {
+        let mut rng = test_rng();
+        let params = CommitmentParams::<C>::setup(&mut rng).unwrap();
         let mut chain = CommitmentChain::new(params);
         let values: Vec<u64> = vec![10, 20, 30, 40, 50];
         for v in &values {
-            chain.push(ScalarField::from(*v), &mut rng).unwrap();
+            chain.push(C::Scalar::from(*v), &mut rng).unwrap();
         }
         let agg = chain.aggregate();
         let expected_sum: u64 = values.iter().sum();
-        assert_eq!(chain.aggregate_value(), ScalarField::from(expected_sum));
+        assert_eq!(chain.aggregate_value().unwrap(), C::Scalar::from(expected_sum));
         assert!(chain.verify_aggregate(&agg).unwrap());
     }
-}
 
+    fn test_regenerate_from_master_seed<C: CurveConfig>() {
+        let mut rng = test_rng();
+        let params = CommitmentParams::<C>::setup(&mut rng).unwrap();
+        let values: Vec<C::Scalar> = [3u64, 3, 9, 27].into_iter().map(C::Scalar::from).collect();
+        let mut chain = CommitmentChain::with_master_seed(params.clone(), [7u8; 32]);
+        for value in &values {
+            chain.push(*value, &mut rng).unwrap();
+        }
+        // Equal values at different indices still get distinct blinding.
+        assert_ne!(chain.commitments[0], chain.commitments[1]);
+
+        let mut regenerated = CommitmentChain::with_master_seed(params.clone(), [7u8; 32]);
+        regenerated.extend_from_values(&values, &mut test_rng()).unwrap();
+        assert_eq!(regenerated.commitments, chain.commitments);
+        assert_eq!(regenerated.randomness, chain.randomness);
+        assert!(regenerated.verify_aggregate(&chain.aggregate()).unwrap());
+
+        let mut other = CommitmentChain::with_master_seed(params, [8u8; 32]);
+        other.extend_from_values(&values, &mut rng).unwrap();
+        assert!(other.commitments.iter().zip(&chain.commitments).all(|(a, b)| a != b));
+
+        chain.clear_secrets();
+        assert!(!chain.is_deterministic());
+    }
+
+    fn test_dedup_keeps_first_occurrence<C: CurveConfig>() {
+        let mut rng = test_rng();
+        let params = CommitmentParams::<C>::setup(&mut rng).unwrap();
+        let mut chain = CommitmentChain::new(params.clone());
+        chain.build_range_index();
+        let meta = |tag: u8| EntryMeta { tx_hash: [tag; 32], ..Default::default() };
+        chain.push_with_meta(C::Scalar::from(1u64), &mut rng, meta(1)).unwrap();
+        chain.push_with_meta(C::Scalar::from(2u64), &mut rng, meta(2)).unwrap();
+        let first = chain.commitments[0].clone();
+        let r = chain.randomness[0].clone();
+        // Replay entry 0 under a different projective representation.
+        let replayed = &(&first + &chain.commitments[1]) - &chain.commitments[1];
+        chain.commitments.push(replayed);
+        chain.values.push(C::Scalar::from(1u64));
+        chain.randomness.push(r);
+        chain.metadata.push(Some(meta(3)));
+        chain.refresh_aggregates();
+        chain.push_with_meta(C::Scalar::from(4u64), &mut rng, meta(4)).unwrap();
+
+        assert_eq!(chain.dedup(), 1);
+        chain.debug_assert_consistent();
+        assert_eq!(chain.dedup(), 0);
+        assert_eq!(chain.len(), 3);
+        assert_eq!(chain.values.len(), 3);
+        assert_eq!(chain.provenance(2).unwrap().tx_hash, [4; 32]);
+        assert_eq!(chain.find_by_tx_hash(&[4; 32]), vec![2]);
+        assert!(chain.find_by_tx_hash(&[3; 32]).is_empty());
+        assert!(chain.verify_aggregate(&chain.aggregate()).unwrap());
+    }
+
+    fn test_refresh_after_direct_edit<C: CurveConfig>() {
+        let mut rng = test_rng();
+        let params = CommitmentParams::<C>::setup(&mut rng).unwrap();
+        let mut chain = CommitmentChain::new(params.clone());
+        chain.push(C::Scalar::from(1u64), &mut rng).unwrap();
+        let (c, r) = params.commit(&C::Scalar::from(2u64), &mut rng).unwrap();
+        chain.commitments.push(c);
+        chain.values.push(C::Scalar::from(2u64));
+        chain.randomness.push(r);
+        assert_eq!(chain.aggregate().count, 1);
+
+        chain.refresh_aggregates();
+        chain.debug_assert_consistent();
+        assert_eq!(chain.aggregate().count, 2);
+        assert_eq!(chain.aggregate_value().unwrap(), C::Scalar::from(3u64));
+        assert!(chain.verify_aggregate(&chain.aggregate()).unwrap());
+    }
+
+    fn test_indexed_range_matches_naive<C: CurveConfig>() {
+        let mut rng = test_rng();
+        let params = CommitmentParams::<C>::setup(&mut rng).unwrap();
+        let mut chain = CommitmentChain::with_master_seed(params, [1u8; 32]);
+        for i in 0..6u64 {
+            chain.push(C::Scalar::from(i), &mut rng).unwrap();
+        }
+        let mut indexed = chain.clone();
+        indexed.build_range_index();
+        assert!(indexed.has_range_index() && !chain.has_range_index());
+        // Appends after the build extend the index.
+        let tail: Vec<C::Scalar> = (6..9u64).map(C::Scalar::from).collect();
+        chain.extend_from_values(&tail, &mut rng).unwrap();
+        indexed.extend_from_values(&tail, &mut rng).unwrap();
+
+        for start in 0..=9 {
+            for end in start..=9 {
+                let naive = chain.aggregate_range(start, end).unwrap();
+                let fast = indexed.aggregate_range(start, end).unwrap();
+                assert_eq!(fast.commitment, naive.commitment);
+                assert_eq!(fast.count, end - start);
+            }
+        }
+        assert_eq!(indexed.aggregate_range(0, 9).unwrap().commitment, chain.aggregate().commitment);
+        assert!(indexed.aggregate_range(3, 10).is_err());
+        assert!(indexed.aggregate_range(5, 4).is_err());
+    }
+
+    fn test_pop_and_truncate<C: CurveConfig>() {
+        let mut rng = test_rng();
+        let params = CommitmentParams::<C>::setup(&mut rng).unwrap();
+        let mut chain = CommitmentChain::new(params);
+        assert!(chain.pop().is_none());
+        chain.build_range_index();
+        let meta = |tag: u8| EntryMeta { tx_hash: [tag; 32], ..Default::default() };
+        for i in 1..=6u64 {
+            chain.push_with_meta(C::Scalar::from(i), &mut rng, meta(i as u8 % 2)).unwrap();
+        }
+        let prefix = chain.aggregate_range(0, 3).unwrap();
+
+        chain.debug_assert_consistent();
+        let (commitment, randomness, value) = chain.pop().unwrap();
+        chain.debug_assert_consistent();
+        assert_eq!(value, C::Scalar::from(6u64));
+        assert!(chain.params.verify(&commitment, &Opening { value, randomness }).unwrap());
+        assert_eq!(chain.find_by_tx_hash(&[0; 32]), vec![1, 3]);
+
+        chain.truncate(5).unwrap();
+        assert_eq!(chain.len(), 5);
+        chain.truncate(3).unwrap();
+        chain.debug_assert_consistent();
+        assert_eq!(chain.len(), 3);
+        assert_eq!(chain.values.len(), 3);
+        assert_eq!(chain.randomness.len(), 3);
+        assert_eq!(chain.aggregate().commitment, prefix.commitment);
+        assert_eq!(chain.aggregate().count, 3);
+        assert_eq!(chain.aggregate_value().unwrap(), C::Scalar::from(6u64));
+        assert!(chain.verify_aggregate(&chain.aggregate()).unwrap());
+        assert_eq!(chain.aggregate_range(0, 3).unwrap().commitment, prefix.commitment);
+        assert_eq!(chain.find_by_tx_hash(&[0; 32]), vec![1]);
+
+        // Pushing after a pop reuses the index cleanly.
+        chain.push(C::Scalar::from(10u64), &mut rng).unwrap();
+        chain.debug_assert_consistent();
+        assert!(chain.verify_aggregate(&chain.aggregate()).unwrap());
+        assert_eq!(chain.aggregate_range(3, 4).unwrap().commitment, chain.commitments[3]);
+
+        chain.clear_secrets();
+        assert!(chain.pop().is_none());
+        assert!(chain.truncate(1).is_err());
+        assert_eq!(chain.len(), 4);
+    }
+
+    fn test_range_index_follows_mutations<C: CurveConfig>() {
+        let mut rng = test_rng();
+        let params = CommitmentParams::<C>::setup(&mut rng).unwrap();
+        let mut chain = CommitmentChain::with_master_seed(params, [3u8; 32]);
+        chain.build_range_index();
+        let values: Vec<C::Scalar> = (0..8u64).map(C::Scalar::from).collect();
+        chain.extend_from_values(&values, &mut rng).unwrap();
+        chain.push_tagged(C::Scalar::from(8u64), [8; 32], &mut rng).unwrap();
+        chain.debug_assert_consistent();
+        let checkpoint = chain.checkpoint();
+
+        chain.push(C::Scalar::from(9u64), &mut rng).unwrap();
+        chain.pop();
+        chain.debug_assert_consistent();
+        chain.extend_from_values(&values[..3], &mut rng).unwrap();
+        chain.rollback_to(&checkpoint).unwrap();
+        chain.debug_assert_consistent();
+        chain.truncate(5).unwrap();
+        chain.debug_assert_consistent();
+        assert_eq!(chain.dedup(), 0);
+        chain.clear_secrets();
+        chain.debug_assert_consistent();
+        for start in 0..=5 {
+            for end in start..=5 {
+                let naive = AggregateCommitment::from_commitments(&chain.commitments()[start..end]);
+                assert_eq!(chain.aggregate_range(start, end).unwrap().commitment, naive.commitment);
+            }
+        }
+    }
+
+    fn test_weighted_aggregate_opens<C: CurveConfig>() {
+        let mut rng = test_rng();
+        let params = CommitmentParams::<C>::setup(&mut rng).unwrap();
+        let mut chain = CommitmentChain::new(params);
+        for i in 1..=5u64 {
+            chain.push(C::Scalar::from(i), &mut rng).unwrap();
+        }
+        let coeffs: Vec<C::Scalar> = [3u64, 0, 1, 7, 2].into_iter().map(C::Scalar::from).collect();
+        let weighted = chain.weighted_aggregate(&coeffs).unwrap();
+        assert_eq!(weighted.count, 5);
+        assert_eq!(chain.weighted_value(&coeffs).unwrap(), C::Scalar::from(3 + 3 + 28 + 10u64));
+        let opening = Opening {
+            value: chain.weighted_value(&coeffs).unwrap(),
+            randomness: chain.weighted_randomness(&coeffs).unwrap(),
+        };
+        assert!(chain.params.verify(&weighted.commitment, &opening).unwrap());
+
+        let ones = vec![C::Scalar::from(1u64); 5];
+        assert_eq!(chain.weighted_aggregate(&ones).unwrap().commitment, chain.aggregate().commitment);
+        assert!(matches!(
+            chain.weighted_aggregate(&coeffs[..4]),
+            Err(ArchimedesError::AggregationError(AggregationErrorKind::CoefficientCountMismatch { expected: 5, found: 4 }))
+        ));
+        assert!(chain.weighted_value(&coeffs[..4]).is_err());
+        assert!(chain.weighted_randomness(&[]).is_err());
+    }
+
+    fn test_find_first_divergence<C: CurveConfig>() {
+        let mut rng = test_rng();
+        let params = CommitmentParams::<C>::setup(&mut rng).unwrap();
+        let values: Vec<C::Scalar> = (0..6u64).map(C::Scalar::from).collect();
+        let a: Vec<Commitment<C>> = params.commit_batch(&values, &mut rng).unwrap().into_iter().map(|(c, _)| c).collect();
+        let mut b = a.clone();
+        assert_eq!(find_first_divergence(&a, &b), None);
+        assert_eq!(find_first_divergence::<C>(&[], &[]), None);
+        assert_eq!(find_first_divergence(&a, &b[..4]), Some(4));
+        assert_eq!(find_first_divergence(&a[..0], &b), Some(0));
+        b[3] = &b[3] + &b[0];
+        assert_eq!(find_first_divergence(&a, &b), Some(3));
+        assert_eq!(find_first_divergence(&a[..5], &b), Some(3));
+    }
+
+    fn test_aggregate_excluding_corrupted_entry<C: CurveConfig>() {
+        let mut rng = test_rng();
+        let params = CommitmentParams::<C>::setup(&mut rng).unwrap();
+        let mut chain = CommitmentChain::new(params);
+        for i in 1..=6u64 {
+            chain.push(C::Scalar::from(i), &mut rng).unwrap();
+        }
+        assert_eq!(chain.aggregate_excluding(&[]).unwrap().commitment, chain.aggregate().commitment);
+
+        chain.commitments[2] = chain.params.commit(&C::Scalar::from(99u64), &mut rng).unwrap().0;
+        chain.refresh_aggregates();
+        assert!(!chain.verify_aggregate(&chain.aggregate()).unwrap());
+
+        let agg = chain.aggregate_excluding(&[2]).unwrap();
+        assert_eq!(agg.count, 5);
+        let opening = Opening {
+            value: chain.aggregate_value_excluding(&[2]).unwrap(),
+            randomness: chain.aggregate_randomness_excluding(&[2]).unwrap(),
+        };
+        assert_eq!(opening.value, C::Scalar::from(18u64));
+        assert!(chain.params.verify(&agg.commitment, &opening).unwrap());
+
+        let agg = chain.aggregate_excluding(&[5, 2, 0]).unwrap();
+        let opening = Opening {
+            value: chain.aggregate_value_excluding(&[5, 2, 0]).unwrap(),
+            randomness: chain.aggregate_randomness_excluding(&[5, 2, 0]).unwrap(),
+        };
+        assert!(chain.params.verify(&agg.commitment, &opening).unwrap());
+
+        assert!(matches!(
+            chain.aggregate_excluding(&[1, 6]),
+            Err(ArchimedesError::AggregationError(AggregationErrorKind::IndexOutOfBounds { index: 6, len: 6 }))
+        ));
+        assert!(matches!(
+            chain.aggregate_value_excluding(&[3, 1, 3]),
+            Err(ArchimedesError::AggregationError(AggregationErrorKind::DuplicateIndex { index: 3 }))
+        ));
+    }
+
+    fn test_verify_all_localizes_tampering<C: CurveConfig>() {
+        let mut rng = test_rng();
+        let params = CommitmentParams::<C>::setup(&mut rng).unwrap();
+        let mut chain = CommitmentChain::new(params);
+        for i in 0..9u64 {
+            chain.push(C::Scalar::from(i), &mut rng).unwrap();
+        }
+        assert!(chain.verify_all().unwrap().is_empty());
+
+        chain.commitments[3] = &chain.commitments[3] + &chain.commitments[0];
+        assert_eq!(chain.verify_all().unwrap(), vec![3]);
+
+        // Errors that cancel in the plain sum are still caught.
+        chain.commitments[7] = chain.commitments[7].sub(&chain.commitments[0]);
+        chain.refresh_aggregates();
+        assert!(chain.verify_aggregate(&chain.aggregate()).unwrap());
+        assert_eq!(chain.verify_all().unwrap(), vec![3, 7]);
+
+        chain.clear_secrets();
+        assert!(chain.verify_all().is_err());
+    }
+
+    fn test_length_limit<C: CurveConfig>() {
+        let mut rng = test_rng();
+        let params = CommitmentParams::<C>::setup(&mut rng).unwrap();
+        let mut chain = CommitmentChain::new_with_limit(params, 4);
+        chain.extend_from_values(&[C::Scalar::from(1u64), C::Scalar::from(2u64)], &mut rng).unwrap();
+        assert!(matches!(
+            chain.extend_from_values(&[C::Scalar::from(3u64); 3], &mut rng),
+            Err(ArchimedesError::AggregationError(AggregationErrorKind::ChainFull { max_len: 4 }))
+        ));
+        assert_eq!(chain.len(), 2);
+        chain.push(C::Scalar::from(3u64), &mut rng).unwrap();
+        chain.push(C::Scalar::from(4u64), &mut rng).unwrap();
+        assert_eq!(chain.len(), 4);
+        assert!(chain.push(C::Scalar::from(5u64), &mut rng).is_err());
+        assert_eq!(chain.len(), 4);
+        assert!(chain.verify_aggregate(&chain.aggregate()).unwrap());
+
+        let full = AggregateCommitment::<C> {
+            commitment: Commitment::zero(),
+            count: usize::MAX,
+        };
+        assert!(matches!(
+            full.merge(&chain.aggregate()),
+            Err(ArchimedesError::AggregationError(AggregationErrorKind::CountOverflow { count: usize::MAX, added: 4 }))
+        ));
+        assert!(full.add(&chain.commitments[0]).is_err());
+        assert_eq!(chain.aggregate_range(0, 2).unwrap().merge(&chain.aggregate_range(2, 4).unwrap()).unwrap().count, 4);
+    }
+
+    fn test_tagged_entries<C: CurveConfig>() {
+        let mut rng = test_rng();
+        let params = CommitmentParams::<C>::setup(&mut rng).unwrap();
+        let mut chain = CommitmentChain::new(params);
+        chain.push_tagged(C::Scalar::from(1u64), [7u8; 32], &mut rng).unwrap();
+        chain.push(C::Scalar::from(2u64), &mut rng).unwrap();
+        chain.push_tagged(C::Scalar::from(3u64), [9u8; 32], &mut rng).unwrap();
+        chain.push_tagged(C::Scalar::from(4u64), [7u8; 32], &mut rng).unwrap();
+
+        assert_eq!(chain.metadata.len(), chain.len());
+        assert_eq!(chain.tag_at(0), Some([7u8; 32]));
+        assert_eq!(chain.tag_at(1), None);
+        assert_eq!(chain.tag_at(4), None);
+        assert_eq!(chain.find_by_tag(&[7u8; 32]), Some(0));
+        assert_eq!(chain.find_by_tag(&[9u8; 32]), Some(2));
+        assert_eq!(chain.find_by_tag(&[1u8; 32]), None);
+
+        chain.pop();
+        chain.pop();
+        assert_eq!(chain.find_by_tag(&[9u8; 32]), None);
+        assert_eq!(chain.tags(), vec![Some([7u8; 32]), None]);
+    }
+
+    fn test_ranged_aggregate<C: CurveConfig>() {
+        let mut rng = test_rng();
+        let params = CommitmentParams::<C>::setup(&mut rng).unwrap();
+        let mut chain = CommitmentChain::with_master_seed(params, [5u8; 32]);
+        chain.extend_from_values(&[C::Scalar::from(0u64); 6], &mut rng).unwrap();
+        let ranged = chain.ranged_aggregate(1, 4).unwrap();
+        assert!(ranged.covers(1, 4));
+        assert!(!ranged.covers(0, 3));
+        assert_eq!(ranged.agg.commitment, chain.aggregate_range(1, 4).unwrap().commitment);
+
+        chain.build_range_index();
+        assert!(chain.ranged_aggregate(1, 4).unwrap().matches(&ranged));
+        // Same commitment and length over another range is not a match.
+        let shifted = RangedAggregate { start: 2, end: 5, agg: ranged.agg.clone() };
+        assert!(!shifted.matches(&ranged));
+        assert!(chain.ranged_aggregate(4, 7).is_err());
+    }
+
+    fn test_indexed_openings<C: CurveConfig>() {
+        let mut rng = test_rng();
+        let params = CommitmentParams::<C>::setup(&mut rng).unwrap();
+        let mut chain = CommitmentChain::new(params);
+        for i in 10..14u64 {
+            chain.push(C::Scalar::from(i), &mut rng).unwrap();
+        }
+        for (index, commitment) in chain.iter() {
+            let opening = chain.opening_at(index).unwrap();
+            assert_eq!(opening.value, C::Scalar::from(10 + index as u64));
+            assert!(chain.params.verify(commitment, &opening).unwrap());
+            let (got, got_opening) = chain.get(index).unwrap();
+            assert_eq!((got, got_opening), (commitment, opening));
+        }
+        assert_eq!(chain.iter().count(), 4);
+        assert!(chain.get(4).is_none());
+        assert!(matches!(
+            chain.opening_at(4),
+            Err(ArchimedesError::AggregationError(AggregationErrorKind::IndexOutOfBounds { index: 4, len: 4 }))
+        ));
+
+        chain.clear_secrets();
+        assert!(chain.get(0).is_none());
+        assert!(matches!(
+            chain.opening_at(0),
+            Err(ArchimedesError::VerificationError(VerificationErrorKind::SecretsCleared))
+        ));
+        assert_eq!(chain.iter().count(), 4);
+    }
+
+    fn test_aggregate_serialization<C: CurveConfig>() {
+        let mut rng = test_rng();
+        let params = CommitmentParams::<C>::setup(&mut rng).unwrap();
+        let mut chain = CommitmentChain::new(params);
+        chain.extend_from_values(&[C::Scalar::from(3u64), C::Scalar::from(4u64)], &mut rng).unwrap();
+        let aggregate = chain.aggregate();
+
+        let mut bytes = Vec::new();
+        aggregate.serialize_compressed(&mut bytes).unwrap();
+        assert_eq!(bytes.len(), aggregate.compressed_size());
+        let decoded = AggregateCommitment::<C>::deserialize_compressed(&bytes[..]).unwrap();
+        assert_eq!((decoded.commitment, decoded.count), (aggregate.commitment.clone(), 2));
+        assert!(AggregateCommitment::<C>::deserialize_compressed(&bytes[..bytes.len() - 1]).is_err());
+
+        let json = serde_json::to_string(&aggregate).unwrap();
+        let decoded: AggregateCommitment<C> = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded.commitment, aggregate.commitment);
+        assert_eq!(decoded.count, 2);
+        assert!(serde_json::from_str::<AggregateCommitment<C>>(r#"{"commitment":"zz","count":1}"#).is_err());
+    }
+
+    #[cfg(target_pointer_width = "32")]
+    #[test]
+    fn test_aggregate_count_overflow_rejected() {
+        let mut bytes = Vec::new();
+        Commitment::<DefaultCurve>::zero().serialize_compressed(&mut bytes).unwrap();
+        (u64::from(u32::MAX) + 1).serialize_compressed(&mut bytes).unwrap();
+        assert!(AggregateCommitment::<DefaultCurve>::deserialize_compressed(&bytes[..]).is_err());
+    }
+
+    fn test_verify_aggregate_range<C: CurveConfig>() {
+        let mut rng = test_rng();
+        let params = CommitmentParams::<C>::setup(&mut rng).unwrap();
+        let mut chain = CommitmentChain::new(params);
+        for i in 1..=8u64 {
+            chain.push(C::Scalar::from(i), &mut rng).unwrap();
+        }
+        let full = chain.aggregate();
+        assert!(chain.verify_aggregate_range(0, 8, &full).unwrap());
+        assert!(chain.verify_aggregate(&full).unwrap());
+
+        let middle = chain.aggregate_range(2, 6).unwrap();
+        assert!(chain.verify_aggregate_range(2, 6, &middle).unwrap());
+        assert!(!chain.verify_aggregate_range(3, 7, &middle).unwrap());
+        assert!(!chain.verify_aggregate_range(2, 7, &middle).unwrap());
+        // Same count, shifted by one: the opening no longer matches.
+        let shifted = AggregateCommitment { count: 5, ..middle.clone() };
+        assert!(!chain.verify_aggregate_range(2, 7, &shifted).unwrap());
+
+        for (start, end) in [(4, 4), (0, 0), (5, 3), (0, 9)] {
+            assert!(matches!(
+                chain.verify_aggregate_range(start, end, &middle),
+                Err(ArchimedesError::AggregationError(AggregationErrorKind::InvalidRange { .. }))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_weighted_check_catches_cancelling_corruption() {
+        use crate::types::ScalarField;
+        use ark_ff::UniformRand;
+
+        let mut rng = test_rng();
+        let params = CommitmentParams::<DefaultCurve>::setup(&mut rng).unwrap();
+        let mut chain = CommitmentChain::new(params.clone());
+        let values: Vec<ScalarField> = (0..1000u64).map(ScalarField::from).collect();
+        chain.extend_from_values(&values, &mut rng).unwrap();
+
+        // Entry 17 now commits to a value 5 too high; entry 900 absorbs the
+        // difference so the plain sum still opens.
+        let delta = params.g * ScalarField::from(5u64);
+        chain.commitments[17].0 += delta;
+        chain.commitments[900].0 -= delta;
+        chain.refresh_aggregates();
+
+        let check = |coeffs: &[ScalarField]| {
+            let opening = Opening {
+                value: chain.weighted_value(coeffs).unwrap(),
+                randomness: chain.weighted_randomness(coeffs).unwrap(),
+            };
+            params.verify(&chain.weighted_aggregate(coeffs).unwrap().commitment, &opening).unwrap()
+        };
+        assert!(check(&vec![ScalarField::from(1u64); 1000]));
+        assert!(chain.verify_aggregate(&chain.aggregate()).unwrap());
+        let random: Vec<ScalarField> = (0..1000).map(|_| ScalarField::rand(&mut rng)).collect();
+        assert!(!check(&random));
+    }
+
+    #[test]
+    fn test_truncate_to_current_length_is_noop() {
+        let mut rng = test_rng();
+        let params = CommitmentParams::<DefaultCurve>::setup(&mut rng).unwrap();
+        let mut chain = CommitmentChain::new(params);
+        chain.truncate(0).unwrap();
+        for i in 0..3u64 {
+            chain.push(crate::types::ScalarField::from(i), &mut rng).unwrap();
+        }
+        let before = chain.aggregate();
+        chain.truncate(3).unwrap();
+        chain.truncate(7).unwrap();
+        assert_eq!(chain.len(), 3);
+        assert_eq!(chain.aggregate().commitment, before.commitment);
+        assert!(chain.verify_aggregate(&before).unwrap());
+    }
+
+    fn test_clear_secrets<C: CurveConfig>() {
+        let mut rng = test_rng();
+        let params = CommitmentParams::<C>::setup(&mut rng).unwrap();
+        let mut chain = CommitmentChain::new(params);
+        for i in 1..=4 {
+            chain.push(C::Scalar::from(i as u64), &mut rng).unwrap();
+        }
+        let before = chain.aggregate();
+        chain.clear_secrets();
+
+        assert!(chain.secrets_cleared());
+        assert!(chain.values.is_empty() && chain.randomness.is_empty());
+        assert_eq!(chain.len(), 4);
+        assert_eq!(chain.aggregate().commitment, before.commitment);
+        assert_eq!(chain.aggregate_range(1, 3).unwrap().count, 2);
+        assert!(matches!(
+            chain.push(C::Scalar::from(5u64), &mut rng),
+            Err(ArchimedesError::CommitmentError(_))
+        ));
+        assert!(chain.verify_aggregate(&before).is_err());
+        assert_eq!(chain.len(), 4);
+    }
+
+    fn test_from_commitments_matches_sequential_sum<C: CurveConfig>() {
+        let mut rng = test_rng();
+        let params = CommitmentParams::<C>::setup(&mut rng).unwrap();
+        let values: Vec<C::Scalar> = (0..1000u64).map(C::Scalar::from).collect();
+        let commitments: Vec<Commitment<C>> = params.commit_batch(&values, &mut rng).unwrap().into_iter().map(|(c, _)| c).collect();
+        for n in [0, 1, 2, 257, 1000] {
+            let sequential = commitments[..n].iter().fold(Commitment::zero(), |acc, c| acc.add(c));
+            let aggregate = AggregateCommitment::from_commitments(&commitments[..n]);
+            assert_eq!(aggregate.commitment.to_bytes(), sequential.to_bytes());
+            assert_eq!(aggregate.count, n);
+        }
+    }
+
+    #[test]
+    fn test_extend_from_values_large_chain() {
+        use crate::types::ScalarField;
+
+        let mut rng = test_rng();
+        let params = CommitmentParams::<DefaultCurve>::setup(&mut rng).unwrap();
+        let mut chain = CommitmentChain::new(params);
+        chain.push(ScalarField::from(7u64), &mut rng).unwrap();
+        let values: Vec<ScalarField> = (1..=10_000u64).map(ScalarField::from).collect();
+        chain.extend_from_values(&values, &mut rng).unwrap();
+
+        assert_eq!(chain.len(), 10_001);
+        assert_eq!(chain.metadata.len(), chain.len());
+        assert_eq!(chain.aggregate_value().unwrap(), ScalarField::from(7u64 + 10_000 * 10_001 / 2));
+        assert!(chain.verify_aggregate(&chain.aggregate()).unwrap());
+    }
+
+    #[test]
+    fn test_cached_aggregate_matches_recomputation() {
+        use crate::types::ScalarField;
+
+        let mut rng = test_rng();
+        let params = CommitmentParams::<DefaultCurve>::setup(&mut rng).unwrap();
+        let mut chain = CommitmentChain::new(params);
+        let values: Vec<ScalarField> = (0..9_990u64).map(ScalarField::from).collect();
+        chain.extend_from_values(&values, &mut rng).unwrap();
+        for i in 0..10u64 {
+            chain.push(ScalarField::from(i), &mut rng).unwrap();
+        }
+        assert_eq!(chain.len(), 10_000);
+
+        let scratch = AggregateCommitment::from_commitments(&chain.commitments);
+        assert_eq!(chain.aggregate().commitment, scratch.commitment);
+        assert_eq!(chain.aggregate().count, scratch.count);
+        assert_eq!(chain.aggregate_value().unwrap(), chain.values.iter().sum::<ScalarField>());
+        assert_eq!(chain.aggregate_randomness().unwrap(), chain.randomness.iter().sum());
+
+        chain.clear_secrets();
+        assert_eq!(chain.aggregate().commitment, scratch.commitment);
+        assert!(matches!(
+            chain.aggregate_value(),
+            Err(ArchimedesError::VerificationError(VerificationErrorKind::SecretsCleared))
+        ));
+        assert!(chain.aggregate_randomness().is_err());
+    }
+}
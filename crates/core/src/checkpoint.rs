@@ -0,0 +1,127 @@
+use crate::aggregation::{AggregateCommitment, CommitmentChain};
+use crate::commitment::{Commitment, CommitmentResult};
+use crate::curve::{CurveConfig, DefaultCurve};
+use crate::errors::{ArchimedesError, CommitmentErrorKind};
+
+/// A point a speculative chain can roll back to.
+#[derive(Clone, Debug)]
+pub struct ChainCheckpoint<C: CurveConfig = DefaultCurve> {
+    chain_id: u64,
+    len: usize,
+    aggregate: AggregateCommitment<C>,
+}
+
+impl<C: CurveConfig> ChainCheckpoint<C> {
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn aggregate(&self) -> &AggregateCommitment<C> {
+        &self.aggregate
+    }
+}
+
+impl<C: CurveConfig> CommitmentChain<C> {
+    pub fn checkpoint(&self) -> ChainCheckpoint<C> {
+        ChainCheckpoint {
+            chain_id: self.chain_id(),
+            len: self.len(),
+            aggregate: self.aggregate(),
+        }
+    }
+
+    /// Truncates back to `checkpoint`. The surviving prefix must still sum to
+    /// the checkpointed aggregate, which catches checkpoints replayed onto a
+    /// clone whose history has since diverged. Nothing changes on error.
+    pub fn rollback_to(&mut self, checkpoint: &ChainCheckpoint<C>) -> CommitmentResult<()> {
+        let error = |kind| Err(ArchimedesError::CommitmentError(kind));
+        if checkpoint.chain_id != self.chain_id() {
+            return error(CommitmentErrorKind::ForeignCheckpoint);
+        }
+        if checkpoint.len > self.len() {
+            return error(CommitmentErrorKind::CheckpointAhead { checkpoint: checkpoint.len, len: self.len() });
+        }
+        if self.secrets_cleared() {
+            return error(CommitmentErrorKind::SecretsCleared);
+        }
+        let dropped = Commitment::sum_iter(self.commitments()[checkpoint.len..].iter());
+        if self.aggregate().commitment - dropped != checkpoint.aggregate.commitment {
+            return error(CommitmentErrorKind::CheckpointDiverged);
+        }
+        self.truncate(checkpoint.len)
+    }
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod tests {
+    use super::*;
+    use crate::commitment::CommitmentParams;
+    use crate::curve::curve_tests;
+    use ark_std::test_rng;
+
+    curve_tests!(test_interleaved_checkpoints, test_rollback_rejects_bad_checkpoints);
+
+    fn test_interleaved_checkpoints<C: CurveConfig>() {
+        let mut rng = test_rng();
+        let mut chain = CommitmentChain::new(CommitmentParams::<C>::setup(&mut rng).unwrap());
+        chain.push(C::Scalar::from(1u64), &mut rng).unwrap();
+        let finalized = chain.checkpoint();
+
+        chain.push(C::Scalar::from(2u64), &mut rng).unwrap();
+        chain.push(C::Scalar::from(3u64), &mut rng).unwrap();
+        let speculative = chain.checkpoint();
+        chain.push(C::Scalar::from(4u64), &mut rng).unwrap();
+        chain.rollback_to(&speculative).unwrap();
+        assert_eq!(chain.len(), 3);
+        assert_eq!(chain.aggregate().commitment, speculative.aggregate().commitment);
+
+        chain.push(C::Scalar::from(5u64), &mut rng).unwrap();
+        chain.rollback_to(&finalized).unwrap();
+        assert_eq!(chain.len(), 1);
+        // Rolling back to the current length is a no-op.
+        chain.rollback_to(&finalized).unwrap();
+
+        chain.push(C::Scalar::from(6u64), &mut rng).unwrap();
+        assert_eq!(chain.aggregate_value().unwrap(), C::Scalar::from(7u64));
+        assert!(chain.verify_aggregate(&chain.aggregate()).unwrap());
+        // The rolled-back speculative checkpoint is now ahead of the chain.
+        assert!(chain.rollback_to(&speculative).is_err());
+    }
+
+    fn test_rollback_rejects_bad_checkpoints<C: CurveConfig>() {
+        let mut rng = test_rng();
+        let params = CommitmentParams::<C>::setup(&mut rng).unwrap();
+        let mut chain = CommitmentChain::new(params.clone());
+        let mut other = CommitmentChain::new(params);
+        for i in 0..3u64 {
+            chain.push(C::Scalar::from(i), &mut rng).unwrap();
+            other.push(C::Scalar::from(i), &mut rng).unwrap();
+        }
+        assert!(matches!(
+            chain.rollback_to(&other.checkpoint()),
+            Err(ArchimedesError::CommitmentError(CommitmentErrorKind::ForeignCheckpoint))
+        ));
+
+        let checkpoint = chain.checkpoint();
+        chain.pop();
+        assert!(matches!(
+            chain.rollback_to(&checkpoint),
+            Err(ArchimedesError::CommitmentError(CommitmentErrorKind::CheckpointAhead { checkpoint: 3, len: 2 }))
+        ));
+
+        let early = chain.checkpoint();
+        let mut fork = chain.clone();
+        fork.pop();
+        fork.push(C::Scalar::from(9u64), &mut rng).unwrap();
+        fork.push(C::Scalar::from(9u64), &mut rng).unwrap();
+        assert!(matches!(
+            fork.rollback_to(&early),
+            Err(ArchimedesError::CommitmentError(CommitmentErrorKind::CheckpointDiverged))
+        ));
+        assert_eq!(fork.len(), 3);
+    }
+}
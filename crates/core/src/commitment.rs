@@ -1,139 +1,605 @@
-use ark_ed_on_bls12_381::{EdwardsProjective as G, Fr as ScalarField};
+use ark_ec::CurveGroup;
+#[cfg(feature = "prover")]
 use ark_ff::UniformRand;
-use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_ff::{PrimeField, Zero};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, Valid};
+#[cfg(feature = "prover")]
 use ark_std::rand::Rng;
-use ark_std::Zero;
+use blake2::{Blake2b512, Digest};
+use subtle::{Choice, ConstantTimeEq};
 
-use crate::errors::ArchimedesError;
+use crate::curve::{hash_to_group, CurveConfig, DefaultCurve};
+use crate::errors::{ArchimedesError, SerializationErrorKind, SetupErrorKind};
 
 pub type CommitmentResult<T> = std::result::Result<T, ArchimedesError>;
 
+const DETERMINISTIC_BLINDING_DOMAIN: &[u8] = b"archimedes-deterministic-blinding-v1";
+const DOMAIN_SETUP_DOMAIN: &[u8] = b"archimedes-domain-setup-v1";
+/// Commitments per rayon task in `Commitment::sum_slice`.
+#[cfg(feature = "parallel")]
+const PARALLEL_SUM_CHUNK: usize = 256;
+
+/// Embeds a full `u128` as `low + high * 2^64`, without truncation.
+pub fn scalar_from_u128<F: PrimeField>(value: u128) -> F {
+    let low = F::from(value as u64);
+    let high = F::from((value >> 64) as u64);
+    let shift = F::from(u64::MAX) + F::one();
+    low + high * shift
+}
+
+/// Generators for one commitment scheme. `domain` labels which protocol the
+/// params belong to; the empty label is the legacy, undomained setup.
 #[derive(Clone, Debug, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
-pub struct CommitmentParams {
-    pub g: G,
-    pub h: G,
+pub struct CommitmentParams<C: CurveConfig = DefaultCurve> {
+    pub g: C::Group,
+    pub h: C::Group,
+    domain: String,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
-pub struct Commitment(pub G);
+pub struct Commitment<C: CurveConfig = DefaultCurve>(pub C::Group);
+
+/// A commitment in affine form, cheaper to serialize and hash repeatedly.
+#[derive(Clone, Debug, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct AffineCommitment<C: CurveConfig = DefaultCurve>(pub <C::Group as CurveGroup>::Affine);
 
 #[derive(Clone, Debug, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
-pub struct Randomness(pub ScalarField);
+pub struct Randomness<C: CurveConfig = DefaultCurve>(pub C::Scalar);
 
 #[derive(Clone, Debug, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
-pub struct Opening {
-    pub value: ScalarField,
-    pub randomness: Randomness,
+pub struct Opening<C: CurveConfig = DefaultCurve> {
+    pub value: C::Scalar,
+    pub randomness: Randomness<C>,
 }
 
-impl CommitmentParams {
-    pub fn setup<R: Rng>(rng: &mut R) -> CommitmentResult<Self> {
-        let g = G::rand(rng);
-        let h = G::rand(rng);
+impl<C: CurveConfig> CommitmentParams<C> {
+    /// Wraps existing generators without validating them.
+    pub fn from_generators(g: C::Group, h: C::Group) -> Self {
+        Self { g, h, domain: String::new() }
+    }
+
+    /// Nothing-up-my-sleeve generators hashed from `domain` alone. Every
+    /// party re-derives the same params without a trusted setup.
+    pub fn setup_deterministic_for_domain(domain: &str) -> CommitmentResult<Self> {
+        Self::derive_for_domain(domain, &[])
+    }
+
+    fn derive_for_domain(domain: &str, seed: &[u8]) -> CommitmentResult<Self> {
+        let label = [&(domain.len() as u64).to_le_bytes()[..], domain.as_bytes(), seed].concat();
+        let params = Self {
+            g: hash_to_group::<C>(DOMAIN_SETUP_DOMAIN, &label, 0),
+            h: hash_to_group::<C>(DOMAIN_SETUP_DOMAIN, &label, 1),
+            domain: domain.to_string(),
+        };
+        params.validate()?;
+        Ok(params)
+    }
+
+    pub(crate) fn with_domain(mut self, domain: String) -> Self {
+        self.domain = domain;
+        self
+    }
+
+    pub fn domain_tag(&self) -> &str {
+        &self.domain
+    }
 
-        if g == G::zero() || h == G::zero() {
-            return Err(ArchimedesError::SetupError(
-                "Generator points cannot be identity".to_string(),
-            ));
+    /// Fails with `DomainMismatch` unless these params belong to `domain`.
+    pub fn check_domain(&self, domain: &str) -> CommitmentResult<()> {
+        if self.domain != domain {
+            return Err(ArchimedesError::DomainMismatch {
+                expected: domain.to_string(),
+                found: self.domain.clone(),
+            });
         }
+        Ok(())
+    }
 
-        Ok(Self { g, h })
+    pub fn validate(&self) -> CommitmentResult<()> {
+        if self.g.is_zero() || self.h.is_zero() {
+            return Err(ArchimedesError::SetupError(SetupErrorKind::IdentityGenerator));
+        }
+        if self.g == self.h {
+            return Err(ArchimedesError::SetupError(SetupErrorKind::EqualGenerators));
+        }
+        if !Commitment::<C>(self.g).is_valid() || !Commitment::<C>(self.h).is_valid() {
+            return Err(ArchimedesError::SetupError(SetupErrorKind::GeneratorNotInSubgroup));
+        }
+        Ok(())
     }
 
-    pub fn commit<R: Rng>(&self, value: &ScalarField, rng: &mut R) -> CommitmentResult<(Commitment, Randomness)> {
-        let r = ScalarField::rand(rng);
-        let commitment = self.commit_with_randomness(value, &Randomness(r.clone()))?;
-        Ok((commitment, Randomness(r)))
+    /// Commits with a blinding factor hashed from `seed` and `value`, so the
+    /// same seed always re-derives the same opening. Hiding rests entirely on
+    /// the seed staying secret and not being reused across unrelated chains.
+    pub fn commit_deterministic(&self, value: &C::Scalar, seed: &[u8; 32]) -> CommitmentResult<(Commitment<C>, Randomness<C>)> {
+        let mut value_bytes = Vec::new();
+        value.serialize_compressed(&mut value_bytes)?;
+        let mut hasher = Blake2b512::new();
+        hasher.update(DETERMINISTIC_BLINDING_DOMAIN);
+        hasher.update(C::CURVE_ID.as_bytes());
+        hasher.update(seed);
+        hasher.update(&value_bytes);
+        let randomness = Randomness(C::Scalar::from_le_bytes_mod_order(&hasher.finalize()));
+        Ok((self.commit_with_randomness(value, &randomness)?, randomness))
     }
 
-    pub fn commit_with_randomness(&self, value: &ScalarField, randomness: &Randomness) -> CommitmentResult<Commitment> {
+    pub fn commit_with_randomness(&self, value: &C::Scalar, randomness: &Randomness<C>) -> CommitmentResult<Commitment<C>> {
         let c = self.g * value + self.h * randomness.0;
         Ok(Commitment(c))
     }
 
-    pub fn verify(&self, commitment: &Commitment, opening: &Opening) -> CommitmentResult<bool> {
+    /// The final comparison is constant-time (see `Commitment::ct_eq`). The
+    /// scalar multiplications before it are arkworks', which make no
+    /// constant-time guarantee.
+    pub fn verify(&self, commitment: &Commitment<C>, opening: &Opening<C>) -> CommitmentResult<bool> {
         let expected = self.commit_with_randomness(&opening.value, &opening.randomness)?;
-        Ok(commitment.0 == expected.0)
+        Ok(commitment.ct_eq(&expected).into())
+    }
+
+    /// Verifies every pair without stopping at the first failure, so timing
+    /// does not reveal which opening was wrong. Only the overall result is
+    /// returned.
+    pub fn verify_batch(&self, commitments: &[Commitment<C>], openings: &[Opening<C>]) -> CommitmentResult<bool> {
+        if commitments.len() != openings.len() {
+            return Ok(false);
+        }
+        let mut valid = Choice::from(1);
+        for (commitment, opening) in commitments.iter().zip(openings) {
+            valid &= commitment.ct_eq(&self.commit_with_randomness(&opening.value, &opening.randomness)?);
+        }
+        Ok(valid.into())
+    }
+
+    /// `verify` for a commitment the caller produced under `domain`.
+    pub fn verify_in_domain(&self, domain: &str, commitment: &Commitment<C>, opening: &Opening<C>) -> CommitmentResult<bool> {
+        self.check_domain(domain)?;
+        self.verify(commitment, opening)
+    }
+}
+
+#[cfg(feature = "prover")]
+impl<C: CurveConfig> CommitmentParams<C> {
+    pub fn setup<R: Rng>(rng: &mut R) -> CommitmentResult<Self> {
+        let g = C::Group::rand(rng);
+        let h = C::Group::rand(rng);
+        let params = Self::from_generators(g, h);
+        params.validate()?;
+        Ok(params)
+    }
+
+    /// Generators hashed from `domain` and a fresh seed, so params set up for
+    /// one protocol can never coincide with another protocol's.
+    pub fn setup_for_domain<R: Rng>(rng: &mut R, domain: &str) -> CommitmentResult<Self> {
+        let mut seed = [0u8; 32];
+        rng.fill_bytes(&mut seed);
+        Self::derive_for_domain(domain, &seed)
+    }
+
+    pub fn commit<R: Rng>(&self, value: &C::Scalar, rng: &mut R) -> CommitmentResult<(Commitment<C>, Randomness<C>)> {
+        let r = C::Scalar::rand(rng);
+        let commitment = self.commit_with_randomness(value, &Randomness(r))?;
+        Ok((commitment, Randomness(r)))
+    }
+
+    /// Commits to every value. Randomness is drawn serially from `rng`, so the
+    /// output is the same with or without the `parallel` feature.
+    pub fn commit_batch<R: Rng>(&self, values: &[C::Scalar], rng: &mut R) -> CommitmentResult<Vec<(Commitment<C>, Randomness<C>)>> {
+        let randomness: Vec<Randomness<C>> = values.iter().map(|_| Randomness(C::Scalar::rand(rng))).collect();
+        let commit_one = |(value, r): (&C::Scalar, Randomness<C>)| Ok((self.commit_with_randomness(value, &r)?, r));
+
+        #[cfg(feature = "parallel")]
+        {
+            use rayon::prelude::*;
+            values.par_iter().zip(randomness.into_par_iter()).map(commit_one).collect()
+        }
+        #[cfg(not(feature = "parallel"))]
+        {
+            values.iter().zip(randomness).map(commit_one).collect()
+        }
+    }
+
+    pub fn commit_u128<R: Rng>(&self, value: u128, rng: &mut R) -> CommitmentResult<(Commitment<C>, Randomness<C>)> {
+        self.commit(&scalar_from_u128(value), rng)
+    }
+
+    /// Blinds `c` with fresh randomness. The returned delta, applied with
+    /// [`Opening::adjust`], turns an opening of `c` into one of the result.
+    pub fn rerandomize<R: Rng>(&self, c: &Commitment<C>, rng: &mut R) -> (Commitment<C>, Randomness<C>) {
+        let delta = C::Scalar::rand(rng);
+        (Commitment(c.0 + self.h * delta), Randomness(delta))
     }
 }
 
-impl Commitment {
+impl<C: CurveConfig> Commitment<C> {
     pub fn zero() -> Self {
-        Commitment(G::zero())
+        Commitment(C::Group::zero())
     }
 
-    pub fn add(&self, other: &Commitment) -> Commitment {
+    pub fn add(&self, other: &Commitment<C>) -> Commitment<C> {
         Commitment(self.0 + other.0)
     }
+
+    pub fn sub(&self, other: &Commitment<C>) -> Commitment<C> {
+        Commitment(self.0 - other.0)
+    }
+
+    /// Sums in projective form. Normalizing to affine first for mixed
+    /// additions was measured slower on Edwards curves, since the batch
+    /// inversion costs more than the additions save. With the `parallel`
+    /// feature the sum is split across rayon workers.
+    pub fn sum_iter<'a>(commitments: impl Iterator<Item = &'a Commitment<C>>) -> Commitment<C> {
+        #[cfg(feature = "parallel")]
+        {
+            let commitments: Vec<Commitment<C>> = commitments.cloned().collect();
+            Self::sum_slice(&commitments)
+        }
+        #[cfg(not(feature = "parallel"))]
+        Commitment(commitments.map(|c| c.0).sum())
+    }
+
+    /// Like `sum_iter`, without the copy. With the `parallel` feature each
+    /// chunk is summed on its own worker and the partial sums are merged.
+    pub fn sum_slice(commitments: &[Commitment<C>]) -> Commitment<C> {
+        #[cfg(feature = "parallel")]
+        let sum = {
+            use rayon::prelude::*;
+            commitments
+                .par_chunks(PARALLEL_SUM_CHUNK)
+                .map(|chunk| chunk.iter().map(|c| c.0).sum::<C::Group>())
+                .reduce(C::Group::zero, |a, b| a + b)
+        };
+        #[cfg(not(feature = "parallel"))]
+        let sum = commitments.iter().map(|c| c.0).sum::<C::Group>();
+        Commitment(sum)
+    }
+
+    /// Constant-time identity check on the canonical compressed encoding.
+    /// Normalizing to affine is not itself constant-time in arkworks, but the
+    /// byte comparison does not exit early.
+    pub fn ct_is_identity(&self) -> Choice {
+        let mut bytes = Vec::with_capacity(self.compressed_size());
+        self.0.serialize_compressed(&mut bytes).expect("serializing to a Vec cannot fail");
+        let mut identity = Vec::with_capacity(bytes.len());
+        C::Group::zero().serialize_compressed(&mut identity).expect("serializing to a Vec cannot fail");
+        bytes.ct_eq(&identity)
+    }
+
+    /// Checks the point is on the curve and in the prime-order subgroup.
+    /// Small-order components would let an aggregate match under two
+    /// different openings.
+    pub fn is_valid(&self) -> bool {
+        self.0.check().is_ok()
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.compressed_size());
+        self.0.serialize_compressed(&mut bytes).expect("serializing to a Vec cannot fail");
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> CommitmentResult<Self> {
+        let point = C::Group::deserialize_compressed_unchecked(bytes)?;
+        let commitment = Commitment(point);
+        if !commitment.is_valid() {
+            return Err(ArchimedesError::serialization(SerializationErrorKind::NotInSubgroup));
+        }
+        Ok(commitment)
+    }
+
+    pub fn to_hex(&self) -> String {
+        encode_hex(&self.to_bytes())
+    }
+
+    pub fn from_hex(s: &str) -> CommitmentResult<Self> {
+        Self::from_bytes(&decode_hex(s)?)
+    }
+}
+
+pub(crate) fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub(crate) fn decode_hex(s: &str) -> CommitmentResult<Vec<u8>> {
+    if !s.len().is_multiple_of(2) || !s.is_ascii() {
+        return Err(ArchimedesError::serialization(SerializationErrorKind::InvalidHex));
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16))
+        .collect::<Result<Vec<u8>, _>>()
+        .map_err(|_| ArchimedesError::serialization(SerializationErrorKind::InvalidHex))
+}
+
+impl<C: CurveConfig> Commitment<C> {
+    pub fn to_affine(&self) -> AffineCommitment<C> {
+        AffineCommitment(self.0.into_affine())
+    }
+
+    /// Normalizes all commitments with a single batched inversion.
+    pub fn batch_to_affine(commitments: &[Commitment<C>]) -> Vec<AffineCommitment<C>> {
+        let points: Vec<C::Group> = commitments.iter().map(|c| c.0).collect();
+        C::Group::normalize_batch(&points).into_iter().map(AffineCommitment).collect()
+    }
+}
+
+impl<C: CurveConfig> AffineCommitment<C> {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.compressed_size());
+        self.0.serialize_compressed(&mut bytes).expect("serializing to a Vec cannot fail");
+        bytes
+    }
+}
+
+impl<C: CurveConfig> From<AffineCommitment<C>> for Commitment<C> {
+    fn from(affine: AffineCommitment<C>) -> Self {
+        Commitment(affine.0.into())
+    }
+}
+
+impl<C: CurveConfig> std::fmt::Display for Commitment<C> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.to_hex())
+    }
 }
 
-impl std::ops::Add for Commitment {
+impl<C: CurveConfig> std::str::FromStr for Commitment<C> {
+    type Err = ArchimedesError;
+    fn from_str(s: &str) -> CommitmentResult<Self> {
+        Self::from_hex(s)
+    }
+}
+
+impl<C: CurveConfig> std::ops::Add for Commitment<C> {
     type Output = Self;
     fn add(self, other: Self) -> Self {
         Commitment(self.0 + other.0)
     }
 }
 
-impl std::ops::Add<&Commitment> for &Commitment {
-    type Output = Commitment;
-    fn add(self, other: &Commitment) -> Commitment {
+impl<C: CurveConfig> std::ops::Add<&Commitment<C>> for &Commitment<C> {
+    type Output = Commitment<C>;
+    fn add(self, other: &Commitment<C>) -> Commitment<C> {
         Commitment(self.0 + other.0)
     }
 }
 
-impl Randomness {
+/// Hashes the canonical compressed encoding, so equal points hash equally
+/// whatever their projective representation.
+impl<C: CurveConfig> std::hash::Hash for Commitment<C> {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.to_bytes().hash(state);
+    }
+}
+
+/// Lexicographic order over the canonical compressed encoding. It exists so
+/// commitments can key ordered collections and means nothing algebraically.
+impl<C: CurveConfig> Ord for Commitment<C> {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.to_bytes().cmp(&other.to_bytes())
+    }
+}
+
+impl<C: CurveConfig> PartialOrd for Commitment<C> {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Equality via a constant-time identity check on the difference.
+impl<C: CurveConfig> ConstantTimeEq for Commitment<C> {
+    fn ct_eq(&self, other: &Self) -> Choice {
+        Commitment::<C>(self.0 - other.0).ct_is_identity()
+    }
+}
+
+impl<C: CurveConfig> std::ops::AddAssign<&Commitment<C>> for Commitment<C> {
+    fn add_assign(&mut self, other: &Commitment<C>) {
+        self.0 += other.0;
+    }
+}
+
+impl<C: CurveConfig> std::ops::AddAssign for Commitment<C> {
+    fn add_assign(&mut self, other: Self) {
+        self.0 += other.0;
+    }
+}
+
+impl<C: CurveConfig> std::iter::Sum for Commitment<C> {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        Commitment(iter.map(|c| c.0).sum())
+    }
+}
+
+impl<'a, C: CurveConfig> std::iter::Sum<&'a Commitment<C>> for Commitment<C> {
+    fn sum<I: Iterator<Item = &'a Commitment<C>>>(iter: I) -> Self {
+        Commitment(iter.map(|c| c.0).sum())
+    }
+}
+
+impl<C: CurveConfig> std::ops::Neg for Commitment<C> {
+    type Output = Self;
+    fn neg(self) -> Self {
+        Commitment(-self.0)
+    }
+}
+
+impl<C: CurveConfig> std::ops::Neg for &Commitment<C> {
+    type Output = Commitment<C>;
+    fn neg(self) -> Commitment<C> {
+        Commitment(-self.0)
+    }
+}
+
+impl<C: CurveConfig> std::ops::Sub for Commitment<C> {
+    type Output = Self;
+    fn sub(self, other: Self) -> Self {
+        Commitment(self.0 - other.0)
+    }
+}
+
+impl<C: CurveConfig> std::ops::Sub<&Commitment<C>> for &Commitment<C> {
+    type Output = Commitment<C>;
+    fn sub(self, other: &Commitment<C>) -> Commitment<C> {
+        Commitment(self.0 - other.0)
+    }
+}
+
+impl<C: CurveConfig> std::ops::SubAssign<&Commitment<C>> for Commitment<C> {
+    fn sub_assign(&mut self, other: &Commitment<C>) {
+        self.0 -= other.0;
+    }
+}
+
+impl<C: CurveConfig> Randomness<C> {
     pub fn zero() -> Self {
-        Randomness(ScalarField::from(0u64))
+        Randomness(C::Scalar::zero())
     }
 
-    pub fn add(&self, other: &Randomness) -> Randomness {
+    pub fn add(&self, other: &Randomness<C>) -> Randomness<C> {
         Randomness(self.0 + other.0)
     }
 }
 
-impl std::ops::Add for Randomness {
+impl<C: CurveConfig> Opening<C> {
+    pub fn adjust(&self, delta: &Randomness<C>) -> Opening<C> {
+        Opening {
+            value: self.value,
+            randomness: self.randomness.add(delta),
+        }
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl<C: CurveConfig> zeroize::Zeroize for Randomness<C> {
+    fn zeroize(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl<C: CurveConfig> Drop for Randomness<C> {
+    fn drop(&mut self) {
+        zeroize::Zeroize::zeroize(self);
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl<C: CurveConfig> zeroize::ZeroizeOnDrop for Randomness<C> {}
+
+#[cfg(feature = "zeroize")]
+impl<C: CurveConfig> zeroize::Zeroize for Opening<C> {
+    fn zeroize(&mut self) {
+        self.value.zeroize();
+        self.randomness.zeroize();
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl<C: CurveConfig> Drop for Opening<C> {
+    fn drop(&mut self) {
+        zeroize::Zeroize::zeroize(self);
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl<C: CurveConfig> zeroize::ZeroizeOnDrop for Opening<C> {}
+
+impl<C: CurveConfig> std::ops::Add for Randomness<C> {
     type Output = Self;
     fn add(self, other: Self) -> Self {
         Randomness(self.0 + other.0)
     }
 }
 
-impl std::ops::Add<&Randomness> for &Randomness {
-    type Output = Randomness;
-    fn add(self, other: &Randomness) -> Randomness {
+impl<C: CurveConfig> std::ops::Add<&Randomness<C>> for &Randomness<C> {
+    type Output = Randomness<C>;
+    fn add(self, other: &Randomness<C>) -> Randomness<C> {
         Randomness(self.0 + other.0)
     }
 }
 
-#[cfg(test)]
+impl<C: CurveConfig> std::ops::AddAssign<&Randomness<C>> for Randomness<C> {
+    fn add_assign(&mut self, other: &Randomness<C>) {
+        self.0 += other.0;
+    }
+}
+
+impl<C: CurveConfig> std::ops::AddAssign for Randomness<C> {
+    fn add_assign(&mut self, other: Self) {
+        self.0 += other.0;
+    }
+}
+
+impl<C: CurveConfig> std::ops::SubAssign<&Randomness<C>> for Randomness<C> {
+    fn sub_assign(&mut self, other: &Randomness<C>) {
+        self.0 -= other.0;
+    }
+}
+
+impl<C: CurveConfig> std::iter::Sum for Randomness<C> {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Randomness::zero(), |mut acc, r| {
+            acc += r;
+            acc
+        })
+    }
+}
+
+impl<'a, C: CurveConfig> std::iter::Sum<&'a Randomness<C>> for Randomness<C> {
+    fn sum<I: Iterator<Item = &'a Randomness<C>>>(iter: I) -> Self {
+        Randomness(iter.map(|r| r.0).sum())
+    }
+}
+
+#[cfg(all(test, feature = "prover"))]
 mod tests {
     use super::*;
+    use crate::curve::curve_tests;
+    use ark_std::rand::SeedableRng;
     use ark_std::test_rng;
 
-    #[test]
-    fn test_commitment_setup() {
+    curve_tests!(
+        test_commitment_setup,
+        test_commit_and_verify,
+        test_commitment_binding,
+        test_commitment_homomorphism,
+        test_commitment_hex_round_trip,
+        test_commitment_from_hex_rejects_bad_input,
+        test_commitment_subtraction,
+        test_rerandomize_chain,
+        test_params_validate,
+        test_commit_u128,
+        test_commit_batch_matches_serial,
+        test_batch_to_affine_round_trip,
+        test_sum_traits,
+        test_commit_deterministic,
+        test_domain_separated_setup,
+        test_ct_eq_matches_eq,
+        test_verify_batch,
+        test_hash_and_ord_follow_encoding,
+    );
+
+    fn test_commitment_setup<C: CurveConfig>() {
         let mut rng = test_rng();
-        let params = CommitmentParams::setup(&mut rng).unwrap();
-        assert_ne!(params.g, G::zero());
-        assert_ne!(params.h, G::zero());
+        let params = CommitmentParams::<C>::setup(&mut rng).unwrap();
+        assert_ne!(params.g, C::Group::zero());
+        assert_ne!(params.h, C::Group::zero());
     }
 
-    #[test]
-    fn test_commit_and_verify() {
+    fn test_commit_and_verify<C: CurveConfig>() {
         let mut rng = test_rng();
-        let params = CommitmentParams::setup(&mut rng).unwrap();
-        let value = ScalarField::from(42u64);
+        let params = CommitmentParams::<C>::setup(&mut rng).unwrap();
+        let value = C::Scalar::from(42u64);
         let (commitment, randomness) = params.commit(&value, &mut rng).unwrap();
         let opening = Opening { value, randomness };
         assert!(params.verify(&commitment, &opening).unwrap());
     }
 
-    #[test]
-    fn test_commitment_binding() {
+    fn test_commitment_binding<C: CurveConfig>() {
         let mut rng = test_rng();
-        let params = CommitmentParams::setup(&mut rng).unwrap();
-        let v1 = ScalarField::from(100u64);
-        let v2 = ScalarField::from(200u64);
+        let params = CommitmentParams::<C>::setup(&mut rng).unwrap();
+        let v1 = C::Scalar::from(100u64);
+        let v2 = C::Scalar::from(200u64);
         let (c1, r1) = params.commit(&v1, &mut rng).unwrap();
         let (c2, _) = params.commit(&v2, &mut rng).unwrap();
         assert_ne!(c1.0, c2.0);
@@ -141,12 +607,11 @@ mod tests {
         assert!(!params.verify(&c1, &wrong_opening).unwrap());
     }
 
-    #[test]
-    fn test_commitment_homomorphism() {
+    fn test_commitment_homomorphism<C: CurveConfig>() {
         let mut rng = test_rng();
-        let params = CommitmentParams::setup(&mut rng).unwrap();
-        let v1 = ScalarField::from(10u64);
-        let v2 = ScalarField::from(20u64);
+        let params = CommitmentParams::<C>::setup(&mut rng).unwrap();
+        let v1 = C::Scalar::from(10u64);
+        let v2 = C::Scalar::from(20u64);
         let (c1, r1) = params.commit(&v1, &mut rng).unwrap();
         let (c2, r2) = params.commit(&v2, &mut rng).unwrap();
         let c_sum = &c1 + &c2;
@@ -155,5 +620,276 @@ mod tests {
         let opening = Opening { value: v_sum, randomness: r_sum };
         assert!(params.verify(&c_sum, &opening).unwrap());
     }
-}
 
+    fn test_commit_deterministic<C: CurveConfig>() {
+        let mut rng = test_rng();
+        let params = CommitmentParams::<C>::setup(&mut rng).unwrap();
+        let value = C::Scalar::from(77u64);
+        let (c1, r1) = params.commit_deterministic(&value, &[1u8; 32]).unwrap();
+        let (c2, r2) = params.commit_deterministic(&value, &[1u8; 32]).unwrap();
+        assert_eq!((&c1, &r1), (&c2, &r2));
+
+        let (other_seed, _) = params.commit_deterministic(&value, &[2u8; 32]).unwrap();
+        let (_, other_value_r) = params.commit_deterministic(&C::Scalar::from(78u64), &[1u8; 32]).unwrap();
+        assert_ne!(c1, other_seed);
+        assert_ne!(r1, other_value_r);
+        assert!(params.verify(&c1, &Opening { value, randomness: r1 }).unwrap());
+    }
+
+    fn test_domain_separated_setup<C: CurveConfig>() {
+        let mut rng = test_rng();
+        let stake = CommitmentParams::<C>::setup_deterministic_for_domain("archimedes/stake").unwrap();
+        assert_eq!(stake, CommitmentParams::<C>::setup_deterministic_for_domain("archimedes/stake").unwrap());
+        assert_eq!(stake.domain_tag(), "archimedes/stake");
+        let shards = CommitmentParams::<C>::setup_deterministic_for_domain("archimedes/shards").unwrap();
+        assert!(stake.g != shards.g && stake.h != shards.h);
+        let random = CommitmentParams::<C>::setup_for_domain(&mut rng, "archimedes/stake").unwrap();
+        assert_eq!(random.domain_tag(), "archimedes/stake");
+        assert_ne!(random.g, stake.g);
+
+        let value = C::Scalar::from(12u64);
+        let (c, r) = stake.commit(&value, &mut rng).unwrap();
+        let opening = Opening { value, randomness: r };
+        assert!(stake.verify_in_domain("archimedes/stake", &c, &opening).unwrap());
+        assert!(matches!(
+            shards.verify_in_domain("archimedes/stake", &c, &opening),
+            Err(ArchimedesError::DomainMismatch { .. })
+        ));
+        assert!(matches!(
+            CommitmentParams::<C>::setup(&mut rng).unwrap().check_domain("archimedes/stake"),
+            Err(ArchimedesError::DomainMismatch { .. })
+        ));
+    }
+
+    fn test_ct_eq_matches_eq<C: CurveConfig>() {
+        let mut rng = test_rng();
+        let params = CommitmentParams::<C>::setup(&mut rng).unwrap();
+        let (c, _) = params.commit(&C::Scalar::from(3u64), &mut rng).unwrap();
+        let (d, _) = params.commit(&C::Scalar::from(3u64), &mut rng).unwrap();
+        // Same point reached through a different projective representation.
+        let roundabout = &(&(&c + &d) + &c) - &(&c + &d);
+        let points = [c.clone(), d, -c.clone(), roundabout, Commitment::zero(), Commitment(params.g)];
+        for a in &points {
+            for b in &points {
+                assert_eq!(bool::from(a.ct_eq(b)), a == b);
+            }
+            assert_eq!(bool::from(a.ct_is_identity()), *a == Commitment::zero());
+        }
+        assert!(bool::from(c.ct_eq(&points[3])));
+    }
+
+    fn test_hash_and_ord_follow_encoding<C: CurveConfig>() {
+        use std::collections::hash_map::DefaultHasher;
+        use std::hash::{Hash, Hasher};
+        let hash = |c: &Commitment<C>| {
+            let mut hasher = DefaultHasher::new();
+            c.hash(&mut hasher);
+            hasher.finish()
+        };
+        let mut rng = test_rng();
+        let params = CommitmentParams::<C>::setup(&mut rng).unwrap();
+        let (c, _) = params.commit(&C::Scalar::from(5u64), &mut rng).unwrap();
+        let (d, _) = params.commit(&C::Scalar::from(6u64), &mut rng).unwrap();
+        let roundabout = &(&c + &d) - &d;
+        assert_eq!(hash(&c), hash(&roundabout));
+        assert_eq!(c.cmp(&roundabout), std::cmp::Ordering::Equal);
+        assert_eq!(c.cmp(&d), c.to_bytes().cmp(&d.to_bytes()));
+        assert_eq!(c.cmp(&d).reverse(), d.cmp(&c));
+    }
+
+    fn test_verify_batch<C: CurveConfig>() {
+        let mut rng = test_rng();
+        let params = CommitmentParams::<C>::setup(&mut rng).unwrap();
+        let values: Vec<C::Scalar> = (1..=4u64).map(C::Scalar::from).collect();
+        let (commitments, randomness): (Vec<_>, Vec<_>) = params.commit_batch(&values, &mut rng).unwrap().into_iter().unzip();
+        let mut openings: Vec<Opening<C>> = values.iter().zip(randomness).map(|(v, r)| Opening { value: *v, randomness: r }).collect();
+        assert!(params.verify_batch(&commitments, &openings).unwrap());
+        assert!(params.verify_batch(&[], &[]).unwrap());
+        assert!(!params.verify_batch(&commitments[..3], &openings).unwrap());
+
+        openings[2].value += C::Scalar::from(1u64);
+        assert!(!params.verify_batch(&commitments, &openings).unwrap());
+        for (c, o) in commitments.iter().zip(&openings) {
+            let expected = params.commit_with_randomness(&o.value, &o.randomness).unwrap();
+            assert_eq!(params.verify(c, o).unwrap(), *c == expected);
+        }
+    }
+
+    fn test_sum_traits<C: CurveConfig>() {
+        let mut rng = test_rng();
+        let params = CommitmentParams::<C>::setup(&mut rng).unwrap();
+        let values: Vec<C::Scalar> = (1..=5u64).map(C::Scalar::from).collect();
+        let (commitments, randomness): (Vec<_>, Vec<_>) = params.commit_batch(&values, &mut rng).unwrap().into_iter().unzip();
+
+        let mut c_acc = Commitment::zero();
+        let mut r_acc = Randomness::zero();
+        for (c, r) in commitments.iter().zip(&randomness) {
+            c_acc += c;
+            r_acc += r;
+        }
+        assert_eq!(commitments.iter().sum::<Commitment<C>>(), c_acc);
+        assert_eq!(commitments.clone().into_iter().sum::<Commitment<C>>(), c_acc);
+        assert_eq!(Commitment::sum_iter(commitments.iter()), c_acc);
+        assert_eq!(randomness.iter().sum::<Randomness<C>>(), r_acc);
+        assert_eq!(randomness.clone().into_iter().sum::<Randomness<C>>(), r_acc);
+
+        let opening = Opening { value: values.iter().sum(), randomness: r_acc };
+        assert!(params.verify(&c_acc, &opening).unwrap());
+        assert_eq!(Commitment::<C>::sum_iter(std::iter::empty()), Commitment::zero());
+    }
+
+    fn test_commitment_hex_round_trip<C: CurveConfig>() {
+        let mut rng = test_rng();
+        let params = CommitmentParams::<C>::setup(&mut rng).unwrap();
+        let (c, _) = params.commit(&C::Scalar::from(99u64), &mut rng).unwrap();
+        for commitment in [c, Commitment::zero()] {
+            let hex = commitment.to_hex();
+            assert_eq!(hex.len(), 2 * commitment.to_bytes().len());
+            assert_eq!(Commitment::<C>::from_hex(&hex).unwrap(), commitment);
+            assert_eq!(Commitment::<C>::from_bytes(&commitment.to_bytes()).unwrap(), commitment);
+            assert_eq!(commitment.to_string().parse::<Commitment<C>>().unwrap(), commitment);
+        }
+    }
+
+    fn test_commitment_from_hex_rejects_bad_input<C: CurveConfig>() {
+        assert!(matches!(Commitment::<C>::from_hex("abc"), Err(ArchimedesError::SerializationError { .. })));
+        assert!(matches!(Commitment::<C>::from_hex("zz"), Err(ArchimedesError::SerializationError { .. })));
+        assert!(matches!(Commitment::<C>::from_hex(""), Err(ArchimedesError::SerializationError { .. })));
+        let not_a_point = "ff".repeat(Commitment::<C>::zero().to_bytes().len());
+        assert!(matches!(Commitment::<C>::from_hex(&not_a_point), Err(ArchimedesError::SerializationError { .. })));
+    }
+
+    fn test_commitment_subtraction<C: CurveConfig>() {
+        let mut rng = test_rng();
+        let params = CommitmentParams::<C>::setup(&mut rng).unwrap();
+        let v1 = C::Scalar::from(50u64);
+        let v2 = C::Scalar::from(20u64);
+        let (c1, r1) = params.commit(&v1, &mut rng).unwrap();
+        let (c2, r2) = params.commit(&v2, &mut rng).unwrap();
+        let c_diff = &c1 - &c2;
+        let opening = Opening { value: v1 - v2, randomness: Randomness(r1.0 - r2.0) };
+        assert!(params.verify(&c_diff, &opening).unwrap());
+        assert_eq!(&c_diff + &c2, c1);
+        assert_eq!(c1.clone() + (-c1.clone()), Commitment::zero());
+        assert_eq!(c1.clone() - c2.clone(), c1.sub(&c2));
+    }
+
+    fn test_rerandomize_chain<C: CurveConfig>() {
+        let mut rng = test_rng();
+        let params = CommitmentParams::<C>::setup(&mut rng).unwrap();
+        let value = C::Scalar::from(314u64);
+        let (c0, randomness) = params.commit(&value, &mut rng).unwrap();
+        let opening0 = Opening { value, randomness };
+
+        let (c1, d1) = params.rerandomize(&c0, &mut rng);
+        let opening1 = opening0.adjust(&d1);
+        let (c2, d2) = params.rerandomize(&c1, &mut rng);
+        let opening2 = opening1.adjust(&d2);
+
+        assert_ne!(c0, c1);
+        assert_ne!(c1, c2);
+        assert_ne!(c0, c2);
+        assert!(params.verify(&c1, &opening1).unwrap());
+        assert!(params.verify(&c2, &opening2).unwrap());
+        assert!(!params.verify(&c2, &opening0).unwrap());
+        assert_eq!(opening2.value, value);
+    }
+
+    fn test_params_validate<C: CurveConfig>() {
+        let mut rng = test_rng();
+        let params = CommitmentParams::<C>::setup(&mut rng).unwrap();
+        params.validate().unwrap();
+        let equal = CommitmentParams::<C>::from_generators(params.g, params.g);
+        assert!(matches!(equal.validate(), Err(ArchimedesError::SetupError(_))));
+        let identity = CommitmentParams::<C>::from_generators(params.g, C::Group::zero());
+        assert!(identity.validate().is_err());
+    }
+
+    fn test_commit_u128<C: CurveConfig>() {
+        let mut rng = test_rng();
+        let params = CommitmentParams::<C>::setup(&mut rng).unwrap();
+        let value = u128::from(u64::MAX) * 3 + 17;
+        let expected = C::Scalar::from(u64::MAX) * C::Scalar::from(3u64) + C::Scalar::from(17u64);
+        assert_eq!(scalar_from_u128::<C::Scalar>(value), expected);
+        assert_eq!(scalar_from_u128::<C::Scalar>(u128::from(u64::MAX) + 1), C::Scalar::from(u64::MAX) + C::Scalar::from(1u64));
+        assert_eq!(scalar_from_u128::<C::Scalar>(42), C::Scalar::from(42u64));
+
+        let (commitment, randomness) = params.commit_u128(value, &mut rng).unwrap();
+        let truncated = Opening { value: C::Scalar::from(value as u64), randomness: randomness.clone() };
+        assert!(!params.verify(&commitment, &truncated).unwrap());
+        let opening = Opening { value: expected, randomness };
+        assert!(params.verify(&commitment, &opening).unwrap());
+
+        let max = scalar_from_u128::<C::Scalar>(u128::MAX);
+        assert_eq!(max + C::Scalar::from(1u64), scalar_from_u128::<C::Scalar>(1u128 << 127) * C::Scalar::from(2u64));
+    }
+
+    fn test_commit_batch_matches_serial<C: CurveConfig>() {
+        let mut rng = test_rng();
+        let params = CommitmentParams::<C>::setup(&mut rng).unwrap();
+        let values: Vec<C::Scalar> = (0..64u64).map(C::Scalar::from).collect();
+        let batch = params.commit_batch(&values, &mut ark_std::rand::rngs::StdRng::seed_from_u64(3)).unwrap();
+
+        let mut serial_rng = ark_std::rand::rngs::StdRng::seed_from_u64(3);
+        for (value, (commitment, randomness)) in values.iter().zip(&batch) {
+            let (expected, expected_r) = params.commit(value, &mut serial_rng).unwrap();
+            assert_eq!(commitment, &expected);
+            assert_eq!(randomness, &expected_r);
+        }
+        assert!(params.commit_batch(&[], &mut rng).unwrap().is_empty());
+    }
+
+    fn test_batch_to_affine_round_trip<C: CurveConfig>() {
+        let mut rng = test_rng();
+        let params = CommitmentParams::<C>::setup(&mut rng).unwrap();
+        let values: Vec<C::Scalar> = (0..16u64).map(C::Scalar::from).collect();
+        let mut commitments: Vec<Commitment<C>> = params.commit_batch(&values, &mut rng).unwrap().into_iter().map(|(c, _)| c).collect();
+        commitments.push(Commitment::zero());
+        let affine = Commitment::batch_to_affine(&commitments);
+        assert_eq!(affine.len(), commitments.len());
+        for (a, c) in affine.into_iter().zip(&commitments) {
+            assert_eq!(a, c.to_affine());
+            assert_eq!(a.to_bytes(), c.to_bytes());
+            assert_eq!(&Commitment::from(a), c);
+        }
+        assert!(Commitment::<C>::batch_to_affine(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_small_order_point_rejected() {
+        use ark_ed_on_bls12_381::{EdwardsAffine, Fq};
+        use ark_ff::One;
+
+        // (0, -1) has order 2 on the twisted Edwards curve.
+        let torsion = EdwardsAffine::new_unchecked(Fq::zero(), -Fq::one());
+        let mut rng = test_rng();
+        let params: CommitmentParams = CommitmentParams::setup(&mut rng).unwrap();
+        let (c, _) = params.commit(&crate::types::ScalarField::from(5u64), &mut rng).unwrap();
+        let tainted: Commitment = Commitment(c.0 + torsion);
+        assert!(c.is_valid());
+        assert!(!tainted.is_valid());
+        assert!(!Commitment::<DefaultCurve>(torsion.into()).is_valid());
+
+        let mut bytes = Vec::new();
+        tainted.0.serialize_compressed(&mut bytes).unwrap();
+        assert!(matches!(Commitment::<DefaultCurve>::from_bytes(&bytes), Err(ArchimedesError::SerializationError { .. })));
+        assert!(Commitment::<DefaultCurve>::deserialize_compressed(&bytes[..]).is_err());
+
+        let bad_params = CommitmentParams::<DefaultCurve>::from_generators(params.g, params.h + torsion);
+        assert!(bad_params.validate().is_err());
+    }
+
+    #[cfg(feature = "zeroize")]
+    #[test]
+    fn test_zeroize_wipes_secrets() {
+        use crate::types::ScalarField;
+        use zeroize::Zeroize;
+        let mut rng = test_rng();
+        let params: CommitmentParams = CommitmentParams::setup(&mut rng).unwrap();
+        let (_, randomness) = params.commit(&ScalarField::from(7u64), &mut rng).unwrap();
+        let mut opening = Opening { value: ScalarField::from(7u64), randomness };
+        opening.zeroize();
+        assert!(opening.value.is_zero());
+        assert_eq!(opening.randomness, Randomness::zero());
+    }
+}
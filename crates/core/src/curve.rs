@@ -0,0 +1,137 @@
+use ark_ec::{AffineRepr, CurveGroup};
+use ark_ff::PrimeField;
+use blake2::{Blake2b512, Digest};
+
+/// Selects the prime-order group the commitment scheme runs over.
+pub trait CurveConfig: 'static + Clone + Copy + std::fmt::Debug + Default + PartialEq + Eq + Send + Sync {
+    type Group: CurveGroup<ScalarField = Self::Scalar>;
+    type Scalar: PrimeField;
+
+    /// Stable identifier used when persisting curve-specific data.
+    const CURVE_ID: &'static str;
+}
+
+/// The twisted Edwards curve embedded in BLS12-381's scalar field.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct EdOnBls12_381;
+
+impl CurveConfig for EdOnBls12_381 {
+    type Group = ark_ed_on_bls12_381::EdwardsProjective;
+    type Scalar = ark_ed_on_bls12_381::Fr;
+    const CURVE_ID: &'static str = "ed-on-bls12-381";
+}
+
+/// G1 of BN254, for deployments settling on BN254-based chains.
+#[cfg(feature = "bn254")]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Bn254;
+
+#[cfg(feature = "bn254")]
+impl CurveConfig for Bn254 {
+    type Group = ark_bn254::G1Projective;
+    type Scalar = ark_bn254::Fr;
+    const CURVE_ID: &'static str = "bn254-g1";
+}
+
+/// Baby Jubjub, embedded in BN254's scalar field so commitments open cheaply
+/// inside BN254 circuits and precompiles.
+#[cfg(feature = "bn254")]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct EdOnBn254;
+
+#[cfg(feature = "bn254")]
+impl CurveConfig for EdOnBn254 {
+    type Group = ark_ed_on_bn254::EdwardsProjective;
+    type Scalar = ark_ed_on_bn254::Fr;
+    const CURVE_ID: &'static str = "ed-on-bn254";
+}
+
+pub type DefaultCurve = EdOnBls12_381;
+
+/// Try-and-increment hash to the prime-order subgroup. Nobody knows the
+/// discrete log of the result relative to any other derived point.
+pub(crate) fn hash_to_group<C: CurveConfig>(domain: &[u8], seed: &[u8], index: usize) -> C::Group {
+    (0u32..)
+        .find_map(|attempt| {
+            let mut hasher = Blake2b512::new();
+            hasher.update(domain);
+            hasher.update(C::CURVE_ID.as_bytes());
+            hasher.update(seed);
+            hasher.update((index as u64).to_le_bytes());
+            hasher.update(attempt.to_le_bytes());
+            let point = <C::Group as CurveGroup>::Affine::from_random_bytes(&hasher.finalize())?.clear_cofactor();
+            (!point.is_zero()).then(|| point.into_group())
+        })
+        .unwrap()
+}
+
+/// Instantiates generic test functions once per enabled curve.
+#[cfg(all(test, feature = "prover"))]
+macro_rules! curve_tests {
+    ($($name:ident),* $(,)?) => {
+        mod ed_on_bls12_381 {
+            $(
+                #[test]
+                fn $name() {
+                    super::$name::<$crate::curve::EdOnBls12_381>();
+                }
+            )*
+        }
+
+        #[cfg(feature = "bn254")]
+        mod bn254 {
+            $(
+                #[test]
+                fn $name() {
+                    super::$name::<$crate::curve::Bn254>();
+                }
+            )*
+        }
+
+        #[cfg(feature = "bn254")]
+        mod ed_on_bn254 {
+            $(
+                #[test]
+                fn $name() {
+                    super::$name::<$crate::curve::EdOnBn254>();
+                }
+            )*
+        }
+    };
+}
+
+#[cfg(all(test, feature = "prover"))]
+pub(crate) use curve_tests;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commitment::{CommitmentParams, Randomness};
+    use ark_ec::PrimeGroup;
+
+    fn golden_commitment<C: CurveConfig>() -> String {
+        let g = C::Group::generator();
+        let params = CommitmentParams::<C>::from_generators(g, g * C::Scalar::from(7u64));
+        params
+            .commit_with_randomness(&C::Scalar::from(42u64), &Randomness(C::Scalar::from(5u64)))
+            .unwrap()
+            .to_hex()
+    }
+
+    #[test]
+    fn test_golden_vector_ed_on_bls12_381() {
+        assert_eq!(golden_commitment::<EdOnBls12_381>(), "7891b60188938f6dbd597276133dbdd7341b45802fe6e2848fc45449b3e8a749");
+    }
+
+    #[cfg(feature = "bn254")]
+    #[test]
+    fn test_golden_vector_bn254() {
+        assert_eq!(golden_commitment::<Bn254>(), "00ff721a65c3f7725afe9e86c3d6c460f38747b106678676a5ba9eb80a8c972f");
+    }
+
+    #[cfg(feature = "bn254")]
+    #[test]
+    fn test_golden_vector_ed_on_bn254() {
+        assert_eq!(golden_commitment::<EdOnBn254>(), "be85d75dab11a05dec926611984c1355317a492ec6819ffc1ff4a4c0f3eece05");
+    }
+}
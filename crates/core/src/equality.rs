@@ -0,0 +1,131 @@
+#[cfg(feature = "prover")]
+use ark_ff::UniformRand;
+use ark_ff::PrimeField;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+#[cfg(feature = "prover")]
+use ark_std::rand::Rng;
+use blake2::{Blake2b512, Digest};
+
+use crate::commitment::{Commitment, CommitmentParams, CommitmentResult};
+#[cfg(feature = "prover")]
+use crate::commitment::Opening;
+use crate::curve::{CurveConfig, DefaultCurve};
+#[cfg(feature = "prover")]
+use crate::errors::{ArchimedesError, InputErrorKind, VerificationErrorKind};
+
+const EQUALITY_PROOF_DOMAIN: &[u8] = b"archimedes-equality-proof-v1";
+
+/// Schnorr proof of knowledge of `r1 - r2` with `C1 - C2 = h * (r1 - r2)`,
+/// which holds exactly when both commitments hide the same value.
+#[derive(Clone, Debug, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct EqualityProof<C: CurveConfig = DefaultCurve> {
+    pub announcement: C::Group,
+    pub response: C::Scalar,
+}
+
+fn equality_challenge<C: CurveConfig>(
+    params: &CommitmentParams<C>,
+    c1: &Commitment<C>,
+    c2: &Commitment<C>,
+    announcement: &C::Group,
+) -> CommitmentResult<C::Scalar> {
+    let mut transcript = Vec::new();
+    for point in [params.g, params.h, c1.0, c2.0, *announcement] {
+        point.serialize_compressed(&mut transcript)?;
+    }
+    let mut hasher = Blake2b512::new();
+    hasher.update(EQUALITY_PROOF_DOMAIN);
+    hasher.update(C::CURVE_ID.as_bytes());
+    hasher.update(&transcript);
+    Ok(C::Scalar::from_le_bytes_mod_order(&hasher.finalize()))
+}
+
+#[cfg(feature = "prover")]
+pub fn prove_equal<C: CurveConfig, R: Rng>(
+    params: &CommitmentParams<C>,
+    c1: &Commitment<C>,
+    o1: &Opening<C>,
+    c2: &Commitment<C>,
+    o2: &Opening<C>,
+    rng: &mut R,
+) -> CommitmentResult<EqualityProof<C>> {
+    if !params.verify(c1, o1)? || !params.verify(c2, o2)? {
+        return Err(ArchimedesError::VerificationError(VerificationErrorKind::OpeningMismatch));
+    }
+    if o1.value != o2.value {
+        return Err(ArchimedesError::InvalidInput(InputErrorKind::ValueMismatch));
+    }
+    let nonce = C::Scalar::rand(rng);
+    let announcement = params.h * nonce;
+    let challenge = equality_challenge(params, c1, c2, &announcement)?;
+    Ok(EqualityProof {
+        announcement,
+        response: nonce + challenge * (o1.randomness.0 - o2.randomness.0),
+    })
+}
+
+pub fn verify_equal<C: CurveConfig>(
+    params: &CommitmentParams<C>,
+    c1: &Commitment<C>,
+    c2: &Commitment<C>,
+    proof: &EqualityProof<C>,
+) -> CommitmentResult<bool> {
+    let challenge = equality_challenge(params, c1, c2, &proof.announcement)?;
+    let difference = c1.0 - c2.0;
+    Ok(params.h * proof.response == proof.announcement + difference * challenge)
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod tests {
+    use super::*;
+    use crate::commitment::Randomness;
+    use crate::curve::curve_tests;
+    use ark_std::test_rng;
+
+    curve_tests!(test_equal_values_prove, test_different_values_fail);
+
+    fn test_equal_values_prove<C: CurveConfig>() {
+        let mut rng = test_rng();
+        let params = CommitmentParams::<C>::setup(&mut rng).unwrap();
+        let value = C::Scalar::from(88u64);
+        let (c1, r1) = params.commit(&value, &mut rng).unwrap();
+        let (c2, r2) = params.commit(&value, &mut rng).unwrap();
+        assert_ne!(c1, c2);
+        let o1 = Opening { value, randomness: r1 };
+        let o2 = Opening { value, randomness: r2 };
+
+        let proof = prove_equal(&params, &c1, &o1, &c2, &o2, &mut rng).unwrap();
+        assert!(verify_equal(&params, &c1, &c2, &proof).unwrap());
+        assert!(!verify_equal(&params, &c2, &c1, &proof).unwrap());
+    }
+
+    fn test_different_values_fail<C: CurveConfig>() {
+        let mut rng = test_rng();
+        let params = CommitmentParams::<C>::setup(&mut rng).unwrap();
+        let (c1, r1) = params.commit(&C::Scalar::from(1u64), &mut rng).unwrap();
+        let (c2, r2) = params.commit(&C::Scalar::from(2u64), &mut rng).unwrap();
+        let o1 = Opening { value: C::Scalar::from(1u64), randomness: r1.clone() };
+        let o2 = Opening { value: C::Scalar::from(2u64), randomness: r2.clone() };
+        assert!(matches!(
+            prove_equal(&params, &c1, &o1, &c2, &o2, &mut rng),
+            Err(ArchimedesError::InvalidInput(_))
+        ));
+
+        // A prover who ignores the value mismatch cannot produce a valid proof.
+        let nonce = C::Scalar::rand(&mut rng);
+        let announcement = params.h * nonce;
+        let challenge = equality_challenge(&params, &c1, &c2, &announcement).unwrap();
+        let forged = EqualityProof {
+            announcement,
+            response: nonce + challenge * (r1.0 - r2.0),
+        };
+        assert!(!verify_equal(&params, &c1, &c2, &forged).unwrap());
+
+        let (c3, r3) = params.commit(&C::Scalar::from(1u64), &mut rng).unwrap();
+        let o3 = Opening { value: C::Scalar::from(1u64), randomness: r3 };
+        let proof = prove_equal(&params, &c1, &o1, &c3, &o3, &mut rng).unwrap();
+        assert!(!verify_equal(&params, &c1, &c2, &proof).unwrap());
+        let bad_opening = Opening { value: C::Scalar::from(1u64), randomness: Randomness(r2.0) };
+        assert!(prove_equal(&params, &c1, &o1, &c3, &bad_opening, &mut rng).is_err());
+    }
+}
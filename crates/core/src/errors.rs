@@ -1,32 +1,345 @@
 use thiserror::Error;
 
-#[derive(Error, Debug, Clone)]
+#[derive(Error, Debug)]
 pub enum ArchimedesError {
     #[error("Commitment setup failed: {0}")]
-    SetupError(String),
+    SetupError(SetupErrorKind),
 
     #[error("Commitment generation failed: {0}")]
-    CommitmentError(String),
+    CommitmentError(CommitmentErrorKind),
 
     #[error("Commitment verification failed: {0}")]
-    VerificationError(String),
+    VerificationError(VerificationErrorKind),
 
     #[error("Aggregation failed: {0}")]
-    AggregationError(String),
+    AggregationError(AggregationErrorKind),
 
     #[error("Invalid input: {0}")]
-    InvalidInput(String),
+    InvalidInput(InputErrorKind),
 
-    #[error("Serialization error: {0}")]
-    SerializationError(String),
+    #[error("Serialization error: {kind}")]
+    SerializationError {
+        kind: SerializationErrorKind,
+        #[source]
+        source: Option<SerializationSource>,
+    },
 
     #[error("State encoding error: {0}")]
-    StateEncodingError(String),
+    StateEncodingError(StateEncodingErrorKind),
 
-    #[error("Merkle tree error: {0}")]
-    MerkleTreeError(String),
+    #[error("Merkle tree error: {kind} (index {index}, {leaf_count} leaves)")]
+    MerkleTreeError {
+        index: usize,
+        leaf_count: usize,
+        kind: MerkleErrorKind,
+    },
 
-    #[error("Dispute resolution error: {0}")]
-    DisputeError(String),
+    #[error("Params belong to domain {found:?}, expected {expected:?}")]
+    DomainMismatch { expected: String, found: String },
+
+    /// `state` is the protocol state the action was attempted in.
+    #[error("Dispute resolution error: {kind} ({action:?} in state {state})")]
+    DisputeError {
+        state: String,
+        action: DisputeAction,
+        kind: DisputeErrorKind,
+    },
+}
+
+impl ArchimedesError {
+    /// A format-level serialization failure with no underlying error.
+    pub fn serialization(kind: SerializationErrorKind) -> Self {
+        ArchimedesError::SerializationError { kind, source: None }
+    }
+}
+
+impl From<ark_serialize::SerializationError> for ArchimedesError {
+    fn from(e: ark_serialize::SerializationError) -> Self {
+        ArchimedesError::SerializationError {
+            kind: SerializationErrorKind::Encoding,
+            source: Some(SerializationSource::Ark(e)),
+        }
+    }
+}
+
+impl From<std::io::Error> for ArchimedesError {
+    fn from(e: std::io::Error) -> Self {
+        ArchimedesError::SerializationError {
+            kind: SerializationErrorKind::Io,
+            source: Some(SerializationSource::Io(e)),
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum SerializationSource {
+    #[error(transparent)]
+    Ark(ark_serialize::SerializationError),
+    #[error(transparent)]
+    Io(std::io::Error),
 }
 
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum SetupErrorKind {
+    #[error("Generator points cannot be identity")]
+    IdentityGenerator,
+    #[error("Generators g and h must differ")]
+    EqualGenerators,
+    #[error("Generators must lie in the prime-order subgroup")]
+    GeneratorNotInSubgroup,
+    #[error("Not a params file")]
+    NotAParamsFile,
+    #[error("Unsupported params version {found} (expected {expected})")]
+    UnsupportedVersion { found: u32, expected: u32 },
+    #[error("Params are for curve {found}, expected {expected}")]
+    CurveMismatch { found: String, expected: &'static str },
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum CommitmentErrorKind {
+    #[error("Cannot extend a chain whose secrets have been cleared")]
+    SecretsCleared,
+    #[error("Checkpoint was taken on a different chain")]
+    ForeignCheckpoint,
+    #[error("Checkpoint at length {checkpoint} is ahead of chain length {len}")]
+    CheckpointAhead { checkpoint: usize, len: usize },
+    #[error("Chain history no longer matches the checkpoint")]
+    CheckpointDiverged,
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum VerificationErrorKind {
+    #[error("Opening does not match commitment")]
+    OpeningMismatch,
+    #[error("Chain secrets have been cleared")]
+    SecretsCleared,
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum AggregationErrorKind {
+    #[error("Cannot subtract aggregate of {subtrahend} commitments from aggregate of {minuend}")]
+    CountUnderflow { minuend: usize, subtrahend: usize },
+    #[error("Invalid range {start}..{end} over {len} commitments")]
+    InvalidRange { start: usize, end: usize, len: usize },
+    #[error("{found} coefficients supplied for {expected} commitments")]
+    CoefficientCountMismatch { expected: usize, found: usize },
+    #[error("Index {index} out of bounds for chain of {len}")]
+    IndexOutOfBounds { index: usize, len: usize },
+    #[error("Index {index} listed more than once")]
+    DuplicateIndex { index: usize },
+    #[error("Adding {added} commitments to an aggregate of {count} overflows")]
+    CountOverflow { count: usize, added: usize },
+    #[error("Chain is at its limit of {max_len} entries")]
+    ChainFull { max_len: usize },
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum InputErrorKind {
+    #[error("Unsupported range proof width: {bits} bits")]
+    UnsupportedRangeWidth { bits: usize },
+    #[error("Value does not fit in {bits} bits")]
+    ValueOutOfRange { bits: usize },
+    #[error("Commitments hide different values")]
+    ValueMismatch,
+    #[error("Dispute already holds a lease")]
+    LeaseExists,
+    #[error("No lease for dispute")]
+    NoLease,
+    #[error("Input of {len} bytes exceeds hasher capacity of {max}")]
+    InputTooLong { len: usize, max: usize },
+    #[error("Vector of length {found} does not match key of length {expected}")]
+    VectorLengthMismatch { expected: usize, found: usize },
+    #[error("Cannot split into {n} shares with threshold {threshold}")]
+    InvalidShareParameters { n: usize, threshold: usize },
+    #[error("{found} shares supplied, {threshold} required")]
+    InsufficientShares { found: usize, threshold: usize },
+    #[error("Shares come from different splits")]
+    MixedShareSets,
+    #[error("Share index {index} is repeated or invalid")]
+    DuplicateShare { index: u32 },
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum SerializationErrorKind {
+    #[error("invalid encoding")]
+    Encoding,
+    #[error("I/O failure")]
+    Io,
+    #[error("Invalid hex string")]
+    InvalidHex,
+    #[error("Commitment is not in the prime-order subgroup")]
+    NotInSubgroup,
+    #[error("Truncated {0}")]
+    Truncated(&'static str),
+    #[error("Trailing bytes after {0}")]
+    TrailingBytes(&'static str),
+    #[error("Unsupported frozen tree version {0}")]
+    UnsupportedFrozenVersion(u32),
+    #[error("Corrupted frozen tree: {0}")]
+    CorruptedFrozenTree(&'static str),
+    #[error("Unsupported chain export version {0}")]
+    UnsupportedChainVersion(u32),
+    #[error("Corrupted chain export: {0}")]
+    CorruptedChain(&'static str),
+    #[error("Not a {0} encoding")]
+    WrongMagic(&'static str),
+    #[error("Unsupported {format} version {version}")]
+    UnsupportedFormatVersion { format: &'static str, version: u32 },
+    #[error("Rebuilt Merkle root does not match the stored root")]
+    TreeRootMismatch,
+    #[error("Proof of depth {depth} exceeds the maximum of {max}")]
+    ProofTooDeep { depth: usize, max: usize },
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum StateEncodingErrorKind {
+    #[error("Empty state batch")]
+    EmptyStateBatch,
+    #[error("Empty transitions")]
+    EmptyTransitions,
+    #[error("Address is not in the batch")]
+    UnknownAddress,
+    #[error("Account does not hold the transition's pre-state")]
+    PreStateMismatch,
+    #[error("Storage trie does not match the account's storage root")]
+    StorageRootMismatch,
+    #[error("State diff overflows a field")]
+    DiffOverflow,
+    #[error("State diff underflows a field")]
+    DiffUnderflow,
+    #[error("Transition {index} breaks the batch's ordering rules")]
+    InvalidTransitionBatch { index: usize },
+    #[error("Snapshot {id} is not open")]
+    UnknownSnapshot { id: u64 },
+    #[error("No code stored for the code hash")]
+    MissingCode,
+    #[error("Code storage failed: {0}")]
+    CodeStorageFailed(String),
+    #[error("Genesis allocations to one address overflow")]
+    GenesisOverflow,
+    #[error("Insufficient balance: {balance} < {amount}")]
+    InsufficientBalance { balance: u128, amount: u128 },
+    #[error("Nonce overflows")]
+    NonceOverflow,
+    #[error("Storage write needs a proof for its key against the storage root")]
+    MissingStorageProof,
+    #[error("Crediting {amount} to balance {balance} overflows")]
+    BalanceOverflow { balance: u128, amount: u128 },
+    #[error("Hash scheme {0} is not built in")]
+    HashSchemeUnavailable(u8),
+    #[error("Chunk size must be non-zero")]
+    ZeroChunkSize,
+    #[error("Hash is not a canonical scalar field element")]
+    NonCanonicalField,
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum MerkleErrorKind {
+    #[error("Cannot build empty tree")]
+    EmptyTree,
+    #[error("Invalid range ending at {end} on level {level}")]
+    InvalidRange { end: usize, level: usize },
+    #[error("Index out of bounds")]
+    IndexOutOfBounds,
+    #[error("Node out of bounds at level {level}")]
+    NodeOutOfBounds { level: usize },
+    #[error("Frozen tree has no aggregates")]
+    NoAggregates,
+    #[error("Expected {expected} leaves, got {found}")]
+    LeafCountMismatch { expected: usize, found: usize },
+    #[error("Stored chunk {0} is missing")]
+    MissingChunk(String),
+    #[error("Storage failure: {0}")]
+    StorageFailed(String),
+    #[error("Tree aggregate does not match the chain aggregate")]
+    AggregateMismatch,
+    #[error("Counterpart node on level {level} does not hash its children")]
+    InconsistentCounterpart { level: usize },
+    #[error("Commitment is not in the sorted set")]
+    NotInSet,
+    #[error("Tree was not built sorted")]
+    NotSorted,
+}
+
+/// Why a Merkle path failed to verify, for callers that report failures
+/// rather than just rejecting them.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum MerkleVerifyError {
+    #[error("Path of {depth} siblings does not fit leaf {index}")]
+    WrongDepth { index: usize, depth: usize },
+    #[error("Sibling on level {level} is on the wrong side for leaf {index}")]
+    WrongDirection { index: usize, level: usize },
+    #[error("Path leads to a different root")]
+    RootMismatch { computed: [u8; 32], expected: [u8; 32] },
+    #[error("Aggregate count overflows on level {level}")]
+    CountOverflow { level: usize },
+    #[error("Sibling on level {level} is not a node hash the hasher produces")]
+    NonCanonicalSibling { level: usize },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DisputeAction {
+    Challenge,
+    Respond,
+    SelectDirection,
+    ExecuteTransition,
+    CheckOrdering,
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum DisputeErrorKind {
+    #[error("Invalid state")]
+    InvalidState,
+    #[error("Invalid dispute range {start}..{end}")]
+    InvalidRange { start: usize, end: usize },
+    #[error("Invalid midpoint {mid}")]
+    InvalidMidpoint { mid: usize },
+    #[error("Aggregate is not a valid commitment")]
+    InvalidAggregate,
+    #[error("No response to bisect")]
+    NoResponse,
+    #[error("Insufficient balance: {balance} < {amount}")]
+    InsufficientBalance { balance: u128, amount: u128 },
+    #[error("Crediting {amount} to balance {balance} overflows")]
+    BalanceOverflow { balance: u128, amount: u128 },
+    #[error("Committed batch has {committed} leaves but {published} transactions were published")]
+    LengthMismatch { committed: usize, published: usize },
+    #[error("Tree of {leaf_count} leaves exceeds the dispute limit of {max}")]
+    TooManyLeaves { leaf_count: usize, max: usize },
+    #[error("Aggregate claims range {start}..{end}")]
+    AggregateRangeMismatch { start: usize, end: usize },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::error::Error;
+
+    #[test]
+    fn test_source_chain_preserved() {
+        let io = std::io::Error::new(std::io::ErrorKind::UnexpectedEof, "short read");
+        let err = ArchimedesError::from(io);
+        assert!(matches!(err, ArchimedesError::SerializationError { kind: SerializationErrorKind::Io, .. }));
+        assert_eq!(err.source().unwrap().to_string(), "short read");
+
+        let err = ArchimedesError::serialization(SerializationErrorKind::InvalidHex);
+        assert_eq!(err.to_string(), "Serialization error: Invalid hex string");
+        assert!(err.source().is_none());
+    }
+
+    #[test]
+    fn test_typed_fields() {
+        let err = ArchimedesError::MerkleTreeError {
+            index: 9,
+            leaf_count: 4,
+            kind: MerkleErrorKind::IndexOutOfBounds,
+        };
+        assert_eq!(err.to_string(), "Merkle tree error: Index out of bounds (index 9, 4 leaves)");
+        let err = ArchimedesError::DisputeError {
+            state: "Initial".to_string(),
+            action: DisputeAction::Respond,
+            kind: DisputeErrorKind::InvalidState,
+        };
+        assert_eq!(err.to_string(), "Dispute resolution error: Invalid state (Respond in state Initial)");
+    }
+}
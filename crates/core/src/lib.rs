@@ -1,10 +1,57 @@
+pub mod aggregate_proof;
 pub mod aggregation;
+pub mod checkpoint;
 pub mod commitment;
+pub mod curve;
+pub mod equality;
 pub mod errors;
+pub mod opening_proof;
+pub mod pedersen;
+pub mod persistence;
+pub mod prepared;
+pub mod public_chain;
+pub mod range_proof;
+pub mod retention;
+pub mod set;
+pub mod sharing;
+pub mod streaming;
+pub mod switch;
+pub mod vector;
 
-pub use aggregation::{AggregateCommitment, CommitmentChain};
-pub use commitment::{Commitment, CommitmentParams, Opening, Randomness};
-pub use errors::ArchimedesError;
+pub use aggregate_proof::AggregateOpeningProof;
+pub use aggregation::{find_first_divergence, AggregateCommitment, CommitmentChain, EntryMeta, RangedAggregate};
+pub use checkpoint::ChainCheckpoint;
+pub use commitment::{scalar_from_u128, AffineCommitment, Commitment, CommitmentParams, Opening, Randomness};
+pub use curve::{CurveConfig, DefaultCurve, EdOnBls12_381};
+#[cfg(feature = "bn254")]
+pub use curve::{Bn254, EdOnBn254};
+#[cfg(feature = "prover")]
+pub use equality::prove_equal;
+pub use equality::{verify_equal, EqualityProof};
+pub use errors::{
+    AggregationErrorKind, ArchimedesError, CommitmentErrorKind, DisputeAction, DisputeErrorKind, InputErrorKind,
+    MerkleErrorKind, MerkleVerifyError, SerializationErrorKind, SerializationSource, SetupErrorKind, StateEncodingErrorKind,
+    VerificationErrorKind,
+};
+pub use opening_proof::{OpeningProof, ProofContext};
+pub use pedersen::{PedersenHasher, PEDERSEN_CHUNK_BYTES};
+pub use persistence::{
+    AGGREGATE_MAGIC, CHAIN_EXPORT_VERSION, CHAIN_PUBLIC_MAGIC, CHAIN_SECRET_MAGIC, PARAMS_MAGIC, PARAMS_VERSION,
+    PUBLIC_CHAIN_MAGIC, WIRE_FORMAT_VERSION,
+};
+pub use prepared::PreparedCommitmentParams;
+pub use public_chain::{PublicCommitmentChain, MAX_PUBLIC_CHAIN_LEN};
+#[cfg(feature = "prover")]
+pub use range_proof::prove_range;
+pub use range_proof::{verify_range, RangeProof};
+pub use retention::{DisputeId, Lease, ResourceId, RetentionRegistry};
+pub use set::CommitmentSet;
+pub use sharing::RandomnessShare;
+pub use streaming::{ProvingAggregator, StreamingAggregator};
+#[cfg(feature = "prover")]
+pub use switch::prove_same_value;
+pub use switch::{verify_same_value, SwitchProof};
+pub use vector::VectorCommitmentKey;
 
 pub mod types {
     pub use ark_ed_on_bls12_381::{EdwardsProjective as G1, Fr as ScalarField};
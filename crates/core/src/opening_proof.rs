@@ -0,0 +1,144 @@
+#[cfg(feature = "prover")]
+use ark_ff::UniformRand;
+use ark_ff::PrimeField;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+#[cfg(feature = "prover")]
+use ark_std::rand::Rng;
+use blake2::{Blake2b512, Digest};
+use serde::{Deserialize, Serialize};
+
+use crate::commitment::{Commitment, CommitmentParams, CommitmentResult};
+#[cfg(feature = "prover")]
+use crate::commitment::Opening;
+use crate::curve::{CurveConfig, DefaultCurve};
+#[cfg(feature = "prover")]
+use crate::errors::{ArchimedesError, VerificationErrorKind};
+
+const OPENING_PROOF_DOMAIN: &[u8] = b"archimedes-opening-proof-v1";
+
+/// Where an opening proof is allowed to be used. Absorbed into the
+/// Fiat-Shamir challenge, so a proof made for one dispute does not verify in
+/// another even when the commitment is identical.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProofContext {
+    pub challenge_id: [u8; 32],
+    pub batch_root: [u8; 32],
+    pub index: u64,
+}
+
+/// Reveals the committed value and proves knowledge of the blinding factor,
+/// i.e. of `r` with `C - g * value = h * r`, without revealing `r`.
+#[derive(Clone, Debug, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct OpeningProof<C: CurveConfig = DefaultCurve> {
+    pub value: C::Scalar,
+    pub announcement: C::Group,
+    pub response: C::Scalar,
+}
+
+impl<C: CurveConfig> CommitmentParams<C> {
+    pub fn verify_opening_proof(
+        &self,
+        commitment: &Commitment<C>,
+        proof: &OpeningProof<C>,
+        context: &ProofContext,
+    ) -> CommitmentResult<bool> {
+        let challenge = self.opening_challenge(commitment, &proof.value, &proof.announcement, context)?;
+        let blinding = commitment.0 - self.g * proof.value;
+        Ok(self.h * proof.response == proof.announcement + blinding * challenge)
+    }
+
+    fn opening_challenge(
+        &self,
+        commitment: &Commitment<C>,
+        value: &C::Scalar,
+        announcement: &C::Group,
+        context: &ProofContext,
+    ) -> CommitmentResult<C::Scalar> {
+        let mut transcript = Vec::new();
+        (self.g, self.h, commitment.0, *value, *announcement).serialize_compressed(&mut transcript)?;
+        let mut hasher = Blake2b512::new();
+        hasher.update(OPENING_PROOF_DOMAIN);
+        hasher.update(C::CURVE_ID.as_bytes());
+        hasher.update(context.challenge_id);
+        hasher.update(context.batch_root);
+        hasher.update(context.index.to_le_bytes());
+        hasher.update(&transcript);
+        Ok(C::Scalar::from_le_bytes_mod_order(&hasher.finalize()))
+    }
+}
+
+#[cfg(feature = "prover")]
+impl<C: CurveConfig> CommitmentParams<C> {
+    pub fn prove_opening<R: Rng>(
+        &self,
+        commitment: &Commitment<C>,
+        opening: &Opening<C>,
+        context: &ProofContext,
+        rng: &mut R,
+    ) -> CommitmentResult<OpeningProof<C>> {
+        if !self.verify(commitment, opening)? {
+            return Err(ArchimedesError::VerificationError(VerificationErrorKind::OpeningMismatch));
+        }
+        let nonce = C::Scalar::rand(rng);
+        let announcement = self.h * nonce;
+        let challenge = self.opening_challenge(commitment, &opening.value, &announcement, context)?;
+        Ok(OpeningProof {
+            value: opening.value,
+            announcement,
+            response: nonce + challenge * opening.randomness.0,
+        })
+    }
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod tests {
+    use super::*;
+    use crate::curve::curve_tests;
+    use ark_std::test_rng;
+
+    curve_tests!(test_opening_proof_round_trip, test_opening_proof_bound_to_context);
+
+    fn context(challenge: u8, index: u64) -> ProofContext {
+        ProofContext {
+            challenge_id: [challenge; 32],
+            batch_root: [9u8; 32],
+            index,
+        }
+    }
+
+    fn test_opening_proof_round_trip<C: CurveConfig>() {
+        let mut rng = test_rng();
+        let params = CommitmentParams::<C>::setup(&mut rng).unwrap();
+        let value = C::Scalar::from(1234u64);
+        let (commitment, randomness) = params.commit(&value, &mut rng).unwrap();
+        let opening = Opening { value, randomness };
+        let proof = params.prove_opening(&commitment, &opening, &context(1, 3), &mut rng).unwrap();
+
+        assert!(params.verify_opening_proof(&commitment, &proof, &context(1, 3)).unwrap());
+        assert!(params.verify_opening_proof(&commitment, &proof, &context(1, 3)).unwrap());
+
+        let mut bytes = Vec::new();
+        proof.serialize_compressed(&mut bytes).unwrap();
+        let decoded = OpeningProof::<C>::deserialize_compressed(&bytes[..]).unwrap();
+        assert_eq!(decoded, proof);
+
+        let lying = OpeningProof { value: value + C::Scalar::from(1u64), ..proof };
+        assert!(!params.verify_opening_proof(&commitment, &lying, &context(1, 3)).unwrap());
+        let wrong_opening = Opening { value: C::Scalar::from(1u64), randomness: opening.randomness.clone() };
+        assert!(params.prove_opening(&commitment, &wrong_opening, &context(1, 3), &mut rng).is_err());
+    }
+
+    fn test_opening_proof_bound_to_context<C: CurveConfig>() {
+        let mut rng = test_rng();
+        let params = CommitmentParams::<C>::setup(&mut rng).unwrap();
+        let value = C::Scalar::from(5u64);
+        let (commitment, randomness) = params.commit(&value, &mut rng).unwrap();
+        let opening = Opening { value, randomness };
+        let proof = params.prove_opening(&commitment, &opening, &context(1, 3), &mut rng).unwrap();
+
+        assert!(!params.verify_opening_proof(&commitment, &proof, &context(2, 3)).unwrap());
+        assert!(!params.verify_opening_proof(&commitment, &proof, &context(1, 4)).unwrap());
+        let other_root = ProofContext { batch_root: [8u8; 32], ..context(1, 3) };
+        assert!(!params.verify_opening_proof(&commitment, &proof, &other_root).unwrap());
+    }
+}
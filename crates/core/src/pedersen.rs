@@ -0,0 +1,127 @@
+use ark_ff::{BigInteger, PrimeField};
+use ark_serialize::CanonicalSerialize;
+
+use crate::commitment::{CommitmentParams, CommitmentResult};
+use crate::curve::{hash_to_group, CurveConfig, DefaultCurve};
+use crate::errors::{ArchimedesError, InputErrorKind};
+
+const PEDERSEN_GENERATOR_DOMAIN: &[u8] = b"archimedes-pedersen-generator";
+
+/// Bytes packed into each input element; 31 bytes always fits below the
+/// scalar modulus of every supported curve.
+pub const PEDERSEN_CHUNK_BYTES: usize = 31;
+
+/// Fixed-length Pedersen hash over byte strings.
+///
+/// Input is split into 31-byte little-endian chunks, prefixed by its length,
+/// and each element multiplies its own generator. Generators are derived by
+/// hashing to the curve from the params, so a given `CommitmentParams` always
+/// yields the same hasher and nobody knows discrete logs between generators.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PedersenHasher<C: CurveConfig = DefaultCurve> {
+    generators: Vec<C::Group>,
+}
+
+impl<C: CurveConfig> PedersenHasher<C> {
+    /// Builds a hasher accepting inputs up to `max_input_len` bytes.
+    pub fn new(params: &CommitmentParams<C>, max_input_len: usize) -> CommitmentResult<Self> {
+        let mut seed = Vec::new();
+        (params.g, params.h).serialize_compressed(&mut seed)?;
+        let count = 1 + max_input_len.div_ceil(PEDERSEN_CHUNK_BYTES);
+        let generators = (0..count).map(|i| hash_to_group::<C>(PEDERSEN_GENERATOR_DOMAIN, &seed, i)).collect();
+        Ok(Self { generators })
+    }
+
+    pub fn max_input_len(&self) -> usize {
+        (self.generators.len() - 1) * PEDERSEN_CHUNK_BYTES
+    }
+
+    pub fn hash_to_point(&self, input: &[u8]) -> CommitmentResult<C::Group> {
+        if input.len() > self.max_input_len() {
+            return Err(ArchimedesError::InvalidInput(InputErrorKind::InputTooLong {
+                len: input.len(),
+                max: self.max_input_len(),
+            }));
+        }
+        // The length prefix keeps inputs differing only in trailing zeros apart.
+        let elements = std::iter::once(C::Scalar::from(input.len() as u64))
+            .chain(input.chunks(PEDERSEN_CHUNK_BYTES).map(C::Scalar::from_le_bytes_mod_order));
+        Ok(elements.zip(&self.generators).map(|(m, g)| *g * m).sum())
+    }
+
+    pub fn hash(&self, input: &[u8]) -> CommitmentResult<C::Scalar> {
+        let mut bytes = Vec::new();
+        self.hash_to_point(input)?.serialize_compressed(&mut bytes)?;
+        Ok(C::Scalar::from_le_bytes_mod_order(&bytes))
+    }
+
+    pub fn hash_bytes(&self, input: &[u8]) -> CommitmentResult<[u8; 32]> {
+        let mut out = [0u8; 32];
+        let le = self.hash(input)?.into_bigint().to_bytes_le();
+        out[..le.len()].copy_from_slice(&le);
+        Ok(out)
+    }
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod tests {
+    use super::*;
+    use crate::curve::curve_tests;
+    use ark_std::test_rng;
+
+    curve_tests!(test_deterministic, test_structured_inputs_distinct, test_rejects_long_input);
+
+    fn test_deterministic<C: CurveConfig>() {
+        let mut rng = test_rng();
+        let params = CommitmentParams::<C>::setup(&mut rng).unwrap();
+        let a = PedersenHasher::new(&params, 64).unwrap();
+        let b = PedersenHasher::new(&params, 64).unwrap();
+        assert_eq!(a, b);
+        assert_eq!(a.hash(b"archimedes").unwrap(), b.hash(b"archimedes").unwrap());
+
+        let other = CommitmentParams::<C>::setup(&mut rng).unwrap();
+        let c = PedersenHasher::new(&other, 64).unwrap();
+        assert_ne!(a.hash(b"archimedes").unwrap(), c.hash(b"archimedes").unwrap());
+    }
+
+    fn test_structured_inputs_distinct<C: CurveConfig>() {
+        let mut rng = test_rng();
+        let params = CommitmentParams::<C>::setup(&mut rng).unwrap();
+        let hasher = PedersenHasher::new(&params, 96).unwrap();
+        let mut inputs: Vec<Vec<u8>> = vec![vec![], vec![0], vec![0, 0], vec![0; 31], vec![0; 32], vec![0; 62]];
+        for len in [1, 30, 31, 32, 63, 96] {
+            for byte in [1u8, 0x80, 0xff] {
+                inputs.push(vec![byte; len]);
+                let mut tail = vec![0u8; len];
+                tail[len - 1] = byte;
+                inputs.push(tail);
+            }
+        }
+        // Same chunk values in different positions.
+        let mut swapped = vec![0u8; 62];
+        swapped[0] = 1;
+        inputs.push(swapped);
+        let mut swapped = vec![0u8; 62];
+        swapped[31] = 1;
+        inputs.push(swapped);
+        inputs.sort();
+        inputs.dedup();
+
+        let mut digests: Vec<[u8; 32]> = inputs.iter().map(|i| hasher.hash_bytes(i).unwrap()).collect();
+        digests.sort();
+        digests.dedup();
+        assert_eq!(digests.len(), inputs.len());
+    }
+
+    fn test_rejects_long_input<C: CurveConfig>() {
+        let mut rng = test_rng();
+        let params = CommitmentParams::<C>::setup(&mut rng).unwrap();
+        let hasher = PedersenHasher::new(&params, 40).unwrap();
+        assert_eq!(hasher.max_input_len(), 62);
+        assert!(hasher.hash(&[7u8; 62]).is_ok());
+        assert!(matches!(
+            hasher.hash(&[7u8; 63]),
+            Err(ArchimedesError::InvalidInput(InputErrorKind::InputTooLong { len: 63, max: 62 }))
+        ));
+    }
+}
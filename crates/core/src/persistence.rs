@@ -0,0 +1,507 @@
+use std::path::Path;
+
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+
+use crate::aggregation::{AggregateCommitment, CommitmentChain};
+use crate::commitment::{Commitment, CommitmentParams, CommitmentResult, Randomness};
+use crate::curve::CurveConfig;
+use crate::public_chain::{check_untrusted_len, PublicCommitmentChain};
+use crate::errors::{ArchimedesError, CommitmentErrorKind, SerializationErrorKind, SetupErrorKind};
+
+pub const PARAMS_MAGIC: [u8; 8] = *b"ARCHPRMS";
+pub const PARAMS_VERSION: u32 = 2;
+pub const CHAIN_PUBLIC_MAGIC: [u8; 8] = *b"ARCHCPUB";
+pub const CHAIN_SECRET_MAGIC: [u8; 8] = *b"ARCHCSEC";
+pub const CHAIN_EXPORT_VERSION: u32 = 1;
+pub const PUBLIC_CHAIN_MAGIC: [u8; 4] = *b"ACPC";
+pub const AGGREGATE_MAGIC: [u8; 4] = *b"ACAG";
+pub const WIRE_FORMAT_VERSION: u32 = 1;
+
+// Layout (integers little-endian):
+//   magic[8] | version u32 | curve_id_len u32 | curve_id | domain_len u32 | domain | g compressed | h compressed
+// Version 1 had no domain fields and loads as the undomained setup.
+
+impl<C: CurveConfig> CommitmentParams<C> {
+    pub fn to_bytes(&self) -> CommitmentResult<Vec<u8>> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&PARAMS_MAGIC);
+        bytes.extend_from_slice(&PARAMS_VERSION.to_le_bytes());
+        bytes.extend_from_slice(&(C::CURVE_ID.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(C::CURVE_ID.as_bytes());
+        bytes.extend_from_slice(&(self.domain_tag().len() as u32).to_le_bytes());
+        bytes.extend_from_slice(self.domain_tag().as_bytes());
+        (self.g, self.h).serialize_compressed(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    /// Loads params written by `to_bytes`. A foreign format version or curve
+    /// is a `SetupError`; the generators are re-validated after decoding.
+    pub fn from_bytes(bytes: &[u8]) -> CommitmentResult<Self> {
+        let truncated = || ArchimedesError::serialization(SerializationErrorKind::Truncated("params header"));
+        let header = bytes.get(..16).ok_or_else(truncated)?;
+        if header[..8] != PARAMS_MAGIC {
+            return Err(ArchimedesError::SetupError(SetupErrorKind::NotAParamsFile));
+        }
+        let version = u32::from_le_bytes(header[8..12].try_into().unwrap());
+        if version == 0 || version > PARAMS_VERSION {
+            return Err(ArchimedesError::SetupError(SetupErrorKind::UnsupportedVersion {
+                found: version,
+                expected: PARAMS_VERSION,
+            }));
+        }
+        let id_len = u32::from_le_bytes(header[12..16].try_into().unwrap()) as usize;
+        let curve_id = bytes.get(16..16 + id_len).ok_or_else(truncated)?;
+        if curve_id != C::CURVE_ID.as_bytes() {
+            return Err(ArchimedesError::SetupError(SetupErrorKind::CurveMismatch {
+                found: String::from_utf8_lossy(curve_id).into_owned(),
+                expected: C::CURVE_ID,
+            }));
+        }
+        let mut body = &bytes[16 + id_len..];
+        let domain = if version >= 2 {
+            let len_bytes = body.get(..4).ok_or_else(truncated)?;
+            let domain_len = u32::from_le_bytes(len_bytes.try_into().unwrap()) as usize;
+            let domain = body.get(4..4 + domain_len).ok_or_else(truncated)?;
+            let domain = String::from_utf8(domain.to_vec())
+                .map_err(|_| ArchimedesError::serialization(SerializationErrorKind::Encoding))?;
+            body = &body[4 + domain_len..];
+            domain
+        } else {
+            String::new()
+        };
+        let (g, h) = <(C::Group, C::Group)>::deserialize_compressed(&mut body)?;
+        if !body.is_empty() {
+            return Err(ArchimedesError::serialization(SerializationErrorKind::TrailingBytes("params")));
+        }
+        let params = Self::from_generators(g, h).with_domain(domain);
+        params.validate()?;
+        Ok(params)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> CommitmentResult<()> {
+        Ok(std::fs::write(path, self.to_bytes()?)?)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> CommitmentResult<Self> {
+        Self::from_bytes(&std::fs::read(path)?)
+    }
+}
+
+// Chain exports (canonical compressed encoding after the header):
+//   public: magic[8] | version u32 | commitments Vec<Group>
+//   secret: magic[8] | version u32 | values Vec<Scalar> | randomness Vec<Scalar> | master_seed Option<[u8; 32]>
+// Entry metadata is operator-local and is not exported.
+
+fn corrupted_chain(reason: &'static str) -> ArchimedesError {
+    ArchimedesError::serialization(SerializationErrorKind::CorruptedChain(reason))
+}
+
+fn chain_export_body<'a>(bytes: &'a [u8], magic: &[u8; 8], what: &'static str) -> CommitmentResult<&'a [u8]> {
+    if bytes.len() < 12 || bytes[..8] != *magic {
+        return Err(corrupted_chain(what));
+    }
+    let version = u32::from_le_bytes(bytes[8..12].try_into().unwrap());
+    if version != CHAIN_EXPORT_VERSION {
+        return Err(ArchimedesError::serialization(SerializationErrorKind::UnsupportedChainVersion(version)));
+    }
+    Ok(&bytes[12..])
+}
+
+impl<C: CurveConfig> CommitmentChain<C> {
+    /// The shareable half of the chain: just the commitments.
+    pub fn export_public(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&CHAIN_PUBLIC_MAGIC);
+        bytes.extend_from_slice(&CHAIN_EXPORT_VERSION.to_le_bytes());
+        let points: Vec<C::Group> = self.commitments().iter().map(|c| c.0).collect();
+        points.serialize_compressed(&mut bytes).expect("serializing to a Vec cannot fail");
+        bytes
+    }
+
+    /// Values, blinding factors and master seed, to be stored apart from the
+    /// public export.
+    pub fn export_secret(&self) -> CommitmentResult<Vec<u8>> {
+        if self.secrets_cleared() {
+            return Err(ArchimedesError::CommitmentError(CommitmentErrorKind::SecretsCleared));
+        }
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&CHAIN_SECRET_MAGIC);
+        bytes.extend_from_slice(&CHAIN_EXPORT_VERSION.to_le_bytes());
+        self.values().serialize_compressed(&mut bytes)?;
+        self.randomness().serialize_compressed(&mut bytes)?;
+        self.master_seed().copied().serialize_compressed(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    /// Rebuilds a chain from its two exports. Lengths must agree and the
+    /// last entry is re-committed as a spot check, so files from different
+    /// chains (or swapped arguments) fail with a `SerializationError`.
+    pub fn restore(params: CommitmentParams<C>, public_bytes: &[u8], secret_bytes: &[u8]) -> CommitmentResult<Self> {
+        let mut public = chain_export_body(public_bytes, &CHAIN_PUBLIC_MAGIC, "public header")?;
+        let points = Vec::<C::Group>::deserialize_compressed(&mut public)?;
+        if !public.is_empty() {
+            return Err(ArchimedesError::serialization(SerializationErrorKind::TrailingBytes("public chain export")));
+        }
+        let mut secret = chain_export_body(secret_bytes, &CHAIN_SECRET_MAGIC, "secret header")?;
+        let (values, randomness, master_seed) =
+            <(Vec<C::Scalar>, Vec<Randomness<C>>, Option<[u8; 32]>)>::deserialize_compressed(&mut secret)?;
+        if !secret.is_empty() {
+            return Err(ArchimedesError::serialization(SerializationErrorKind::TrailingBytes("secret chain export")));
+        }
+        if values.len() != points.len() || randomness.len() != points.len() {
+            return Err(corrupted_chain("public and secret lengths differ"));
+        }
+        if let (Some(point), Some(value), Some(r)) = (points.last(), values.last(), randomness.last()) {
+            if params.commit_with_randomness(value, r)?.0 != *point {
+                return Err(corrupted_chain("spot check failed"));
+            }
+        }
+
+        let mut chain = match master_seed {
+            Some(seed) => CommitmentChain::with_master_seed(params, seed),
+            None => CommitmentChain::new(params),
+        };
+        for ((point, value), r) in points.into_iter().zip(values).zip(randomness) {
+            chain.append(value, Commitment(point), r, None);
+        }
+        Ok(chain)
+    }
+}
+
+// Wire formats exchanged between services (integers little-endian):
+//   aggregate:    magic[4] | version u32 | count u64 | commitment compressed
+//   public chain: magic[4] | version u32 | params_len u32 | params | count u64
+//                 | count × commitment compressed | count × (0 | 1 tag[32])
+// Decoders check every length against the remaining input before reading.
+
+fn truncated(what: &'static str) -> ArchimedesError {
+    ArchimedesError::serialization(SerializationErrorKind::Truncated(what))
+}
+
+struct WireReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> WireReader<'a> {
+    fn open(bytes: &'a [u8], magic: &[u8; 4], format: &'static str) -> CommitmentResult<Self> {
+        let mut reader = Self { bytes };
+        if reader.take(4, "header")? != magic {
+            return Err(ArchimedesError::serialization(SerializationErrorKind::WrongMagic(format)));
+        }
+        let version = reader.u32("header")?;
+        if version != WIRE_FORMAT_VERSION {
+            return Err(ArchimedesError::serialization(SerializationErrorKind::UnsupportedFormatVersion { format, version }));
+        }
+        Ok(reader)
+    }
+
+    fn take(&mut self, n: usize, what: &'static str) -> CommitmentResult<&'a [u8]> {
+        if self.bytes.len() < n {
+            return Err(truncated(what));
+        }
+        let (head, rest) = self.bytes.split_at(n);
+        self.bytes = rest;
+        Ok(head)
+    }
+
+    fn u32(&mut self, what: &'static str) -> CommitmentResult<u32> {
+        Ok(u32::from_le_bytes(self.take(4, what)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self, what: &'static str) -> CommitmentResult<u64> {
+        Ok(u64::from_le_bytes(self.take(8, what)?.try_into().unwrap()))
+    }
+
+    fn finish(self, format: &'static str) -> CommitmentResult<()> {
+        if !self.bytes.is_empty() {
+            return Err(ArchimedesError::serialization(SerializationErrorKind::TrailingBytes(format)));
+        }
+        Ok(())
+    }
+}
+
+fn point_size<C: CurveConfig>() -> usize {
+    Commitment::<C>::zero().to_bytes().len()
+}
+
+impl<C: CurveConfig> AggregateCommitment<C> {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(16 + point_size::<C>());
+        bytes.extend_from_slice(&AGGREGATE_MAGIC);
+        bytes.extend_from_slice(&WIRE_FORMAT_VERSION.to_le_bytes());
+        bytes.extend_from_slice(&(self.count as u64).to_le_bytes());
+        bytes.extend_from_slice(&self.commitment.to_bytes());
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> CommitmentResult<Self> {
+        let mut reader = WireReader::open(bytes, &AGGREGATE_MAGIC, "aggregate")?;
+        let count = usize::try_from(reader.u64("aggregate count")?)
+            .map_err(|_| ArchimedesError::serialization(SerializationErrorKind::Encoding))?;
+        let commitment = Commitment::from_bytes(reader.take(point_size::<C>(), "aggregate commitment")?)?;
+        reader.finish("aggregate")?;
+        Ok(Self { commitment, count })
+    }
+}
+
+impl<C: CurveConfig> PublicCommitmentChain<C> {
+    pub fn to_bytes(&self) -> CommitmentResult<Vec<u8>> {
+        let params = self.params.to_bytes()?;
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&PUBLIC_CHAIN_MAGIC);
+        bytes.extend_from_slice(&WIRE_FORMAT_VERSION.to_le_bytes());
+        bytes.extend_from_slice(&(params.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&params);
+        bytes.extend_from_slice(&(self.commitments().len() as u64).to_le_bytes());
+        for commitment in self.commitments() {
+            bytes.extend_from_slice(&commitment.to_bytes());
+        }
+        for tag in self.tags() {
+            match tag {
+                Some(tag) => {
+                    bytes.push(1);
+                    bytes.extend_from_slice(tag);
+                }
+                None => bytes.push(0),
+            }
+        }
+        Ok(bytes)
+    }
+
+    /// Decodes with the same length and subgroup checks as `from_untrusted`.
+    pub fn from_bytes(bytes: &[u8]) -> CommitmentResult<Self> {
+        let mut reader = WireReader::open(bytes, &PUBLIC_CHAIN_MAGIC, "public chain")?;
+        let params_len = reader.u32("params length")? as usize;
+        let params = CommitmentParams::from_bytes(reader.take(params_len, "params")?)?;
+        let count = reader.u64("commitment count")?;
+        let count = usize::try_from(count).map_err(|_| truncated("commitments"))?;
+        check_untrusted_len(count)?;
+        let size = point_size::<C>();
+        let points = reader.take(count.checked_mul(size).ok_or_else(|| truncated("commitments"))?, "commitments")?;
+        let encoded: Vec<&[u8]> = points.chunks_exact(size).collect();
+        let mut tags = Vec::with_capacity(count);
+        for _ in 0..count {
+            tags.push(match reader.take(1, "tags")?[0] {
+                0 => None,
+                1 => Some(reader.take(32, "tags")?.try_into().unwrap()),
+                _ => return Err(corrupted_chain("invalid tag flag")),
+            });
+        }
+        reader.finish("public chain")?;
+        PublicCommitmentChain::from_untrusted_bytes(params, &encoded)?.with_tags(tags)
+    }
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod tests {
+    use super::*;
+    use crate::curve::{curve_tests, DefaultCurve};
+    use ark_ec::PrimeGroup;
+    use ark_std::test_rng;
+
+    // Generated by `to_bytes` for g = generator, h = 7 * generator. Must keep
+    // loading unchanged.
+    const GOLDEN_PARAMS_V1: &str = "4152434850524d53010000000f00000065642d6f6e2d626c7331322d333831aa92d2590e873fccd7fe20c25cba263ec3c066c8782e1393171aabddf13c521df069d0537a8f7e4ca477c8d9a0212ee66d738f5a402177d0c57c9c41783c49bc";
+    // The same params written as version 2, with an empty domain.
+    const GOLDEN_PARAMS_V2: &str = "4152434850524d53020000000f00000065642d6f6e2d626c7331322d33383100000000aa92d2590e873fccd7fe20c25cba263ec3c066c8782e1393171aabddf13c521df069d0537a8f7e4ca477c8d9a0212ee66d738f5a402177d0c57c9c41783c49bc";
+
+    curve_tests!(
+        test_round_trip,
+        test_rejects_foreign_headers,
+        test_chain_export_round_trip,
+        test_chain_restore_rejects_mismatch,
+        test_wire_round_trip,
+        test_wire_decoders_never_panic,
+    );
+
+    fn test_round_trip<C: CurveConfig>() {
+        let mut rng = test_rng();
+        let params = CommitmentParams::<C>::setup(&mut rng).unwrap();
+        let bytes = params.to_bytes().unwrap();
+        assert_eq!(CommitmentParams::<C>::from_bytes(&bytes).unwrap(), params);
+
+        let path = std::env::temp_dir().join(format!("archimedes-params-{}-{}", C::CURVE_ID, std::process::id()));
+        params.save(&path).unwrap();
+        assert_eq!(CommitmentParams::<C>::load(&path).unwrap(), params);
+        let _ = std::fs::remove_file(&path);
+
+        let domained = CommitmentParams::<C>::setup_for_domain(&mut rng, "archimedes/stake").unwrap();
+        let loaded = CommitmentParams::<C>::from_bytes(&domained.to_bytes().unwrap()).unwrap();
+        assert_eq!(loaded.domain_tag(), "archimedes/stake");
+        assert_eq!(loaded, domained);
+    }
+
+    fn test_rejects_foreign_headers<C: CurveConfig>() {
+        let mut rng = test_rng();
+        let bytes = CommitmentParams::<C>::setup(&mut rng).unwrap().to_bytes().unwrap();
+
+        let mut future = bytes.clone();
+        future[8..12].copy_from_slice(&(PARAMS_VERSION + 1).to_le_bytes());
+        assert!(matches!(
+            CommitmentParams::<C>::from_bytes(&future),
+            Err(ArchimedesError::SetupError(SetupErrorKind::UnsupportedVersion { .. }))
+        ));
+
+        let mut other_curve = bytes.clone();
+        other_curve[16] ^= 1;
+        assert!(matches!(
+            CommitmentParams::<C>::from_bytes(&other_curve),
+            Err(ArchimedesError::SetupError(SetupErrorKind::CurveMismatch { .. }))
+        ));
+
+        assert!(matches!(CommitmentParams::<C>::from_bytes(&bytes[..10]), Err(ArchimedesError::SerializationError { .. })));
+        assert!(CommitmentParams::<C>::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+    }
+
+    fn test_chain_export_round_trip<C: CurveConfig>() {
+        let mut rng = test_rng();
+        let params = CommitmentParams::<C>::setup(&mut rng).unwrap();
+        let mut chain = CommitmentChain::new(params.clone());
+        let values: Vec<C::Scalar> = (0..1000u64).map(C::Scalar::from).collect();
+        chain.extend_from_values(&values, &mut rng).unwrap();
+
+        let public = chain.export_public();
+        let secret = chain.export_secret().unwrap();
+        let restored = CommitmentChain::restore(params.clone(), &public, &secret).unwrap();
+        assert_eq!(restored.commitments(), chain.commitments());
+        assert_eq!(restored.values(), chain.values());
+        assert_eq!(restored.randomness(), chain.randomness());
+        assert!(restored.verify_aggregate(&chain.aggregate()).unwrap());
+
+        let mut seeded = CommitmentChain::with_master_seed(params.clone(), [3u8; 32]);
+        seeded.extend_from_values(&values[..5], &mut rng).unwrap();
+        let restored = CommitmentChain::restore(params, &seeded.export_public(), &seeded.export_secret().unwrap()).unwrap();
+        assert!(restored.is_deterministic());
+
+        let empty = CommitmentChain::new(restored.params.clone());
+        assert!(CommitmentChain::restore(restored.params.clone(), &empty.export_public(), &empty.export_secret().unwrap())
+            .unwrap()
+            .is_empty());
+        seeded.clear_secrets();
+        assert!(seeded.export_secret().is_err());
+    }
+
+    fn test_chain_restore_rejects_mismatch<C: CurveConfig>() {
+        let mut rng = test_rng();
+        let params = CommitmentParams::<C>::setup(&mut rng).unwrap();
+        let values: Vec<C::Scalar> = (0..8u64).map(C::Scalar::from).collect();
+        let mut chain = CommitmentChain::new(params.clone());
+        chain.extend_from_values(&values, &mut rng).unwrap();
+        let mut other = CommitmentChain::new(params.clone());
+        other.extend_from_values(&values, &mut rng).unwrap();
+        let (public, secret) = (chain.export_public(), chain.export_secret().unwrap());
+
+        let is_serialization_error = |result: CommitmentResult<CommitmentChain<C>>| {
+            matches!(result, Err(ArchimedesError::SerializationError { .. }))
+        };
+        assert!(is_serialization_error(CommitmentChain::restore(params.clone(), &secret, &public)));
+        assert!(is_serialization_error(CommitmentChain::restore(params.clone(), &public, &other.export_secret().unwrap())));
+        assert!(is_serialization_error(CommitmentChain::restore(params.clone(), &public[..public.len() - 3], &secret)));
+        let mut shorter = chain.clone();
+        shorter.pop();
+        assert!(is_serialization_error(CommitmentChain::restore(params.clone(), &public, &shorter.export_secret().unwrap())));
+
+        // Corrupt the spot-checked last entry: it either fails to decode or
+        // no longer matches its opening.
+        let mut flipped = public.clone();
+        let last = flipped.len() - 10;
+        flipped[last] ^= 0x40;
+        assert!(is_serialization_error(CommitmentChain::restore(params.clone(), &flipped, &secret)));
+        let mut future = secret.clone();
+        future[8..12].copy_from_slice(&(CHAIN_EXPORT_VERSION + 1).to_le_bytes());
+        assert!(matches!(
+            CommitmentChain::restore(params, &public, &future),
+            Err(ArchimedesError::SerializationError { kind: SerializationErrorKind::UnsupportedChainVersion(2), .. })
+        ));
+    }
+
+    fn wire_chain<C: CurveConfig>() -> PublicCommitmentChain<C> {
+        let mut rng = test_rng();
+        let mut chain = CommitmentChain::new(CommitmentParams::<C>::setup(&mut rng).unwrap());
+        for i in 0..6u64 {
+            if i % 2 == 0 {
+                chain.push_tagged(C::Scalar::from(i), [i as u8; 32], &mut rng).unwrap();
+            } else {
+                chain.push(C::Scalar::from(i), &mut rng).unwrap();
+            }
+        }
+        chain.to_public()
+    }
+
+    fn test_wire_round_trip<C: CurveConfig>() {
+        let public = wire_chain::<C>();
+        let bytes = public.to_bytes().unwrap();
+        assert_eq!(bytes[..4], PUBLIC_CHAIN_MAGIC);
+        let decoded = PublicCommitmentChain::<C>::from_bytes(&bytes).unwrap();
+        assert_eq!(decoded, public);
+        assert_eq!(decoded.tag_at(2), Some([2u8; 32]));
+        assert_eq!(decoded.aggregate().commitment, public.aggregate().commitment);
+
+        let aggregate = public.aggregate_range(1, 5).unwrap();
+        let decoded = AggregateCommitment::<C>::from_bytes(&aggregate.to_bytes()).unwrap();
+        assert_eq!((&decoded.commitment, decoded.count), (&aggregate.commitment, 4));
+
+        let mut future = aggregate.to_bytes();
+        future[4..8].copy_from_slice(&(WIRE_FORMAT_VERSION + 1).to_le_bytes());
+        assert!(matches!(
+            AggregateCommitment::<C>::from_bytes(&future),
+            Err(ArchimedesError::SerializationError {
+                kind: SerializationErrorKind::UnsupportedFormatVersion { format: "aggregate", version: 2 },
+                ..
+            })
+        ));
+        assert!(matches!(
+            AggregateCommitment::<C>::from_bytes(&bytes),
+            Err(ArchimedesError::SerializationError { kind: SerializationErrorKind::WrongMagic("aggregate"), .. })
+        ));
+        let mut huge = bytes.clone();
+        let count_at = 12 + public.params.to_bytes().unwrap().len();
+        huge[count_at..count_at + 8].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert!(PublicCommitmentChain::<C>::from_bytes(&huge).is_err());
+    }
+
+    fn test_wire_decoders_never_panic<C: CurveConfig>() {
+        use ark_std::rand::{Rng, RngCore};
+
+        let chain = wire_chain::<C>().to_bytes().unwrap();
+        let aggregate = wire_chain::<C>().aggregate().to_bytes();
+        for cut in 0..chain.len() {
+            assert!(PublicCommitmentChain::<C>::from_bytes(&chain[..cut]).is_err());
+        }
+        for cut in 0..aggregate.len() {
+            assert!(matches!(
+                AggregateCommitment::<C>::from_bytes(&aggregate[..cut]),
+                Err(ArchimedesError::SerializationError { .. })
+            ));
+        }
+
+        let mut rng = test_rng();
+        for _ in 0..200 {
+            let mut noise = vec![0u8; rng.gen_range(0..256)];
+            rng.fill_bytes(&mut noise);
+            let _ = AggregateCommitment::<C>::from_bytes(&noise);
+            let _ = PublicCommitmentChain::<C>::from_bytes(&noise);
+            // Keep a valid header so the body parsers are exercised too.
+            let mut framed = chain.clone();
+            let at = rng.gen_range(8..framed.len());
+            framed[at] ^= rng.gen::<u8>() | 1;
+            let _ = PublicCommitmentChain::<C>::from_bytes(&framed);
+            let mut framed = aggregate.clone();
+            framed.truncate(rng.gen_range(8..=framed.len()));
+            framed.extend_from_slice(&noise[..noise.len().min(8)]);
+            let _ = AggregateCommitment::<C>::from_bytes(&framed);
+        }
+    }
+
+    #[test]
+    fn test_golden_params_load() {
+        let g = <DefaultCurve as CurveConfig>::Group::generator();
+        let expected = CommitmentParams::<DefaultCurve>::from_generators(g, g * <DefaultCurve as CurveConfig>::Scalar::from(7u64));
+        for golden in [GOLDEN_PARAMS_V1, GOLDEN_PARAMS_V2] {
+            let loaded = CommitmentParams::<DefaultCurve>::from_bytes(&hex::decode(golden).unwrap()).unwrap();
+            assert_eq!(loaded, expected);
+            assert_eq!(loaded.domain_tag(), "");
+        }
+        assert_eq!(hex::encode(expected.to_bytes().unwrap()), GOLDEN_PARAMS_V2);
+    }
+}
@@ -0,0 +1,137 @@
+use ark_ec::CurveGroup;
+#[cfg(feature = "prover")]
+use ark_ff::UniformRand;
+use ark_ff::{BigInteger, PrimeField};
+#[cfg(feature = "prover")]
+use ark_std::rand::Rng;
+use subtle::ConstantTimeEq;
+
+use crate::commitment::{Commitment, CommitmentParams, CommitmentResult, Opening, Randomness};
+use crate::curve::{CurveConfig, DefaultCurve};
+
+const WINDOW_BITS: usize = 8;
+
+/// Multiples `d * 2^(WINDOW_BITS * i) * base` for every window `i` and digit `d`,
+/// so a scalar multiplication becomes one mixed addition per window.
+#[derive(Clone, Debug)]
+struct FixedBaseTable<G: CurveGroup> {
+    windows: Vec<Vec<G::Affine>>,
+}
+
+impl<G: CurveGroup> FixedBaseTable<G> {
+    fn new(base: G) -> Self {
+        let num_windows = (G::ScalarField::MODULUS_BIT_SIZE as usize).div_ceil(WINDOW_BITS);
+        let mut windows = Vec::with_capacity(num_windows);
+        let mut window_base = base;
+        for _ in 0..num_windows {
+            let mut row = Vec::with_capacity(1 << WINDOW_BITS);
+            let mut acc = G::zero();
+            for _ in 0..(1 << WINDOW_BITS) {
+                row.push(acc);
+                acc += window_base;
+            }
+            windows.push(G::normalize_batch(&row));
+            for _ in 0..WINDOW_BITS {
+                window_base.double_in_place();
+            }
+        }
+        Self { windows }
+    }
+
+    fn mul(&self, scalar: &G::ScalarField) -> G {
+        let bits = scalar.into_bigint();
+        let mut acc = G::zero();
+        for (i, row) in self.windows.iter().enumerate() {
+            let digit = (0..WINDOW_BITS)
+                .filter(|b| bits.get_bit(i * WINDOW_BITS + b))
+                .fold(0usize, |d, b| d | (1 << b));
+            acc += row[digit];
+        }
+        acc
+    }
+}
+
+/// `CommitmentParams` with fixed-base tables for `g` and `h`. Produces exactly
+/// the same commitments as the plain parameters, without per-call scalar
+/// multiplication by the generators.
+#[derive(Clone, Debug)]
+pub struct PreparedCommitmentParams<C: CurveConfig = DefaultCurve> {
+    params: CommitmentParams<C>,
+    g_table: FixedBaseTable<C::Group>,
+    h_table: FixedBaseTable<C::Group>,
+}
+
+impl<C: CurveConfig> PreparedCommitmentParams<C> {
+    pub fn new(params: CommitmentParams<C>) -> Self {
+        Self {
+            g_table: FixedBaseTable::new(params.g),
+            h_table: FixedBaseTable::new(params.h),
+            params,
+        }
+    }
+
+    pub fn params(&self) -> &CommitmentParams<C> {
+        &self.params
+    }
+
+    pub fn commit_with_randomness(&self, value: &C::Scalar, randomness: &Randomness<C>) -> CommitmentResult<Commitment<C>> {
+        Ok(Commitment(self.g_table.mul(value) + self.h_table.mul(&randomness.0)))
+    }
+
+    /// Same constant-time comparison as `CommitmentParams::verify`.
+    pub fn verify(&self, commitment: &Commitment<C>, opening: &Opening<C>) -> CommitmentResult<bool> {
+        let expected = self.commit_with_randomness(&opening.value, &opening.randomness)?;
+        Ok(commitment.ct_eq(&expected).into())
+    }
+}
+
+#[cfg(feature = "prover")]
+impl<C: CurveConfig> PreparedCommitmentParams<C> {
+    pub fn commit<R: Rng>(&self, value: &C::Scalar, rng: &mut R) -> CommitmentResult<(Commitment<C>, Randomness<C>)> {
+        let randomness = Randomness(C::Scalar::rand(rng));
+        let commitment = self.commit_with_randomness(value, &randomness)?;
+        Ok((commitment, randomness))
+    }
+}
+
+impl<C: CurveConfig> CommitmentParams<C> {
+    pub fn prepare(&self) -> PreparedCommitmentParams<C> {
+        PreparedCommitmentParams::new(self.clone())
+    }
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod tests {
+    use super::*;
+    use crate::curve::curve_tests;
+    use ark_ff::Zero;
+    use ark_std::test_rng;
+
+    curve_tests!(test_prepared_matches_plain, test_prepared_verify);
+
+    fn test_prepared_matches_plain<C: CurveConfig>() {
+        let mut rng = test_rng();
+        let params = CommitmentParams::<C>::setup(&mut rng).unwrap();
+        let prepared = params.prepare();
+        let mut values = vec![C::Scalar::zero(), C::Scalar::from(1u64), -C::Scalar::from(1u64)];
+        values.extend((0..20).map(|_| C::Scalar::rand(&mut rng)));
+        for value in values {
+            let randomness = Randomness(C::Scalar::rand(&mut rng));
+            let plain = params.commit_with_randomness(&value, &randomness).unwrap();
+            let fast = prepared.commit_with_randomness(&value, &randomness).unwrap();
+            assert_eq!(plain.to_bytes(), fast.to_bytes());
+        }
+    }
+
+    fn test_prepared_verify<C: CurveConfig>() {
+        let mut rng = test_rng();
+        let prepared = CommitmentParams::<C>::setup(&mut rng).unwrap().prepare();
+        let value = C::Scalar::from(77u64);
+        let (commitment, randomness) = prepared.commit(&value, &mut rng).unwrap();
+        let opening = Opening { value, randomness };
+        assert!(prepared.verify(&commitment, &opening).unwrap());
+        assert!(prepared.params().verify(&commitment, &opening).unwrap());
+        let wrong = Opening { value: value + C::Scalar::from(1u64), randomness: opening.randomness.clone() };
+        assert!(!prepared.verify(&commitment, &wrong).unwrap());
+    }
+}
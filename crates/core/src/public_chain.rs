@@ -0,0 +1,237 @@
+use serde::{Deserialize, Serialize};
+
+use crate::aggregation::{AggregateCommitment, CommitmentChain};
+use crate::commitment::{decode_hex, encode_hex, Commitment, CommitmentParams, CommitmentResult};
+use crate::curve::{CurveConfig, DefaultCurve};
+use crate::errors::{AggregationErrorKind, ArchimedesError, SerializationErrorKind};
+
+type Tag = [u8; 32];
+
+/// Most commitments `from_untrusted` will accept.
+pub const MAX_PUBLIC_CHAIN_LEN: usize = 1 << 30;
+
+/// The commitments of a chain without its values or blinding factors: what a
+/// challenger or verifier holds, and safe to hand to either.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(try_from = "PublicChainRepr", into = "PublicChainRepr", bound = "")]
+pub struct PublicCommitmentChain<C: CurveConfig = DefaultCurve> {
+    pub params: CommitmentParams<C>,
+    commitments: Vec<Commitment<C>>,
+    /// The source chain's per-entry tags, `None` where untagged. Always the
+    /// same length as `commitments`.
+    tags: Vec<Option<Tag>>,
+    aggregate: AggregateCommitment<C>,
+}
+
+impl<C: CurveConfig> PartialEq for PublicCommitmentChain<C> {
+    fn eq(&self, other: &Self) -> bool {
+        self.params == other.params && self.commitments == other.commitments && self.tags == other.tags
+    }
+}
+
+impl<C: CurveConfig> Eq for PublicCommitmentChain<C> {}
+
+/// Wire form: params in the persisted format, compressed commitments and
+/// tags, all hex-encoded. Decoding re-runs the params and subgroup checks.
+/// `tags` may be omitted, meaning every entry is untagged.
+#[derive(Serialize, Deserialize)]
+struct PublicChainRepr {
+    params: String,
+    commitments: Vec<String>,
+    #[serde(default)]
+    tags: Vec<Option<String>>,
+}
+
+impl<C: CurveConfig> From<PublicCommitmentChain<C>> for PublicChainRepr {
+    fn from(chain: PublicCommitmentChain<C>) -> Self {
+        Self {
+            params: encode_hex(&chain.params.to_bytes().expect("serializing to a Vec cannot fail")),
+            commitments: chain.commitments.iter().map(Commitment::to_hex).collect(),
+            tags: chain.tags.iter().map(|t| t.as_ref().map(|t| encode_hex(t))).collect(),
+        }
+    }
+}
+
+impl<C: CurveConfig> TryFrom<PublicChainRepr> for PublicCommitmentChain<C> {
+    type Error = ArchimedesError;
+
+    fn try_from(repr: PublicChainRepr) -> CommitmentResult<Self> {
+        check_untrusted_len(repr.commitments.len())?;
+        let params = CommitmentParams::from_bytes(&decode_hex(&repr.params)?)?;
+        let commitments = repr.commitments.iter().map(|c| Commitment::from_hex(c)).collect::<CommitmentResult<_>>()?;
+        let chain = Self::from_commitments(params, commitments);
+        if repr.tags.is_empty() {
+            return Ok(chain);
+        }
+        let tags = repr.tags.iter().map(|t| t.as_deref().map(decode_tag).transpose()).collect::<CommitmentResult<_>>()?;
+        chain.with_tags(tags)
+    }
+}
+
+impl<C: CurveConfig> PublicCommitmentChain<C> {
+    /// Trusts `commitments` as given; see `from_untrusted` for network input.
+    pub fn from_commitments(params: CommitmentParams<C>, commitments: Vec<Commitment<C>>) -> Self {
+        let aggregate = AggregateCommitment::from_commitments(&commitments);
+        Self {
+            params,
+            tags: vec![None; commitments.len()],
+            commitments,
+            aggregate,
+        }
+    }
+
+    /// Attaches per-entry tags, one for each commitment.
+    pub fn with_tags(self, tags: Vec<Option<Tag>>) -> CommitmentResult<Self> {
+        if tags.len() != self.commitments.len() {
+            return Err(ArchimedesError::serialization(SerializationErrorKind::CorruptedChain(
+                "tag count does not match commitments",
+            )));
+        }
+        Ok(Self { tags, ..self })
+    }
+
+    pub fn commitments(&self) -> &[Commitment<C>] {
+        &self.commitments
+    }
+
+    pub fn tags(&self) -> &[Option<Tag>] {
+        &self.tags
+    }
+
+    pub fn tag_at(&self, index: usize) -> Option<Tag> {
+        self.tags.get(index).copied().flatten()
+    }
+
+    /// Rejects more than `MAX_PUBLIC_CHAIN_LEN` commitments, or any outside
+    /// the prime-order subgroup, before summing them.
+    pub fn from_untrusted(params: CommitmentParams<C>, commitments: Vec<Commitment<C>>) -> CommitmentResult<Self> {
+        check_untrusted_len(commitments.len())?;
+        if !commitments.iter().all(Commitment::is_valid) {
+            return Err(ArchimedesError::serialization(SerializationErrorKind::NotInSubgroup));
+        }
+        Ok(Self::from_commitments(params, commitments))
+    }
+
+    /// `from_untrusted` over compressed encodings, one per commitment.
+    pub fn from_untrusted_bytes<B: AsRef<[u8]>>(params: CommitmentParams<C>, encoded: &[B]) -> CommitmentResult<Self> {
+        check_untrusted_len(encoded.len())?;
+        let commitments = encoded.iter().map(|b| Commitment::from_bytes(b.as_ref())).collect::<CommitmentResult<_>>()?;
+        Ok(Self::from_commitments(params, commitments))
+    }
+
+    /// Computed once at construction; the commitments cannot change after.
+    pub fn aggregate(&self) -> AggregateCommitment<C> {
+        self.aggregate.clone()
+    }
+
+    pub fn aggregate_range(&self, start: usize, end: usize) -> CommitmentResult<AggregateCommitment<C>> {
+        if end > self.commitments.len() || start > end {
+            return Err(ArchimedesError::AggregationError(AggregationErrorKind::InvalidRange {
+                start,
+                end,
+                len: self.commitments.len(),
+            }));
+        }
+        Ok(AggregateCommitment::from_commitments(&self.commitments[start..end]))
+    }
+
+    pub fn len(&self) -> usize {
+        self.commitments.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commitments.is_empty()
+    }
+}
+
+fn decode_tag(hex: &str) -> CommitmentResult<Tag> {
+    decode_hex(hex)?
+        .try_into()
+        .map_err(|_| ArchimedesError::serialization(SerializationErrorKind::CorruptedChain("tag is not 32 bytes")))
+}
+
+pub(crate) fn check_untrusted_len(len: usize) -> CommitmentResult<()> {
+    if len > MAX_PUBLIC_CHAIN_LEN {
+        return Err(ArchimedesError::AggregationError(AggregationErrorKind::ChainFull {
+            max_len: MAX_PUBLIC_CHAIN_LEN,
+        }));
+    }
+    Ok(())
+}
+
+impl<C: CurveConfig> CommitmentChain<C> {
+    pub fn to_public(&self) -> PublicCommitmentChain<C> {
+        PublicCommitmentChain::from_commitments(self.params.clone(), self.commitments().to_vec())
+            .with_tags(self.tags())
+            .expect("chain keeps one metadata slot per commitment")
+    }
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod tests {
+    use super::*;
+    use crate::curve::curve_tests;
+    use ark_std::test_rng;
+
+    curve_tests!(test_public_matches_chain, test_serde_round_trip, test_from_untrusted);
+
+    fn chain_of<C: CurveConfig>(n: u64) -> CommitmentChain<C> {
+        let mut rng = test_rng();
+        let mut chain = CommitmentChain::new(CommitmentParams::<C>::setup(&mut rng).unwrap());
+        for i in 0..n {
+            chain.push(C::Scalar::from(i), &mut rng).unwrap();
+        }
+        chain
+    }
+
+    fn test_public_matches_chain<C: CurveConfig>() {
+        let chain = chain_of::<C>(7);
+        let public = chain.to_public();
+        assert_eq!(public.len(), 7);
+        assert_eq!(public.aggregate().commitment, chain.aggregate().commitment);
+        assert_eq!(public.aggregate_range(2, 5).unwrap().commitment, chain.aggregate_range(2, 5).unwrap().commitment);
+        assert_eq!(public.aggregate_range(3, 3).unwrap().count, 0);
+        assert!(public.aggregate_range(4, 8).is_err());
+        assert_eq!(PublicCommitmentChain::from_commitments(chain.params.clone(), chain.commitments().to_vec()), public);
+    }
+
+    fn test_from_untrusted<C: CurveConfig>() {
+        let chain = chain_of::<C>(5);
+        let public = PublicCommitmentChain::from_untrusted(chain.params.clone(), chain.commitments().to_vec()).unwrap();
+        assert_eq!(public, chain.to_public());
+        assert_eq!(public.aggregate().commitment, chain.aggregate().commitment);
+
+        let encoded: Vec<Vec<u8>> = chain.commitments().iter().map(Commitment::to_bytes).collect();
+        let decoded = PublicCommitmentChain::from_untrusted_bytes(chain.params.clone(), &encoded).unwrap();
+        assert_eq!(decoded, public);
+
+        let mut bad = encoded.clone();
+        bad[2] = vec![0xff; bad[2].len()];
+        assert!(PublicCommitmentChain::<C>::from_untrusted_bytes(chain.params.clone(), &bad).is_err());
+        assert!(check_untrusted_len(MAX_PUBLIC_CHAIN_LEN).is_ok());
+        assert!(check_untrusted_len(MAX_PUBLIC_CHAIN_LEN + 1).is_err());
+    }
+
+    fn test_serde_round_trip<C: CurveConfig>() {
+        let mut chain = chain_of::<C>(4);
+        chain.push_tagged(C::Scalar::from(4u64), [0xabu8; 32], &mut test_rng()).unwrap();
+        let public = chain.to_public();
+        assert_eq!(public.tag_at(4), Some([0xabu8; 32]));
+        assert_eq!(public.tag_at(0), None);
+        let json = serde_json::to_string(&public).unwrap();
+        let decoded: PublicCommitmentChain<C> = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, public);
+        assert_eq!(decoded.tag_at(4), Some([0xabu8; 32]));
+
+        let mut untagged: serde_json::Value = serde_json::from_str(&json).unwrap();
+        untagged.as_object_mut().unwrap().remove("tags");
+        let decoded: PublicCommitmentChain<C> = serde_json::from_value(untagged.clone()).unwrap();
+        assert_eq!(decoded.tags, vec![None; 5]);
+        untagged["tags"] = serde_json::json!([null]);
+        assert!(serde_json::from_value::<PublicCommitmentChain<C>>(untagged).is_err());
+
+        let mut value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        value["commitments"][1] = serde_json::Value::String("00".repeat(public.commitments[0].to_bytes().len() - 1) + "zz");
+        assert!(serde_json::from_value::<PublicCommitmentChain<C>>(value).is_err());
+    }
+}
@@ -0,0 +1,222 @@
+use ark_ff::{AdditiveGroup, PrimeField, Zero};
+#[cfg(feature = "prover")]
+use ark_ff::{BigInteger, One, UniformRand};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+#[cfg(feature = "prover")]
+use ark_std::rand::Rng;
+use blake2::{Blake2b512, Digest};
+
+use crate::commitment::{Commitment, CommitmentParams, CommitmentResult};
+#[cfg(feature = "prover")]
+use crate::commitment::Randomness;
+use crate::curve::{CurveConfig, DefaultCurve};
+use crate::errors::{ArchimedesError, InputErrorKind};
+
+const RANGE_PROOF_DOMAIN: &[u8] = b"archimedes-range-proof-v1";
+
+/// Sigma OR proof that a bit commitment `B` opens to 0 (`B = h*r`) or to 1
+/// (`B - g = h*r`). The second challenge is implied: `e1 = e - e0`.
+#[derive(Clone, Debug, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct BitProof<C: CurveConfig = DefaultCurve> {
+    pub announcements: (C::Group, C::Group),
+    pub challenge_zero: C::Scalar,
+    pub responses: (C::Scalar, C::Scalar),
+}
+
+/// Shows a commitment opens to a value in `[0, 2^bits)`. The bit commitments
+/// are blinded so that `sum(2^i * B_i)` equals the original commitment.
+#[derive(Clone, Debug, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct RangeProof<C: CurveConfig = DefaultCurve> {
+    pub bit_commitments: Vec<C::Group>,
+    pub bit_proofs: Vec<BitProof<C>>,
+}
+
+fn check_bits<C: CurveConfig>(bits: usize) -> CommitmentResult<()> {
+    if bits == 0 || bits >= C::Scalar::MODULUS_BIT_SIZE as usize {
+        return Err(ArchimedesError::InvalidInput(InputErrorKind::UnsupportedRangeWidth { bits }));
+    }
+    Ok(())
+}
+
+fn bit_challenge<C: CurveConfig>(
+    params: &CommitmentParams<C>,
+    commitment: &C::Group,
+    bits: usize,
+    index: usize,
+    bit_commitment: &C::Group,
+    announcements: &(C::Group, C::Group),
+) -> CommitmentResult<C::Scalar> {
+    let mut transcript = Vec::new();
+    for point in [params.g, params.h, *commitment, *bit_commitment, announcements.0, announcements.1] {
+        point.serialize_compressed(&mut transcript)?;
+    }
+    let mut hasher = Blake2b512::new();
+    hasher.update(RANGE_PROOF_DOMAIN);
+    hasher.update(C::CURVE_ID.as_bytes());
+    hasher.update((bits as u64).to_le_bytes());
+    hasher.update((index as u64).to_le_bytes());
+    hasher.update(&transcript);
+    Ok(C::Scalar::from_le_bytes_mod_order(&hasher.finalize()))
+}
+
+#[cfg(feature = "prover")]
+pub fn prove_range<C: CurveConfig, R: Rng>(
+    params: &CommitmentParams<C>,
+    value: &C::Scalar,
+    randomness: &Randomness<C>,
+    bits: usize,
+    rng: &mut R,
+) -> CommitmentResult<RangeProof<C>> {
+    check_bits::<C>(bits)?;
+    let value_bits = value.into_bigint();
+    if value_bits.num_bits() as usize > bits {
+        return Err(ArchimedesError::InvalidInput(InputErrorKind::ValueOutOfRange { bits }));
+    }
+    let commitment = params.commit_with_randomness(value, randomness)?.0;
+
+    // Pick every blinding but the lowest at random; the lowest absorbs the
+    // remainder so the weighted sum of bit commitments reproduces `commitment`.
+    let mut blindings: Vec<C::Scalar> = (0..bits).map(|_| C::Scalar::rand(rng)).collect();
+    let mut weight = C::Scalar::one();
+    let mut weighted = C::Scalar::zero();
+    for r in blindings.iter().skip(1) {
+        weight.double_in_place();
+        weighted += weight * r;
+    }
+    blindings[0] = randomness.0 - weighted;
+
+    let mut bit_commitments = Vec::with_capacity(bits);
+    let mut bit_proofs = Vec::with_capacity(bits);
+    for (i, r) in blindings.iter().enumerate() {
+        let bit = value_bits.get_bit(i);
+        let bit_commitment = if bit { params.g + params.h * r } else { params.h * r };
+        // Simulate the branch for the bit we don't have.
+        let fake = if bit { bit_commitment } else { bit_commitment - params.g };
+
+        let simulated_challenge = C::Scalar::rand(rng);
+        let simulated_response = C::Scalar::rand(rng);
+        let nonce = C::Scalar::rand(rng);
+        let fake_announcement = params.h * simulated_response - fake * simulated_challenge;
+        let real_announcement = params.h * nonce;
+        let announcements = if bit {
+            (fake_announcement, real_announcement)
+        } else {
+            (real_announcement, fake_announcement)
+        };
+
+        let challenge = bit_challenge(params, &commitment, bits, i, &bit_commitment, &announcements)?;
+        let real_challenge = challenge - simulated_challenge;
+        let real_response = nonce + real_challenge * r;
+        let proof = if bit {
+            BitProof {
+                announcements,
+                challenge_zero: simulated_challenge,
+                responses: (simulated_response, real_response),
+            }
+        } else {
+            BitProof {
+                announcements,
+                challenge_zero: real_challenge,
+                responses: (real_response, simulated_response),
+            }
+        };
+        bit_commitments.push(bit_commitment);
+        bit_proofs.push(proof);
+    }
+
+    Ok(RangeProof {
+        bit_commitments,
+        bit_proofs,
+    })
+}
+
+pub fn verify_range<C: CurveConfig>(
+    params: &CommitmentParams<C>,
+    commitment: &Commitment<C>,
+    proof: &RangeProof<C>,
+    bits: usize,
+) -> CommitmentResult<bool> {
+    check_bits::<C>(bits)?;
+    if proof.bit_commitments.len() != bits || proof.bit_proofs.len() != bits {
+        return Ok(false);
+    }
+
+    let mut recombined = C::Group::zero();
+    for b in proof.bit_commitments.iter().rev() {
+        recombined.double_in_place();
+        recombined += b;
+    }
+    if recombined != commitment.0 {
+        return Ok(false);
+    }
+
+    for (i, (b, p)) in proof.bit_commitments.iter().zip(&proof.bit_proofs).enumerate() {
+        let challenge = bit_challenge(params, &commitment.0, bits, i, b, &p.announcements)?;
+        let challenge_one = challenge - p.challenge_zero;
+        let zero_ok = params.h * p.responses.0 == p.announcements.0 + *b * p.challenge_zero;
+        let one_ok = params.h * p.responses.1 == p.announcements.1 + (*b - params.g) * challenge_one;
+        if !zero_ok || !one_ok {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod tests {
+    use super::*;
+    use crate::curve::curve_tests;
+    use ark_std::test_rng;
+
+    curve_tests!(test_range_proof_round_trip, test_range_proof_rejects_out_of_range);
+
+    fn test_range_proof_round_trip<C: CurveConfig>() {
+        let mut rng = test_rng();
+        let params = CommitmentParams::<C>::setup(&mut rng).unwrap();
+        for value in [0u64, 1, 0xdead_beef, u64::MAX] {
+            let value = C::Scalar::from(value);
+            let (commitment, randomness) = params.commit(&value, &mut rng).unwrap();
+            let proof = prove_range(&params, &value, &randomness, 64, &mut rng).unwrap();
+            assert!(verify_range(&params, &commitment, &proof, 64).unwrap());
+
+            let mut bytes = Vec::new();
+            proof.serialize_compressed(&mut bytes).unwrap();
+            let decoded = RangeProof::<C>::deserialize_compressed(&bytes[..]).unwrap();
+            assert!(verify_range(&params, &commitment, &decoded, 64).unwrap());
+        }
+
+        let value = crate::commitment::scalar_from_u128::<C::Scalar>(u128::MAX);
+        let (commitment, randomness) = params.commit(&value, &mut rng).unwrap();
+        let proof = prove_range(&params, &value, &randomness, 128, &mut rng).unwrap();
+        assert!(verify_range(&params, &commitment, &proof, 128).unwrap());
+    }
+
+    fn test_range_proof_rejects_out_of_range<C: CurveConfig>() {
+        let mut rng = test_rng();
+        let params = CommitmentParams::<C>::setup(&mut rng).unwrap();
+        let wrapped = -C::Scalar::from(1u64);
+        let (_, randomness) = params.commit(&wrapped, &mut rng).unwrap();
+        assert!(prove_range(&params, &wrapped, &randomness, 64, &mut rng).is_err());
+        let big = C::Scalar::from(u64::MAX) + C::Scalar::from(1u64);
+        assert!(prove_range(&params, &big, &randomness, 64, &mut rng).is_err());
+
+        // A 65-bit proof does not pass as a 64-bit one.
+        let (commitment, randomness) = params.commit(&big, &mut rng).unwrap();
+        let wide = prove_range(&params, &big, &randomness, 65, &mut rng).unwrap();
+        assert!(verify_range(&params, &commitment, &wide, 65).unwrap());
+        assert!(!verify_range(&params, &commitment, &wide, 64).unwrap());
+
+        // Forge a "bit" commitment opening to 2 by shifting value between bits.
+        let value = C::Scalar::from(2u64);
+        let (commitment, randomness) = params.commit(&value, &mut rng).unwrap();
+        let mut forged = prove_range(&params, &value, &randomness, 8, &mut rng).unwrap();
+        forged.bit_commitments[0] += params.g + params.g;
+        forged.bit_commitments[1] -= params.g;
+        assert!(!verify_range(&params, &commitment, &forged, 8).unwrap());
+
+        let (other, _) = params.commit(&value, &mut rng).unwrap();
+        let proof = prove_range(&params, &value, &randomness, 8, &mut rng).unwrap();
+        assert!(!verify_range(&params, &other, &proof, 8).unwrap());
+        assert!(verify_range(&params, &commitment, &proof, 0).is_err());
+    }
+}
@@ -0,0 +1,157 @@
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{Deserialize, Serialize};
+
+use crate::errors::{ArchimedesError, InputErrorKind};
+
+type Result<T> = std::result::Result<T, ArchimedesError>;
+
+pub type DisputeId = [u8; 32];
+
+/// Anything a component may want to delete that a dispute may still need.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum ResourceId {
+    Content([u8; 32]),
+    Witness([u8; 32]),
+    TreeSnapshot([u8; 32]),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Lease {
+    pub dispute_id: DisputeId,
+    pub resources: Vec<ResourceId>,
+    pub acquired_at: u64,
+    pub expires_at: u64,
+}
+
+/// Single source of truth for "may this be deleted". Disputes hold leases on
+/// sets of resources; every deleting component asks `is_retained` first.
+///
+/// Leases are released on resolution. A dispute that disappears without
+/// resolving keeps its lease until `lease_timeout` has passed and
+/// `expire_stale` is run.
+#[derive(Clone, Debug)]
+pub struct RetentionRegistry {
+    lease_timeout: u64,
+    leases: BTreeMap<DisputeId, Lease>,
+    holds: HashMap<ResourceId, usize>,
+}
+
+impl RetentionRegistry {
+    pub fn new(lease_timeout: u64) -> Self {
+        Self {
+            lease_timeout,
+            leases: BTreeMap::new(),
+            holds: HashMap::new(),
+        }
+    }
+
+    pub fn acquire(&mut self, dispute_id: DisputeId, resources: &[ResourceId], now: u64) -> Result<&Lease> {
+        if self.leases.contains_key(&dispute_id) {
+            return Err(ArchimedesError::InvalidInput(InputErrorKind::LeaseExists));
+        }
+        let mut resources = resources.to_vec();
+        resources.sort();
+        resources.dedup();
+        for resource in &resources {
+            *self.holds.entry(*resource).or_default() += 1;
+        }
+        let lease = Lease {
+            dispute_id,
+            resources,
+            acquired_at: now,
+            expires_at: now.saturating_add(self.lease_timeout),
+        };
+        Ok(self.leases.entry(dispute_id).or_insert(lease))
+    }
+
+    /// Extends a live dispute's lease by another full timeout from `now`.
+    pub fn renew(&mut self, dispute_id: &DisputeId, now: u64) -> Result<()> {
+        let lease = self
+            .leases
+            .get_mut(dispute_id)
+            .ok_or(ArchimedesError::InvalidInput(InputErrorKind::NoLease))?;
+        lease.expires_at = now.saturating_add(self.lease_timeout);
+        Ok(())
+    }
+
+    pub fn release(&mut self, dispute_id: &DisputeId) -> Option<Lease> {
+        let lease = self.leases.remove(dispute_id)?;
+        for resource in &lease.resources {
+            if let Some(count) = self.holds.get_mut(resource) {
+                *count -= 1;
+                if *count == 0 {
+                    self.holds.remove(resource);
+                }
+            }
+        }
+        Some(lease)
+    }
+
+    /// Releases every lease whose expiry is at or before `now`, returning the
+    /// disputes that were dropped.
+    pub fn expire_stale(&mut self, now: u64) -> Vec<DisputeId> {
+        let stale: Vec<DisputeId> = self
+            .leases
+            .values()
+            .filter(|lease| lease.expires_at <= now)
+            .map(|lease| lease.dispute_id)
+            .collect();
+        for dispute_id in &stale {
+            self.release(dispute_id);
+        }
+        stale
+    }
+
+    pub fn is_retained(&self, resource: &ResourceId) -> bool {
+        self.holds.contains_key(resource)
+    }
+
+    pub fn lease(&self, dispute_id: &DisputeId) -> Option<&Lease> {
+        self.leases.get(dispute_id)
+    }
+
+    /// Current leases ordered by dispute id, for audit listings.
+    pub fn leases(&self) -> impl Iterator<Item = &Lease> {
+        self.leases.values()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_overlapping_leases() {
+        let mut registry = RetentionRegistry::new(1_000);
+        let shared = ResourceId::Content([1u8; 32]);
+        let witness = ResourceId::Witness([2u8; 32]);
+        registry.acquire([10u8; 32], &[shared, witness, shared], 0).unwrap();
+        registry.acquire([11u8; 32], &[shared], 5).unwrap();
+        assert!(registry.acquire([11u8; 32], &[witness], 6).is_err());
+
+        assert_eq!(registry.lease(&[10u8; 32]).unwrap().resources.len(), 2);
+        registry.release(&[10u8; 32]).unwrap();
+        assert!(registry.is_retained(&shared));
+        assert!(!registry.is_retained(&witness));
+        registry.release(&[11u8; 32]).unwrap();
+        assert!(!registry.is_retained(&shared));
+        assert!(registry.release(&[11u8; 32]).is_none());
+    }
+
+    #[test]
+    fn test_stale_leases_expire() {
+        let mut registry = RetentionRegistry::new(100);
+        let snapshot = ResourceId::TreeSnapshot([3u8; 32]);
+        registry.acquire([1u8; 32], &[snapshot], 0).unwrap();
+        registry.acquire([2u8; 32], &[snapshot], 50).unwrap();
+        registry.renew(&[1u8; 32], 90).unwrap();
+
+        assert!(registry.expire_stale(149).is_empty());
+        assert_eq!(registry.expire_stale(150), vec![[2u8; 32]]);
+        assert!(registry.is_retained(&snapshot));
+        assert_eq!(registry.expire_stale(190), vec![[1u8; 32]]);
+        assert!(!registry.is_retained(&snapshot));
+        assert_eq!(registry.leases().count(), 0);
+    }
+}
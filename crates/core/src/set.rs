@@ -0,0 +1,86 @@
+use std::collections::BTreeSet;
+
+use crate::commitment::Commitment;
+use crate::curve::{CurveConfig, DefaultCurve};
+
+/// Ordered set of commitments, keyed by their canonical encoding.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CommitmentSet<C: CurveConfig = DefaultCurve> {
+    inner: BTreeSet<Commitment<C>>,
+}
+
+impl<C: CurveConfig> CommitmentSet<C> {
+    pub fn new() -> Self {
+        Self { inner: BTreeSet::new() }
+    }
+
+    /// Returns whether the commitment was newly inserted.
+    pub fn insert(&mut self, commitment: Commitment<C>) -> bool {
+        self.inner.insert(commitment)
+    }
+
+    pub fn contains(&self, commitment: &Commitment<C>) -> bool {
+        self.inner.contains(commitment)
+    }
+
+    pub fn union(&self, other: &Self) -> Self {
+        Self {
+            inner: self.inner.union(&other.inner).cloned().collect(),
+        }
+    }
+
+    pub fn intersection(&self, other: &Self) -> Self {
+        Self {
+            inner: self.inner.intersection(&other.inner).cloned().collect(),
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Commitment<C>> {
+        self.inner.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+}
+
+impl<C: CurveConfig> FromIterator<Commitment<C>> for CommitmentSet<C> {
+    fn from_iter<I: IntoIterator<Item = Commitment<C>>>(iter: I) -> Self {
+        Self {
+            inner: iter.into_iter().collect(),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod tests {
+    use super::*;
+    use crate::commitment::CommitmentParams;
+    use crate::curve::curve_tests;
+    use ark_std::test_rng;
+
+    curve_tests!(test_union_intersection);
+
+    fn test_union_intersection<C: CurveConfig>() {
+        let mut rng = test_rng();
+        let params = CommitmentParams::<C>::setup(&mut rng).unwrap();
+        let c: Vec<Commitment<C>> = (0..4u64).map(|v| params.commit(&C::Scalar::from(v), &mut rng).unwrap().0).collect();
+        let a: CommitmentSet<C> = c[..3].iter().cloned().collect();
+        let b: CommitmentSet<C> = c[1..].iter().cloned().collect();
+
+        assert_eq!(a.union(&b).len(), 4);
+        let both = a.intersection(&b);
+        assert_eq!(both.len(), 2);
+        assert!(both.contains(&c[1]) && both.contains(&c[2]) && !both.contains(&c[0]));
+
+        let mut set = CommitmentSet::new();
+        assert!(set.insert(c[0].clone()));
+        // The same point in another projective representation is not new.
+        assert!(!set.insert(&(&c[0] + &c[1]) - &c[1]));
+        assert_eq!(set.len(), 1);
+    }
+}
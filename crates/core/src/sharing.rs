@@ -0,0 +1,151 @@
+#[cfg(feature = "prover")]
+use ark_ff::UniformRand;
+use ark_ff::{Field, Zero};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+#[cfg(feature = "prover")]
+use ark_std::rand::Rng;
+
+use crate::commitment::{CommitmentResult, Randomness};
+use crate::curve::{CurveConfig, DefaultCurve};
+use crate::errors::{ArchimedesError, InputErrorKind};
+
+/// One custodian's Shamir share of a blinding factor: the sharing
+/// polynomial evaluated at `index`. Shares only combine with others carrying
+/// the same `set_id`, which is drawn fresh for every split.
+#[derive(Clone, Debug, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct RandomnessShare<C: CurveConfig = DefaultCurve> {
+    pub set_id: [u8; 32],
+    pub threshold: u32,
+    pub index: u32,
+    pub value: C::Scalar,
+}
+
+#[cfg(feature = "zeroize")]
+impl<C: CurveConfig> zeroize::Zeroize for RandomnessShare<C> {
+    fn zeroize(&mut self) {
+        self.value.zeroize();
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl<C: CurveConfig> Drop for RandomnessShare<C> {
+    fn drop(&mut self) {
+        zeroize::Zeroize::zeroize(self);
+    }
+}
+
+impl<C: CurveConfig> Randomness<C> {
+    /// Lagrange-interpolates the shares at zero. Fails on fewer than
+    /// `threshold` shares, shares from different splits, or repeated indices.
+    pub fn combine(shares: &[RandomnessShare<C>]) -> CommitmentResult<Self> {
+        let first = shares.first().ok_or(ArchimedesError::InvalidInput(InputErrorKind::InsufficientShares {
+            found: 0,
+            threshold: 0,
+        }))?;
+        if shares.iter().any(|s| s.set_id != first.set_id || s.threshold != first.threshold) {
+            return Err(ArchimedesError::InvalidInput(InputErrorKind::MixedShareSets));
+        }
+        if shares.len() < first.threshold as usize {
+            return Err(ArchimedesError::InvalidInput(InputErrorKind::InsufficientShares {
+                found: shares.len(),
+                threshold: first.threshold as usize,
+            }));
+        }
+        let used = &shares[..first.threshold as usize];
+        for (i, share) in used.iter().enumerate() {
+            if share.index == 0 || used[..i].iter().any(|s| s.index == share.index) {
+                return Err(ArchimedesError::InvalidInput(InputErrorKind::DuplicateShare { index: share.index }));
+            }
+        }
+
+        let mut secret = C::Scalar::zero();
+        for share in used {
+            let x_i = C::Scalar::from(share.index);
+            let (mut num, mut den) = (C::Scalar::ONE, C::Scalar::ONE);
+            for other in used.iter().filter(|s| s.index != share.index) {
+                let x_j = C::Scalar::from(other.index);
+                num *= x_j;
+                den *= x_j - x_i;
+            }
+            secret += share.value * num * den.inverse().expect("indices are distinct");
+        }
+        Ok(Randomness(secret))
+    }
+}
+
+#[cfg(feature = "prover")]
+impl<C: CurveConfig> Randomness<C> {
+    /// Splits into `n` shares, any `threshold` of which recover the value.
+    pub fn split<R: Rng>(&self, n: usize, threshold: usize, rng: &mut R) -> CommitmentResult<Vec<RandomnessShare<C>>> {
+        if threshold == 0 || threshold > n || n > u32::MAX as usize {
+            return Err(ArchimedesError::InvalidInput(InputErrorKind::InvalidShareParameters { n, threshold }));
+        }
+        let mut set_id = [0u8; 32];
+        rng.fill_bytes(&mut set_id);
+        let mut coefficients = vec![self.0];
+        coefficients.extend((1..threshold).map(|_| C::Scalar::rand(rng)));
+        let shares = (1..=n as u32)
+            .map(|index| {
+                let x = C::Scalar::from(index);
+                let value = coefficients.iter().rev().fold(C::Scalar::zero(), |acc, c| acc * x + c);
+                RandomnessShare { set_id, threshold: threshold as u32, index, value }
+            })
+            .collect();
+        #[cfg(feature = "zeroize")]
+        zeroize::Zeroize::zeroize(&mut coefficients);
+        Ok(shares)
+    }
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod tests {
+    use super::*;
+    use crate::commitment::{CommitmentParams, Opening};
+    use crate::curve::curve_tests;
+    use ark_std::test_rng;
+
+    curve_tests!(test_threshold_and_above_recombine, test_insufficient_and_mixed_sets_fail);
+
+    fn test_threshold_and_above_recombine<C: CurveConfig>() {
+        let mut rng = test_rng();
+        let params = CommitmentParams::<C>::setup(&mut rng).unwrap();
+        let value = C::Scalar::from(1_000_000u64);
+        let (commitment, randomness) = params.commit(&value, &mut rng).unwrap();
+        let shares = randomness.split(5, 3, &mut rng).unwrap();
+        assert_eq!(shares.len(), 5);
+
+        let exact = Randomness::combine(&[shares[4].clone(), shares[0].clone(), shares[2].clone()]).unwrap();
+        assert_eq!(exact, randomness);
+        let all = Randomness::combine(&shares).unwrap();
+        assert!(params.verify(&commitment, &Opening { value, randomness: all }).unwrap());
+
+        let single = randomness.split(1, 1, &mut rng).unwrap();
+        assert_eq!(Randomness::combine(&single).unwrap(), randomness);
+    }
+
+    fn test_insufficient_and_mixed_sets_fail<C: CurveConfig>() {
+        let mut rng = test_rng();
+        let randomness = Randomness::<C>(C::Scalar::from(42u64));
+        let shares = randomness.split(4, 3, &mut rng).unwrap();
+        let other = randomness.split(4, 3, &mut rng).unwrap();
+
+        assert!(matches!(
+            Randomness::combine(&shares[..2]),
+            Err(ArchimedesError::InvalidInput(InputErrorKind::InsufficientShares { found: 2, threshold: 3 }))
+        ));
+        assert!(Randomness::<C>::combine(&[]).is_err());
+        assert!(matches!(
+            Randomness::combine(&[shares[0].clone(), shares[1].clone(), other[2].clone()]),
+            Err(ArchimedesError::InvalidInput(InputErrorKind::MixedShareSets))
+        ));
+        assert!(matches!(
+            Randomness::combine(&[shares[0].clone(), shares[1].clone(), shares[1].clone()]),
+            Err(ArchimedesError::InvalidInput(InputErrorKind::DuplicateShare { index: 2 }))
+        ));
+        assert!(matches!(
+            randomness.split(2, 3, &mut rng),
+            Err(ArchimedesError::InvalidInput(InputErrorKind::InvalidShareParameters { n: 2, threshold: 3 }))
+        ));
+        assert!(randomness.split(2, 0, &mut rng).is_err());
+    }
+}
@@ -0,0 +1,177 @@
+use ark_ff::Zero;
+
+use crate::aggregation::{checked_count, AggregateCommitment};
+use crate::commitment::{Commitment, Opening, Randomness};
+use crate::curve::{CurveConfig, DefaultCurve};
+use crate::errors::ArchimedesError;
+
+type Result<T> = std::result::Result<T, ArchimedesError>;
+
+/// Running aggregate over commitments fed one at a time, for histories too
+/// long to hold in memory. Matches `AggregateCommitment::from_commitments`
+/// over the same sequence.
+#[derive(Clone, Debug)]
+pub struct StreamingAggregator<C: CurveConfig = DefaultCurve> {
+    aggregate: AggregateCommitment<C>,
+}
+
+impl<C: CurveConfig> Default for StreamingAggregator<C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<C: CurveConfig> StreamingAggregator<C> {
+    pub fn new() -> Self {
+        Self {
+            aggregate: AggregateCommitment::empty(),
+        }
+    }
+
+    /// Fails, leaving the aggregate unchanged, if the count would overflow.
+    pub fn absorb(&mut self, commitment: &Commitment<C>) -> Result<()> {
+        self.aggregate.count = checked_count(self.aggregate.count, 1)?;
+        self.aggregate.commitment += commitment;
+        Ok(())
+    }
+
+    pub fn absorb_all<'a>(&mut self, commitments: impl IntoIterator<Item = &'a Commitment<C>>) -> Result<()> {
+        commitments.into_iter().try_for_each(|commitment| self.absorb(commitment))
+    }
+
+    pub fn count(&self) -> usize {
+        self.aggregate.count
+    }
+
+    pub fn finalize(&self) -> AggregateCommitment<C> {
+        self.aggregate.clone()
+    }
+}
+
+/// `StreamingAggregator` that also sums openings, so the prover can open the
+/// final aggregate. With the `zeroize` feature the running opening is wiped
+/// on drop.
+#[derive(Clone, Debug)]
+pub struct ProvingAggregator<C: CurveConfig = DefaultCurve> {
+    inner: StreamingAggregator<C>,
+    opening: Opening<C>,
+}
+
+impl<C: CurveConfig> Default for ProvingAggregator<C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<C: CurveConfig> ProvingAggregator<C> {
+    pub fn new() -> Self {
+        Self {
+            inner: StreamingAggregator::new(),
+            opening: Opening {
+                value: C::Scalar::zero(),
+                randomness: Randomness::zero(),
+            },
+        }
+    }
+
+    /// The opening is trusted as given; a mismatch only shows up when the
+    /// final aggregate fails to verify.
+    pub fn absorb_value_randomness(
+        &mut self,
+        commitment: &Commitment<C>,
+        value: &C::Scalar,
+        randomness: &Randomness<C>,
+    ) -> Result<()> {
+        self.inner.absorb(commitment)?;
+        self.opening.value += value;
+        self.opening.randomness += randomness;
+        Ok(())
+    }
+
+    pub fn count(&self) -> usize {
+        self.inner.count()
+    }
+
+    pub fn finalize(&self) -> AggregateCommitment<C> {
+        self.inner.finalize()
+    }
+
+    pub fn finalize_opening(&self) -> Opening<C> {
+        self.opening.clone()
+    }
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod tests {
+    use super::*;
+    use crate::commitment::CommitmentParams;
+    use crate::curve::curve_tests;
+    use crate::errors::AggregationErrorKind;
+    use ark_ff::UniformRand;
+    use ark_std::test_rng;
+
+    curve_tests!(test_matches_from_commitments, test_prover_opening_verifies, test_count_overflow);
+
+    fn test_matches_from_commitments<C: CurveConfig>() {
+        const TOTAL: u64 = 1_000_000;
+        const CHUNK: u64 = 4096;
+
+        // Synthetic commitments i·base, generated a chunk at a time and dropped.
+        let mut rng = test_rng();
+        let base = C::Group::rand(&mut rng);
+        let mut next = base;
+        let mut streaming = StreamingAggregator::<C>::new();
+        let mut merged = AggregateCommitment::<C>::empty();
+        let mut produced = 0;
+        while produced < TOTAL {
+            let n = CHUNK.min(TOTAL - produced);
+            let chunk: Vec<Commitment<C>> = (0..n)
+                .map(|_| {
+                    let c = Commitment(next);
+                    next += base;
+                    c
+                })
+                .collect();
+            streaming.absorb_all(&chunk).unwrap();
+            merged = merged.merge(&AggregateCommitment::from_commitments(&chunk)).unwrap();
+            produced += n;
+        }
+
+        let aggregate = streaming.finalize();
+        assert_eq!(aggregate.count, TOTAL as usize);
+        assert_eq!(aggregate.commitment, merged.commitment);
+        assert_eq!(aggregate.commitment.0, base * C::Scalar::from(TOTAL * (TOTAL + 1) / 2));
+    }
+
+    fn test_prover_opening_verifies<C: CurveConfig>() {
+        let mut rng = test_rng();
+        let params = CommitmentParams::<C>::setup(&mut rng).unwrap();
+        let values: Vec<C::Scalar> = (0..20u64).map(C::Scalar::from).collect();
+        let committed = params.commit_batch(&values, &mut rng).unwrap();
+        let mut prover = ProvingAggregator::<C>::new();
+        for (value, (commitment, randomness)) in values.iter().zip(&committed) {
+            prover.absorb_value_randomness(commitment, value, randomness).unwrap();
+        }
+        let commitments: Vec<Commitment<C>> = committed.into_iter().map(|(c, _)| c).collect();
+        let aggregate = prover.finalize();
+        assert_eq!(aggregate.commitment, AggregateCommitment::from_commitments(&commitments).commitment);
+        assert_eq!(prover.count(), 20);
+        let opening = prover.finalize_opening();
+        assert_eq!(opening.value, C::Scalar::from(190u64));
+        assert!(params.verify(&aggregate.commitment, &opening).unwrap());
+    }
+
+    fn test_count_overflow<C: CurveConfig>() {
+        let mut rng = test_rng();
+        let commitment = Commitment::<C>(C::Group::rand(&mut rng));
+        let mut streaming = StreamingAggregator::<C>::new();
+        streaming.aggregate.count = usize::MAX - 1;
+        streaming.absorb(&commitment).unwrap();
+        assert!(matches!(
+            streaming.absorb(&commitment),
+            Err(ArchimedesError::AggregationError(AggregationErrorKind::CountOverflow { count: usize::MAX, added: 1 }))
+        ));
+        assert_eq!(streaming.count(), usize::MAX);
+        assert_eq!(streaming.finalize().commitment, commitment);
+    }
+}
@@ -0,0 +1,187 @@
+#[cfg(feature = "prover")]
+use ark_ff::UniformRand;
+use ark_ff::PrimeField;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+#[cfg(feature = "prover")]
+use ark_std::rand::Rng;
+use blake2::{Blake2b512, Digest};
+
+use crate::commitment::{Commitment, CommitmentParams, CommitmentResult};
+#[cfg(feature = "prover")]
+use crate::commitment::Opening;
+use crate::curve::{CurveConfig, DefaultCurve};
+#[cfg(feature = "prover")]
+use crate::errors::{ArchimedesError, InputErrorKind, VerificationErrorKind};
+
+const SWITCH_PROOF_DOMAIN: &[u8] = b"archimedes-switch-proof-v1";
+
+/// Proof that `C_a = v*g_a + r_a*h_a` and `C_b = v*g_b + r_b*h_b` share `v`,
+/// for migrating commitments between two parameter sets. A single response
+/// for `v` ties the two Schnorr proofs together.
+#[derive(Clone, Debug, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct SwitchProof<C: CurveConfig = DefaultCurve> {
+    pub announcement_a: C::Group,
+    pub announcement_b: C::Group,
+    pub value_response: C::Scalar,
+    pub randomness_response_a: C::Scalar,
+    pub randomness_response_b: C::Scalar,
+}
+
+fn switch_challenge<C: CurveConfig>(
+    params_a: &CommitmentParams<C>,
+    c_a: &Commitment<C>,
+    params_b: &CommitmentParams<C>,
+    c_b: &Commitment<C>,
+    announcements: (&C::Group, &C::Group),
+) -> CommitmentResult<C::Scalar> {
+    let mut transcript = Vec::new();
+    for point in [params_a.g, params_a.h, c_a.0, params_b.g, params_b.h, c_b.0, *announcements.0, *announcements.1] {
+        point.serialize_compressed(&mut transcript)?;
+    }
+    let mut hasher = Blake2b512::new();
+    hasher.update(SWITCH_PROOF_DOMAIN);
+    hasher.update(C::CURVE_ID.as_bytes());
+    hasher.update(&transcript);
+    Ok(C::Scalar::from_le_bytes_mod_order(&hasher.finalize()))
+}
+
+#[cfg(feature = "prover")]
+pub fn prove_same_value<C: CurveConfig, R: Rng>(
+    params_a: &CommitmentParams<C>,
+    c_a: &Commitment<C>,
+    o_a: &Opening<C>,
+    params_b: &CommitmentParams<C>,
+    c_b: &Commitment<C>,
+    o_b: &Opening<C>,
+    rng: &mut R,
+) -> CommitmentResult<SwitchProof<C>> {
+    if !params_a.verify(c_a, o_a)? || !params_b.verify(c_b, o_b)? {
+        return Err(ArchimedesError::VerificationError(VerificationErrorKind::OpeningMismatch));
+    }
+    if o_a.value != o_b.value {
+        return Err(ArchimedesError::InvalidInput(InputErrorKind::ValueMismatch));
+    }
+    let value_nonce = C::Scalar::rand(rng);
+    let nonce_a = C::Scalar::rand(rng);
+    let nonce_b = C::Scalar::rand(rng);
+    let announcement_a = params_a.g * value_nonce + params_a.h * nonce_a;
+    let announcement_b = params_b.g * value_nonce + params_b.h * nonce_b;
+    let challenge = switch_challenge(params_a, c_a, params_b, c_b, (&announcement_a, &announcement_b))?;
+    Ok(SwitchProof {
+        announcement_a,
+        announcement_b,
+        value_response: value_nonce + challenge * o_a.value,
+        randomness_response_a: nonce_a + challenge * o_a.randomness.0,
+        randomness_response_b: nonce_b + challenge * o_b.randomness.0,
+    })
+}
+
+pub fn verify_same_value<C: CurveConfig>(
+    params_a: &CommitmentParams<C>,
+    c_a: &Commitment<C>,
+    params_b: &CommitmentParams<C>,
+    c_b: &Commitment<C>,
+    proof: &SwitchProof<C>,
+) -> CommitmentResult<bool> {
+    let challenge = switch_challenge(params_a, c_a, params_b, c_b, (&proof.announcement_a, &proof.announcement_b))?;
+    let lhs_a = params_a.g * proof.value_response + params_a.h * proof.randomness_response_a;
+    let lhs_b = params_b.g * proof.value_response + params_b.h * proof.randomness_response_b;
+    Ok(lhs_a == proof.announcement_a + c_a.0 * challenge && lhs_b == proof.announcement_b + c_b.0 * challenge)
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod tests {
+    use super::*;
+    use crate::aggregation::CommitmentChain;
+    use crate::curve::curve_tests;
+    use ark_std::test_rng;
+
+    curve_tests!(test_switch_round_trip, test_different_values_fail, test_migrate_chain);
+
+    fn test_switch_round_trip<C: CurveConfig>() {
+        let mut rng = test_rng();
+        let params_a = CommitmentParams::<C>::setup(&mut rng).unwrap();
+        let params_b = CommitmentParams::<C>::setup(&mut rng).unwrap();
+        let value = C::Scalar::from(500u64);
+        let (c_a, r_a) = params_a.commit(&value, &mut rng).unwrap();
+        let (c_b, r_b) = params_b.commit(&value, &mut rng).unwrap();
+        let o_a = Opening { value, randomness: r_a };
+        let o_b = Opening { value, randomness: r_b };
+
+        let proof = prove_same_value(&params_a, &c_a, &o_a, &params_b, &c_b, &o_b, &mut rng).unwrap();
+        let mut bytes = Vec::new();
+        proof.serialize_compressed(&mut bytes).unwrap();
+        let decoded = SwitchProof::<C>::deserialize_compressed(&bytes[..]).unwrap();
+        assert_eq!(decoded, proof);
+        assert!(verify_same_value(&params_a, &c_a, &params_b, &c_b, &decoded).unwrap());
+        assert!(!verify_same_value(&params_b, &c_b, &params_a, &c_a, &decoded).unwrap());
+    }
+
+    fn test_different_values_fail<C: CurveConfig>() {
+        let mut rng = test_rng();
+        let params_a = CommitmentParams::<C>::setup(&mut rng).unwrap();
+        let params_b = CommitmentParams::<C>::setup(&mut rng).unwrap();
+        let (c_a, r_a) = params_a.commit(&C::Scalar::from(1u64), &mut rng).unwrap();
+        let (c_b, r_b) = params_b.commit(&C::Scalar::from(2u64), &mut rng).unwrap();
+        let o_a = Opening { value: C::Scalar::from(1u64), randomness: r_a.clone() };
+        let o_b = Opening { value: C::Scalar::from(2u64), randomness: r_b.clone() };
+        assert!(matches!(
+            prove_same_value(&params_a, &c_a, &o_a, &params_b, &c_b, &o_b, &mut rng),
+            Err(ArchimedesError::InvalidInput(InputErrorKind::ValueMismatch))
+        ));
+
+        // A prover who ignores the mismatch and answers for value 1 is caught
+        // on the second commitment.
+        let (value_nonce, nonce_a, nonce_b) = (C::Scalar::rand(&mut rng), C::Scalar::rand(&mut rng), C::Scalar::rand(&mut rng));
+        let announcement_a = params_a.g * value_nonce + params_a.h * nonce_a;
+        let announcement_b = params_b.g * value_nonce + params_b.h * nonce_b;
+        let challenge = switch_challenge(&params_a, &c_a, &params_b, &c_b, (&announcement_a, &announcement_b)).unwrap();
+        let forged = SwitchProof {
+            announcement_a,
+            announcement_b,
+            value_response: value_nonce + challenge * C::Scalar::from(1u64),
+            randomness_response_a: nonce_a + challenge * r_a.0,
+            randomness_response_b: nonce_b + challenge * r_b.0,
+        };
+        assert!(!verify_same_value(&params_a, &c_a, &params_b, &c_b, &forged).unwrap());
+    }
+
+    fn test_migrate_chain<C: CurveConfig>() {
+        let mut rng = test_rng();
+        let old_params = CommitmentParams::<C>::setup(&mut rng).unwrap();
+        let new_params = CommitmentParams::<C>::setup(&mut rng).unwrap();
+        let mut old_chain = CommitmentChain::new(old_params.clone());
+        for i in 1..=6u64 {
+            old_chain.push(C::Scalar::from(i * 11), &mut rng).unwrap();
+        }
+
+        let mut new_chain = CommitmentChain::new(new_params.clone());
+        new_chain.extend_from_values(old_chain.values(), &mut rng).unwrap();
+        let proofs: Vec<SwitchProof<C>> = (0..old_chain.len())
+            .map(|i| {
+                let old_opening = Opening { value: old_chain.values()[i], randomness: old_chain.randomness()[i].clone() };
+                let new_opening = Opening { value: new_chain.values()[i], randomness: new_chain.randomness()[i].clone() };
+                prove_same_value(
+                    &old_params,
+                    &old_chain.commitments()[i],
+                    &old_opening,
+                    &new_params,
+                    &new_chain.commitments()[i],
+                    &new_opening,
+                    &mut rng,
+                )
+                .unwrap()
+            })
+            .collect();
+
+        // A verifier holding only the two public chains accepts every entry,
+        // and rejects proofs checked against the wrong position.
+        for (i, proof) in proofs.iter().enumerate() {
+            let (old, new) = (&old_chain.commitments()[i], &new_chain.commitments()[i]);
+            assert!(verify_same_value(&old_params, old, &new_params, new, proof).unwrap());
+            let other = &new_chain.commitments()[(i + 1) % new_chain.len()];
+            assert!(!verify_same_value(&old_params, old, &new_params, other, proof).unwrap());
+        }
+        assert_eq!(old_chain.aggregate_value().unwrap(), new_chain.aggregate_value().unwrap());
+    }
+}
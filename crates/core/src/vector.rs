@@ -0,0 +1,105 @@
+#[cfg(feature = "prover")]
+use ark_ff::UniformRand;
+use ark_serialize::CanonicalSerialize;
+#[cfg(feature = "prover")]
+use ark_std::rand::Rng;
+use subtle::ConstantTimeEq;
+
+use crate::commitment::{Commitment, CommitmentParams, CommitmentResult, Randomness};
+use crate::curve::{hash_to_group, CurveConfig, DefaultCurve};
+use crate::errors::{ArchimedesError, InputErrorKind};
+
+const VECTOR_GENERATOR_DOMAIN: &[u8] = b"archimedes-vector-generator";
+
+/// Commits to a fixed-length vector as `sum(v_i * g_i) + r * h`. The `g_i` are
+/// hashed from the params, so no relation between them (or to `h`) is known.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VectorCommitmentKey<C: CurveConfig = DefaultCurve> {
+    generators: Vec<C::Group>,
+    h: C::Group,
+}
+
+impl<C: CurveConfig> CommitmentParams<C> {
+    pub fn vector_key(&self, len: usize) -> CommitmentResult<VectorCommitmentKey<C>> {
+        let mut seed = Vec::new();
+        (self.g, self.h).serialize_compressed(&mut seed)?;
+        Ok(VectorCommitmentKey {
+            generators: (0..len).map(|i| hash_to_group::<C>(VECTOR_GENERATOR_DOMAIN, &seed, i)).collect(),
+            h: self.h,
+        })
+    }
+}
+
+impl<C: CurveConfig> VectorCommitmentKey<C> {
+    pub fn len(&self) -> usize {
+        self.generators.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.generators.is_empty()
+    }
+
+    pub fn commit_with_randomness(&self, values: &[C::Scalar], randomness: &Randomness<C>) -> CommitmentResult<Commitment<C>> {
+        if values.len() != self.generators.len() {
+            return Err(ArchimedesError::InvalidInput(InputErrorKind::VectorLengthMismatch {
+                expected: self.generators.len(),
+                found: values.len(),
+            }));
+        }
+        let sum: C::Group = self.generators.iter().zip(values).map(|(g, v)| *g * v).sum();
+        Ok(Commitment(sum + self.h * randomness.0))
+    }
+
+    pub fn verify(&self, commitment: &Commitment<C>, values: &[C::Scalar], randomness: &Randomness<C>) -> CommitmentResult<bool> {
+        let expected = self.commit_with_randomness(values, randomness)?;
+        Ok(commitment.ct_eq(&expected).into())
+    }
+}
+
+#[cfg(feature = "prover")]
+impl<C: CurveConfig> VectorCommitmentKey<C> {
+    pub fn commit<R: Rng>(&self, values: &[C::Scalar], rng: &mut R) -> CommitmentResult<(Commitment<C>, Randomness<C>)> {
+        let randomness = Randomness(C::Scalar::rand(rng));
+        Ok((self.commit_with_randomness(values, &randomness)?, randomness))
+    }
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod tests {
+    use super::*;
+    use crate::curve::curve_tests;
+    use ark_std::test_rng;
+
+    curve_tests!(test_vector_commit_verify, test_vector_positions_bound);
+
+    fn test_vector_commit_verify<C: CurveConfig>() {
+        let mut rng = test_rng();
+        let params = CommitmentParams::<C>::setup(&mut rng).unwrap();
+        let key = params.vector_key(3).unwrap();
+        assert_eq!(key, params.vector_key(3).unwrap());
+        let values: Vec<C::Scalar> = [4u64, 5, 6].into_iter().map(C::Scalar::from).collect();
+        let (c, r) = key.commit(&values, &mut rng).unwrap();
+        assert!(key.verify(&c, &values, &r).unwrap());
+        assert!(!key.verify(&c, &values, &Randomness::zero()).unwrap());
+        assert!(matches!(
+            key.commit(&values[..2], &mut rng),
+            Err(ArchimedesError::InvalidInput(InputErrorKind::VectorLengthMismatch { expected: 3, found: 2 }))
+        ));
+    }
+
+    fn test_vector_positions_bound<C: CurveConfig>() {
+        let mut rng = test_rng();
+        let params = CommitmentParams::<C>::setup(&mut rng).unwrap();
+        let key = params.vector_key(2).unwrap();
+        let r = Randomness(C::Scalar::from(9u64));
+        let a = key.commit_with_randomness(&[C::Scalar::from(1u64), C::Scalar::from(2u64)], &r).unwrap();
+        let swapped = key.commit_with_randomness(&[C::Scalar::from(2u64), C::Scalar::from(1u64)], &r).unwrap();
+        assert_ne!(a, swapped);
+        // Homomorphic element-wise.
+        let b = key.commit_with_randomness(&[C::Scalar::from(10u64), C::Scalar::from(20u64)], &r).unwrap();
+        let sum = key
+            .commit_with_randomness(&[C::Scalar::from(11u64), C::Scalar::from(22u64)], &Randomness(C::Scalar::from(18u64)))
+            .unwrap();
+        assert_eq!(&a + &b, sum);
+    }
+}
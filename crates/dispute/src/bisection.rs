@@ -1,9 +1,15 @@
-use archimedes_core::{AggregateCommitment, ArchimedesError};
-use archimedes_state::CommitmentMerkleTree;
+use archimedes_core::{
+    AggregateCommitment, ArchimedesError, Commitment, CommitmentParams, DisputeAction, DisputeErrorKind, PublicCommitmentChain,
+    RangedAggregate,
+};
+use archimedes_state::{CommitmentMerkleTree, ToMerkleTree};
 use serde::{Deserialize, Serialize};
 
 type Result<T> = std::result::Result<T, ArchimedesError>;
 
+/// Largest batch a dispute may be opened over; bounds `max_rounds` at 31.
+pub const MAX_DISPUTE_LEAVES: usize = 1 << 30;
+
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum BisectionState {
     Initial,
@@ -21,7 +27,7 @@ pub enum DisputeResult {
     Timeout,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Challenge {
     pub challenger_id: [u8; 32],
     pub disputed_range: (usize, usize),
@@ -29,12 +35,12 @@ pub struct Challenge {
     pub timestamp: u64,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Response {
     pub proposer_id: [u8; 32],
     pub mid_index: usize,
-    pub left_aggregate: AggregateCommitment,
-    pub right_aggregate: AggregateCommitment,
+    pub left_aggregate: RangedAggregate,
+    pub right_aggregate: RangedAggregate,
     pub timestamp: u64,
 }
 
@@ -45,32 +51,63 @@ pub struct BisectionProtocol {
     pub current_range: (usize, usize),
     pub challenge: Option<Challenge>,
     pub responses: Vec<Response>,
+    /// Midpoint of the current round's response, cleared when a direction
+    /// is selected.
+    pub pending_mid: Option<usize>,
     pub round: usize,
     pub max_rounds: usize,
 }
 
+fn check_leaf_count(leaf_count: usize) -> Result<()> {
+    if leaf_count > MAX_DISPUTE_LEAVES {
+        return Err(ArchimedesError::DisputeError {
+            state: format!("{:?}", BisectionState::Initial),
+            action: DisputeAction::Challenge,
+            kind: DisputeErrorKind::TooManyLeaves { leaf_count, max: MAX_DISPUTE_LEAVES },
+        });
+    }
+    Ok(())
+}
+
 impl BisectionProtocol {
-    pub fn new(tree: CommitmentMerkleTree) -> Self {
+    pub fn new(tree: CommitmentMerkleTree) -> Result<Self> {
         let leaf_count = tree.leaf_count();
+        check_leaf_count(leaf_count)?;
         let max_rounds = (leaf_count as f64).log2().ceil() as usize + 1;
-        Self {
+        Ok(Self {
             state: BisectionState::Initial,
             current_range: (0, leaf_count),
             tree,
             challenge: None,
             responses: Vec::new(),
+            pending_mid: None,
             round: 0,
             max_rounds,
-        }
+        })
+    }
+
+    /// Builds the protocol from commitments alone, so a challenger never
+    /// needs the proposer's values or blinding factors.
+    pub fn from_public(chain: &PublicCommitmentChain) -> Result<Self> {
+        Self::new(chain.to_merkle_tree()?)
+    }
+
+    /// `from_public` over commitments received from the network, which are
+    /// length- and subgroup-checked before the tree is built.
+    pub fn from_public_chain(params: CommitmentParams, commitments: Vec<Commitment>) -> Result<Self> {
+        Self::from_public(&PublicCommitmentChain::from_untrusted(params, commitments)?)
     }
 
     pub fn initiate_challenge(&mut self, challenge: Challenge) -> Result<()> {
         if self.state != BisectionState::Initial {
-            return Err(ArchimedesError::DisputeError("Invalid state for challenge".to_string()));
+            return Err(self.error(DisputeAction::Challenge, DisputeErrorKind::InvalidState));
+        }
+        if !challenge.claimed_aggregate.commitment.is_valid() {
+            return Err(self.error(DisputeAction::Challenge, DisputeErrorKind::InvalidAggregate));
         }
         let (start, end) = challenge.disputed_range;
         if end > self.tree.leaf_count() || start >= end {
-            return Err(ArchimedesError::DisputeError("Invalid dispute range".to_string()));
+            return Err(self.error(DisputeAction::Challenge, DisputeErrorKind::InvalidRange { start, end }));
         }
         self.current_range = (start, end);
         self.challenge = Some(challenge);
@@ -80,42 +117,50 @@ impl BisectionProtocol {
 
     pub fn respond(&mut self, response: Response) -> Result<()> {
         if !matches!(self.state, BisectionState::Challenged | BisectionState::BisectLeft | BisectionState::BisectRight) {
-            return Err(ArchimedesError::DisputeError("Invalid state for response".to_string()));
+            return Err(self.error(DisputeAction::Respond, DisputeErrorKind::InvalidState));
+        }
+        if self.pending_mid.is_some() {
+            return Err(self.error(DisputeAction::Respond, DisputeErrorKind::InvalidState));
         }
         let (start, end) = self.current_range;
         let mid = response.mid_index;
         if mid <= start || mid >= end {
-            return Err(ArchimedesError::DisputeError("Invalid midpoint".to_string()));
+            return Err(self.error(DisputeAction::Respond, DisputeErrorKind::InvalidMidpoint { mid }));
+        }
+        for (claimed, (lo, hi)) in [(&response.left_aggregate, (start, mid)), (&response.right_aggregate, (mid, end))] {
+            if !claimed.covers(lo, hi) {
+                let kind = DisputeErrorKind::AggregateRangeMismatch { start: claimed.start, end: claimed.end };
+                return Err(self.error(DisputeAction::Respond, kind));
+            }
         }
-        let left_agg = self.tree.range_aggregate(start, mid)?;
-        let right_agg = self.tree.range_aggregate(mid, end)?;
-        if left_agg.commitment.0 != response.left_aggregate.commitment.0 ||
-           right_agg.commitment.0 != response.right_aggregate.commitment.0 {
+        if !response.left_aggregate.agg.commitment.is_valid() || !response.right_aggregate.agg.commitment.is_valid() {
+            return Err(self.error(DisputeAction::Respond, DisputeErrorKind::InvalidAggregate));
+        }
+        if !self.tree.range_aggregate_matches(start, mid, &response.left_aggregate.agg)?
+            || !self.tree.range_aggregate_matches(mid, end, &response.right_aggregate.agg)?
+        {
             self.state = BisectionState::Complete(DisputeResult::ChallengerWins);
             return Ok(());
         }
+        self.pending_mid = Some(mid);
         self.responses.push(response);
         self.round += 1;
-        if end - start <= 2 {
-            self.state = BisectionState::Resolve;
-        }
         Ok(())
     }
 
     pub fn select_direction(&mut self, go_left: bool) -> Result<()> {
         if !matches!(self.state, BisectionState::Challenged | BisectionState::BisectLeft | BisectionState::BisectRight) {
-            return Err(ArchimedesError::DisputeError("Invalid state".to_string()));
-        }
-        if self.responses.is_empty() {
-            return Err(ArchimedesError::DisputeError("No response to bisect".to_string()));
+            return Err(self.error(DisputeAction::SelectDirection, DisputeErrorKind::InvalidState));
         }
-        let last = self.responses.last().unwrap();
+        let Some(mid) = self.pending_mid.take() else {
+            return Err(self.error(DisputeAction::SelectDirection, DisputeErrorKind::NoResponse));
+        };
         let (start, end) = self.current_range;
         if go_left {
-            self.current_range = (start, last.mid_index);
+            self.current_range = (start, mid);
             self.state = BisectionState::BisectLeft;
         } else {
-            self.current_range = (last.mid_index, end);
+            self.current_range = (mid, end);
             self.state = BisectionState::BisectRight;
         }
         if self.current_range.1 - self.current_range.0 <= 1 {
@@ -124,6 +169,14 @@ impl BisectionProtocol {
         Ok(())
     }
 
+    fn error(&self, action: DisputeAction, kind: DisputeErrorKind) -> ArchimedesError {
+        ArchimedesError::DisputeError {
+            state: format!("{:?}", self.state),
+            action,
+            kind,
+        }
+    }
+
     pub fn is_resolved(&self) -> bool {
         matches!(self.state, BisectionState::Complete(_) | BisectionState::Resolve)
     }
@@ -151,22 +204,217 @@ mod tests {
         for i in 1..=size {
             chain.push(ScalarField::from(i as u64), &mut rng).unwrap();
         }
-        CommitmentMerkleTree::build(&chain.commitments).unwrap()
+        CommitmentMerkleTree::from_chain(&chain).unwrap()
+    }
+
+    fn setup_tree_with_duplicates() -> (CommitmentMerkleTree, Vec<archimedes_core::Commitment>) {
+        let mut rng = test_rng();
+        let params = CommitmentParams::setup(&mut rng).unwrap();
+        let mut chain = CommitmentChain::new(params);
+        for i in 1..=8 {
+            chain.push(ScalarField::from(i as u64), &mut rng).unwrap();
+        }
+        let mut commitments = chain.commitments().to_vec();
+        commitments[5] = commitments[2].clone();
+        commitments[6] = commitments[2].clone();
+        (CommitmentMerkleTree::build(&commitments).unwrap(), commitments)
+    }
+
+    fn bisect_to(protocol: &mut BisectionProtocol, target: usize) {
+        while protocol.current_range.1 - protocol.current_range.0 > 1 {
+            let (start, end) = protocol.current_range;
+            let mid = (start + end) / 2;
+            let response = Response {
+                proposer_id: [2u8; 32],
+                mid_index: mid,
+                left_aggregate: protocol.tree.ranged_aggregate(start, mid).unwrap(),
+                right_aggregate: protocol.tree.ranged_aggregate(mid, end).unwrap(),
+                timestamp: protocol.round as u64,
+            };
+            protocol.respond(response).unwrap();
+            protocol.select_direction(target < mid).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_bisection_converges_on_duplicate_positions() {
+        let (tree, commitments) = setup_tree_with_duplicates();
+        for target in [2, 5, 6] {
+            let agg = tree.aggregate().clone();
+            let mut protocol = BisectionProtocol::new(tree.clone()).unwrap();
+            protocol.initiate_challenge(Challenge {
+                challenger_id: [1u8; 32],
+                disputed_range: (0, 8),
+                claimed_aggregate: agg,
+                timestamp: 0,
+            }).unwrap();
+            bisect_to(&mut protocol, target);
+            assert_eq!(protocol.disputed_index(), Some(target));
+            let leaf = tree.range_aggregate(target, target + 1).unwrap();
+            assert_eq!(leaf.commitment, commitments[2]);
+        }
+    }
+
+    #[test]
+    fn test_bisection_with_indexed_chain_responses() {
+        let mut rng = test_rng();
+        let params = CommitmentParams::setup(&mut rng).unwrap();
+        let mut chain = CommitmentChain::new(params);
+        chain.build_range_index();
+        for i in 1..=13 {
+            chain.push(ScalarField::from(i as u64), &mut rng).unwrap();
+        }
+        let tree = CommitmentMerkleTree::from_chain(&chain).unwrap();
+        let mut protocol = BisectionProtocol::new(tree).unwrap();
+        protocol.initiate_challenge(Challenge {
+            challenger_id: [1u8; 32],
+            disputed_range: (0, 13),
+            claimed_aggregate: chain.aggregate_range(0, 13).unwrap(),
+            timestamp: 0,
+        }).unwrap();
+        while protocol.current_range.1 - protocol.current_range.0 > 1 {
+            let (start, end) = protocol.current_range;
+            let mid = (start + end) / 2;
+            protocol.respond(Response {
+                proposer_id: [2u8; 32],
+                mid_index: mid,
+                left_aggregate: chain.ranged_aggregate(start, mid).unwrap(),
+                right_aggregate: chain.ranged_aggregate(mid, end).unwrap(),
+                timestamp: protocol.round as u64,
+            }).unwrap();
+            assert_ne!(protocol.state, BisectionState::Complete(DisputeResult::ChallengerWins));
+            protocol.select_direction(9 < mid).unwrap();
+        }
+        assert_eq!(protocol.disputed_index(), Some(9));
+    }
+
+    #[test]
+    fn test_bisection_from_public_chain() {
+        let mut rng = test_rng();
+        let params = CommitmentParams::setup(&mut rng).unwrap();
+        let mut chain = CommitmentChain::new(params);
+        for i in 1..=8 {
+            chain.push(ScalarField::from(i as u64), &mut rng).unwrap();
+        }
+        let public = chain.to_public();
+        let mut protocol = BisectionProtocol::from_public(&public).unwrap();
+        assert_eq!(protocol.current_range, (0, 8));
+        protocol.initiate_challenge(Challenge {
+            challenger_id: [1u8; 32],
+            disputed_range: (0, 8),
+            claimed_aggregate: public.aggregate(),
+            timestamp: 0,
+        }).unwrap();
+        bisect_to(&mut protocol, 6);
+        assert_eq!(protocol.disputed_index(), Some(6));
+    }
+
+    #[test]
+    fn test_from_public_chain_rejects_small_order_commitment() {
+        use ark_ed_on_bls12_381::{EdwardsAffine, Fq};
+        use ark_ff::{One, Zero};
+
+        let mut rng = test_rng();
+        let params = CommitmentParams::setup(&mut rng).unwrap();
+        let mut chain = CommitmentChain::new(params.clone());
+        for i in 1..=5 {
+            chain.push(ScalarField::from(i as u64), &mut rng).unwrap();
+        }
+        let protocol = BisectionProtocol::from_public_chain(params.clone(), chain.commitments().to_vec()).unwrap();
+        assert_eq!(protocol.current_range, (0, 5));
+        assert_eq!(protocol.tree.aggregate().commitment, chain.aggregate().commitment);
+
+        let mut commitments = chain.commitments().to_vec();
+        commitments[3].0 += EdwardsAffine::new_unchecked(Fq::zero(), -Fq::one());
+        assert!(BisectionProtocol::from_public_chain(params, commitments).is_err());
+    }
+
+    #[test]
+    fn test_challenge_response_serde() {
+        let tree = setup_tree(8);
+        let challenge = Challenge {
+            challenger_id: [1u8; 32],
+            disputed_range: (0, 8),
+            claimed_aggregate: tree.aggregate().clone(),
+            timestamp: 7,
+        };
+        let response = Response {
+            proposer_id: [2u8; 32],
+            mid_index: 4,
+            left_aggregate: tree.ranged_aggregate(0, 4).unwrap(),
+            right_aggregate: tree.ranged_aggregate(4, 8).unwrap(),
+            timestamp: 8,
+        };
+        let challenge: Challenge = serde_json::from_str(&serde_json::to_string(&challenge).unwrap()).unwrap();
+        let response: Response = serde_json::from_str(&serde_json::to_string(&response).unwrap()).unwrap();
+
+        let mut protocol = BisectionProtocol::new(tree).unwrap();
+        protocol.initiate_challenge(challenge).unwrap();
+        protocol.respond(response).unwrap();
+        assert_eq!(protocol.round, 1);
+        assert_eq!(protocol.state, BisectionState::Challenged);
+    }
+
+    #[test]
+    fn test_direction_needs_fresh_response() {
+        let tree = setup_tree(8);
+        let mut protocol = BisectionProtocol::new(tree.clone()).unwrap();
+        protocol.initiate_challenge(Challenge {
+            challenger_id: [1u8; 32],
+            disputed_range: (0, 8),
+            claimed_aggregate: tree.aggregate().clone(),
+            timestamp: 0,
+        }).unwrap();
+        let response = |mid: usize, end: usize| Response {
+            proposer_id: [2u8; 32],
+            mid_index: mid,
+            left_aggregate: tree.ranged_aggregate(0, mid).unwrap(),
+            right_aggregate: tree.ranged_aggregate(mid, end).unwrap(),
+            timestamp: 1,
+        };
+        protocol.respond(response(4, 8)).unwrap();
+        assert!(matches!(
+            protocol.respond(response(4, 8)),
+            Err(ArchimedesError::DisputeError { kind: DisputeErrorKind::InvalidState, .. })
+        ));
+        protocol.select_direction(true).unwrap();
+        assert_eq!(protocol.current_range, (0, 4));
+
+        // The round-one midpoint must not be reused for round two.
+        assert!(matches!(
+            protocol.select_direction(true),
+            Err(ArchimedesError::DisputeError { kind: DisputeErrorKind::NoResponse, .. })
+        ));
+        assert_eq!(protocol.current_range, (0, 4));
+        protocol.respond(response(2, 4)).unwrap();
+        protocol.select_direction(false).unwrap();
+        assert_eq!(protocol.current_range, (2, 4));
     }
 
     #[test]
     fn test_bisection_init() {
         let tree = setup_tree(8);
-        let protocol = BisectionProtocol::new(tree);
+        let protocol = BisectionProtocol::new(tree).unwrap();
         assert_eq!(protocol.state, BisectionState::Initial);
         assert_eq!(protocol.current_range, (0, 8));
     }
 
+    #[test]
+    fn test_leaf_count_limit() {
+        assert!(check_leaf_count(MAX_DISPUTE_LEAVES).is_ok());
+        assert!(matches!(
+            check_leaf_count(MAX_DISPUTE_LEAVES + 1),
+            Err(ArchimedesError::DisputeError { kind: DisputeErrorKind::TooManyLeaves { leaf_count, .. }, .. })
+                if leaf_count == MAX_DISPUTE_LEAVES + 1
+        ));
+        assert_eq!(BisectionProtocol::new(setup_tree(8)).unwrap().max_rounds, 4);
+    }
+
     #[test]
     fn test_challenge_initiation() {
         let tree = setup_tree(8);
         let agg = tree.aggregate().clone();
-        let mut protocol = BisectionProtocol::new(tree);
+        let mut protocol = BisectionProtocol::new(tree).unwrap();
         let challenge = Challenge {
             challenger_id: [1u8; 32],
             disputed_range: (0, 8),
@@ -176,5 +424,134 @@ mod tests {
         protocol.initiate_challenge(challenge).unwrap();
         assert_eq!(protocol.state, BisectionState::Challenged);
     }
-}
 
+    #[test]
+    fn test_respond_rejects_misranged_aggregates() {
+        let tree = setup_tree(8);
+        let agg = tree.aggregate().clone();
+        let mut protocol = BisectionProtocol::new(tree.clone()).unwrap();
+        protocol.initiate_challenge(Challenge {
+            challenger_id: [1u8; 32],
+            disputed_range: (0, 8),
+            claimed_aggregate: agg,
+            timestamp: 0,
+        }).unwrap();
+
+        let response = |left: RangedAggregate, right: RangedAggregate| Response {
+            proposer_id: [2u8; 32],
+            mid_index: 4,
+            left_aggregate: left,
+            right_aggregate: right,
+            timestamp: 1,
+        };
+        let swapped = response(tree.ranged_aggregate(4, 8).unwrap(), tree.ranged_aggregate(0, 4).unwrap());
+        assert!(matches!(
+            protocol.respond(swapped),
+            Err(ArchimedesError::DisputeError { kind: DisputeErrorKind::AggregateRangeMismatch { start: 4, end: 8 }, .. })
+        ));
+        let short = response(tree.ranged_aggregate(0, 3).unwrap(), tree.ranged_aggregate(4, 8).unwrap());
+        assert!(protocol.respond(short).is_err());
+        assert_eq!(protocol.state, BisectionState::Challenged);
+
+        // Relabelling an aggregate from another range passes the range check
+        // but no longer matches the tree.
+        let mut relabelled = tree.ranged_aggregate(1, 5).unwrap();
+        (relabelled.start, relabelled.end) = (0, 4);
+        protocol.respond(response(relabelled, tree.ranged_aggregate(4, 8).unwrap())).unwrap();
+        assert_eq!(protocol.state, BisectionState::Complete(DisputeResult::ChallengerWins));
+    }
+
+    #[test]
+    fn test_respond_detects_count_only_mismatch() {
+        let tree = setup_tree(8);
+        let mut protocol = BisectionProtocol::new(tree.clone()).unwrap();
+        protocol.initiate_challenge(Challenge {
+            challenger_id: [1u8; 32],
+            disputed_range: (0, 8),
+            claimed_aggregate: tree.aggregate().clone(),
+            timestamp: 0,
+        }).unwrap();
+
+        let mut left = tree.ranged_aggregate(0, 4).unwrap();
+        left.agg.count += 1;
+        assert!(!tree.range_aggregate_matches(0, 4, &left.agg).unwrap());
+        let result = protocol.respond(Response {
+            proposer_id: [2u8; 32],
+            mid_index: 4,
+            left_aggregate: left,
+            right_aggregate: tree.ranged_aggregate(4, 8).unwrap(),
+            timestamp: 1,
+        });
+        // A count that disagrees with the range never reaches the tree
+        // comparison; the range check rejects it first.
+        assert!(matches!(
+            result,
+            Err(ArchimedesError::DisputeError { kind: DisputeErrorKind::AggregateRangeMismatch { start: 0, end: 4 }, .. })
+        ));
+        assert_eq!(protocol.state, BisectionState::Challenged);
+    }
+
+    #[test]
+    fn test_respond_compares_both_halves_with_tree() {
+        let tree = setup_tree(8);
+        let challenge = Challenge {
+            challenger_id: [1u8; 32],
+            disputed_range: (0, 8),
+            claimed_aggregate: tree.aggregate().clone(),
+            timestamp: 0,
+        };
+        // Right half holds leaves 3..7 relabelled as 4..8: the range and
+        // count check out, only the tree comparison can reject it.
+        let mut right = tree.ranged_aggregate(3, 7).unwrap();
+        (right.start, right.end) = (4, 8);
+        assert!(right.covers(4, 8));
+        assert!(tree.range_aggregate_matches(0, 4, &tree.range_aggregate(0, 4).unwrap()).unwrap());
+        assert!(!tree.range_aggregate_matches(4, 8, &right.agg).unwrap());
+
+        let mut protocol = BisectionProtocol::new(tree.clone()).unwrap();
+        protocol.initiate_challenge(challenge).unwrap();
+        protocol
+            .respond(Response {
+                proposer_id: [2u8; 32],
+                mid_index: 4,
+                left_aggregate: tree.ranged_aggregate(0, 4).unwrap(),
+                right_aggregate: right,
+                timestamp: 1,
+            })
+            .unwrap();
+        assert_eq!(protocol.state, BisectionState::Complete(DisputeResult::ChallengerWins));
+        assert!(protocol.responses.is_empty());
+    }
+
+    #[test]
+    fn test_respond_rejects_small_order_aggregate() {
+        use ark_ed_on_bls12_381::{EdwardsAffine, Fq};
+        use ark_ff::{One, Zero};
+
+        let tree = setup_tree(8);
+        let agg = tree.aggregate().clone();
+        let mut protocol = BisectionProtocol::new(tree.clone()).unwrap();
+        protocol.initiate_challenge(Challenge {
+            challenger_id: [1u8; 32],
+            disputed_range: (0, 8),
+            claimed_aggregate: agg,
+            timestamp: 0,
+        }).unwrap();
+
+        let torsion = EdwardsAffine::new_unchecked(Fq::zero(), -Fq::one());
+        let mut left = tree.ranged_aggregate(0, 4).unwrap();
+        left.agg.commitment.0 += torsion;
+        let result = protocol.respond(Response {
+            proposer_id: [2u8; 32],
+            mid_index: 4,
+            left_aggregate: left,
+            right_aggregate: tree.ranged_aggregate(4, 8).unwrap(),
+            timestamp: 1,
+        });
+        assert!(matches!(
+            result,
+            Err(ArchimedesError::DisputeError { action: DisputeAction::Respond, kind: DisputeErrorKind::InvalidAggregate, .. })
+        ));
+        assert_eq!(protocol.state, BisectionState::Challenged);
+    }
+}
@@ -1,6 +1,11 @@
 pub mod bisection;
+pub mod ordering;
 pub mod resolution;
 
-pub use bisection::{BisectionProtocol, BisectionState, Challenge, Response};
-pub use resolution::{DisputeOutcome, DisputeResolver, SingleStepProof};
+pub use bisection::{BisectionProtocol, MAX_DISPUTE_LEAVES, BisectionState, Challenge, Response};
+pub use ordering::OrderingViolationEvidence;
+pub use resolution::{
+    DisputeOutcome, DisputeResolver, PreStateInclusion, ProtocolConfig, SingleStepProof, StateStepProof, StepOpening,
+    StepInclusion, StorageWriteStepProof, TransferStepProof,
+};
 
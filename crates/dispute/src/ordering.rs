@@ -0,0 +1,99 @@
+use archimedes_core::{ArchimedesError, DisputeAction, DisputeErrorKind};
+use archimedes_state::{BatchOrdering, BatchTx, StateTransition};
+use serde::{Deserialize, Serialize};
+
+use crate::bisection::BisectionState;
+
+type Result<T> = std::result::Result<T, ArchimedesError>;
+
+/// Proof that a committed batch does not follow the canonical ordering of its
+/// published transactions. Checkable directly against the published data, so
+/// it needs no bisection game.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OrderingViolationEvidence {
+    pub position: usize,
+    pub committed_tx_hash: [u8; 32],
+    pub expected_tx_hash: [u8; 32],
+    pub canonical_digest: [u8; 32],
+}
+
+impl OrderingViolationEvidence {
+    /// Returns evidence naming the first committed leaf that is out of
+    /// canonical order, or `None` if the batch is correctly ordered.
+    pub fn detect(committed: &[StateTransition], published: &[BatchTx]) -> Result<Option<Self>> {
+        if committed.len() != published.len() {
+            return Err(ArchimedesError::DisputeError {
+                state: format!("{:?}", BisectionState::Initial),
+                action: DisputeAction::CheckOrdering,
+                kind: DisputeErrorKind::LengthMismatch {
+                    committed: committed.len(),
+                    published: published.len(),
+                },
+            });
+        }
+        let canonical = BatchOrdering::canonical_order(published);
+        let violation = committed
+            .iter()
+            .zip(&canonical)
+            .position(|(leaf, tx)| leaf.tx_hash != tx.tx_hash);
+        Ok(violation.map(|position| Self {
+            position,
+            committed_tx_hash: committed[position].tx_hash,
+            expected_tx_hash: canonical[position].tx_hash,
+            canonical_digest: BatchOrdering::canonical_digest(published),
+        }))
+    }
+
+    pub fn verify(&self, committed: &[StateTransition], published: &[BatchTx]) -> bool {
+        matches!(Self::detect(committed, published), Ok(Some(evidence)) if evidence == *self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use archimedes_state::AccountState;
+
+    fn published() -> Vec<BatchTx> {
+        (0..6u8)
+            .map(|i| BatchTx {
+                sender: [i % 3; 32],
+                nonce: (i / 3) as u64,
+                tx_hash: [100 + i; 32],
+            })
+            .collect()
+    }
+
+    fn commit_in_order(txs: &[BatchTx]) -> Vec<StateTransition> {
+        txs.iter()
+            .enumerate()
+            .map(|(i, tx)| StateTransition::new(AccountState::new(1000, i as u64), AccountState::new(900, i as u64 + 1), tx.tx_hash))
+            .collect()
+    }
+
+    #[test]
+    fn test_canonical_batch_has_no_violation() {
+        let published = published();
+        let committed = commit_in_order(&BatchOrdering::canonical_order(&published));
+        assert_eq!(OrderingViolationEvidence::detect(&committed, &published).unwrap(), None);
+    }
+
+    #[test]
+    fn test_misordered_batch_produces_evidence() {
+        let published = published();
+        let mut ordered = BatchOrdering::canonical_order(&published);
+        ordered.swap(3, 4);
+        let committed = commit_in_order(&ordered);
+
+        let evidence = OrderingViolationEvidence::detect(&committed, &published).unwrap().unwrap();
+        assert_eq!(evidence.position, 3);
+        assert_eq!(evidence.committed_tx_hash, ordered[3].tx_hash);
+        assert_eq!(evidence.expected_tx_hash, ordered[4].tx_hash);
+        assert!(evidence.verify(&committed, &published));
+
+        let mut forged = evidence.clone();
+        forged.position = 4;
+        assert!(!forged.verify(&committed, &published));
+        assert!(OrderingViolationEvidence::detect(&committed[..5], &published).is_err());
+    }
+}
@@ -1,7 +1,16 @@
-use archimedes_core::{ArchimedesError, Commitment, CommitmentParams, Opening};
-use archimedes_state::{AccountState, StateTransition};
+use archimedes_core::{
+    ArchimedesError, Commitment, CommitmentParams, DisputeAction, DisputeErrorKind, MerkleVerifyError, Opening,
+    OpeningProof, ProofContext, StateEncodingErrorKind,
+};
+use archimedes_state::{
+    code_hash, verify_state_with, AccountProof, AccountState, Address, AggregatePathProof, CommitmentEncoding, HashScheme,
+    StateCommitment, StateOpening, StateTransition, StorageProof, TransferTransition, TransitionOperation,
+};
+use ark_ed_on_bls12_381::Fr as ScalarField;
 use serde::{Deserialize, Serialize};
 
+use crate::bisection::BisectionState;
+
 type Result<T> = std::result::Result<T, ArchimedesError>;
 
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
@@ -12,55 +21,361 @@ pub enum DisputeOutcome {
     Timeout,
 }
 
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProtocolConfig {
+    /// Accept bare openings, which are not bound to a dispute and can be
+    /// replayed across disputes sharing a commitment.
+    pub allow_plain_openings: bool,
+    /// Encoding the step commitments were made under.
+    #[serde(default)]
+    pub commitment_encoding: CommitmentEncoding,
+    /// Hash the step commitments are anchored to, combined with
+    /// `commitment_encoding`. `None` is the untagged SHA-256 hash that
+    /// predates schemes.
+    #[serde(default)]
+    pub hash_scheme: Option<HashScheme>,
+}
+
+#[derive(Clone, Debug)]
+pub enum StepOpening {
+    Plain(Opening),
+    Bound(OpeningProof),
+}
+
 #[derive(Clone, Debug)]
 pub struct SingleStepProof {
     pub index: usize,
     pub pre_state: AccountState,
     pub post_state: AccountState,
+    pub tx_hash: [u8; 32],
+    /// Ties `pre_state` to the account set the step started from.
+    pub pre_state_inclusion: Option<PreStateInclusion>,
+    pub step_inclusion: Option<StepInclusion>,
     pub commitment: Commitment,
-    pub opening: Opening,
+    pub opening: StepOpening,
+}
+
+/// Shows a step's commitment is leaf `index` of the batch commitment tree.
+/// `leaf_count` is bound by the committed root it is checked against.
+#[derive(Clone, Debug)]
+pub struct StepInclusion {
+    pub leaf_count: usize,
+    pub path: AggregatePathProof,
+}
+
+/// Shows `address` held the step's pre-state in the `StateBatch` whose
+/// root is `state_root`.
+#[derive(Clone, Debug)]
+pub struct PreStateInclusion {
+    pub address: Address,
+    pub state_root: [u8; 32],
+    pub proof: AccountProof,
+}
+
+/// A step committing to a `TransferTransition`, so the receiver's credit is
+/// checked along with the sender's debit.
+#[derive(Clone, Debug)]
+pub struct TransferStepProof {
+    pub index: usize,
+    pub transition: TransferTransition,
+    pub step_inclusion: Option<StepInclusion>,
+    pub commitment: Commitment,
+    pub opening: StepOpening,
+}
+
+/// A step that writes `value` at `key` in the account's storage. The
+/// committed transition is `pre_state -> post_state` under `tx_hash`, which
+/// must be the write's `TransitionOperation::hash`; `storage_proof` opens
+/// `key` against `pre_state.storage_root`.
+#[derive(Clone, Debug)]
+pub struct StorageWriteStepProof {
+    pub index: usize,
+    pub pre_state: AccountState,
+    pub post_state: AccountState,
+    pub tx_hash: [u8; 32],
+    pub key: [u8; 32],
+    pub value: [u8; 32],
+    pub storage_proof: StorageProof,
+    pub step_inclusion: Option<StepInclusion>,
+    pub commitment: Commitment,
+    pub opening: StepOpening,
+}
+
+/// A step whose pre and post states were committed field-wise with
+/// `commit_state`, so the resolver re-executes the transfer itself instead of
+/// comparing a transition hash.
+#[derive(Clone, Debug)]
+pub struct StateStepProof {
+    pub index: usize,
+    pub tx_value: u128,
+    pub pre_commitment: Commitment,
+    pub pre_opening: StateOpening,
+    pub post_commitment: Commitment,
+    pub post_opening: StateOpening,
 }
 
 pub struct DisputeResolver {
     params: CommitmentParams,
+    config: ProtocolConfig,
 }
 
 impl DisputeResolver {
     pub fn new(params: CommitmentParams) -> Self {
-        Self { params }
+        Self::with_config(params, ProtocolConfig::default())
     }
 
-    pub fn verify_single_step(&self, proof: &SingleStepProof) -> Result<DisputeOutcome> {
-        if !self.params.verify(&proof.commitment, &proof.opening)? {
+    pub fn with_config(params: CommitmentParams, config: ProtocolConfig) -> Self {
+        Self { params, config }
+    }
+
+    /// `context` identifies the dispute the proof is submitted to; bound
+    /// opening proofs made for any other context are rejected.
+    ///
+    /// `pre_state_root` is the trusted state root the disputed step started
+    /// from. When given, the proof must carry a `pre_state_inclusion` for
+    /// that root; without it, `pre_state_inclusion` is not checked.
+    ///
+    /// `batch_root` is the trusted committed root of the batch commitment
+    /// tree. When given, the proof must carry a `step_inclusion` placing its
+    /// commitment at `proof.index` in that tree.
+    pub fn verify_single_step(
+        &self,
+        proof: &SingleStepProof,
+        context: &ProofContext,
+        pre_state_root: Option<[u8; 32]>,
+        batch_root: Option<[u8; 32]>,
+    ) -> Result<DisputeOutcome> {
+        if !self.step_included(proof.index, &proof.commitment, proof.step_inclusion.as_ref(), batch_root) {
             return Ok(DisputeOutcome::InvalidProof);
         }
+        let Some(value) = self.opened_value(&proof.commitment, &proof.opening, proof.index, context)? else {
+            return Ok(DisputeOutcome::InvalidProof);
+        };
+        if let Some(root) = pre_state_root {
+            let included = proof.pre_state_inclusion.as_ref().is_some_and(|inclusion| {
+                inclusion.state_root == root
+                    && inclusion.proof.verify(root, &inclusion.address, proof.pre_state.hash())
+            });
+            if !included {
+                return Ok(DisputeOutcome::InvalidProof);
+            }
+        }
 
         let transition = StateTransition::new(
             proof.pre_state.clone(),
             proof.post_state.clone(),
-            [0u8; 32],
+            proof.tx_hash,
         );
-        let expected_value = transition.to_commitment_value();
+        let expected_value = self.expected_value(|| transition.transition_hash(), |scheme| transition.hash_with(scheme))?;
 
-        if proof.opening.value != expected_value {
+        if Some(value) != expected_value {
             return Ok(DisputeOutcome::ProposerFaulty);
         }
 
         Ok(DisputeOutcome::ProposerCorrect)
     }
 
+    /// `verify_single_step` for transfers: the commitment must open to the
+    /// claimed transfer, and re-executing it must give both claimed post
+    /// states, so a wrong receiver credit faults the proposer.
+    pub fn verify_transfer_step(
+        &self,
+        proof: &TransferStepProof,
+        context: &ProofContext,
+        batch_root: Option<[u8; 32]>,
+    ) -> Result<DisputeOutcome> {
+        if !self.step_included(proof.index, &proof.commitment, proof.step_inclusion.as_ref(), batch_root) {
+            return Ok(DisputeOutcome::InvalidProof);
+        }
+        let Some(value) = self.opened_value(&proof.commitment, &proof.opening, proof.index, context)? else {
+            return Ok(DisputeOutcome::InvalidProof);
+        };
+        let transfer = &proof.transition;
+        if Some(value) != self.expected_value(|| transfer.transition_hash(), |scheme| transfer.hash_with(scheme))? {
+            return Ok(DisputeOutcome::ProposerFaulty);
+        }
+        match self.execute_transfer(&transfer.sender_pre, &transfer.receiver_pre, transfer.amount) {
+            Ok((sender, receiver)) if sender == transfer.sender_post && receiver == transfer.receiver_post => {
+                Ok(DisputeOutcome::ProposerCorrect)
+            }
+            _ => Ok(DisputeOutcome::ProposerFaulty),
+        }
+    }
+
+    /// The commitment must open to the claimed transition, and the claimed
+    /// post state must be the pre state with only its storage root moved to
+    /// the root the proof gives after the write. The write is bound to the
+    /// commitment through `tx_hash`, so a key or value other than the
+    /// committed one is an invalid proof.
+    pub fn verify_storage_write_step(
+        &self,
+        proof: &StorageWriteStepProof,
+        context: &ProofContext,
+        batch_root: Option<[u8; 32]>,
+    ) -> Result<DisputeOutcome> {
+        if !self.step_included(proof.index, &proof.commitment, proof.step_inclusion.as_ref(), batch_root) {
+            return Ok(DisputeOutcome::InvalidProof);
+        }
+        let write = TransitionOperation::StorageWrite {
+            key: proof.key,
+            value: proof.value,
+        };
+        if write.hash() != proof.tx_hash {
+            return Ok(DisputeOutcome::InvalidProof);
+        }
+        let Some(value) = self.opened_value(&proof.commitment, &proof.opening, proof.index, context)? else {
+            return Ok(DisputeOutcome::InvalidProof);
+        };
+        let transition = StateTransition::new(proof.pre_state.clone(), proof.post_state.clone(), proof.tx_hash);
+        if Some(value) != self.expected_value(|| transition.transition_hash(), |scheme| transition.hash_with(scheme))? {
+            return Ok(DisputeOutcome::ProposerFaulty);
+        }
+        let Ok(expected) = proof.pre_state.apply_with(&write, Some(&proof.storage_proof)) else {
+            return Ok(DisputeOutcome::InvalidProof);
+        };
+        if expected == proof.post_state {
+            Ok(DisputeOutcome::ProposerCorrect)
+        } else {
+            Ok(DisputeOutcome::ProposerFaulty)
+        }
+    }
+
+    /// The value a commitment to a step hashing to `untagged`, or to
+    /// `tagged` under the configured scheme, opens to. `None` if the hash
+    /// has no value under the configured encoding, so no commitment opens
+    /// to the step.
+    fn expected_value(
+        &self,
+        untagged: impl FnOnce() -> [u8; 32],
+        tagged: impl FnOnce(HashScheme) -> Result<[u8; 32]>,
+    ) -> Result<Option<ScalarField>> {
+        let hash = match self.config.hash_scheme {
+            None => untagged(),
+            Some(scheme) => tagged(scheme)?,
+        };
+        Ok(self.config.commitment_encoding.hash_to_field(&hash).ok())
+    }
+
+    /// The value `opening` reveals for `commitment`, or `None` if it does not
+    /// verify or, for bound openings, belongs to another step or dispute.
+    fn opened_value(
+        &self,
+        commitment: &Commitment,
+        opening: &StepOpening,
+        index: usize,
+        context: &ProofContext,
+    ) -> Result<Option<ScalarField>> {
+        match opening {
+            StepOpening::Plain(opening) => {
+                if !self.config.allow_plain_openings || !self.params.verify(commitment, opening)? {
+                    return Ok(None);
+                }
+                Ok(Some(opening.value))
+            }
+            StepOpening::Bound(opening_proof) => {
+                if context.index != index as u64 || !self.params.verify_opening_proof(commitment, opening_proof, context)? {
+                    return Ok(None);
+                }
+                Ok(Some(opening_proof.value))
+            }
+        }
+    }
+
+    /// Checks that `commitment` is leaf `index` of the batch tree with
+    /// committed root `committed` over `leaf_count` leaves. The path is
+    /// checked at `index`, so `inclusion` cannot place the step elsewhere.
+    pub fn verify_step_inclusion(
+        &self,
+        index: usize,
+        commitment: &Commitment,
+        inclusion: &AggregatePathProof,
+        committed: [u8; 32],
+        leaf_count: usize,
+    ) -> std::result::Result<(), MerkleVerifyError> {
+        let path = AggregatePathProof { index, siblings: inclusion.siblings.clone() };
+        path.verify_committed_detailed(commitment, committed, leaf_count)
+    }
+
+    /// Whether a step passes the `batch_root` check the step verifiers
+    /// share; with no trusted root there is nothing to check.
+    fn step_included(
+        &self,
+        index: usize,
+        commitment: &Commitment,
+        inclusion: Option<&StepInclusion>,
+        batch_root: Option<[u8; 32]>,
+    ) -> bool {
+        let Some(root) = batch_root else {
+            return true;
+        };
+        inclusion.is_some_and(|inclusion| {
+            self.verify_step_inclusion(index, commitment, &inclusion.path, root, inclusion.leaf_count).is_ok()
+        })
+    }
+
+    /// `verify_single_step` for state-committed steps. State openings are not
+    /// bound to a dispute, so like plain openings they need
+    /// `allow_plain_openings`.
+    pub fn verify_state_step(&self, proof: &StateStepProof, context: &ProofContext) -> Result<DisputeOutcome> {
+        if !self.config.allow_plain_openings || context.index != proof.index as u64 {
+            return Ok(DisputeOutcome::InvalidProof);
+        }
+        let key = self.params.state_key()?;
+        if !verify_state_with(&key, &proof.pre_commitment, &proof.pre_opening)?
+            || !verify_state_with(&key, &proof.post_commitment, &proof.post_opening)?
+        {
+            return Ok(DisputeOutcome::InvalidProof);
+        }
+        match self.execute_transition(&proof.pre_opening.state, proof.tx_value) {
+            Ok(expected) if expected == proof.post_opening.state => Ok(DisputeOutcome::ProposerCorrect),
+            _ => Ok(DisputeOutcome::ProposerFaulty),
+        }
+    }
+
     pub fn execute_transition(&self, pre: &AccountState, tx_value: u128) -> Result<AccountState> {
-        if pre.balance < tx_value {
-            return Err(ArchimedesError::DisputeError("Insufficient balance".to_string()));
-        }
-        Ok(AccountState {
-            balance: pre.balance - tx_value,
-            nonce: pre.nonce + 1,
-            code_hash: pre.code_hash,
-            storage_root: pre.storage_root,
+        pre.apply(&TransitionOperation::Transfer { amount: tx_value }).map_err(|e| match e {
+            ArchimedesError::StateEncodingError(StateEncodingErrorKind::InsufficientBalance { balance, amount }) => {
+                ArchimedesError::DisputeError {
+                    state: format!("{:?}", BisectionState::Resolve),
+                    action: DisputeAction::ExecuteTransition,
+                    kind: DisputeErrorKind::InsufficientBalance { balance, amount },
+                }
+            }
+            other => other,
         })
     }
 
+    /// Post states of both sides of a transfer. The receiver's nonce is
+    /// unchanged.
+    pub fn execute_transfer(
+        &self,
+        sender: &AccountState,
+        receiver: &AccountState,
+        amount: u128,
+    ) -> Result<(AccountState, AccountState)> {
+        let sender_post = self.execute_transition(sender, amount)?;
+        let balance = receiver.balance.checked_add(amount).ok_or_else(|| ArchimedesError::DisputeError {
+            state: format!("{:?}", BisectionState::Resolve),
+            action: DisputeAction::ExecuteTransition,
+            kind: DisputeErrorKind::BalanceOverflow {
+                balance: receiver.balance,
+                amount,
+            },
+        })?;
+        Ok((sender_post, AccountState { balance, ..receiver.clone() }))
+    }
+
+    /// `pre` running `code`, with its nonce bumped.
+    pub fn execute_code_deploy(&self, pre: &AccountState, code: &[u8]) -> Result<AccountState> {
+        pre.apply(&TransitionOperation::CodeDeploy { code_hash: code_hash(code) })
+    }
+
+    /// Checks a claimed deployment against the published code, so a
+    /// post-state whose `code_hash` is not the code's hash is rejected.
+    pub fn verify_code_deploy(&self, pre: &AccountState, post: &AccountState, code: &[u8]) -> bool {
+        matches!(self.execute_code_deploy(pre, code), Ok(expected) if expected == *post)
+    }
+
     pub fn verify_transition(
         &self,
         pre: &AccountState,
@@ -75,6 +390,7 @@ impl DisputeResolver {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use archimedes_state::{StateBatch, StorageTrie, TransitionBatch};
     use ark_std::test_rng;
 
     #[test]
@@ -115,30 +431,562 @@ mod tests {
         assert!(result.is_err());
     }
 
+    const TX_HASH: [u8; 32] = [9u8; 32];
+
+    fn step_fixture(rng: &mut impl ark_std::rand::Rng) -> (CommitmentParams, Commitment, Opening, AccountState, AccountState) {
+        let params = CommitmentParams::setup(rng).unwrap();
+        let pre = AccountState::new(1000, 0);
+        let post = AccountState::new(900, 1);
+        let value = StateTransition::new(pre.clone(), post.clone(), TX_HASH).to_commitment_value();
+        let (commitment, randomness) = params.commit(&value, rng).unwrap();
+        (params, commitment, Opening { value, randomness }, pre, post)
+    }
+
+    fn context(challenge: u8) -> ProofContext {
+        ProofContext {
+            challenge_id: [challenge; 32],
+            batch_root: [7u8; 32],
+            index: 0,
+        }
+    }
+
     #[test]
     fn test_single_step_verification() {
         let mut rng = test_rng();
-        let params = CommitmentParams::setup(&mut rng).unwrap();
+        let (params, commitment, opening, pre, post) = step_fixture(&mut rng);
         let resolver = DisputeResolver::new(params.clone());
+        let opening_proof = params.prove_opening(&commitment, &opening, &context(1), &mut rng).unwrap();
 
-        let pre = AccountState::new(1000, 0);
-        let post = AccountState::new(900, 1);
-        let transition = StateTransition::new(pre.clone(), post.clone(), [0u8; 32]);
-        let value = transition.to_commitment_value();
+        let proof = SingleStepProof {
+            index: 0,
+            pre_state: pre,
+            post_state: post,
+            tx_hash: TX_HASH,
+            pre_state_inclusion: None,
+            step_inclusion: None,
+            commitment,
+            opening: StepOpening::Bound(opening_proof),
+        };
+
+        let outcome = resolver.verify_single_step(&proof, &context(1), None, None).unwrap();
+        assert_eq!(outcome, DisputeOutcome::ProposerCorrect);
+        assert_eq!(resolver.verify_single_step(&proof, &context(1), None, None).unwrap(), DisputeOutcome::ProposerCorrect);
 
+        let other_tx = SingleStepProof { tx_hash: [0u8; 32], ..proof };
+        assert_eq!(resolver.verify_single_step(&other_tx, &context(1), None, None).unwrap(), DisputeOutcome::ProposerFaulty);
+    }
+
+    #[test]
+    fn test_single_step_pre_state_inclusion() {
+        let mut rng = test_rng();
+        let (params, commitment, opening, pre, post) = step_fixture(&mut rng);
+        let resolver = DisputeResolver::new(params.clone());
+        let (sender, other) = (Address([1; 32]), Address([2; 32]));
+        let batch: StateBatch = [(sender, pre.clone()), (other, AccountState::new(5, 0))].into_iter().collect();
+        let root = batch.state_root().unwrap();
+        let inclusion = |batch: &StateBatch, address: Address| PreStateInclusion {
+            address,
+            state_root: batch.state_root().unwrap(),
+            proof: batch.prove_account(&address).unwrap(),
+        };
+        let proof = SingleStepProof {
+            index: 0,
+            pre_state: pre.clone(),
+            post_state: post,
+            tx_hash: TX_HASH,
+            pre_state_inclusion: Some(inclusion(&batch, sender)),
+            step_inclusion: None,
+            opening: StepOpening::Bound(params.prove_opening(&commitment, &opening, &context(1), &mut rng).unwrap()),
+            commitment,
+        };
+        let verify = |proof: &SingleStepProof| resolver.verify_single_step(proof, &context(1), Some(root), None).unwrap();
+        assert_eq!(verify(&proof), DisputeOutcome::ProposerCorrect);
+
+        let wrong_account = SingleStepProof { pre_state_inclusion: Some(inclusion(&batch, other)), ..proof.clone() };
+        assert_eq!(verify(&wrong_account), DisputeOutcome::InvalidProof);
+        let absent = SingleStepProof { pre_state_inclusion: Some(inclusion(&batch, Address([3; 32]))), ..proof.clone() };
+        assert_eq!(verify(&absent), DisputeOutcome::InvalidProof);
+        let missing = SingleStepProof { pre_state_inclusion: None, ..proof.clone() };
+        assert_eq!(verify(&missing), DisputeOutcome::InvalidProof);
+
+        // A batch the prover made up holds the pre-state too, but its root is
+        // not the one the step started from.
+        let forged: StateBatch = [(sender, pre)].into_iter().collect();
+        let self_consistent = SingleStepProof { pre_state_inclusion: Some(inclusion(&forged, sender)), ..proof.clone() };
+        assert_eq!(verify(&self_consistent), DisputeOutcome::InvalidProof);
+        let relabeled = SingleStepProof {
+            pre_state_inclusion: Some(PreStateInclusion { state_root: root, ..inclusion(&forged, sender) }),
+            ..proof
+        };
+        assert_eq!(verify(&relabeled), DisputeOutcome::InvalidProof);
+    }
+
+    /// A tx hash for which the fixture transition has a `Full` value under
+    /// `value`; most hashes are above the scalar field's order.
+    fn full_tx_hash(
+        pre: &AccountState,
+        post: &AccountState,
+        value: impl Fn(&StateTransition) -> Result<ScalarField>,
+    ) -> [u8; 32] {
+        (0..=u8::MAX)
+            .map(|b| [b; 32])
+            .find(|&tx_hash| value(&StateTransition::new(pre.clone(), post.clone(), tx_hash)).is_ok())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_single_step_commitment_encoding() {
+        let mut rng = test_rng();
+        let (params, legacy_commitment, legacy_opening, pre, post) = step_fixture(&mut rng);
+        let tx_hash = full_tx_hash(&pre, &post, |t| t.commitment_value(CommitmentEncoding::Full));
+        let value = StateTransition::new(pre.clone(), post.clone(), tx_hash)
+            .commitment_value(CommitmentEncoding::Full)
+            .unwrap();
         let (commitment, randomness) = params.commit(&value, &mut rng).unwrap();
         let opening = Opening { value, randomness };
+        let full = DisputeResolver::with_config(
+            params.clone(),
+            ProtocolConfig {
+                commitment_encoding: CommitmentEncoding::Full,
+                ..Default::default()
+            },
+        );
+        let legacy = DisputeResolver::new(params.clone());
+
+        let step = |commitment: Commitment, opening: &Opening, tx_hash, rng: &mut _| SingleStepProof {
+            index: 0,
+            pre_state: pre.clone(),
+            post_state: post.clone(),
+            tx_hash,
+            pre_state_inclusion: None,
+            step_inclusion: None,
+            opening: StepOpening::Bound(params.prove_opening(&commitment, opening, &context(1), rng).unwrap()),
+            commitment,
+        };
+        let new_step = step(commitment, &opening, tx_hash, &mut rng);
+        let old_step = step(legacy_commitment, &legacy_opening, TX_HASH, &mut rng);
+        assert_eq!(full.verify_single_step(&new_step, &context(1), None, None).unwrap(), DisputeOutcome::ProposerCorrect);
+        assert_eq!(legacy.verify_single_step(&old_step, &context(1), None, None).unwrap(), DisputeOutcome::ProposerCorrect);
+        // The legacy step's hash is above the field order, so nothing opens
+        // to it under `Full`.
+        assert!(StateTransition::new(pre.clone(), post.clone(), TX_HASH).commitment_value(CommitmentEncoding::Full).is_err());
+        assert_eq!(full.verify_single_step(&old_step, &context(1), None, None).unwrap(), DisputeOutcome::ProposerFaulty);
+        assert_eq!(legacy.verify_single_step(&new_step, &context(1), None, None).unwrap(), DisputeOutcome::ProposerFaulty);
+    }
+
+    #[test]
+    fn test_single_step_hash_scheme() {
+        let mut rng = test_rng();
+        let (params, legacy_commitment, legacy_opening, pre, post) = step_fixture(&mut rng);
+        let resolver = |scheme: Option<HashScheme>| {
+            let config = ProtocolConfig {
+                commitment_encoding: CommitmentEncoding::Full,
+                hash_scheme: scheme,
+                ..Default::default()
+            };
+            DisputeResolver::with_config(params.clone(), config)
+        };
+        let step = |commitment: Commitment, opening: &Opening, tx_hash, rng: &mut _| SingleStepProof {
+            index: 0,
+            pre_state: pre.clone(),
+            post_state: post.clone(),
+            tx_hash,
+            pre_state_inclusion: None,
+            step_inclusion: None,
+            opening: StepOpening::Bound(params.prove_opening(&commitment, opening, &context(1), rng).unwrap()),
+            commitment,
+        };
+        let commit = |scheme: HashScheme, rng: &mut _| {
+            let tx_hash = full_tx_hash(&pre, &post, |t| t.commitment_value_with(scheme, CommitmentEncoding::Full));
+            let value = StateTransition::new(pre.clone(), post.clone(), tx_hash)
+                .commitment_value_with(scheme, CommitmentEncoding::Full)
+                .unwrap();
+            let (commitment, randomness) = params.commit(&value, rng).unwrap();
+            step(commitment, &Opening { value, randomness }, tx_hash, rng)
+        };
+
+        let sha = commit(HashScheme::Sha256, &mut rng);
+        let verify = |scheme, proof: &SingleStepProof| resolver(scheme).verify_single_step(proof, &context(1), None, None);
+        assert_eq!(verify(Some(HashScheme::Sha256), &sha).unwrap(), DisputeOutcome::ProposerCorrect);
+        assert_eq!(verify(None, &sha).unwrap(), DisputeOutcome::ProposerFaulty);
+        // An untagged Truncated commitment does not open under a scheme.
+        let legacy = step(legacy_commitment, &legacy_opening, TX_HASH, &mut rng);
+        assert_eq!(verify(Some(HashScheme::Sha256), &legacy).unwrap(), DisputeOutcome::ProposerFaulty);
+
+        #[cfg(feature = "poseidon")]
+        {
+            let poseidon = commit(HashScheme::Poseidon, &mut rng);
+            assert_eq!(verify(Some(HashScheme::Poseidon), &poseidon).unwrap(), DisputeOutcome::ProposerCorrect);
+            assert_eq!(verify(Some(HashScheme::Sha256), &poseidon).unwrap(), DisputeOutcome::ProposerFaulty);
+        }
+        #[cfg(not(feature = "poseidon"))]
+        assert!(matches!(
+            verify(Some(HashScheme::Poseidon), &sha),
+            Err(ArchimedesError::StateEncodingError(StateEncodingErrorKind::HashSchemeUnavailable(1)))
+        ));
+    }
+
+    #[test]
+    fn test_single_step_from_chain_opening() {
+        use archimedes_core::CommitmentChain;
+        use archimedes_state::TransitionChain;
+
+        let mut rng = test_rng();
+        let params = CommitmentParams::setup(&mut rng).unwrap();
+        let resolver = DisputeResolver::new(params.clone());
+        let transitions: Vec<StateTransition> = (0..4u64)
+            .map(|i| StateTransition::new(AccountState::new(1000, i), AccountState::new(900, i + 1), [i as u8; 32]))
+            .collect();
+        let mut chain = CommitmentChain::new(params.clone());
+        for t in &transitions {
+            chain.push_transition(t, &mut rng).unwrap();
+        }
 
+        let index = 2;
+        let context = ProofContext { index: index as u64, ..context(1) };
+        let opening = chain.opening_at(index).unwrap();
+        let commitment = chain.commitments()[index].clone();
+        let proof = SingleStepProof {
+            index,
+            pre_state: transitions[index].pre_state.clone(),
+            post_state: transitions[index].post_state.clone(),
+            tx_hash: chain.transition_hash_at(index).unwrap(),
+            pre_state_inclusion: None,
+            step_inclusion: None,
+            opening: StepOpening::Bound(params.prove_opening(&commitment, &opening, &context, &mut rng).unwrap()),
+            commitment,
+        };
+        assert_eq!(resolver.verify_single_step(&proof, &context, None, None).unwrap(), DisputeOutcome::ProposerCorrect);
+        assert!(chain.opening_at(transitions.len()).is_err());
+    }
+
+    /// Two accounts paying 100 per step, with the proposer minting 1000 into
+    /// the post-state at `faulty`. Returns the published and committed batches.
+    fn faulty_batch(faulty: usize) -> (TransitionBatch, TransitionBatch) {
+        let honest: TransitionBatch = (0..6u64)
+            .map(|i| {
+                let step = i / 2;
+                let pre = AccountState::new(1000 - 100 * step as u128, step);
+                let post = AccountState::new(900 - 100 * step as u128, step + 1);
+                (Address([(i % 2) as u8; 32]), StateTransition::new(pre, post, [i as u8; 32]))
+            })
+            .collect();
+        let committed = honest
+            .iter()
+            .enumerate()
+            .map(|(i, (address, t))| {
+                let mut t = t.clone();
+                if i == faulty {
+                    t.post_state.balance += 1_000;
+                }
+                (*address, t)
+            })
+            .collect();
+        (honest, committed)
+    }
+
+    #[test]
+    fn test_single_step_faults_known_faulty_batch() {
+        use archimedes_core::CommitmentChain;
+
+        let mut rng = test_rng();
+        let params = CommitmentParams::setup(&mut rng).unwrap();
+        let resolver = DisputeResolver::new(params.clone());
+        let (honest, committed) = faulty_batch(2);
+        assert_eq!(honest.validate(), Ok(()));
+        let violations = committed.validate().unwrap_err();
+        assert_eq!(violations.iter().map(|v| v.index).collect::<Vec<_>>(), vec![4]);
+        assert!(committed.to_field_elements().is_err());
+
+        let mut chain = CommitmentChain::new(params.clone());
+        chain.extend_from_values(&committed.encode_unchecked().unwrap(), &mut rng).unwrap();
+        for index in 0..honest.len() {
+            let transition = &honest.get(index).unwrap().1;
+            let context = ProofContext { index: index as u64, ..context(1) };
+            let commitment = chain.commitments()[index].clone();
+            let opening = chain.opening_at(index).unwrap();
+            let proof = SingleStepProof {
+                index,
+                pre_state: transition.pre_state.clone(),
+                post_state: transition.post_state.clone(),
+                tx_hash: transition.tx_hash,
+                pre_state_inclusion: None,
+                step_inclusion: None,
+                opening: StepOpening::Bound(params.prove_opening(&commitment, &opening, &context, &mut rng).unwrap()),
+                commitment,
+            };
+            let expected = if index == 2 { DisputeOutcome::ProposerFaulty } else { DisputeOutcome::ProposerCorrect };
+            assert_eq!(resolver.verify_single_step(&proof, &context, None, None).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn test_step_inclusion_reports_failures() {
+        use archimedes_state::CommitmentMerkleTree;
+
+        let mut rng = test_rng();
+        let (params, commitment, opening, pre, post) = step_fixture(&mut rng);
+        let config = ProtocolConfig { allow_plain_openings: true, ..ProtocolConfig::default() };
+        let resolver = DisputeResolver::with_config(params.clone(), config);
+        let mut leaves: Vec<Commitment> = (0..5u64).map(|i| params.commit(&i.into(), &mut rng).unwrap().0).collect();
+        leaves[3] = commitment.clone();
+        let tree = CommitmentMerkleTree::build(&leaves).unwrap();
+        let proof = SingleStepProof {
+            index: 3,
+            pre_state: pre,
+            post_state: post,
+            tx_hash: TX_HASH,
+            pre_state_inclusion: None,
+            step_inclusion: None,
+            commitment,
+            opening: StepOpening::Plain(opening),
+        };
+
+        let inclusion = tree.generate_proof(3).unwrap();
+        let committed = tree.committed_root();
+        assert_eq!(resolver.verify_step_inclusion(proof.index, &proof.commitment, &inclusion, committed, 5), Ok(()));
+        assert!(matches!(
+            resolver.verify_step_inclusion(proof.index, &proof.commitment, &inclusion, tree.root_hash(), 5),
+            Err(MerkleVerifyError::RootMismatch { .. })
+        ));
+        assert!(matches!(
+            resolver.verify_step_inclusion(proof.index, &proof.commitment, &inclusion, committed, 6),
+            Err(MerkleVerifyError::RootMismatch { .. })
+        ));
+        assert_eq!(
+            resolver.verify_step_inclusion(proof.index, &proof.commitment, &inclusion, committed, 4),
+            Err(MerkleVerifyError::WrongDepth { index: 3, depth: 3 })
+        );
+        assert!(matches!(
+            resolver.verify_step_inclusion(2, &proof.commitment, &inclusion, committed, 5),
+            Err(MerkleVerifyError::WrongDirection { index: 2, level: 0 })
+        ));
+        let elsewhere = tree.generate_proof(2).unwrap();
+        assert!(matches!(
+            resolver.verify_step_inclusion(2, &proof.commitment, &elsewhere, committed, 5),
+            Err(MerkleVerifyError::RootMismatch { .. })
+        ));
+        assert_eq!(
+            resolver.verify_step_inclusion(9, &proof.commitment, &inclusion, committed, 5),
+            Err(MerkleVerifyError::WrongDepth { index: 9, depth: 3 })
+        );
+
+        // With a trusted batch root, the step verifiers require the path.
+        let verify = |proof: &SingleStepProof| resolver.verify_single_step(proof, &context(3), None, Some(committed)).unwrap();
+        assert_eq!(verify(&proof), DisputeOutcome::InvalidProof);
+        let included = SingleStepProof {
+            step_inclusion: Some(StepInclusion { leaf_count: 5, path: inclusion.clone() }),
+            ..proof.clone()
+        };
+        assert_eq!(verify(&included), DisputeOutcome::ProposerCorrect);
+        let moved = SingleStepProof { index: 2, ..included.clone() };
+        assert_eq!(verify(&moved), DisputeOutcome::InvalidProof);
+        let recounted = SingleStepProof {
+            step_inclusion: Some(StepInclusion { leaf_count: 6, path: inclusion }),
+            ..proof
+        };
+        assert_eq!(verify(&recounted), DisputeOutcome::InvalidProof);
+    }
+
+    #[test]
+    fn test_transfer_step_faults_wrong_receiver_credit() {
+        let mut rng = test_rng();
+        let params = CommitmentParams::setup(&mut rng).unwrap();
+        let resolver = DisputeResolver::new(params.clone());
+        let honest = TransferTransition {
+            sender_pre: AccountState::new(1000, 0),
+            sender_post: AccountState::new(900, 1),
+            receiver_pre: AccountState::new(50, 7),
+            receiver_post: AccountState::new(150, 7),
+            amount: 100,
+            tx_hash: TX_HASH,
+        };
+        let step = |transition: TransferTransition, rng: &mut _| {
+            let value = transition.to_commitment_value();
+            let (commitment, randomness) = params.commit(&value, rng).unwrap();
+            let opening = params.prove_opening(&commitment, &Opening { value, randomness }, &context(1), rng).unwrap();
+            TransferStepProof {
+                index: 0,
+                transition,
+                step_inclusion: None,
+                commitment,
+                opening: StepOpening::Bound(opening),
+            }
+        };
+
+        let proof = step(honest.clone(), &mut rng);
+        assert_eq!(resolver.verify_transfer_step(&proof, &context(1), None).unwrap(), DisputeOutcome::ProposerCorrect);
+        assert_eq!(resolver.verify_transfer_step(&proof, &context(2), None).unwrap(), DisputeOutcome::InvalidProof);
+
+        // The sender's side alone is unchanged, so only the two-party check
+        // catches the miscredit.
+        let miscredited = TransferTransition {
+            receiver_post: AccountState::new(250, 7),
+            ..honest.clone()
+        };
+        let sender = StateTransition::from(miscredited.clone());
+        assert!(resolver.verify_transition(&sender.pre_state, &sender.post_state, 100).unwrap());
+        let proof = step(miscredited, &mut rng);
+        assert_eq!(resolver.verify_transfer_step(&proof, &context(1), None).unwrap(), DisputeOutcome::ProposerFaulty);
+
+        let overflow = AccountState::new(u128::MAX, 0);
+        assert!(matches!(
+            resolver.execute_transfer(&honest.sender_pre, &overflow, 1),
+            Err(ArchimedesError::DisputeError { kind: DisputeErrorKind::BalanceOverflow { .. }, .. })
+        ));
+    }
+
+    #[test]
+    fn test_storage_write_step() {
+        let mut rng = test_rng();
+        let params = CommitmentParams::setup(&mut rng).unwrap();
+        let resolver = DisputeResolver::new(params.clone());
+        let mut storage = StorageTrie::new();
+        let mut pre = AccountState::new(1000, 0);
+        pre.apply_storage_write([1; 32], [10; 32], &mut storage).unwrap();
+        let storage_proof = storage.prove(&[2; 32]);
+        let mut post = pre.clone();
+        post.apply_storage_write([2; 32], [20; 32], &mut storage).unwrap();
+
+        let tx_hash = TransitionOperation::StorageWrite { key: [2; 32], value: [20; 32] }.hash();
+        let step = |post_state: AccountState, storage_proof: StorageProof, rng: &mut _| {
+            let value = StateTransition::new(pre.clone(), post_state.clone(), tx_hash).to_commitment_value();
+            let (commitment, randomness) = params.commit(&value, rng).unwrap();
+            let opening = params.prove_opening(&commitment, &Opening { value, randomness }, &context(1), rng).unwrap();
+            StorageWriteStepProof {
+                index: 0,
+                pre_state: pre.clone(),
+                post_state,
+                tx_hash,
+                key: [2; 32],
+                value: [20; 32],
+                storage_proof,
+                step_inclusion: None,
+                commitment,
+                opening: StepOpening::Bound(opening),
+            }
+        };
+
+        let honest = step(post.clone(), storage_proof.clone(), &mut rng);
+        assert_eq!(resolver.verify_storage_write_step(&honest, &context(1), None).unwrap(), DisputeOutcome::ProposerCorrect);
+
+        let wrong_root = AccountState { storage_root: [9; 32], ..post.clone() };
+        let faulty = step(wrong_root, storage_proof.clone(), &mut rng);
+        assert_eq!(resolver.verify_storage_write_step(&faulty, &context(1), None).unwrap(), DisputeOutcome::ProposerFaulty);
+
+        // A proof taken after the write does not open against the pre root.
+        let stale = step(post, storage.prove(&[2; 32]), &mut rng);
+        assert_eq!(resolver.verify_storage_write_step(&stale, &context(1), None).unwrap(), DisputeOutcome::InvalidProof);
+
+        // A write other than the committed one cannot explain the post state.
+        let mut swapped = honest.clone();
+        swapped.value = [21; 32];
+        assert_eq!(resolver.verify_storage_write_step(&swapped, &context(1), None).unwrap(), DisputeOutcome::InvalidProof);
+        let moved = StorageWriteStepProof { key: [3; 32], ..honest };
+        assert_eq!(resolver.verify_storage_write_step(&moved, &context(1), None).unwrap(), DisputeOutcome::InvalidProof);
+    }
+
+    #[test]
+    fn test_code_deploy_witness_checked() {
+        use archimedes_proof::WitnessGenerator;
+
+        let params = CommitmentParams::setup(&mut test_rng()).unwrap();
+        let resolver = DisputeResolver::new(params);
+        let code = b"\x60\x01\x60\x02\x01";
+        let witness = WitnessGenerator::generate_code_deploy(AccountState::new(500, 4), code).unwrap();
+        assert!(resolver.verify_code_deploy(&witness.pre_state, &witness.post_state, code));
+        assert!(!resolver.verify_code_deploy(&witness.pre_state, &witness.post_state, b"\x60\x01"));
+        assert!(!resolver.verify_code_deploy(&witness.pre_state, &witness.post_state, &[]));
+
+        let mut swapped = witness.post_state.clone();
+        swapped.set_code(b"other");
+        assert!(!resolver.verify_code_deploy(&witness.pre_state, &swapped, code));
+
+        let empty = WitnessGenerator::generate_code_deploy(AccountState::new(500, 4), &[]).unwrap();
+        assert!(resolver.verify_code_deploy(&empty.pre_state, &empty.post_state, &[]));
+    }
+
+    #[test]
+    fn test_bound_proof_replay_rejected() {
+        let mut rng = test_rng();
+        let (params, commitment, opening, pre, post) = step_fixture(&mut rng);
+        let resolver = DisputeResolver::new(params.clone());
+        let opening_proof = params.prove_opening(&commitment, &opening, &context(1), &mut rng).unwrap();
         let proof = SingleStepProof {
             index: 0,
             pre_state: pre,
             post_state: post,
+            tx_hash: TX_HASH,
+            pre_state_inclusion: None,
+            step_inclusion: None,
             commitment,
-            opening,
+            opening: StepOpening::Bound(opening_proof),
         };
 
-        let outcome = resolver.verify_single_step(&proof).unwrap();
-        assert_eq!(outcome, DisputeOutcome::ProposerCorrect);
+        assert_eq!(resolver.verify_single_step(&proof, &context(2), None, None).unwrap(), DisputeOutcome::InvalidProof);
+        let moved = SingleStepProof { index: 1, ..proof };
+        assert_eq!(
+            resolver.verify_single_step(&moved, &ProofContext { index: 1, ..context(1) }, None, None).unwrap(),
+            DisputeOutcome::InvalidProof
+        );
+    }
+
+    #[test]
+    fn test_plain_openings_require_config() {
+        let mut rng = test_rng();
+        let (params, commitment, opening, pre, post) = step_fixture(&mut rng);
+        let proof = SingleStepProof {
+            index: 0,
+            pre_state: pre,
+            post_state: post,
+            tx_hash: TX_HASH,
+            pre_state_inclusion: None,
+            step_inclusion: None,
+            commitment,
+            opening: StepOpening::Plain(opening),
+        };
+
+        let strict = DisputeResolver::new(params.clone());
+        assert_eq!(strict.verify_single_step(&proof, &context(1), None, None).unwrap(), DisputeOutcome::InvalidProof);
+        let lenient = DisputeResolver::with_config(params, ProtocolConfig {
+            allow_plain_openings: true,
+            ..Default::default()
+        });
+        assert_eq!(lenient.verify_single_step(&proof, &context(1), None, None).unwrap(), DisputeOutcome::ProposerCorrect);
     }
-}
 
+    fn state_step(rng: &mut impl ark_std::rand::Rng, params: &CommitmentParams, post: &AccountState, tx_value: u128) -> StateStepProof {
+        let (pre_commitment, pre_opening) = params.commit_state(&AccountState::new(1000, 0), rng).unwrap();
+        let (post_commitment, post_opening) = params.commit_state(post, rng).unwrap();
+        StateStepProof { index: 0, tx_value, pre_commitment, pre_opening, post_commitment, post_opening }
+    }
+
+    #[test]
+    fn test_state_step_verification() {
+        let mut rng = test_rng();
+        let params = CommitmentParams::setup(&mut rng).unwrap();
+        let resolver = DisputeResolver::with_config(params.clone(), ProtocolConfig {
+            allow_plain_openings: true,
+            ..Default::default()
+        });
+
+        let honest = state_step(&mut rng, &params, &AccountState::new(900, 1), 100);
+        assert_eq!(resolver.verify_state_step(&honest, &context(1)).unwrap(), DisputeOutcome::ProposerCorrect);
+        assert_eq!(
+            DisputeResolver::new(params.clone()).verify_state_step(&honest, &context(1)).unwrap(),
+            DisputeOutcome::InvalidProof
+        );
+
+        let wrong_post = state_step(&mut rng, &params, &AccountState::new(950, 1), 100);
+        assert_eq!(resolver.verify_state_step(&wrong_post, &context(1)).unwrap(), DisputeOutcome::ProposerFaulty);
+        let overdraw = state_step(&mut rng, &params, &AccountState::new(0, 1), 2000);
+        assert_eq!(resolver.verify_state_step(&overdraw, &context(1)).unwrap(), DisputeOutcome::ProposerFaulty);
+
+        // Opening the committed post state to a different state is rejected.
+        let mut equivocated = honest.clone();
+        equivocated.post_opening.state.balance = 950;
+        assert_eq!(resolver.verify_state_step(&equivocated, &context(1)).unwrap(), DisputeOutcome::InvalidProof);
+        let moved = StateStepProof { index: 1, ..honest };
+        assert_eq!(resolver.verify_state_step(&moved, &context(1)).unwrap(), DisputeOutcome::InvalidProof);
+    }
+}
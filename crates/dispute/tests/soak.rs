@@ -0,0 +1,824 @@
+//! Deterministic long-run scenario interleaving batches, disputes, data
+//! loss, stake churn and snapshot restores, with global invariants checked
+//! after every step. Ignored by default:
+//!
+//! ```text
+//! cargo test --release -p archimedes-dispute --features soak --test soak -- --ignored --nocapture
+//! ```
+//!
+//! `SOAK_SEED` and `SOAK_STEPS` override the defaults. A failure reports the
+//! seed and the tail of the step log.
+#![cfg(feature = "soak")]
+
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt::Display;
+use std::time::{Duration, Instant};
+
+use archimedes_availability::{ContentAddressedStorage, ContentId};
+use archimedes_core::{
+    AggregateCommitment, Commitment, CommitmentChain, CommitmentParams, DisputeId, Opening, ProofContext,
+    ResourceId, RetentionRegistry,
+};
+use archimedes_dispute::{
+    BisectionProtocol, BisectionState, Challenge, DisputeOutcome, DisputeResolver, Response, SingleStepProof,
+    StepInclusion, StepOpening,
+};
+use archimedes_incentive::reward::DisputeOutcome as RewardOutcome;
+use archimedes_incentive::{BondManager, LinearRamp, RewardDistributor, StakeManager};
+use archimedes_state::{Address, AccountState, CommitmentMerkleTree, FrozenTree, StateTransition, TransitionBatch};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+const ACCOUNTS: u64 = 16;
+const PROPOSERS: usize = 5;
+const CHALLENGERS: usize = 3;
+const INITIAL_FUNDS: u128 = 1_000_000;
+const INITIAL_BALANCE: u128 = 1_000_000;
+const STAKED_VALUE: u128 = 40_000;
+const MAX_TX_VALUE: u128 = 5_000;
+const CHALLENGE_WINDOW: u64 = 60;
+const LEASE_TIMEOUT: u64 = 30;
+const STAKE_LOCK: u64 = 40;
+const MAX_PENDING: usize = 6;
+const LOG_TAIL: usize = 48;
+
+type StepResult<T = ()> = Result<T, String>;
+
+fn fail<E: Display>(e: E) -> String {
+    e.to_string()
+}
+
+fn ensure(condition: bool, message: impl FnOnce() -> String) -> StepResult {
+    if condition {
+        Ok(())
+    } else {
+        Err(message())
+    }
+}
+
+fn env_or(name: &str, default: u64) -> u64 {
+    std::env::var(name).ok().and_then(|v| v.parse().ok()).unwrap_or(default)
+}
+
+struct OpenDispute {
+    id: DisputeId,
+    challenger: String,
+    bond_key: String,
+    target: usize,
+    silent: bool,
+    opened_at: u64,
+    protocol: BisectionProtocol,
+}
+
+struct PendingBatch {
+    id: u64,
+    proposer: String,
+    transitions: Vec<(u64, StateTransition)>,
+    chain: CommitmentChain,
+    tree: CommitmentMerkleTree,
+    content: ContentId,
+    faulty_index: Option<usize>,
+    window_end: u64,
+    dispute: Option<OpenDispute>,
+    verdicts: Vec<DisputeOutcome>,
+}
+
+struct Snapshot {
+    frozen: FrozenTree,
+    accounts: BTreeMap<u64, AccountState>,
+    replay_from: usize,
+}
+
+pub struct ScenarioBuilder {
+    seed: u64,
+    steps: u64,
+    time_limit: Duration,
+    max_batch_size: usize,
+}
+
+impl ScenarioBuilder {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            steps: 20_000,
+            time_limit: Duration::from_secs(180),
+            max_batch_size: 8,
+        }
+    }
+
+    pub fn steps(mut self, steps: u64) -> Self {
+        self.steps = steps;
+        self
+    }
+
+    pub fn time_limit(mut self, time_limit: Duration) -> Self {
+        self.time_limit = time_limit;
+        self
+    }
+
+    pub fn max_batch_size(mut self, max_batch_size: usize) -> Self {
+        self.max_batch_size = max_batch_size.max(2);
+        self
+    }
+
+    pub fn build(self) -> Scenario {
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        let params: CommitmentParams = CommitmentParams::setup(&mut rng).unwrap();
+        let proposers: Vec<String> = (0..PROPOSERS).map(|i| format!("p{i}")).collect();
+        let challengers: Vec<String> = (0..CHALLENGERS).map(|i| format!("c{i}")).collect();
+        let mut wallets: BTreeMap<String, u128> =
+            proposers.iter().chain(&challengers).map(|id| (id.clone(), INITIAL_FUNDS)).collect();
+        let total_supply = wallets.values().sum();
+        let accounts = (0..ACCOUNTS).map(|id| (id, AccountState::new(INITIAL_BALANCE, 0))).collect();
+        let mut stakes = StakeManager::with_ramp(1000, 20000, LinearRamp { batches_to_floor: 5 }).unwrap();
+        for proposer in &proposers {
+            let amount = stakes.required_stake_for(proposer, STAKED_VALUE);
+            stakes.deposit(proposer.clone(), amount, STAKED_VALUE, STAKE_LOCK).unwrap();
+            *wallets.get_mut(proposer).unwrap() -= amount;
+        }
+
+        Scenario {
+            seed: self.seed,
+            steps: self.steps,
+            time_limit: self.time_limit,
+            max_batch_size: self.max_batch_size,
+            rng,
+            resolver: DisputeResolver::new(params.clone()),
+            params,
+            stakes,
+            bonds: BondManager::new(500, 100),
+            rewards: RewardDistributor::new(500, 400),
+            retention: RetentionRegistry::new(LEASE_TIMEOUT),
+            storage: ContentAddressedStorage::new(usize::MAX),
+            proposers,
+            challengers,
+            wallets,
+            treasury: 0,
+            total_supply,
+            settled_accounts: accounts,
+            settled_commitments: Vec::new(),
+            settled_tree: None,
+            finalized_transitions: Vec::new(),
+            finalized_verdicts: Vec::new(),
+            pending: VecDeque::new(),
+            snapshot: None,
+            now: 0,
+            step: 0,
+            next_batch: 0,
+            next_dispute: 0,
+            log: VecDeque::new(),
+        }
+    }
+}
+
+pub struct Scenario {
+    seed: u64,
+    steps: u64,
+    time_limit: Duration,
+    max_batch_size: usize,
+    rng: StdRng,
+    params: CommitmentParams,
+    resolver: DisputeResolver,
+    stakes: StakeManager,
+    bonds: BondManager,
+    rewards: RewardDistributor,
+    retention: RetentionRegistry,
+    storage: ContentAddressedStorage,
+    proposers: Vec<String>,
+    challengers: Vec<String>,
+    wallets: BTreeMap<String, u128>,
+    treasury: u128,
+    total_supply: u128,
+    settled_accounts: BTreeMap<u64, AccountState>,
+    settled_commitments: Vec<Commitment>,
+    settled_tree: Option<CommitmentMerkleTree>,
+    finalized_transitions: Vec<(u64, StateTransition)>,
+    finalized_verdicts: Vec<(u64, Vec<DisputeOutcome>)>,
+    pending: VecDeque<PendingBatch>,
+    snapshot: Option<Snapshot>,
+    now: u64,
+    step: u64,
+    next_batch: u64,
+    next_dispute: u64,
+    log: VecDeque<String>,
+}
+
+impl Scenario {
+    pub fn run(mut self) {
+        println!("soak seed {}, up to {} steps", self.seed, self.steps);
+        let started = Instant::now();
+        for step in 0..self.steps {
+            self.step = step;
+            if started.elapsed() > self.time_limit {
+                println!("time limit reached after {step} steps");
+                break;
+            }
+            if let Err(e) = self.run_step().and_then(|_| self.check_invariants()) {
+                let tail: Vec<&str> = self.log.iter().map(String::as_str).collect();
+                panic!(
+                    "soak failed at step {step} (t={}): {e}\nreproduce with SOAK_SEED={}\nlast steps:\n{}",
+                    self.now,
+                    self.seed,
+                    tail.join("\n")
+                );
+            }
+        }
+        println!(
+            "finished at t={}: {} batches finalized, {} settled leaves, treasury {}",
+            self.now,
+            self.finalized_verdicts.len(),
+            self.settled_commitments.len(),
+            self.treasury
+        );
+    }
+
+    fn record(&mut self, message: String) {
+        if self.log.len() == LOG_TAIL {
+            self.log.pop_front();
+        }
+        self.log.push_back(format!("{:>6} t={:<6} {message}", self.step, self.now));
+    }
+
+    fn run_step(&mut self) -> StepResult {
+        self.tick()?;
+        self.watch_for_faults()?;
+        self.advance_disputes()?;
+        match self.rng.gen_range(0..100) {
+            0..=39 => self.propose(),
+            40..=51 => self.grief(),
+            52..=79 => self.finalize(),
+            80..=88 => self.churn(),
+            89..=94 => self.lose_data(),
+            _ => self.snapshot_or_restore(),
+        }
+    }
+
+    fn wallet(&mut self, id: &str) -> &mut u128 {
+        self.wallets.get_mut(id).expect("known participant")
+    }
+
+    fn head_accounts(&self) -> BTreeMap<u64, AccountState> {
+        let mut accounts = self.settled_accounts.clone();
+        for batch in &self.pending {
+            for (account, transition) in &batch.transitions {
+                accounts.insert(*account, transition.post_state.clone());
+            }
+        }
+        accounts
+    }
+
+    /// Advances time. Proposers that went silent lose their disputes once the
+    /// lease runs out, which must happen before stale leases are swept.
+    fn tick(&mut self) -> StepResult {
+        self.now += self.rng.gen_range(1..=5);
+        while let Some(index) = self.pending.iter().position(|batch| {
+            batch.dispute.as_ref().is_some_and(|d| {
+                self.retention.lease(&d.id).is_some_and(|lease| lease.expires_at <= self.now)
+            })
+        }) {
+            self.pending[index].verdicts.push(DisputeOutcome::Timeout);
+            self.record(format!("timeout b{}", self.pending[index].id));
+            self.settle_challenger_wins(index)?;
+        }
+        let expired = self.retention.expire_stale(self.now);
+        ensure(expired.is_empty(), || format!("{} leases expired under live disputes", expired.len()))
+    }
+
+    fn propose(&mut self) -> StepResult {
+        if self.pending.len() >= MAX_PENDING {
+            return Ok(());
+        }
+        let proposer = self.proposers[self.rng.gen_range(0..self.proposers.len())].clone();
+        let mut accounts = self.head_accounts();
+        let size = self.rng.gen_range(2..=self.max_batch_size);
+        let batch_id = self.next_batch;
+
+        let mut transitions = Vec::with_capacity(size);
+        let mut total_value = 0u128;
+        for i in 0..size {
+            let account = self.rng.gen_range(0..ACCOUNTS);
+            let pre = accounts[&account].clone();
+            let value = self.rng.gen_range(0..=pre.balance.min(MAX_TX_VALUE));
+            let post = self.resolver.execute_transition(&pre, value).map_err(fail)?;
+            let mut tx_hash = [0u8; 32];
+            tx_hash[..8].copy_from_slice(&batch_id.to_be_bytes());
+            tx_hash[8..16].copy_from_slice(&(i as u64).to_be_bytes());
+            accounts.insert(account, post.clone());
+            transitions.push((account, StateTransition::new(pre, post, tx_hash)));
+            total_value += value;
+        }
+        if self.stakes.check_compliance(&proposer, total_value).is_err() {
+            self.record(format!("propose skipped: {proposer} not compliant"));
+            return Ok(());
+        }
+
+        let faulty_index = self.rng.gen_bool(0.15).then(|| self.rng.gen_range(0..size));
+        let committed: TransitionBatch = transitions
+            .iter()
+            .enumerate()
+            .map(|(i, (account, t))| {
+                let mut t = t.clone();
+                if Some(i) == faulty_index {
+                    t.post_state.balance += 1_000;
+                }
+                let mut address = [0u8; 32];
+                address[..8].copy_from_slice(&account.to_be_bytes());
+                (Address(address), t)
+            })
+            .collect();
+        let values = if faulty_index.is_some() {
+            committed.encode_unchecked()
+        } else {
+            committed.to_field_elements()
+        }
+        .map_err(fail)?;
+        let mut chain = CommitmentChain::new(self.params.clone());
+        chain.extend_from_values(&values, &mut self.rng).map_err(fail)?;
+        let tree = CommitmentMerkleTree::from_chain(&chain).map_err(fail)?;
+
+        let mut data = batch_id.to_be_bytes().to_vec();
+        for (_, t) in &transitions {
+            data.extend_from_slice(&t.transition_hash());
+        }
+        let content = self.storage.store(data, self.now).map_err(fail)?;
+        let window_end = self.now + CHALLENGE_WINDOW;
+        self.stakes.extend_lock(&proposer, window_end).map_err(fail)?;
+
+        self.next_batch += 1;
+        self.record(format!("propose b{batch_id} by {proposer}: {size} txs, faulty={faulty_index:?}"));
+        self.pending.push_back(PendingBatch {
+            id: batch_id,
+            proposer,
+            transitions,
+            chain,
+            tree,
+            content,
+            faulty_index,
+            window_end,
+            dispute: None,
+            verdicts: Vec::new(),
+        });
+        Ok(())
+    }
+
+    /// Honest watchers challenge every faulty batch as soon as they can.
+    fn watch_for_faults(&mut self) -> StepResult {
+        for index in 0..self.pending.len() {
+            let batch = &self.pending[index];
+            if let (Some(target), None) = (batch.faulty_index, &batch.dispute) {
+                if self.now < batch.window_end {
+                    self.open_dispute(index, target)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// A mistaken or malicious challenger disputes an honest batch.
+    fn grief(&mut self) -> StepResult {
+        let candidates: Vec<usize> = (0..self.pending.len())
+            .filter(|&i| self.pending[i].dispute.is_none() && self.now < self.pending[i].window_end)
+            .collect();
+        if candidates.is_empty() {
+            return Ok(());
+        }
+        let index = candidates[self.rng.gen_range(0..candidates.len())];
+        let target = self.rng.gen_range(0..self.pending[index].transitions.len());
+        self.open_dispute(index, target)
+    }
+
+    fn open_dispute(&mut self, index: usize, target: usize) -> StepResult {
+        let bond = self.bonds.required_bond(0);
+        let funded: Vec<String> = self.challengers.iter().filter(|c| self.wallets[*c] >= bond).cloned().collect();
+        if funded.is_empty() {
+            return Err("no challenger can fund a bond".to_string());
+        }
+        let challenger = funded[self.rng.gen_range(0..funded.len())].clone();
+        let bond_key = format!("d{}", self.next_dispute);
+        let mut id = [0u8; 32];
+        id[..8].copy_from_slice(&self.next_dispute.to_be_bytes());
+        self.next_dispute += 1;
+
+        *self.wallet(&challenger) -= bond;
+        self.bonds.post_bond(challenger.clone(), bond_key.clone(), bond, 0).map_err(fail)?;
+
+        let batch = &self.pending[index];
+        let resources = [batch.content.resource_id(), ResourceId::TreeSnapshot(batch.tree.committed_root())];
+        let lease_end = self.retention.acquire(id, &resources, self.now).map_err(fail)?.expires_at;
+        // The stake must outlive the dispute, not just the challenge window.
+        self.stakes.extend_lock(&batch.proposer, lease_end).map_err(fail)?;
+
+        let mut protocol = BisectionProtocol::new(batch.tree.clone()).map_err(fail)?;
+        protocol
+            .initiate_challenge(Challenge {
+                challenger_id: id,
+                disputed_range: (0, batch.transitions.len()),
+                claimed_aggregate: batch.tree.aggregate().clone(),
+                timestamp: self.now,
+            })
+            .map_err(fail)?;
+        let silent = batch.faulty_index.is_some() && self.rng.gen_bool(0.5);
+        let batch_id = batch.id;
+
+        self.record(format!("challenge b{batch_id} at {target} by {challenger} (silent={silent})"));
+        self.pending[index].dispute = Some(OpenDispute {
+            id,
+            challenger,
+            bond_key,
+            target,
+            silent,
+            opened_at: self.now,
+            protocol,
+        });
+        Ok(())
+    }
+
+    /// Plays one bisection round, or the single-step proof, on every dispute
+    /// whose proposer is still responding.
+    fn advance_disputes(&mut self) -> StepResult {
+        let mut index = 0;
+        while index < self.pending.len() {
+            let live = self.pending[index].dispute.as_ref().is_some_and(|d| !d.silent);
+            if live && !self.play_round(index)? {
+                // The batch and everything after it were rolled back.
+                break;
+            }
+            index += 1;
+        }
+        Ok(())
+    }
+
+    /// Returns false if the round ended in a rollback.
+    fn play_round(&mut self, index: usize) -> StepResult<bool> {
+        let batch = &mut self.pending[index];
+        let dispute = batch.dispute.as_mut().unwrap();
+        match dispute.protocol.state {
+            BisectionState::Challenged | BisectionState::BisectLeft | BisectionState::BisectRight => {
+                let (start, end) = dispute.protocol.current_range;
+                let mid = start + (end - start) / 2;
+                dispute
+                    .protocol
+                    .respond(Response {
+                        proposer_id: [0u8; 32],
+                        mid_index: mid,
+                        left_aggregate: batch.tree.ranged_aggregate(start, mid).map_err(fail)?,
+                        right_aggregate: batch.tree.ranged_aggregate(mid, end).map_err(fail)?,
+                        timestamp: self.now,
+                    })
+                    .map_err(fail)?;
+                ensure(!matches!(dispute.protocol.state, BisectionState::Complete(_)), || {
+                    format!("honest bisection response rejected in b{}", batch.id)
+                })?;
+                dispute.protocol.select_direction(dispute.target < mid).map_err(fail)?;
+                self.retention.renew(&dispute.id, self.now).map_err(fail)?;
+                self.stakes.extend_lock(&batch.proposer, self.now + LEASE_TIMEOUT).map_err(fail)?;
+                Ok(true)
+            }
+            BisectionState::Resolve => self.resolve_leaf(index),
+            ref state => Err(format!("dispute on b{} in unexpected state {state:?}", batch.id)),
+        }
+    }
+
+    fn resolve_leaf(&mut self, index: usize) -> StepResult<bool> {
+        let batch = &self.pending[index];
+        let dispute = batch.dispute.as_ref().unwrap();
+        let leaf = dispute.protocol.disputed_index().ok_or("resolved dispute has no leaf")?;
+        ensure(leaf == dispute.target, || format!("bisection reached {leaf}, challenger aimed at {}", dispute.target))?;
+
+        let context = ProofContext {
+            challenge_id: dispute.id,
+            batch_root: batch.tree.committed_root(),
+            index: leaf as u64,
+        };
+        let commitment = batch.chain.commitments()[leaf].clone();
+        let opening = Opening {
+            value: batch.chain.values()[leaf],
+            randomness: batch.chain.randomness()[leaf].clone(),
+        };
+        let opening_proof = self.params.prove_opening(&commitment, &opening, &context, &mut self.rng).map_err(fail)?;
+        let (_, transition) = &batch.transitions[leaf];
+        let proof = SingleStepProof {
+            index: leaf,
+            pre_state: transition.pre_state.clone(),
+            post_state: transition.post_state.clone(),
+            tx_hash: transition.tx_hash,
+            pre_state_inclusion: None,
+            step_inclusion: Some(StepInclusion {
+                leaf_count: batch.tree.leaf_count(),
+                path: batch.tree.generate_proof(leaf).map_err(fail)?,
+            }),
+            commitment,
+            opening: StepOpening::Bound(opening_proof),
+        };
+        let outcome = self
+            .resolver
+            .verify_single_step(&proof, &context, None, Some(batch.tree.committed_root()))
+            .map_err(fail)?;
+        let expected = if batch.faulty_index == Some(leaf) {
+            DisputeOutcome::ProposerFaulty
+        } else {
+            DisputeOutcome::ProposerCorrect
+        };
+        ensure(outcome == expected, || format!("b{} leaf {leaf}: got {outcome:?}, expected {expected:?}", batch.id))?;
+
+        let batch_id = batch.id;
+        self.record(format!("resolve b{batch_id} leaf {leaf}: {outcome:?}"));
+        self.pending[index].verdicts.push(outcome.clone());
+        if outcome == DisputeOutcome::ProposerFaulty {
+            self.settle_challenger_wins(index)?;
+            Ok(false)
+        } else {
+            self.settle_proposer_wins(index)?;
+            Ok(true)
+        }
+    }
+
+    fn settle_proposer_wins(&mut self, index: usize) -> StepResult {
+        let batch = &mut self.pending[index];
+        let dispute = batch.dispute.take().unwrap();
+        let proposer = batch.proposer.clone();
+        let bond = self.bonds.forfeit(&dispute.bond_key).map_err(fail)?;
+        let reward = self
+            .rewards
+            .calculate_reward(
+                dispute.challenger.clone(),
+                proposer.clone(),
+                RewardOutcome::ProposerWins,
+                0,
+                bond,
+                self.now - dispute.opened_at,
+            )
+            .map_err(fail)?;
+        *self.wallet(&proposer) += reward.proposer_reward;
+        *self.wallet(&dispute.challenger) += reward.challenger_reward;
+        self.treasury += reward.protocol_fee;
+        self.retention.release(&dispute.id).ok_or("resolved dispute held no lease")?;
+        Ok(())
+    }
+
+    /// Slashes the proposer, pays the challenger and rolls back the batch
+    /// together with every batch built on top of it.
+    fn settle_challenger_wins(&mut self, index: usize) -> StepResult {
+        let batch = &mut self.pending[index];
+        let dispute = batch.dispute.take().unwrap();
+        let proposer = batch.proposer.clone();
+        let bond = self.bonds.return_bond(&dispute.bond_key).map_err(fail)?;
+        let stake = self.stakes.slash(&proposer).map_err(fail)?;
+        let reward = self
+            .rewards
+            .calculate_reward(
+                dispute.challenger.clone(),
+                proposer.clone(),
+                RewardOutcome::ChallengerWins,
+                stake,
+                bond,
+                self.now - dispute.opened_at,
+            )
+            .map_err(fail)?;
+        *self.wallet(&dispute.challenger) += reward.challenger_reward;
+        *self.wallet(&proposer) += reward.proposer_reward;
+        self.treasury += reward.protocol_fee;
+        self.retention.release(&dispute.id).ok_or("resolved dispute held no lease")?;
+        self.record(format!("slash {proposer} for {stake}"));
+        self.rollback(index)
+    }
+
+    /// Drops `pending[index..]`. Disputes on dropped batches are moot, so
+    /// their bonds are returned and their leases released before the batch
+    /// data is purged.
+    fn rollback(&mut self, index: usize) -> StepResult {
+        let dropped: Vec<PendingBatch> = self.pending.drain(index..).collect();
+        let ids: Vec<u64> = dropped.iter().map(|b| b.id).collect();
+        for batch in dropped {
+            if let Some(dispute) = batch.dispute {
+                let bond = self.bonds.return_bond(&dispute.bond_key).map_err(fail)?;
+                *self.wallet(&dispute.challenger) += bond;
+                self.retention.release(&dispute.id).ok_or("cancelled dispute held no lease")?;
+            }
+            self.storage.purge(&batch.content, &self.retention).map_err(fail)?;
+        }
+        self.record(format!("rollback {ids:?}"));
+        Ok(())
+    }
+
+    fn finalize(&mut self) -> StepResult {
+        let ready = self
+            .pending
+            .front()
+            .is_some_and(|batch| self.now >= batch.window_end && batch.dispute.is_none());
+        if !ready {
+            return Ok(());
+        }
+        let mut batch = self.pending.pop_front().unwrap();
+        ensure(batch.faulty_index.is_none(), || format!("faulty batch b{} finalized", batch.id))?;
+
+        self.stakes.record_finalized(&batch.proposer);
+        self.settled_commitments.extend(batch.chain.commitments().iter().cloned());
+        self.settled_tree = Some(CommitmentMerkleTree::build(&self.settled_commitments).map_err(fail)?);
+        for (account, transition) in &batch.transitions {
+            self.settled_accounts.insert(*account, transition.post_state.clone());
+        }
+        self.finalized_transitions.extend(batch.transitions.iter().cloned());
+        self.storage.purge(&batch.content, &self.retention).map_err(fail)?;
+        batch.chain.clear_secrets();
+        self.record(format!("finalize b{} ({} verdicts)", batch.id, batch.verdicts.len()));
+        self.finalized_verdicts.push((batch.id, batch.verdicts));
+        Ok(())
+    }
+
+    fn churn(&mut self) -> StepResult {
+        let proposer = self.proposers[self.rng.gen_range(0..self.proposers.len())].clone();
+        if self.stakes.get_stake(&proposer).is_none() {
+            let required = self.stakes.required_stake_for(&proposer, STAKED_VALUE);
+            let amount = required + self.rng.gen_range(0..=required / 2);
+            if self.wallets[&proposer] < amount {
+                self.record(format!("deposit skipped: {proposer} cannot cover {amount}"));
+                return Ok(());
+            }
+            *self.wallet(&proposer) -= amount;
+            self.stakes
+                .deposit(proposer.clone(), amount, STAKED_VALUE, self.now + STAKE_LOCK)
+                .map_err(fail)?;
+            self.record(format!("deposit {proposer} {amount}"));
+            return Ok(());
+        }
+        match self.stakes.withdraw(&proposer, self.now) {
+            Ok(amount) => {
+                let exposed = self
+                    .pending
+                    .iter()
+                    .any(|b| b.proposer == proposer && (self.now < b.window_end || b.dispute.is_some()));
+                ensure(!exposed, || format!("{proposer} withdrew while a batch is still challengeable"))?;
+                *self.wallet(&proposer) += amount;
+                self.record(format!("withdraw {proposer} {amount}"));
+            }
+            Err(_) => self.record(format!("withdraw {proposer} refused: locked")),
+        }
+        Ok(())
+    }
+
+    /// Storage tries to drop a pending batch's data. Disputed data must be
+    /// protected by its lease; undisputed data is lost and the batch, which
+    /// can no longer be checked, is rolled back.
+    fn lose_data(&mut self) -> StepResult {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        let index = self.rng.gen_range(0..self.pending.len());
+        let batch = &self.pending[index];
+        let (batch_id, content, disputed) = (batch.id, batch.content.clone(), batch.dispute.is_some());
+        let purged = self.storage.purge(&content, &self.retention);
+        if disputed {
+            ensure(purged.is_err(), || format!("retained data of b{batch_id} was purged"))?;
+            self.record(format!("purge of b{batch_id} refused: retained"));
+            Ok(())
+        } else {
+            purged.map_err(fail)?;
+            self.record(format!("data of b{batch_id} lost"));
+            self.pending.remove(index);
+            self.rollback(index)
+        }
+    }
+
+    /// Freezes the settled tree, or restores from the last snapshot and
+    /// replays the batches finalized since, which must reproduce the live
+    /// settled state exactly.
+    fn snapshot_or_restore(&mut self) -> StepResult {
+        let Some(tree) = &self.settled_tree else {
+            return Ok(());
+        };
+        if self.snapshot.is_none() || self.rng.gen_bool(0.5) {
+            let mut bytes = Vec::new();
+            tree.freeze_with_aggregates_to(&mut bytes).map_err(fail)?;
+            let frozen = FrozenTree::from_bytes(bytes).map_err(fail)?;
+            ensure(frozen.committed_root() == tree.committed_root(), || "frozen root differs".to_string())?;
+            ensure(frozen.verify_integrity(), || "frozen tree failed integrity check".to_string())?;
+            let leaf = self.rng.gen_range(0..self.settled_commitments.len());
+            ensure(frozen.verify_leaf(&self.settled_commitments[leaf], leaf).map_err(fail)?, || {
+                format!("frozen leaf {leaf} does not verify")
+            })?;
+            self.record(format!("snapshot at {} leaves", frozen.leaf_count()));
+            self.snapshot = Some(Snapshot {
+                frozen,
+                accounts: self.settled_accounts.clone(),
+                replay_from: self.finalized_transitions.len(),
+            });
+            return Ok(());
+        }
+
+        let snapshot = self.snapshot.as_ref().unwrap();
+        let prefix = snapshot.frozen.leaf_count();
+        let rebuilt = CommitmentMerkleTree::build(&self.settled_commitments[..prefix]).map_err(fail)?;
+        ensure(rebuilt.committed_root() == snapshot.frozen.committed_root(), || {
+            "settled history changed since the snapshot".to_string()
+        })?;
+        let mut accounts = snapshot.accounts.clone();
+        for (account, transition) in &self.finalized_transitions[snapshot.replay_from..] {
+            ensure(accounts[account] == transition.pre_state, || format!("replay diverged at account {account}"))?;
+            accounts.insert(*account, transition.post_state.clone());
+        }
+        ensure(accounts == self.settled_accounts, || "restored state differs from live state".to_string())?;
+        self.record(format!("restore from {prefix} leaves, replayed to {}", self.settled_commitments.len()));
+        Ok(())
+    }
+
+    fn check_invariants(&self) -> StepResult {
+        self.check_funds()?;
+        self.check_chains()?;
+        self.check_retention()?;
+        for (batch_id, verdicts) in &self.finalized_verdicts {
+            ensure(verdicts.iter().all(|v| *v == DisputeOutcome::ProposerCorrect), || {
+                format!("finalized b{batch_id} carries verdicts {verdicts:?}")
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Wallets, live stake, posted bonds and the treasury always add up to
+    /// the initial supply.
+    fn check_funds(&self) -> StepResult {
+        let wallets: u128 = self.wallets.values().sum();
+        let staked: u128 = self
+            .proposers
+            .iter()
+            .filter_map(|p| self.stakes.get_stake(p))
+            .filter(|stake| !stake.slashed)
+            .map(|stake| stake.amount)
+            .sum();
+        let mut bonded = 0u128;
+        for dispute in self.pending.iter().filter_map(|b| b.dispute.as_ref()) {
+            let bond = self.bonds.get_bond(&dispute.bond_key).ok_or("open dispute has no bond")?;
+            ensure(!bond.forfeited, || format!("open dispute {} has a forfeited bond", dispute.bond_key))?;
+            bonded += bond.amount;
+        }
+        let total = wallets + staked + bonded + self.treasury;
+        ensure(total == self.total_supply, || {
+            format!(
+                "funds not conserved: wallets {wallets} + staked {staked} + bonded {bonded} + treasury {} = {total}, supply {}",
+                self.treasury, self.total_supply
+            )
+        })
+    }
+
+    fn check_chains(&self) -> StepResult {
+        let mut accounts = self.settled_accounts.clone();
+        for batch in &self.pending {
+            ensure(
+                batch.tree.leaf_count() == batch.chain.len() && batch.chain.len() == batch.transitions.len(),
+                || format!("b{} tree, chain and transitions differ in length", batch.id),
+            )?;
+            ensure(batch.tree.aggregate_matches(&batch.chain.aggregate()), || {
+                format!("b{} tree aggregate differs from chain", batch.id)
+            })?;
+            ensure(batch.chain.verify_aggregate(batch.tree.aggregate()).map_err(fail)?, || {
+                format!("b{} aggregate does not open", batch.id)
+            })?;
+            for (account, transition) in &batch.transitions {
+                ensure(accounts[account] == transition.pre_state, || {
+                    format!("b{} does not build on the state before it", batch.id)
+                })?;
+                accounts.insert(*account, transition.post_state.clone());
+            }
+        }
+        if let Some(tree) = &self.settled_tree {
+            ensure(tree.leaf_count() == self.settled_commitments.len(), || "settled tree is stale".to_string())?;
+            let expected = AggregateCommitment::from_commitments(&self.settled_commitments);
+            ensure(tree.aggregate_matches(&expected), || {
+                "settled tree aggregate differs from settled commitments".to_string()
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Exactly the open disputes hold leases, and exactly their data is
+    /// retained.
+    fn check_retention(&self) -> StepResult {
+        let open = self.pending.iter().filter(|b| b.dispute.is_some()).count();
+        ensure(self.retention.leases().count() == open, || {
+            format!("{} leases for {open} open disputes", self.retention.leases().count())
+        })?;
+        for batch in &self.pending {
+            ensure(self.storage.exists(&batch.content), || format!("data of pending b{} missing", batch.id))?;
+            let retained = self.retention.is_retained(&batch.content.resource_id());
+            match &batch.dispute {
+                Some(dispute) => {
+                    let lease = self.retention.lease(&dispute.id).ok_or("open dispute holds no lease")?;
+                    ensure(lease.expires_at > self.now, || format!("lease on b{} already expired", batch.id))?;
+                    ensure(retained, || format!("disputed b{} data not retained", batch.id))?;
+                }
+                None => ensure(!retained, || format!("undisputed b{} data still retained", batch.id))?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[test]
+#[ignore]
+fn soak_long_run() {
+    ScenarioBuilder::new(env_or("SOAK_SEED", 0x5eed))
+        .steps(env_or("SOAK_STEPS", 20_000))
+        .time_limit(Duration::from_secs(180))
+        .build()
+        .run();
+}
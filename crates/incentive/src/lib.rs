@@ -1,8 +1,11 @@
 pub mod stake;
 pub mod bond;
 pub mod reward;
+pub mod sampling;
+pub mod proposer;
 
-pub use stake::{StakeManager, StakeInfo};
+pub use stake::{StakeManager, StakeInfo, RampCurve, LinearRamp, ExponentialRamp, Reputation};
 pub use bond::{BondManager, ChallengerBond};
 pub use reward::{RewardDistributor, DisputeReward};
-
+pub use sampling::{SamplingRewardPool, SamplingRewardConfig, SamplingSubmission, SamplingPayout};
+pub use proposer::{ProposerRegistry, EpochSchedule, RotationMode, ProposalHeader, IneligibleProposerEvidence};
@@ -0,0 +1,429 @@
+use crate::stake::{StakeError, StakeManager};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum ProposerError {
+    #[error("Proposer has no active stake: {0}")]
+    NotStaked(String),
+    #[error("Proposer already registered: {0}")]
+    AlreadyRegistered(String),
+    #[error("Proposer not registered: {0}")]
+    NotRegistered(String),
+    #[error("Proposer {proposer_id} is under-staked: required {required}, available {available}")]
+    InsufficientStake { proposer_id: String, required: u128, available: u128 },
+    #[error("No schedule for epoch {0}")]
+    UnknownEpoch(u64),
+    #[error("Epoch {0} has no eligible proposers")]
+    EmptySchedule(u64),
+    #[error("Epoch {0} is already scheduled")]
+    EpochAlreadyScheduled(u64),
+    #[error("Total proposer weight overflows in epoch {0}")]
+    WeightOverflow(u64),
+    #[error("Wrong proposer for batch {batch_sequence}: expected {expected}, got {actual}")]
+    WrongProposer { batch_sequence: u64, expected: String, actual: String },
+    #[error("Invalid epoch length")]
+    InvalidConfig,
+}
+
+type Result<T> = std::result::Result<T, ProposerError>;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RotationMode {
+    RoundRobin,
+    StakeWeighted,
+}
+
+/// The frozen proposer set for one epoch. Everything needed to recompute who
+/// may propose any batch in the epoch, so it doubles as evidence input.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EpochSchedule {
+    pub epoch: u64,
+    pub epoch_length: u64,
+    pub mode: RotationMode,
+    pub seed: [u8; 32],
+    pub proposers: Vec<(String, u128)>,
+}
+
+impl EpochSchedule {
+    fn draw(&self, batch_sequence: u64) -> u128 {
+        let mut hasher = Sha256::new();
+        hasher.update(b"archimedes-proposer-rotation");
+        hasher.update(self.seed);
+        hasher.update(batch_sequence.to_be_bytes());
+        let digest = hasher.finalize();
+        u128::from_be_bytes(digest[..16].try_into().unwrap())
+    }
+
+    pub fn contains(&self, batch_sequence: u64) -> bool {
+        batch_sequence / self.epoch_length == self.epoch
+    }
+
+    pub fn select(&self, batch_sequence: u64) -> Option<&str> {
+        if !self.contains(batch_sequence) || self.proposers.is_empty() {
+            return None;
+        }
+        let index = match self.mode {
+            RotationMode::RoundRobin => {
+                let offset = (self.draw(self.epoch * self.epoch_length) % self.proposers.len() as u128) as u64;
+                ((batch_sequence + offset) % self.proposers.len() as u64) as usize
+            }
+            RotationMode::StakeWeighted => {
+                let total = total_weight(&self.proposers)?;
+                if total == 0 {
+                    return None;
+                }
+                let mut target = self.draw(batch_sequence) % total;
+                self.proposers
+                    .iter()
+                    .position(|(_, w)| {
+                        if target < *w {
+                            true
+                        } else {
+                            target -= w;
+                            false
+                        }
+                    })
+                    .unwrap()
+            }
+        };
+        Some(&self.proposers[index].0)
+    }
+
+    pub fn digest(&self) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update(self.epoch.to_be_bytes());
+        hasher.update(self.epoch_length.to_be_bytes());
+        hasher.update([self.mode as u8]);
+        hasher.update(self.seed);
+        for (id, weight) in &self.proposers {
+            hasher.update((id.len() as u64).to_be_bytes());
+            hasher.update(id.as_bytes());
+            hasher.update(weight.to_be_bytes());
+        }
+        hasher.finalize().into()
+    }
+}
+
+fn total_weight(proposers: &[(String, u128)]) -> Option<u128> {
+    proposers.iter().try_fold(0u128, |total, (_, weight)| total.checked_add(*weight))
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProposalHeader {
+    pub batch_sequence: u64,
+    pub proposer_id: String,
+    pub batch_root: [u8; 32],
+}
+
+/// A header whose proposer was not the scheduled one, together with the
+/// schedule it was checked against.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IneligibleProposerEvidence {
+    pub header: ProposalHeader,
+    pub schedule: EpochSchedule,
+}
+
+impl IneligibleProposerEvidence {
+    /// `schedule_digest` is the digest the verifier already trusts for the epoch.
+    pub fn verify(&self, schedule_digest: &[u8; 32]) -> bool {
+        self.schedule.digest() == *schedule_digest
+            && matches!(self.schedule.select(self.header.batch_sequence), Some(expected) if expected != self.header.proposer_id)
+    }
+}
+
+/// Tracks the live proposer set and freezes it into an `EpochSchedule` at each
+/// epoch boundary, so registrations and retirements apply from the next epoch.
+pub struct ProposerRegistry {
+    epoch_length: u64,
+    mode: RotationMode,
+    active: BTreeMap<String, u128>,
+    schedules: BTreeMap<u64, EpochSchedule>,
+}
+
+impl ProposerRegistry {
+    pub fn new(epoch_length: u64, mode: RotationMode) -> Result<Self> {
+        if epoch_length == 0 {
+            return Err(ProposerError::InvalidConfig);
+        }
+        Ok(Self {
+            epoch_length,
+            mode,
+            active: BTreeMap::new(),
+            schedules: BTreeMap::new(),
+        })
+    }
+
+    /// Registers with the proposer's current stake as its selection weight.
+    /// The stake must be compliant for the commitment value it was deposited
+    /// against.
+    pub fn register(&mut self, proposer_id: &str, stakes: &StakeManager) -> Result<()> {
+        if self.active.contains_key(proposer_id) {
+            return Err(ProposerError::AlreadyRegistered(proposer_id.to_string()));
+        }
+        let amount = compliant_stake(proposer_id, stakes)?;
+        self.active.insert(proposer_id.to_string(), amount);
+        Ok(())
+    }
+
+    pub fn retire(&mut self, proposer_id: &str) -> Result<()> {
+        self.active
+            .remove(proposer_id)
+            .map(|_| ())
+            .ok_or_else(|| ProposerError::NotRegistered(proposer_id.to_string()))
+    }
+
+    /// Freezes the current set for `epoch` using that epoch's beacon output.
+    /// Stakes are re-checked against `stakes`: proposers slashed, withdrawn
+    /// or under-staked since registering sit the epoch out, and the rest are
+    /// weighted by their current stake. A frozen schedule is never replaced,
+    /// since evidence is checked against its digest.
+    pub fn begin_epoch(&mut self, epoch: u64, beacon_seed: [u8; 32], stakes: &StakeManager) -> Result<&EpochSchedule> {
+        if self.schedules.contains_key(&epoch) {
+            return Err(ProposerError::EpochAlreadyScheduled(epoch));
+        }
+        let proposers: Vec<(String, u128)> = self
+            .active
+            .keys()
+            .filter_map(|id| compliant_stake(id, stakes).ok().map(|amount| (id.clone(), amount)))
+            .collect();
+        if proposers.is_empty() {
+            return Err(ProposerError::EmptySchedule(epoch));
+        }
+        total_weight(&proposers).ok_or(ProposerError::WeightOverflow(epoch))?;
+        let schedule = EpochSchedule {
+            epoch,
+            epoch_length: self.epoch_length,
+            mode: self.mode,
+            seed: beacon_seed,
+            proposers,
+        };
+        self.schedules.insert(epoch, schedule);
+        Ok(&self.schedules[&epoch])
+    }
+
+    pub fn schedule(&self, epoch: u64) -> Option<&EpochSchedule> {
+        self.schedules.get(&epoch)
+    }
+
+    pub fn eligible_proposer(&self, batch_sequence: u64) -> Result<&str> {
+        let epoch = batch_sequence / self.epoch_length;
+        let schedule = self.schedules.get(&epoch).ok_or(ProposerError::UnknownEpoch(epoch))?;
+        schedule.select(batch_sequence).ok_or(ProposerError::EmptySchedule(epoch))
+    }
+
+    /// Entry point for a proposed batch: the header must come from the
+    /// scheduled proposer, whose stake must still be compliant now, since a
+    /// slash mid-epoch does not change the frozen schedule.
+    pub fn admit_proposal(&self, header: &ProposalHeader, stakes: &StakeManager) -> Result<()> {
+        self.check_admission(header)?;
+        compliant_stake(&header.proposer_id, stakes).map(|_| ())
+    }
+
+    /// Schedule check for `header`, without the stake check that
+    /// `admit_proposal` adds.
+    pub fn check_admission(&self, header: &ProposalHeader) -> Result<()> {
+        let expected = self.eligible_proposer(header.batch_sequence)?;
+        if expected != header.proposer_id {
+            return Err(ProposerError::WrongProposer {
+                batch_sequence: header.batch_sequence,
+                expected: expected.to_string(),
+                actual: header.proposer_id.clone(),
+            });
+        }
+        Ok(())
+    }
+
+    pub fn evidence_for(&self, header: &ProposalHeader) -> Option<IneligibleProposerEvidence> {
+        if !matches!(self.check_admission(header), Err(ProposerError::WrongProposer { .. })) {
+            return None;
+        }
+        let schedule = self.schedules.get(&(header.batch_sequence / self.epoch_length))?.clone();
+        Some(IneligibleProposerEvidence {
+            header: header.clone(),
+            schedule,
+        })
+    }
+}
+
+/// The proposer's stake amount, if it is unslashed and covers the
+/// commitment value it was deposited against.
+fn compliant_stake(proposer_id: &str, stakes: &StakeManager) -> Result<u128> {
+    let stake = stakes
+        .get_stake(proposer_id)
+        .filter(|s| !s.slashed && s.amount > 0)
+        .ok_or_else(|| ProposerError::NotStaked(proposer_id.to_string()))?;
+    stakes.check_compliance(proposer_id, stake.commitment_value).map_err(|e| match e {
+        StakeError::InsufficientStake { required, available } => ProposerError::InsufficientStake {
+            proposer_id: proposer_id.to_string(),
+            required,
+            available,
+        },
+        _ => ProposerError::NotStaked(proposer_id.to_string()),
+    })?;
+    Ok(stake.amount)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stakes(amounts: &[(&str, u128)]) -> StakeManager {
+        let mut manager = StakeManager::new(100);
+        for (id, amount) in amounts {
+            manager.deposit(id.to_string(), *amount, 0, 0).unwrap();
+        }
+        manager
+    }
+
+    fn registry(mode: RotationMode, amounts: &[(&str, u128)]) -> (ProposerRegistry, StakeManager) {
+        let stakes = stakes(amounts);
+        let mut registry = ProposerRegistry::new(10, mode).unwrap();
+        for (id, _) in amounts {
+            registry.register(id, &stakes).unwrap();
+        }
+        (registry, stakes)
+    }
+
+    fn header(seq: u64, proposer: &str) -> ProposalHeader {
+        ProposalHeader {
+            batch_sequence: seq,
+            proposer_id: proposer.to_string(),
+            batch_root: [0u8; 32],
+        }
+    }
+
+    #[test]
+    fn test_schedule_deterministic_across_nodes() {
+        let amounts = [("a", 100), ("b", 200), ("c", 300)];
+        for mode in [RotationMode::RoundRobin, RotationMode::StakeWeighted] {
+            let (mut node1, stakes1) = registry(mode, &amounts);
+            let (mut node2, stakes2) = registry(mode, &[("c", 300), ("a", 100), ("b", 200)]);
+            node1.begin_epoch(4, [9u8; 32], &stakes1).unwrap();
+            node2.begin_epoch(4, [9u8; 32], &stakes2).unwrap();
+            assert_eq!(node1.schedule(4).unwrap().digest(), node2.schedule(4).unwrap().digest());
+            for seq in 40..50 {
+                assert_eq!(node1.eligible_proposer(seq).unwrap(), node2.eligible_proposer(seq).unwrap());
+            }
+            assert_eq!(node1.eligible_proposer(50), Err(ProposerError::UnknownEpoch(5)));
+        }
+
+        let (mut rr, stakes) = registry(RotationMode::RoundRobin, &amounts);
+        rr.begin_epoch(0, [1u8; 32], &stakes).unwrap();
+        let picks: Vec<&str> = (0..6).map(|s| rr.eligible_proposer(s).unwrap()).collect();
+        assert_eq!(picks[..3], picks[3..]);
+        assert!(picks[..3].contains(&"a") && picks[..3].contains(&"b") && picks[..3].contains(&"c"));
+    }
+
+    #[test]
+    fn test_stake_weighted_distribution() {
+        let (mut registry, stakes) = registry(RotationMode::StakeWeighted, &[("a", 100), ("b", 300), ("c", 600)]);
+        let mut counts: BTreeMap<String, u64> = BTreeMap::new();
+        for epoch in 0..500u64 {
+            let mut seed = [0u8; 32];
+            seed[..8].copy_from_slice(&epoch.to_be_bytes());
+            registry.begin_epoch(epoch, seed, &stakes).unwrap();
+            for seq in epoch * 10..(epoch + 1) * 10 {
+                *counts.entry(registry.eligible_proposer(seq).unwrap().to_string()).or_default() += 1;
+            }
+        }
+        // 5000 draws; expected 500/1500/3000 with standard deviations of about 21/32/35.
+        assert!((400..600).contains(&counts["a"]), "{:?}", counts);
+        assert!((1350..1650).contains(&counts["b"]), "{:?}", counts);
+        assert!((2850..3150).contains(&counts["c"]), "{:?}", counts);
+    }
+
+    #[test]
+    fn test_retirement_applies_at_epoch_boundary() {
+        let (mut registry, live) = registry(RotationMode::RoundRobin, &[("a", 1), ("b", 1)]);
+        registry.begin_epoch(0, [0u8; 32], &live).unwrap();
+        registry.retire("a").unwrap();
+        let in_epoch: Vec<&str> = (0..10).map(|s| registry.eligible_proposer(s).unwrap()).collect();
+        assert!(in_epoch.contains(&"a"));
+
+        registry.begin_epoch(1, [1u8; 32], &live).unwrap();
+        assert!((10..20).all(|s| registry.eligible_proposer(s).unwrap() == "b"));
+        // Re-freezing an epoch would rewrite who was eligible in it.
+        let frozen = registry.schedule(0).unwrap().clone();
+        assert_eq!(registry.begin_epoch(0, [2u8; 32], &live), Err(ProposerError::EpochAlreadyScheduled(0)));
+        assert_eq!(registry.schedule(0), Some(&frozen));
+        assert_eq!(registry.retire("a"), Err(ProposerError::NotRegistered("a".to_string())));
+
+        let slashed = {
+            let mut s = stakes(&[("x", 10)]);
+            s.slash("x").unwrap();
+            s
+        };
+        assert_eq!(registry.register("x", &slashed), Err(ProposerError::NotStaked("x".to_string())));
+    }
+
+    #[test]
+    fn test_ineligible_proposer_evidence() {
+        let (mut registry, stakes) = registry(RotationMode::StakeWeighted, &[("a", 100), ("b", 100)]);
+        let digest = registry.begin_epoch(2, [5u8; 32], &stakes).unwrap().digest();
+        let expected = registry.eligible_proposer(23).unwrap().to_string();
+        let wrong = if expected == "a" { "b" } else { "a" };
+
+        registry.check_admission(&header(23, &expected)).unwrap();
+        registry.admit_proposal(&header(23, &expected), &stakes).unwrap();
+        assert!(matches!(registry.admit_proposal(&header(23, wrong), &stakes), Err(ProposerError::WrongProposer { .. })));
+        assert!(matches!(registry.check_admission(&header(23, wrong)), Err(ProposerError::WrongProposer { .. })));
+        assert!(registry.evidence_for(&header(23, &expected)).is_none());
+
+        let evidence = registry.evidence_for(&header(23, wrong)).unwrap();
+        assert!(evidence.verify(&digest));
+        assert!(!evidence.verify(&[0u8; 32]));
+
+        let mut framed = evidence.clone();
+        framed.header.proposer_id = expected.clone();
+        assert!(!framed.verify(&digest));
+        let mut tampered = evidence;
+        tampered.schedule.seed = [6u8; 32];
+        assert!(!tampered.verify(&digest));
+    }
+
+    #[test]
+    fn test_epoch_rechecks_stakes() {
+        let (mut registry, mut stakes) = registry(RotationMode::StakeWeighted, &[("a", 100), ("b", 100), ("c", 100)]);
+        registry.begin_epoch(0, [0u8; 32], &stakes).unwrap();
+        let scheduled = (0..10).find(|&s| registry.eligible_proposer(s).unwrap() == "b").unwrap();
+
+        // Slashed mid-epoch: still scheduled, but no longer admitted.
+        stakes.slash("b").unwrap();
+        assert_eq!(
+            registry.admit_proposal(&header(scheduled, "b"), &stakes),
+            Err(ProposerError::NotStaked("b".to_string()))
+        );
+
+        // Left out of the next epoch, and of every later one until restaked.
+        let schedule = registry.begin_epoch(1, [1u8; 32], &stakes).unwrap();
+        assert_eq!(schedule.proposers, vec![("a".to_string(), 100), ("c".to_string(), 100)]);
+        stakes.withdraw("b", 0).unwrap();
+        stakes.deposit("b".to_string(), 400, 0, 0).unwrap();
+        let schedule = registry.begin_epoch(2, [2u8; 32], &stakes).unwrap();
+        assert_eq!(schedule.proposers[1], ("b".to_string(), 400));
+
+        stakes.slash("a").unwrap();
+        stakes.slash("b").unwrap();
+        stakes.slash("c").unwrap();
+        assert_eq!(registry.begin_epoch(3, [3u8; 32], &stakes), Err(ProposerError::EmptySchedule(3)));
+    }
+
+    #[test]
+    fn test_weight_overflow_rejected() {
+        let (mut registry, stakes) = registry(RotationMode::StakeWeighted, &[("a", u128::MAX), ("b", 1)]);
+        assert_eq!(registry.begin_epoch(0, [0u8; 32], &stakes), Err(ProposerError::WeightOverflow(0)));
+
+        // A schedule supplied as evidence gets no further than `select`.
+        let schedule = EpochSchedule {
+            epoch: 0,
+            epoch_length: 10,
+            mode: RotationMode::StakeWeighted,
+            seed: [0u8; 32],
+            proposers: vec![("a".to_string(), u128::MAX), ("b".to_string(), 1)],
+        };
+        assert_eq!(schedule.select(3), None);
+    }
+}
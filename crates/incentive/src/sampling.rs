@@ -0,0 +1,320 @@
+use archimedes_availability::{AvailabilitySampler, ContentId, SampleProof};
+use archimedes_availability::sampling::SamplingError;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum SamplingRewardError {
+    #[error("Submission after challenge window: submitted {submitted_at}, window closed {window_end}")]
+    WindowClosed { submitted_at: u64, window_end: u64 },
+    #[error("Sampler already submitted: {0}")]
+    DuplicateSampler(String),
+    #[error("Submission contains no sample indices")]
+    EmptySubmission,
+    #[error("Sample proof for shard {shard_index} does not verify: {source}")]
+    InvalidSample { shard_index: usize, source: SamplingError },
+    #[error("Pool already finalized")]
+    AlreadyFinalized,
+    #[error("Challenge window still open until {0}")]
+    WindowOpen(u64),
+    #[error("Invalid pool configuration")]
+    InvalidConfig,
+}
+
+type Result<T> = std::result::Result<T, SamplingRewardError>;
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SamplingRewardConfig {
+    pub fee_share_bps: u128,
+    pub max_paid_samplers: usize,
+    pub overlap_threshold_bps: u128,
+    pub overlap_rate_bps: u128,
+}
+
+impl SamplingRewardConfig {
+    pub fn validate(&self) -> Result<()> {
+        if self.fee_share_bps > 10000
+            || self.max_paid_samplers == 0
+            || self.overlap_threshold_bps > 10000
+            || self.overlap_rate_bps > 10000
+        {
+            return Err(SamplingRewardError::InvalidConfig);
+        }
+        Ok(())
+    }
+}
+
+/// A sampler's claim: one proof per sampled shard, each checked against the
+/// pool's shard root before the submission is recorded.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SamplingSubmission {
+    pub sampler_id: String,
+    pub proofs: Vec<SampleProof>,
+    pub submitted_at: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AcceptedSample {
+    pub sampler_id: String,
+    pub overlap_bps: u128,
+    pub rate_bps: u128,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SamplingPayout {
+    pub batch_id: String,
+    pub payouts: Vec<(String, u128)>,
+    pub treasury: u128,
+}
+
+pub struct SamplingRewardPool {
+    batch_id: String,
+    config: SamplingRewardConfig,
+    shard_root: ContentId,
+    verifier: AvailabilitySampler,
+    funded: u128,
+    window_end: u64,
+    accepted: Vec<AcceptedSample>,
+    covered: BTreeSet<usize>,
+    finalized: bool,
+}
+
+impl SamplingRewardPool {
+    /// `shard_root` is the batch's committed `AvailabilitySampler::shard_root`
+    /// over `total_shards` shards.
+    pub fn new(
+        batch_id: String,
+        proposer_fee: u128,
+        window_end: u64,
+        shard_root: ContentId,
+        total_shards: usize,
+        config: SamplingRewardConfig,
+    ) -> Result<Self> {
+        config.validate()?;
+        let funded = proposer_fee * config.fee_share_bps / 10000;
+        Ok(Self {
+            batch_id,
+            config,
+            shard_root,
+            verifier: AvailabilitySampler::new(0, total_shards),
+            funded,
+            window_end,
+            accepted: Vec::new(),
+            covered: BTreeSet::new(),
+            finalized: false,
+        })
+    }
+
+    pub fn funded(&self) -> u128 {
+        self.funded
+    }
+
+    pub fn accepted(&self) -> &[AcceptedSample] {
+        &self.accepted
+    }
+
+    /// Records a submission. Only the first `max_paid_samplers` distinct
+    /// samplers are paid; later valid submissions are accepted with a zero
+    /// rate. Submissions whose indices mostly repeat earlier coverage are paid
+    /// at `overlap_rate_bps`.
+    pub fn submit(&mut self, submission: SamplingSubmission) -> Result<&AcceptedSample> {
+        if self.finalized {
+            return Err(SamplingRewardError::AlreadyFinalized);
+        }
+        if submission.submitted_at > self.window_end {
+            return Err(SamplingRewardError::WindowClosed {
+                submitted_at: submission.submitted_at,
+                window_end: self.window_end,
+            });
+        }
+        if self.accepted.iter().any(|a| a.sampler_id == submission.sampler_id) {
+            return Err(SamplingRewardError::DuplicateSampler(submission.sampler_id));
+        }
+        if submission.proofs.is_empty() {
+            return Err(SamplingRewardError::EmptySubmission);
+        }
+        for proof in &submission.proofs {
+            self.verifier
+                .verify_proof_detailed(proof, &self.shard_root)
+                .map_err(|source| SamplingRewardError::InvalidSample { shard_index: proof.shard_index, source })?;
+        }
+        let indices: BTreeSet<usize> = submission.proofs.iter().map(|proof| proof.shard_index).collect();
+
+        let overlapping = indices.intersection(&self.covered).count() as u128;
+        let overlap_bps = overlapping * 10000 / indices.len() as u128;
+        let rate_bps = if self.accepted.len() >= self.config.max_paid_samplers {
+            0
+        } else if overlap_bps > self.config.overlap_threshold_bps {
+            self.config.overlap_rate_bps
+        } else {
+            10000
+        };
+
+        self.covered.extend(indices);
+        self.accepted.push(AcceptedSample {
+            sampler_id: submission.sampler_id,
+            overlap_bps,
+            rate_bps,
+        });
+        Ok(self.accepted.last().unwrap())
+    }
+
+    /// Pays each accepted sampler its slot share scaled by its rate and rolls
+    /// everything unclaimed to the treasury.
+    pub fn finalize(&mut self, now: u64) -> Result<SamplingPayout> {
+        if self.finalized {
+            return Err(SamplingRewardError::AlreadyFinalized);
+        }
+        if now <= self.window_end {
+            return Err(SamplingRewardError::WindowOpen(self.window_end));
+        }
+        let slot = self.funded / self.config.max_paid_samplers as u128;
+        let payouts: Vec<(String, u128)> = self
+            .accepted
+            .iter()
+            .filter(|a| a.rate_bps > 0)
+            .map(|a| (a.sampler_id.clone(), slot * a.rate_bps / 10000))
+            .collect();
+        let paid: u128 = payouts.iter().map(|(_, amount)| amount).sum();
+        self.finalized = true;
+        Ok(SamplingPayout {
+            batch_id: self.batch_id.clone(),
+            payouts,
+            treasury: self.funded - paid,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use archimedes_availability::erasure::EncodedShard;
+    use archimedes_core::MerkleVerifyError;
+
+    const SHARDS: usize = 24;
+
+    fn shards() -> Vec<EncodedShard> {
+        (0..SHARDS)
+            .map(|index| EncodedShard {
+                index,
+                data: vec![index as u8; 4],
+                is_parity: false,
+            })
+            .collect()
+    }
+
+    fn pool(proposer_fee: u128) -> SamplingRewardPool {
+        let root = AvailabilitySampler::shard_root(&shards());
+        SamplingRewardPool::new("batch1".to_string(), proposer_fee, 100, root, SHARDS, config()).unwrap()
+    }
+
+    fn config() -> SamplingRewardConfig {
+        SamplingRewardConfig {
+            fee_share_bps: 2000,
+            max_paid_samplers: 3,
+            overlap_threshold_bps: 5000,
+            overlap_rate_bps: 2500,
+        }
+    }
+
+    fn submission(id: &str, indices: &[usize], at: u64) -> SamplingSubmission {
+        let shards = shards();
+        SamplingSubmission {
+            sampler_id: id.to_string(),
+            proofs: indices.iter().map(|&i| AvailabilitySampler::create_proof(&shards[i], &shards)).collect(),
+            submitted_at: at,
+        }
+    }
+
+    #[test]
+    fn test_full_pool_claims() {
+        let mut pool = pool(10_000);
+        assert_eq!(pool.funded(), 2000);
+        pool.submit(submission("s1", &[0, 1, 2], 10)).unwrap();
+        pool.submit(submission("s2", &[3, 4, 5], 20)).unwrap();
+        pool.submit(submission("s3", &[6, 7, 8], 30)).unwrap();
+        let late_slot = pool.submit(submission("s4", &[9, 10, 11], 40)).unwrap();
+        assert_eq!(late_slot.rate_bps, 0);
+
+        let payout = pool.finalize(101).unwrap();
+        assert_eq!(payout.payouts.len(), 3);
+        assert!(payout.payouts.iter().all(|(_, amount)| *amount == 666));
+        assert_eq!(payout.treasury, 2);
+    }
+
+    #[test]
+    fn test_overlap_discounting() {
+        let mut pool = pool(10_000);
+        pool.submit(submission("s1", &[0, 1, 2, 3], 10)).unwrap();
+        let copycat = pool.submit(submission("s2", &[0, 1, 2, 9], 11)).unwrap();
+        assert_eq!(copycat.overlap_bps, 7500);
+        assert_eq!(copycat.rate_bps, 2500);
+        let partial = pool.submit(submission("s3", &[3, 7, 8, 9], 12)).unwrap();
+        assert_eq!(partial.overlap_bps, 5000);
+        assert_eq!(partial.rate_bps, 10000);
+
+        assert!(matches!(
+            pool.submit(submission("s1", &[20], 13)),
+            Err(SamplingRewardError::DuplicateSampler(_))
+        ));
+
+        let payout = pool.finalize(200).unwrap();
+        assert_eq!(payout.payouts[1], ("s2".to_string(), 166));
+    }
+
+    #[test]
+    fn test_late_submission_rejected() {
+        let mut pool = pool(10_000);
+        assert!(matches!(
+            pool.submit(submission("s1", &[0], 101)),
+            Err(SamplingRewardError::WindowClosed { .. })
+        ));
+        assert!(matches!(pool.finalize(100), Err(SamplingRewardError::WindowOpen(100))));
+        pool.finalize(101).unwrap();
+        assert!(matches!(
+            pool.submit(submission("s2", &[0], 50)),
+            Err(SamplingRewardError::AlreadyFinalized)
+        ));
+    }
+
+    #[test]
+    fn test_unproven_samples_rejected() {
+        let mut pool = pool(10_000);
+        let mut forged = submission("s1", &[3, 4], 10);
+        forged.proofs[1].shard_hash = forged.proofs[0].shard_hash;
+        assert!(matches!(
+            pool.submit(forged),
+            Err(SamplingRewardError::InvalidSample {
+                shard_index: 4,
+                source: SamplingError::InvalidMerkleProof(MerkleVerifyError::RootMismatch { .. }),
+            })
+        ));
+        let mut relabelled = submission("s1", &[3], 10);
+        relabelled.proofs[0].shard_index = SHARDS;
+        assert!(matches!(pool.submit(relabelled), Err(SamplingRewardError::InvalidSample { .. })));
+        assert!(pool.accepted().is_empty());
+
+        // Proofs against another batch's shards do not count here.
+        let other: Vec<EncodedShard> = shards().into_iter().map(|s| EncodedShard { data: vec![0xff; 4], ..s }).collect();
+        let foreign = SamplingSubmission {
+            sampler_id: "s2".to_string(),
+            proofs: vec![AvailabilitySampler::create_proof(&other[0], &other)],
+            submitted_at: 10,
+        };
+        assert!(matches!(pool.submit(foreign), Err(SamplingRewardError::InvalidSample { shard_index: 0, .. })));
+        pool.submit(submission("s1", &[3], 11)).unwrap();
+    }
+
+    #[test]
+    fn test_payout_conservation() {
+        for fee in [0u128, 1, 999, 10_000, 123_457] {
+            let mut pool = pool(fee);
+            pool.submit(submission("s1", &[0, 1], 1)).unwrap();
+            pool.submit(submission("s2", &[0, 1], 2)).unwrap();
+            let payout = pool.finalize(101).unwrap();
+            let paid: u128 = payout.payouts.iter().map(|(_, a)| a).sum();
+            assert_eq!(paid + payout.treasury, pool.funded());
+        }
+    }
+}
@@ -12,6 +12,8 @@ pub enum StakeError {
     StakeAlreadyExists(String),
     #[error("Invalid stake amount")]
     InvalidAmount,
+    #[error("Invalid ramp configuration")]
+    InvalidConfig,
 }
 
 type Result<T> = std::result::Result<T, StakeError>;
@@ -37,13 +39,73 @@ impl StakeInfo {
     }
 
     pub fn is_locked(&self, current_time: u64) -> bool {
-        current_time < self.locked_until && !self.slashed
+        current_time < self.locked_until
     }
 }
 
+/// Shapes how a new or recently slashed proposer's stake premium decays as it
+/// finalizes batches. Premiums are in basis points on top of the base ratio.
+pub trait RampCurve: Send + Sync {
+    fn premium_bps(&self, initial_premium_bps: u128, finalized_since_slash: u64) -> u128;
+
+    fn is_valid(&self) -> bool {
+        true
+    }
+}
+
+/// Premium falls in equal steps and reaches zero after `batches_to_floor`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LinearRamp {
+    pub batches_to_floor: u64,
+}
+
+impl RampCurve for LinearRamp {
+    fn premium_bps(&self, initial_premium_bps: u128, finalized_since_slash: u64) -> u128 {
+        let remaining = self.batches_to_floor.saturating_sub(finalized_since_slash) as u128;
+        initial_premium_bps * remaining / self.batches_to_floor as u128
+    }
+
+    fn is_valid(&self) -> bool {
+        self.batches_to_floor > 0
+    }
+}
+
+/// Premium keeps `retain_bps` of its previous value after each finalized batch.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExponentialRamp {
+    pub retain_bps: u128,
+}
+
+impl RampCurve for ExponentialRamp {
+    fn premium_bps(&self, initial_premium_bps: u128, finalized_since_slash: u64) -> u128 {
+        let mut premium = initial_premium_bps;
+        for _ in 0..finalized_since_slash {
+            if premium == 0 {
+                break;
+            }
+            premium = premium * self.retain_bps / 10000;
+        }
+        premium
+    }
+
+    fn is_valid(&self) -> bool {
+        self.retain_bps < 10000
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Reputation {
+    pub finalized_batches: u64,
+    pub slash_count: u64,
+    pub finalized_since_slash: u64,
+}
+
 pub struct StakeManager {
     stakes: HashMap<String, StakeInfo>,
     min_stake_ratio: u128, // basis points (1/10000)
+    initial_multiplier_bps: u128,
+    ramp: Box<dyn RampCurve>,
+    reputations: HashMap<String, Reputation>,
 }
 
 impl StakeManager {
@@ -51,19 +113,69 @@ impl StakeManager {
         Self {
             stakes: HashMap::new(),
             min_stake_ratio,
+            initial_multiplier_bps: 10000,
+            ramp: Box::new(LinearRamp { batches_to_floor: 1 }),
+            reputations: HashMap::new(),
         }
     }
 
+    /// Requires new and recently slashed proposers to post
+    /// `initial_multiplier_bps / 10000` times the base ratio, decaying along
+    /// `ramp` as they finalize batches.
+    pub fn with_ramp<R: RampCurve + 'static>(min_stake_ratio: u128, initial_multiplier_bps: u128, ramp: R) -> Result<Self> {
+        if initial_multiplier_bps < 10000 || !ramp.is_valid() {
+            return Err(StakeError::InvalidConfig);
+        }
+        Ok(Self {
+            initial_multiplier_bps,
+            ramp: Box::new(ramp),
+            ..Self::new(min_stake_ratio)
+        })
+    }
+
     pub fn required_stake(&self, commitment_value: u128) -> u128 {
         commitment_value * self.min_stake_ratio / 10000
     }
 
+    pub fn reputation(&self, proposer_id: &str) -> Reputation {
+        self.reputations.get(proposer_id).cloned().unwrap_or_default()
+    }
+
+    pub fn effective_ratio(&self, proposer_id: &str) -> u128 {
+        let reputation = self.reputation(proposer_id);
+        let premium = self.ramp.premium_bps(self.initial_multiplier_bps - 10000, reputation.finalized_since_slash);
+        self.min_stake_ratio * (10000 + premium) / 10000
+    }
+
+    pub fn required_stake_for(&self, proposer_id: &str, commitment_value: u128) -> u128 {
+        commitment_value * self.effective_ratio(proposer_id) / 10000
+    }
+
+    /// Batch-admission check: the proposer's live stake must cover `commitment_value`
+    /// at its current effective ratio.
+    pub fn check_compliance(&self, proposer_id: &str, commitment_value: u128) -> Result<()> {
+        let stake = self.stakes.get(proposer_id)
+            .ok_or_else(|| StakeError::ProposerNotFound(proposer_id.to_string()))?;
+        let available = if stake.slashed { 0 } else { stake.amount };
+        let required = self.required_stake_for(proposer_id, commitment_value);
+        if available < required {
+            return Err(StakeError::InsufficientStake { required, available });
+        }
+        Ok(())
+    }
+
+    pub fn record_finalized(&mut self, proposer_id: &str) {
+        let reputation = self.reputations.entry(proposer_id.to_string()).or_default();
+        reputation.finalized_batches += 1;
+        reputation.finalized_since_slash += 1;
+    }
+
     pub fn deposit(&mut self, proposer_id: String, amount: u128, commitment_value: u128, lock_duration: u64) -> Result<()> {
         if self.stakes.contains_key(&proposer_id) {
             return Err(StakeError::StakeAlreadyExists(proposer_id));
         }
 
-        let required = self.required_stake(commitment_value);
+        let required = self.required_stake_for(&proposer_id, commitment_value);
         if amount < required {
             return Err(StakeError::InsufficientStake { required, available: amount });
         }
@@ -82,7 +194,11 @@ impl StakeManager {
         }
         
         stake.slashed = true;
-        Ok(stake.amount)
+        let amount = stake.amount;
+        let reputation = self.reputations.entry(proposer_id.to_string()).or_default();
+        reputation.slash_count += 1;
+        reputation.finalized_since_slash = 0;
+        Ok(amount)
     }
 
     pub fn withdraw(&mut self, proposer_id: &str, current_time: u64) -> Result<u128> {
@@ -93,15 +209,22 @@ impl StakeManager {
             return Err(StakeError::InvalidAmount);
         }
         
-        if stake.slashed {
-            return Ok(0);
-        }
-        
-        let amount = stake.amount;
+        // A slashed stake pays nothing out, but its record is still cleared so
+        // the proposer can post a fresh stake. Reputation survives separately.
+        let amount = if stake.slashed { 0 } else { stake.amount };
         self.stakes.remove(proposer_id);
         Ok(amount)
     }
 
+    /// Keeps the stake locked until at least `until`, e.g. the end of the
+    /// challenge window of the proposer's latest batch.
+    pub fn extend_lock(&mut self, proposer_id: &str, until: u64) -> Result<()> {
+        let stake = self.stakes.get_mut(proposer_id)
+            .ok_or_else(|| StakeError::ProposerNotFound(proposer_id.to_string()))?;
+        stake.locked_until = stake.locked_until.max(until);
+        Ok(())
+    }
+
     pub fn get_stake(&self, proposer_id: &str) -> Option<&StakeInfo> {
         self.stakes.get(proposer_id)
     }
@@ -137,6 +260,85 @@ mod tests {
         
         let stake = manager.get_stake("proposer1").unwrap();
         assert!(stake.slashed);
+
+        assert!(matches!(manager.withdraw("proposer1", 50), Err(StakeError::InvalidAmount)));
+        assert_eq!(manager.withdraw("proposer1", 100).unwrap(), 0);
+        assert!(manager.get_stake("proposer1").is_none());
+        manager.deposit("proposer1".to_string(), 1000, 10000, 200).unwrap();
     }
-}
 
+    #[test]
+    fn test_extend_lock() {
+        let mut manager = StakeManager::new(100);
+        manager.deposit("p1".to_string(), 1000, 10000, 100).unwrap();
+        manager.extend_lock("p1", 300).unwrap();
+        manager.extend_lock("p1", 200).unwrap();
+        assert!(matches!(manager.withdraw("p1", 299), Err(StakeError::InvalidAmount)));
+        assert_eq!(manager.withdraw("p1", 300).unwrap(), 1000);
+        assert!(matches!(manager.extend_lock("p1", 400), Err(StakeError::ProposerNotFound(_))));
+    }
+
+    #[test]
+    fn test_ramp_down_over_finalizations() {
+        let mut manager = StakeManager::with_ramp(100, 30000, LinearRamp { batches_to_floor: 4 }).unwrap();
+        assert_eq!(manager.effective_ratio("p1"), 300);
+        assert!(matches!(
+            manager.deposit("p1".to_string(), 1000, 100_000, 100),
+            Err(StakeError::InsufficientStake { required: 3000, .. })
+        ));
+        manager.deposit("p1".to_string(), 3000, 100_000, 100).unwrap();
+        manager.check_compliance("p1", 100_000).unwrap();
+
+        let expected = [250, 200, 150, 100, 100];
+        for ratio in expected {
+            manager.record_finalized("p1");
+            assert_eq!(manager.effective_ratio("p1"), ratio);
+        }
+        assert_eq!(manager.reputation("p1").finalized_batches, 5);
+        assert_eq!(manager.required_stake_for("p1", 100_000), manager.required_stake(100_000));
+
+        let mut exp = StakeManager::with_ramp(100, 30000, ExponentialRamp { retain_bps: 5000 }).unwrap();
+        let mut last = exp.effective_ratio("p2");
+        for _ in 0..20 {
+            exp.record_finalized("p2");
+            let ratio = exp.effective_ratio("p2");
+            assert!(ratio <= last);
+            last = ratio;
+        }
+        assert_eq!(last, 100);
+    }
+
+    #[test]
+    fn test_ramp_resets_on_slash() {
+        let mut manager = StakeManager::with_ramp(100, 20000, LinearRamp { batches_to_floor: 2 }).unwrap();
+        manager.deposit("p1".to_string(), 2000, 100_000, 0).unwrap();
+        manager.record_finalized("p1");
+        manager.record_finalized("p1");
+        assert_eq!(manager.effective_ratio("p1"), 100);
+
+        manager.slash("p1").unwrap();
+        let reputation = manager.reputation("p1");
+        assert_eq!(reputation.slash_count, 1);
+        assert_eq!(reputation.finalized_since_slash, 0);
+        assert_eq!(reputation.finalized_batches, 2);
+        assert_eq!(manager.effective_ratio("p1"), 200);
+        assert!(matches!(
+            manager.check_compliance("p1", 100_000),
+            Err(StakeError::InsufficientStake { available: 0, .. })
+        ));
+
+        manager.record_finalized("p1");
+        assert_eq!(manager.effective_ratio("p1"), 150);
+    }
+
+    #[test]
+    fn test_ramp_config_validation() {
+        assert!(matches!(
+            StakeManager::with_ramp(100, 9999, LinearRamp { batches_to_floor: 4 }),
+            Err(StakeError::InvalidConfig)
+        ));
+        assert!(StakeManager::with_ramp(100, 20000, LinearRamp { batches_to_floor: 0 }).is_err());
+        assert!(StakeManager::with_ramp(100, 20000, ExponentialRamp { retain_bps: 10000 }).is_err());
+        assert!(StakeManager::with_ramp(100, 10000, ExponentialRamp { retain_bps: 0 }).is_ok());
+    }
+}
@@ -1,6 +1,5 @@
-use crate::witness::{TransitionOperation, TransitionWitness};
+use crate::witness::TransitionWitness;
 use serde::{Deserialize, Serialize};
-use sha2::{Digest, Sha256};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -48,7 +47,7 @@ impl TransitionCircuit {
         let pre_hash = witness.pre_state.hash();
         let post_hash = witness.post_state.hash();
         
-        let operation_hash = Self::hash_operation(&witness.operation);
+        let operation_hash = witness.operation.hash();
 
         let input = CircuitInput {
             pre_state_hash: pre_hash,
@@ -56,25 +55,23 @@ impl TransitionCircuit {
             operation_hash,
         };
 
-        let mut constraints = Vec::new();
-
-        constraints.push(Constraint {
-            left: ConstraintTerm::Variable(0),
-            right: ConstraintTerm::Constant(1),
-            output: ConstraintTerm::Variable(1),
-        });
-
-        constraints.push(Constraint {
-            left: ConstraintTerm::Variable(1),
-            right: ConstraintTerm::Variable(2),
-            output: ConstraintTerm::Variable(3),
-        });
-
-        constraints.push(Constraint {
-            left: ConstraintTerm::LinearCombination(vec![(1, 0), (1, 1)]),
-            right: ConstraintTerm::Constant(1),
-            output: ConstraintTerm::Variable(4),
-        });
+        let constraints = vec![
+            Constraint {
+                left: ConstraintTerm::Variable(0),
+                right: ConstraintTerm::Constant(1),
+                output: ConstraintTerm::Variable(1),
+            },
+            Constraint {
+                left: ConstraintTerm::Variable(1),
+                right: ConstraintTerm::Variable(2),
+                output: ConstraintTerm::Variable(3),
+            },
+            Constraint {
+                left: ConstraintTerm::LinearCombination(vec![(1, 0), (1, 1)]),
+                right: ConstraintTerm::Constant(1),
+                output: ConstraintTerm::Variable(4),
+            },
+        ];
 
         Ok(Self {
             constraints,
@@ -83,28 +80,6 @@ impl TransitionCircuit {
         })
     }
 
-    fn hash_operation(op: &TransitionOperation) -> [u8; 32] {
-        let mut hasher = Sha256::new();
-        match op {
-            TransitionOperation::Transfer { amount } => {
-                hasher.update(b"transfer");
-                hasher.update(&amount.to_le_bytes());
-            }
-            TransitionOperation::NonceIncrement => {
-                hasher.update(b"nonce_inc");
-            }
-            TransitionOperation::StorageWrite { key, value } => {
-                hasher.update(b"storage_write");
-                hasher.update(key);
-                hasher.update(value);
-            }
-        }
-        let result = hasher.finalize();
-        let mut hash = [0u8; 32];
-        hash.copy_from_slice(&result);
-        hash
-    }
-
     pub fn verify_constraints(&self, assignment: &[u64]) -> Result<bool> {
         if assignment.len() < self.num_variables {
             return Err(CircuitError::InvalidInput);
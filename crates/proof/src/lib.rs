@@ -1,8 +1,14 @@
+#[cfg(feature = "witness")]
 pub mod witness;
+#[cfg(feature = "circuit")]
 pub mod circuit;
+#[cfg(feature = "transcript")]
 pub mod transcript;
 
+pub use archimedes_state::TransitionOperation;
+#[cfg(feature = "witness")]
 pub use witness::{TransitionWitness, WitnessGenerator};
+#[cfg(feature = "circuit")]
 pub use circuit::{TransitionCircuit, CircuitInput};
+#[cfg(feature = "transcript")]
 pub use transcript::{ProofTranscript, TranscriptEntry};
-
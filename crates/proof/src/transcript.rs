@@ -42,8 +42,8 @@ impl ProofTranscript {
         data_hash.copy_from_slice(&data_result);
 
         let mut running_hasher = Sha256::new();
-        running_hasher.update(&self.current_hash);
-        running_hasher.update(&data_hash);
+        running_hasher.update(self.current_hash);
+        running_hasher.update(data_hash);
         let running_result = running_hasher.finalize();
         let mut running_hash = [0u8; 32];
         running_hash.copy_from_slice(&running_result);
@@ -62,7 +62,7 @@ impl ProofTranscript {
     pub fn challenge(&self) -> [u8; 32] {
         let mut hasher = Sha256::new();
         hasher.update(b"challenge");
-        hasher.update(&self.current_hash);
+        hasher.update(self.current_hash);
         let result = hasher.finalize();
         let mut challenge = [0u8; 32];
         challenge.copy_from_slice(&result);
@@ -74,8 +74,8 @@ impl ProofTranscript {
 
         for entry in &self.entries {
             let mut hasher = Sha256::new();
-            hasher.update(&expected_hash);
-            hasher.update(&entry.data_hash);
+            hasher.update(expected_hash);
+            hasher.update(entry.data_hash);
             let result = hasher.finalize();
             expected_hash.copy_from_slice(&result);
 
@@ -1,4 +1,6 @@
-use archimedes_state::AccountState;
+use archimedes_core::{ArchimedesError, StateEncodingErrorKind};
+use archimedes_state::{AccountState, Address, TransferTransition, WorldState};
+pub use archimedes_state::TransitionOperation;
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use thiserror::Error;
@@ -21,13 +23,9 @@ pub struct TransitionWitness {
     pub post_state: AccountState,
     pub operation: TransitionOperation,
     pub intermediate_values: Vec<IntermediateValue>,
-}
-
-#[derive(Clone, Debug, Serialize, Deserialize)]
-pub enum TransitionOperation {
-    Transfer { amount: u128 },
-    NonceIncrement,
-    StorageWrite { key: [u8; 32], value: [u8; 32] },
+    /// Receiver's pre and post states for a transfer.
+    #[serde(default)]
+    pub receiver: Option<(AccountState, AccountState)>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -40,32 +38,76 @@ pub struct IntermediateValue {
 impl TransitionWitness {
     pub fn compute_hash(&self) -> [u8; 32] {
         let mut hasher = Sha256::new();
-        hasher.update(&self.pre_state.hash());
-        hasher.update(&self.post_state.hash());
+        hasher.update(self.pre_state.hash());
+        hasher.update(self.post_state.hash());
+        if let Some((receiver_pre, receiver_post)) = &self.receiver {
+            hasher.update(receiver_pre.hash());
+            hasher.update(receiver_post.hash());
+        }
         let result = hasher.finalize();
         let mut hash = [0u8; 32];
         hash.copy_from_slice(&result);
         hash
     }
+
+    /// Both sides of a transfer witness, or `None` for other operations.
+    pub fn to_transfer_transition(&self, tx_hash: [u8; 32]) -> Option<TransferTransition> {
+        let TransitionOperation::Transfer { amount } = self.operation else {
+            return None;
+        };
+        let (receiver_pre, receiver_post) = self.receiver.clone()?;
+        Some(TransferTransition {
+            sender_pre: self.pre_state.clone(),
+            sender_post: self.post_state.clone(),
+            receiver_pre,
+            receiver_post,
+            amount,
+            tx_hash,
+        })
+    }
+}
+
+/// Builds witnesses. The associated functions take pre-states as given;
+/// an instance from `new` reads them from a `WorldState`.
+pub struct WitnessGenerator<'a> {
+    world: &'a WorldState,
 }
 
-pub struct WitnessGenerator;
+impl<'a> WitnessGenerator<'a> {
+    pub fn new(world: &'a WorldState) -> Self {
+        Self { world }
+    }
+
+    /// `generate_transfer` between the current states of `from` and `to`.
+    pub fn transfer(&self, from: &Address, to: &Address, amount: u128) -> Result<TransitionWitness> {
+        Self::generate_transfer(self.account(from)?, self.account(to)?, amount)
+    }
+
+    /// `generate_code_deploy` on the current state of `address`.
+    pub fn code_deploy(&self, address: &Address, code: &[u8]) -> Result<TransitionWitness> {
+        Self::generate_code_deploy(self.account(address)?, code)
+    }
+
+    fn account(&self, address: &Address) -> Result<AccountState> {
+        self.world
+            .get(address)
+            .cloned()
+            .ok_or_else(|| transition_error(ArchimedesError::StateEncodingError(StateEncodingErrorKind::UnknownAddress)))
+    }
 
-impl WitnessGenerator {
     pub fn generate_transfer(
         from_state: AccountState,
         to_state: AccountState,
         amount: u128,
     ) -> Result<TransitionWitness> {
-        if from_state.balance < amount {
-            return Err(WitnessError::InvalidTransition);
-        }
+        let operation = TransitionOperation::Transfer { amount };
+        let post_from = from_state.apply(&operation).map_err(transition_error)?;
 
         let mut intermediates = Vec::new();
         
         let mut step1_hasher = Sha256::new();
-        step1_hasher.update(&from_state.balance.to_le_bytes());
-        step1_hasher.update(&amount.to_le_bytes());
+        step1_hasher.update(from_state.balance.to_le_bytes());
+        step1_hasher.update(amount.to_le_bytes());
         let step1_result = step1_hasher.finalize();
         let mut step1_hash = [0u8; 32];
         step1_hash.copy_from_slice(&step1_result);
@@ -75,9 +117,8 @@ impl WitnessGenerator {
             value_hash: step1_hash,
         });
 
-        let new_from_balance = from_state.balance - amount;
         let mut step2_hasher = Sha256::new();
-        step2_hasher.update(&new_from_balance.to_le_bytes());
+        step2_hasher.update(post_from.balance.to_le_bytes());
         let step2_result = step2_hasher.finalize();
         let mut step2_hash = [0u8; 32];
         step2_hash.copy_from_slice(&step2_result);
@@ -87,9 +128,9 @@ impl WitnessGenerator {
             value_hash: step2_hash,
         });
 
-        let new_to_balance = to_state.balance + amount;
+        let new_to_balance = to_state.balance.checked_add(amount).ok_or(WitnessError::InvalidTransition)?;
         let mut step3_hasher = Sha256::new();
-        step3_hasher.update(&new_to_balance.to_le_bytes());
+        step3_hasher.update(new_to_balance.to_le_bytes());
         let step3_result = step3_hasher.finalize();
         let mut step3_hash = [0u8; 32];
         step3_hash.copy_from_slice(&step3_result);
@@ -99,24 +140,53 @@ impl WitnessGenerator {
             value_hash: step3_hash,
         });
 
-        let post_from = AccountState {
-            balance: new_from_balance,
-            nonce: from_state.nonce + 1,
-            code_hash: from_state.code_hash,
-            storage_root: from_state.storage_root,
+        let post_to = AccountState {
+            balance: new_to_balance,
+            ..to_state.clone()
         };
 
         Ok(TransitionWitness {
             pre_state: from_state.clone(),
             post_state: post_from,
-            operation: TransitionOperation::Transfer {
-                amount,
-            },
+            operation,
             intermediate_values: intermediates,
+            receiver: Some((to_state, post_to)),
+        })
+    }
+
+    /// Installs `code` on `state`, bumping its nonce like any other
+    /// transaction from the account.
+    pub fn generate_code_deploy(state: AccountState, code: &[u8]) -> Result<TransitionWitness> {
+        let code_hash = archimedes_state::code_hash(code);
+        let post_state = state
+            .apply(&TransitionOperation::CodeDeploy { code_hash })
+            .map_err(transition_error)?;
+
+        Ok(TransitionWitness {
+            pre_state: state,
+            post_state,
+            operation: TransitionOperation::CodeDeploy { code_hash },
+            intermediate_values: vec![IntermediateValue {
+                step: 1,
+                description: "code_hash".to_string(),
+                value_hash: code_hash,
+            }],
+            receiver: None,
         })
     }
 }
 
+/// Arithmetic failures make the transition itself invalid; anything else is
+/// a failure to build the witness.
+fn transition_error(err: ArchimedesError) -> WitnessError {
+    match err {
+        ArchimedesError::StateEncodingError(
+            StateEncodingErrorKind::InsufficientBalance { .. } | StateEncodingErrorKind::NonceOverflow,
+        ) => WitnessError::InvalidTransition,
+        other => WitnessError::GenerationFailed(other.to_string()),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -140,6 +210,32 @@ mod tests {
         assert_eq!(witness.intermediate_values.len(), 3);
         assert_eq!(witness.post_state.balance, 900);
         assert_eq!(witness.post_state.nonce, 1);
+        let (_, receiver_post) = witness.receiver.as_ref().unwrap();
+        assert_eq!(receiver_post.balance, 600);
+        assert_eq!(receiver_post.nonce, 0);
+
+        let transfer = witness.to_transfer_transition([5u8; 32]).unwrap();
+        assert_eq!(transfer.receiver_post.balance, 600);
+        assert_eq!(transfer.amount, 100);
+        let mut miscredited = witness.clone();
+        miscredited.receiver.as_mut().unwrap().1.balance = 700;
+        assert_ne!(miscredited.compute_hash(), witness.compute_hash());
+    }
+
+    #[test]
+    fn test_generate_code_deploy() {
+        let witness = WitnessGenerator::generate_code_deploy(test_account(10), b"code").unwrap();
+        assert!(witness.post_state.verify_code(b"code"));
+        assert_eq!(witness.post_state.nonce, 1);
+        assert_eq!(witness.post_state.balance, 10);
+        assert!(matches!(
+            witness.operation,
+            TransitionOperation::CodeDeploy { code_hash } if code_hash == witness.post_state.code_hash
+        ));
+        assert!(witness.to_transfer_transition([0; 32]).is_none());
+
+        let empty = WitnessGenerator::generate_code_deploy(test_account(10), &[]).unwrap();
+        assert_eq!(empty.post_state.code_hash, archimedes_state::EMPTY_CODE_HASH);
     }
 
     #[test]
@@ -149,6 +245,27 @@ mod tests {
 
         let result = WitnessGenerator::generate_transfer(from, to, 200);
         assert!(matches!(result, Err(WitnessError::InvalidTransition)));
+
+        let result = WitnessGenerator::generate_transfer(test_account(100), test_account(u128::MAX), 1);
+        assert!(matches!(result, Err(WitnessError::InvalidTransition)));
     }
-}
 
+    #[test]
+    fn test_witness_from_world_state() {
+        let (alice, bob) = (Address([1; 32]), Address([2; 32]));
+        let mut world = WorldState::new();
+        world.insert(alice, AccountState::new(1000, 4));
+        world.insert(bob, AccountState::new(500, 0));
+        let witness = WitnessGenerator::new(&world).transfer(&alice, &bob, 100).unwrap();
+        assert_eq!(witness.pre_state, AccountState::new(1000, 4));
+        assert_eq!(witness.post_state, AccountState::new(900, 5));
+
+        let transitions = world.execute(&witness.operation, &alice, Some(&bob)).unwrap();
+        assert_eq!(transitions[0].post_state, witness.post_state);
+        assert_eq!(Some((transitions[1].pre_state.clone(), transitions[1].post_state.clone())), witness.receiver);
+
+        let generator = WitnessGenerator::new(&world);
+        assert_eq!(generator.code_deploy(&alice, b"code").unwrap().pre_state, AccountState::new(900, 5));
+        assert!(matches!(generator.transfer(&Address([3; 32]), &bob, 1), Err(WitnessError::GenerationFailed(_))));
+    }
+}
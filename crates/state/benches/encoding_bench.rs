@@ -0,0 +1,33 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use archimedes_state::{encode_transitions, encode_transitions_chunked, AccountState, StateTransition};
+
+const CHUNK: usize = 4096;
+
+fn transitions(n: usize) -> Vec<StateTransition> {
+    (0..n as u64)
+        .map(|i| {
+            let mut tx_hash = [0u8; 32];
+            tx_hash[..8].copy_from_slice(&i.to_be_bytes());
+            StateTransition::new(AccountState::new(1_000 + i as u128, i), AccountState::new(900 + i as u128, i + 1), tx_hash)
+        })
+        .collect()
+}
+
+// Run with `--features parallel` to compare against the serial numbers.
+fn bench_encode_transitions(c: &mut Criterion) {
+    let mut group = c.benchmark_group("encode_transitions");
+    group.sample_size(10);
+    for n in [10_000, 100_000] {
+        let transitions = transitions(n);
+        group.bench_with_input(BenchmarkId::new("whole", n), &transitions, |b, t| {
+            b.iter(|| black_box(encode_transitions(t).unwrap()))
+        });
+        group.bench_with_input(BenchmarkId::new("chunked", n), &transitions, |b, t| {
+            b.iter(|| black_box(encode_transitions_chunked(t, CHUNK).unwrap()))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_encode_transitions);
+criterion_main!(benches);
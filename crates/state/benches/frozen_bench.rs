@@ -0,0 +1,61 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use archimedes_core::{Commitment, CommitmentParams};
+use archimedes_state::{CommitmentMerkleTree, FrozenTree};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::test_rng;
+use std::fs::File;
+use std::io::BufWriter;
+
+const LEAVES: usize = 1 << 20;
+
+fn synthetic_commitments(n: usize) -> Vec<Commitment> {
+    let mut rng = test_rng();
+    let params: CommitmentParams = CommitmentParams::setup(&mut rng).unwrap();
+    let step = Commitment(params.g);
+    let mut current = Commitment(params.h);
+    (0..n)
+        .map(|_| {
+            current = &current + &step;
+            current.clone()
+        })
+        .collect()
+}
+
+fn bench_frozen_open(c: &mut Criterion) {
+    let commitments = synthetic_commitments(LEAVES);
+    let tree = CommitmentMerkleTree::build(&commitments).unwrap();
+
+    let dir = std::env::temp_dir();
+    let frozen_path = dir.join(format!("archimedes-bench-{}.frozen", std::process::id()));
+    let leaves_path = dir.join(format!("archimedes-bench-{}.leaves", std::process::id()));
+    let mut writer = BufWriter::new(File::create(&frozen_path).unwrap());
+    tree.freeze_with_aggregates_to(&mut writer).unwrap();
+    drop(writer);
+    let mut writer = BufWriter::new(File::create(&leaves_path).unwrap());
+    commitments.serialize_uncompressed(&mut writer).unwrap();
+    drop(writer);
+
+    let mut group = c.benchmark_group("frozen_tree_1m");
+    group.sample_size(10);
+    group.bench_function("open", |b| {
+        b.iter(|| {
+            let frozen = FrozenTree::open(&frozen_path).unwrap();
+            black_box(frozen.generate_proof(LEAVES / 2).unwrap())
+        })
+    });
+    group.bench_function("deserialize_and_rebuild", |b| {
+        b.iter(|| {
+            let bytes = std::fs::read(&leaves_path).unwrap();
+            let leaves = Vec::<Commitment>::deserialize_uncompressed_unchecked(&bytes[..]).unwrap();
+            let tree = CommitmentMerkleTree::build(&leaves).unwrap();
+            black_box(tree.generate_proof(LEAVES / 2).unwrap())
+        })
+    });
+    group.finish();
+
+    let _ = std::fs::remove_file(&frozen_path);
+    let _ = std::fs::remove_file(&leaves_path);
+}
+
+criterion_group!(benches, bench_frozen_open);
+criterion_main!(benches);
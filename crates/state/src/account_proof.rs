@@ -0,0 +1,249 @@
+#[cfg(feature = "prover")]
+use archimedes_core::StateEncodingErrorKind;
+use archimedes_core::{ArchimedesError, SerializationErrorKind};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::address::Address;
+#[cfg(feature = "prover")]
+use crate::encoding::AccountState;
+use crate::merkle_proof::{MerkleProof, MERKLE_PADDING};
+#[cfg(feature = "prover")]
+use crate::state_batch::StateBatch;
+
+type Result<T> = std::result::Result<T, ArchimedesError>;
+
+const PRESENT_TAG: u8 = 0;
+const ABSENT_TAG: u8 = 1;
+const HAS_LEFT: u8 = 1;
+const HAS_RIGHT: u8 = 2;
+
+// Layout:
+//   present: 0u8 | Merkle proof
+//   absent:  1u8 | flags u8 (1 = left, 2 = right) | per neighbor, left first:
+//            address[32] | account_hash[32] | Merkle proof
+// Merkle proofs use `MerkleProof::to_bytes`, whose length follows from its
+// depth byte.
+
+/// A leaf of the batch tree a non-inclusion proof opens.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccountNeighbor {
+    pub address: Address,
+    pub account_hash: [u8; 32],
+    pub proof: MerkleProof,
+}
+
+/// Shows an address is, or is not, in a `StateBatch` with a given root.
+/// Leaves are in address order, so an absent address is shown by the
+/// adjacent leaves on either side of it, or by the first or last leaf when
+/// it falls outside the batch.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AccountProof {
+    Present(MerkleProof),
+    Absent {
+        left: Option<AccountNeighbor>,
+        right: Option<AccountNeighbor>,
+    },
+}
+
+/// Leaf of the batch tree holding `address` with the account hashing to
+/// `account_hash`: `SHA256(address || account_hash)`.
+pub fn account_leaf_hash(address: &Address, account_hash: [u8; 32]) -> [u8; 32] {
+    Sha256::new().chain_update(address.0).chain_update(account_hash).finalize().into()
+}
+
+#[cfg(feature = "prover")]
+impl StateBatch {
+    pub fn prove_account(&self, address: &Address) -> Result<AccountProof> {
+        if self.is_empty() {
+            return Err(ArchimedesError::StateEncodingError(StateEncodingErrorKind::EmptyStateBatch));
+        }
+        if self.get(address).is_some() {
+            return Ok(AccountProof::Present(self.inclusion_proof(address)?));
+        }
+        let neighbor = |(neighbor, state): (&Address, &AccountState)| -> Result<AccountNeighbor> {
+            Ok(AccountNeighbor {
+                address: *neighbor,
+                account_hash: state.hash(),
+                proof: self.inclusion_proof(neighbor)?,
+            })
+        };
+        let left = self.iter().take_while(|(a, _)| *a < address).last().map(neighbor).transpose()?;
+        let right = self.iter().find(|(a, _)| *a > address).map(neighbor).transpose()?;
+        Ok(AccountProof::Absent { left, right })
+    }
+}
+
+impl AccountProof {
+    /// True if this proves `address` holds the account hashing to
+    /// `account_hash` in the batch with `root`.
+    pub fn verify(&self, root: [u8; 32], address: &Address, account_hash: [u8; 32]) -> bool {
+        match self {
+            AccountProof::Present(proof) => proof.verify(account_leaf_hash(address, account_hash), root),
+            AccountProof::Absent { .. } => false,
+        }
+    }
+
+    /// True if this proves `address` is not in the batch with `root`.
+    pub fn verify_absent(&self, root: [u8; 32], address: &Address) -> bool {
+        let AccountProof::Absent { left, right } = self else {
+            return false;
+        };
+        let opens = |n: &AccountNeighbor| n.proof.verify(account_leaf_hash(&n.address, n.account_hash), root);
+        match (left, right) {
+            (Some(l), Some(r)) => {
+                opens(l) && opens(r) && l.address < *address && *address < r.address && r.proof.index == l.proof.index + 1
+            }
+            (None, Some(r)) => opens(r) && *address < r.address && r.proof.index == 0,
+            (Some(l), None) => opens(l) && l.address < *address && is_last(&l.proof),
+            (None, None) => false,
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            AccountProof::Present(proof) => [vec![PRESENT_TAG], proof.to_bytes()].concat(),
+            AccountProof::Absent { left, right } => {
+                let flags = if left.is_some() { HAS_LEFT } else { 0 } | if right.is_some() { HAS_RIGHT } else { 0 };
+                let mut bytes = vec![ABSENT_TAG, flags];
+                for neighbor in left.iter().chain(right) {
+                    bytes.extend_from_slice(&neighbor.address.0);
+                    bytes.extend_from_slice(&neighbor.account_hash);
+                    bytes.extend_from_slice(&neighbor.proof.to_bytes());
+                }
+                bytes
+            }
+        }
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let (&tag, rest) = bytes.split_first().ok_or_else(|| truncated("account proof"))?;
+        match tag {
+            PRESENT_TAG => Ok(AccountProof::Present(MerkleProof::from_bytes(rest)?)),
+            ABSENT_TAG => {
+                let (&flags, mut rest) = rest.split_first().ok_or_else(|| truncated("account proof flags"))?;
+                if flags & !(HAS_LEFT | HAS_RIGHT) != 0 {
+                    return Err(ArchimedesError::serialization(SerializationErrorKind::Encoding));
+                }
+                let mut read = |present: bool| -> Result<Option<AccountNeighbor>> {
+                    if !present {
+                        return Ok(None);
+                    }
+                    let header = rest.get(..64).ok_or_else(|| truncated("account proof neighbor"))?;
+                    let proof_len = rest
+                        .get(64 + 8)
+                        .map(|&depth| 9 + (depth as usize).div_ceil(8) + 32 * depth as usize)
+                        .ok_or_else(|| truncated("account proof neighbor"))?;
+                    let proof = rest.get(64..64 + proof_len).ok_or_else(|| truncated("account proof neighbor"))?;
+                    let neighbor = AccountNeighbor {
+                        address: Address(header[..32].try_into().unwrap()),
+                        account_hash: header[32..].try_into().unwrap(),
+                        proof: MerkleProof::from_bytes(proof)?,
+                    };
+                    rest = &rest[64 + proof_len..];
+                    Ok(Some(neighbor))
+                };
+                let left = read(flags & HAS_LEFT != 0)?;
+                let right = read(flags & HAS_RIGHT != 0)?;
+                if !rest.is_empty() {
+                    return Err(ArchimedesError::serialization(SerializationErrorKind::TrailingBytes("account proof")));
+                }
+                Ok(AccountProof::Absent { left, right })
+            }
+            _ => Err(ArchimedesError::serialization(SerializationErrorKind::Encoding)),
+        }
+    }
+}
+
+/// Whether `proof` opens the last leaf: wherever its path is a left child,
+/// the right sibling is padding, which no real node hashes to.
+fn is_last(proof: &MerkleProof) -> bool {
+    proof
+        .siblings
+        .iter()
+        .all(|(sibling, is_left)| !is_left || *sibling == MERKLE_PADDING)
+}
+
+fn truncated(what: &'static str) -> ArchimedesError {
+    ArchimedesError::serialization(SerializationErrorKind::Truncated(what))
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod tests {
+    use super::*;
+
+    /// Addresses 2, 4, 6, ... so every odd address is absent.
+    fn batch(n: usize) -> StateBatch {
+        (0..n)
+            .map(|i| (address(2 * i + 2), AccountState::new(100 + i as u128, i as u64)))
+            .collect()
+    }
+
+    fn address(i: usize) -> Address {
+        let mut a = [0u8; 32];
+        a[24..].copy_from_slice(&(i as u64).to_be_bytes());
+        Address(a)
+    }
+
+    #[test]
+    fn test_present_and_absent_accounts() {
+        for n in [1, 2, 1000] {
+            let batch = batch(n);
+            let root = batch.state_root().unwrap();
+            for i in [0, 1, n / 2, n - 1].into_iter().filter(|&i| i < n) {
+                let (a, state) = (address(2 * i + 2), batch.get(&address(2 * i + 2)).unwrap());
+                let proof = batch.prove_account(&a).unwrap();
+                assert!(proof.verify(root, &a, state.hash()));
+                assert!(!proof.verify_absent(root, &a));
+                assert!(!proof.verify(root, &a, AccountState::new(0, 0).hash()));
+                assert!(!proof.verify(root, &address(2 * i + 4), state.hash()));
+                assert_eq!(AccountProof::from_bytes(&proof.to_bytes()).unwrap(), proof);
+            }
+            for i in [0, 1, 2 * (n / 2) + 1, 2 * n + 1, 2 * n + 3] {
+                let a = address(i);
+                let proof = batch.prove_account(&a).unwrap();
+                assert!(proof.verify_absent(root, &a), "address {i} of {n}");
+                assert!(!proof.verify(root, &a, AccountState::new(0, 0).hash()));
+                assert_eq!(AccountProof::from_bytes(&proof.to_bytes()).unwrap(), proof);
+                assert!(!batch.prove_account(&address(2)).unwrap().verify_absent(root, &a));
+            }
+        }
+    }
+
+    #[test]
+    fn test_tampered_non_inclusion_rejected() {
+        let batch = batch(8);
+        let root = batch.state_root().unwrap();
+        let AccountProof::Absent { left, right } = batch.prove_account(&address(7)).unwrap() else {
+            panic!("address 7 is absent");
+        };
+        let (left, right) = (left.unwrap(), right.unwrap());
+
+        // Skipping a leaf between the neighbors hides the account there.
+        let wide = AccountProof::Absent {
+            left: Some(left.clone()),
+            right: batch.prove_account(&address(9)).ok().and_then(|p| match p {
+                AccountProof::Absent { right, .. } => right,
+                _ => None,
+            }),
+        };
+        assert!(!wide.verify_absent(root, &address(8)));
+        // Dropping the right neighbor does not make the left one the last leaf.
+        let no_right = AccountProof::Absent { left: Some(left.clone()), right: None };
+        assert!(!no_right.verify_absent(root, &address(7)));
+        let mut forged = right.clone();
+        forged.account_hash = AccountState::new(1, 1).hash();
+        assert!(!AccountProof::Absent { left: Some(left), right: Some(forged) }.verify_absent(root, &address(7)));
+        // A different batch's root does not accept the proof.
+        let mut changed = batch.clone();
+        changed.insert(address(2), AccountState::new(1, 1));
+        assert!(!batch.prove_account(&address(7)).unwrap().verify_absent(changed.state_root().unwrap(), &address(7)));
+
+        let mut bytes = batch.prove_account(&address(7)).unwrap().to_bytes();
+        bytes.push(0);
+        assert!(AccountProof::from_bytes(&bytes).is_err());
+        for len in 0..bytes.len() - 1 {
+            assert!(AccountProof::from_bytes(&bytes[..len]).is_err());
+        }
+    }
+}
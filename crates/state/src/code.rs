@@ -0,0 +1,120 @@
+use archimedes_availability::storage::StorageError;
+use archimedes_availability::{ContentAddressedStorage, ContentId};
+use archimedes_core::{ArchimedesError, StateEncodingErrorKind};
+use sha2::{Digest, Sha256};
+
+use crate::encoding::AccountState;
+
+type Result<T> = std::result::Result<T, ArchimedesError>;
+
+/// `code_hash` of an account without code, which is what `AccountState::new`
+/// starts with. Empty code hashes to this rather than to `SHA256("")`.
+pub const EMPTY_CODE_HASH: [u8; 32] = [0u8; 32];
+
+pub fn code_hash(code: &[u8]) -> [u8; 32] {
+    if code.is_empty() {
+        EMPTY_CODE_HASH
+    } else {
+        Sha256::digest(code).into()
+    }
+}
+
+impl AccountState {
+    pub fn set_code(&mut self, code: &[u8]) {
+        self.code_hash = code_hash(code);
+    }
+
+    pub fn verify_code(&self, code: &[u8]) -> bool {
+        self.code_hash == code_hash(code)
+    }
+}
+
+/// Contract code keyed by `code_hash`. Empty code is never stored; looking
+/// up `EMPTY_CODE_HASH` always succeeds with no bytes.
+pub struct CodeStore {
+    storage: ContentAddressedStorage,
+}
+
+impl CodeStore {
+    pub fn new(max_size: usize) -> Self {
+        Self {
+            storage: ContentAddressedStorage::new(max_size),
+        }
+    }
+
+    /// Stores `code` and returns its hash. Storing the same code again
+    /// takes another reference to it.
+    pub fn insert(&mut self, code: Vec<u8>, timestamp: u64) -> Result<[u8; 32]> {
+        if code.is_empty() {
+            return Ok(EMPTY_CODE_HASH);
+        }
+        Ok(self.storage.store(code, timestamp).map_err(code_error)?.0)
+    }
+
+    pub fn get(&self, code_hash: &[u8; 32]) -> Result<&[u8]> {
+        if *code_hash == EMPTY_CODE_HASH {
+            return Ok(&[]);
+        }
+        self.storage.retrieve(&ContentId(*code_hash)).map_err(code_error)
+    }
+
+    pub fn contains(&self, code_hash: &[u8; 32]) -> bool {
+        *code_hash == EMPTY_CODE_HASH || self.storage.exists(&ContentId(*code_hash))
+    }
+
+    /// The code `account` runs, if it is stored.
+    pub fn code_of(&self, account: &AccountState) -> Result<&[u8]> {
+        self.get(&account.code_hash)
+    }
+}
+
+fn code_error(err: StorageError) -> ArchimedesError {
+    let kind = match err {
+        StorageError::NotFound(_) => StateEncodingErrorKind::MissingCode,
+        other => StateEncodingErrorKind::CodeStorageFailed(other.to_string()),
+    };
+    ArchimedesError::StateEncodingError(kind)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_and_verify_code() {
+        let mut account = AccountState::new(10, 0);
+        assert!(account.verify_code(&[]));
+        account.set_code(b"\x60\x00\x60\x00");
+        assert_eq!(account.code_hash, <[u8; 32]>::from(Sha256::digest(b"\x60\x00\x60\x00")));
+        assert!(account.verify_code(b"\x60\x00\x60\x00"));
+        assert!(!account.verify_code(b"\x60\x00\x60\x01"));
+        assert!(!account.verify_code(&[]));
+
+        account.set_code(&[]);
+        assert_eq!(account.code_hash, EMPTY_CODE_HASH);
+        assert_eq!(account, AccountState::new(10, 0));
+    }
+
+    #[test]
+    fn test_code_store() {
+        let mut store = CodeStore::new(1024);
+        let code = b"contract".to_vec();
+        let hash = store.insert(code.clone(), 0).unwrap();
+        assert_eq!(hash, code_hash(&code));
+        assert_eq!(store.get(&hash).unwrap(), &code[..]);
+        assert_eq!(store.insert(Vec::new(), 0).unwrap(), EMPTY_CODE_HASH);
+        assert_eq!(store.get(&EMPTY_CODE_HASH).unwrap(), &[] as &[u8]);
+
+        let mut account = AccountState::new(0, 0);
+        account.set_code(&code);
+        assert!(account.verify_code(store.code_of(&account).unwrap()));
+        assert!(matches!(
+            store.get(&[1; 32]),
+            Err(ArchimedesError::StateEncodingError(StateEncodingErrorKind::MissingCode))
+        ));
+        assert!(matches!(
+            CodeStore::new(4).insert(code, 0),
+            Err(ArchimedesError::StateEncodingError(StateEncodingErrorKind::CodeStorageFailed(_)))
+        ));
+    }
+}
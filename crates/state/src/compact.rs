@@ -0,0 +1,207 @@
+use archimedes_core::{AggregateCommitment, ArchimedesError, Commitment, MerkleErrorKind};
+
+use crate::merkle::{AggregatePathProof, CommitmentMerkleTree, MerkleHasher, MerkleNode, Sha256Hasher, MERKLE_PADDING};
+
+type Result<T> = std::result::Result<T, ArchimedesError>;
+
+/// `CommitmentMerkleTree` holding only its leaves and root. Internal nodes
+/// are recomputed on demand, so a proof costs a pass over the leaves and a
+/// range aggregate a sum over the range.
+#[derive(Clone, Debug)]
+pub struct CompactCommitmentMerkleTree<H: MerkleHasher = Sha256Hasher> {
+    leaves: Vec<MerkleNode>,
+    root: MerkleNode,
+    hasher: H,
+}
+
+impl CompactCommitmentMerkleTree {
+    pub fn build(commitments: &[Commitment]) -> Result<Self> {
+        Self::build_with_hasher(commitments, &Sha256Hasher)
+    }
+}
+
+impl<H: MerkleHasher> CompactCommitmentMerkleTree<H> {
+    pub fn build_with_hasher(commitments: &[Commitment], hasher: &H) -> Result<Self> {
+        if commitments.is_empty() {
+            return Err(ArchimedesError::MerkleTreeError {
+                index: 0,
+                leaf_count: 0,
+                kind: MerkleErrorKind::EmptyTree,
+            });
+        }
+        let affine = Commitment::batch_to_affine(commitments);
+        let leaves: Vec<MerkleNode> = commitments
+            .iter()
+            .zip(&affine)
+            .enumerate()
+            .map(|(i, (c, a))| MerkleNode::leaf_with_hasher(hasher, c, a, i))
+            .collect();
+        let depth = leaves.len().next_power_of_two().trailing_zeros() as usize;
+        let mut tree = Self {
+            root: leaves[0].clone(),
+            leaves,
+            hasher: hasher.clone(),
+        };
+        tree.root = tree.subtree(depth, 0)?;
+        Ok(tree)
+    }
+
+    pub(crate) fn from_parts(leaves: Vec<MerkleNode>, root: MerkleNode, hasher: H) -> Self {
+        Self { leaves, root, hasher }
+    }
+
+    pub fn root(&self) -> &MerkleNode {
+        &self.root
+    }
+
+    pub fn root_hash(&self) -> [u8; 32] {
+        self.root.hash
+    }
+
+    pub fn committed_root(&self) -> [u8; 32] {
+        crate::merkle::committed_root(self.root.hash, self.leaves.len())
+    }
+
+    pub fn aggregate(&self) -> &AggregateCommitment {
+        &self.root.aggregate
+    }
+
+    pub fn leaf_count(&self) -> usize {
+        self.leaves.len()
+    }
+
+    pub fn depth(&self) -> usize {
+        self.leaves.len().next_power_of_two().trailing_zeros() as usize
+    }
+
+    pub fn hasher(&self) -> &H {
+        &self.hasher
+    }
+
+    /// Approximate heap and inline bytes held by the tree.
+    pub fn memory_estimate(&self) -> usize {
+        std::mem::size_of::<Self>() + self.leaves.capacity() * std::mem::size_of::<MerkleNode>()
+    }
+
+    pub fn leaf_hash(&self, index: usize) -> Result<[u8; 32]> {
+        self.check_leaf_index(index)?;
+        Ok(self.leaves[index].hash)
+    }
+
+    pub fn generate_proof(&self, index: usize) -> Result<AggregatePathProof> {
+        self.check_leaf_index(index)?;
+        let siblings = (0..self.depth())
+            .map(|level| {
+                let current = index >> level;
+                let (hash, aggregate) = if current ^ 1 < self.level_len(level) {
+                    let sibling = self.subtree(level, current ^ 1)?;
+                    (sibling.hash, sibling.aggregate)
+                } else {
+                    (MERKLE_PADDING, AggregateCommitment::empty())
+                };
+                Ok((hash, aggregate, current.is_multiple_of(2)))
+            })
+            .collect::<Result<_>>()?;
+        Ok(AggregatePathProof { index, siblings })
+    }
+
+    pub fn range_aggregate(&self, start: usize, end: usize) -> Result<AggregateCommitment> {
+        if end > self.leaves.len() || start >= end {
+            return Err(ArchimedesError::MerkleTreeError {
+                index: start,
+                leaf_count: self.leaves.len(),
+                kind: MerkleErrorKind::InvalidRange { end, level: 0 },
+            });
+        }
+        let mut commitment = Commitment::zero();
+        for leaf in &self.leaves[start..end] {
+            commitment += &leaf.aggregate.commitment;
+        }
+        Ok(AggregateCommitment {
+            commitment,
+            count: end - start,
+        })
+    }
+
+    /// Rebuilds the internal levels.
+    pub fn expand(&self) -> Result<CommitmentMerkleTree<H>> {
+        let commitments: Vec<Commitment> = self.leaves.iter().map(|leaf| leaf.aggregate.commitment.clone()).collect();
+        CommitmentMerkleTree::build_with_hasher(&commitments, &self.hasher)
+    }
+
+    fn check_leaf_index(&self, index: usize) -> Result<()> {
+        if index >= self.leaves.len() {
+            return Err(ArchimedesError::MerkleTreeError {
+                index,
+                leaf_count: self.leaves.len(),
+                kind: MerkleErrorKind::IndexOutOfBounds,
+            });
+        }
+        Ok(())
+    }
+
+    fn level_len(&self, level: usize) -> usize {
+        self.leaves.len().div_ceil(1 << level)
+    }
+
+    fn subtree(&self, level: usize, index: usize) -> Result<MerkleNode> {
+        if level == 0 {
+            return Ok(self.leaves[index].clone());
+        }
+        let left = self.subtree(level - 1, 2 * index)?;
+        if 2 * index + 1 < self.level_len(level - 1) {
+            MerkleNode::internal_with_hasher(&self.hasher, &left, &self.subtree(level - 1, 2 * index + 1)?)
+        } else {
+            Ok(MerkleNode::padded_with_hasher(&self.hasher, &left))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_ec::PrimeGroup;
+    use ark_ed_on_bls12_381::EdwardsProjective;
+
+    fn commitments(n: usize) -> Vec<Commitment> {
+        let g = EdwardsProjective::generator();
+        std::iter::successors(Some(g), |p| Some(*p + g)).take(n).map(Commitment).collect()
+    }
+
+    #[test]
+    fn test_matches_full_tree() {
+        for n in [1, 2, 5, 8, 13, 64] {
+            let commitments = commitments(n);
+            let full = CommitmentMerkleTree::build(&commitments).unwrap();
+            let compact = CompactCommitmentMerkleTree::build(&commitments).unwrap();
+            assert_eq!(compact.committed_root(), full.committed_root());
+            assert_eq!(compact.aggregate().commitment, full.aggregate().commitment);
+            assert_eq!(compact.depth(), full.depth());
+            for i in 0..n {
+                assert_eq!(compact.generate_proof(i).unwrap().to_bytes(), full.generate_proof(i).unwrap().to_bytes());
+            }
+            let (start, end) = (n / 3, n - n / 4);
+            assert_eq!(
+                compact.range_aggregate(start, end).unwrap().commitment,
+                full.range_aggregate(start, end).unwrap().commitment
+            );
+            assert_eq!(full.clone().into_compact().root_hash(), compact.root_hash());
+            assert_eq!(compact.expand().unwrap().root_hash(), full.root_hash());
+        }
+        let compact = CompactCommitmentMerkleTree::build(&commitments(3)).unwrap();
+        assert!(compact.generate_proof(3).is_err());
+        assert!(compact.range_aggregate(2, 4).is_err());
+        assert!(CompactCommitmentMerkleTree::build(&[]).is_err());
+    }
+
+    #[test]
+    fn test_memory_estimate() {
+        let commitments = commitments(1 << 10);
+        let full = CommitmentMerkleTree::build(&commitments).unwrap();
+        let compact = full.clone().into_compact();
+        let leaves = (1 << 10) * std::mem::size_of::<MerkleNode>();
+        assert!(compact.memory_estimate() >= leaves);
+        assert!(compact.memory_estimate() < leaves + 1024);
+        assert!(full.memory_estimate() >= 2 * leaves - std::mem::size_of::<MerkleNode>());
+    }
+}
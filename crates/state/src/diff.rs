@@ -0,0 +1,139 @@
+use archimedes_core::{ArchimedesError, StateEncodingErrorKind};
+use serde::{Deserialize, Serialize};
+
+use crate::encoding::{AccountState, StateTransition};
+
+type Result<T> = std::result::Result<T, ArchimedesError>;
+
+/// A signed change, kept as sign and magnitude so any two `u128` values
+/// have a delta.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Delta {
+    #[default]
+    Zero,
+    Increase(u128),
+    Decrease(u128),
+}
+
+/// The fields a transition changes; hashes are `None` when unchanged.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StateDiff {
+    pub balance: Delta,
+    pub nonce: Delta,
+    pub code_hash: Option<[u8; 32]>,
+    pub storage_root: Option<[u8; 32]>,
+}
+
+impl Delta {
+    pub fn between(pre: u128, post: u128) -> Self {
+        match post.cmp(&pre) {
+            std::cmp::Ordering::Equal => Delta::Zero,
+            std::cmp::Ordering::Greater => Delta::Increase(post - pre),
+            std::cmp::Ordering::Less => Delta::Decrease(pre - post),
+        }
+    }
+
+    pub fn apply(self, value: u128) -> Result<u128> {
+        match self {
+            Delta::Zero => Ok(value),
+            Delta::Increase(d) => value.checked_add(d).ok_or(diff_error(StateEncodingErrorKind::DiffOverflow)),
+            Delta::Decrease(d) => value.checked_sub(d).ok_or(diff_error(StateEncodingErrorKind::DiffUnderflow)),
+        }
+    }
+}
+
+impl StateDiff {
+    pub fn between(pre: &AccountState, post: &AccountState) -> Self {
+        let changed = |pre: &[u8; 32], post: &[u8; 32]| (pre != post).then_some(*post);
+        Self {
+            balance: Delta::between(pre.balance, post.balance),
+            nonce: Delta::between(pre.nonce as u128, post.nonce as u128),
+            code_hash: changed(&pre.code_hash, &post.code_hash),
+            storage_root: changed(&pre.storage_root, &post.storage_root),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl AccountState {
+    /// Fails if the balance or nonce would leave its range, which means the
+    /// diff was taken against a different pre-state.
+    pub fn apply_diff(&self, diff: &StateDiff) -> Result<AccountState> {
+        let nonce = u64::try_from(diff.nonce.apply(self.nonce as u128)?)
+            .map_err(|_| diff_error(StateEncodingErrorKind::DiffOverflow))?;
+        Ok(AccountState {
+            balance: diff.balance.apply(self.balance)?,
+            nonce,
+            code_hash: diff.code_hash.unwrap_or(self.code_hash),
+            storage_root: diff.storage_root.unwrap_or(self.storage_root),
+        })
+    }
+}
+
+impl StateTransition {
+    pub fn to_diff(&self) -> StateDiff {
+        StateDiff::between(&self.pre_state, &self.post_state)
+    }
+
+    pub fn from_pre_and_diff(pre_state: AccountState, diff: &StateDiff, tx_hash: [u8; 32]) -> Result<Self> {
+        let post_state = pre_state.apply_diff(diff)?;
+        Ok(Self::new(pre_state, post_state, tx_hash))
+    }
+}
+
+fn diff_error(kind: StateEncodingErrorKind) -> ArchimedesError {
+    ArchimedesError::StateEncodingError(kind)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{Rng, SeedableRng};
+
+    fn random_state(rng: &mut impl Rng) -> AccountState {
+        AccountState {
+            balance: if rng.gen() { rng.gen() } else { rng.gen_range(0..1000) },
+            nonce: rng.gen(),
+            code_hash: if rng.gen() { [0; 32] } else { rng.gen() },
+            storage_root: if rng.gen() { [0; 32] } else { rng.gen() },
+        }
+    }
+
+    #[test]
+    fn test_diff_round_trip() {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(838);
+        for _ in 0..500 {
+            let pre = random_state(&mut rng);
+            let post = random_state(&mut rng);
+            let transition = StateTransition::new(pre.clone(), post.clone(), [3; 32]);
+            assert_eq!(pre.apply_diff(&StateDiff::between(&pre, &post)).unwrap(), post);
+            assert_eq!(StateTransition::from_pre_and_diff(pre, &transition.to_diff(), [3; 32]).unwrap(), transition);
+        }
+        let state = AccountState::new(u128::MAX, u64::MAX);
+        assert!(StateDiff::between(&state, &state).is_empty());
+        assert_eq!(StateDiff::between(&AccountState::new(0, 0), &state).balance, Delta::Increase(u128::MAX));
+    }
+
+    #[test]
+    fn test_conflicting_diff_rejected() {
+        let pre = AccountState::new(1000, 5);
+        let diff = StateDiff::between(&pre, &AccountState::new(100, 6));
+        assert_eq!(diff.balance, Delta::Decrease(900));
+        assert!(matches!(
+            AccountState::new(500, 5).apply_diff(&diff),
+            Err(ArchimedesError::StateEncodingError(StateEncodingErrorKind::DiffUnderflow))
+        ));
+        assert!(matches!(
+            AccountState::new(1000, u64::MAX).apply_diff(&diff),
+            Err(ArchimedesError::StateEncodingError(StateEncodingErrorKind::DiffOverflow))
+        ));
+        let credit = StateDiff::between(&pre, &AccountState::new(2000, 5));
+        assert!(matches!(
+            AccountState::new(u128::MAX, 5).apply_diff(&credit),
+            Err(ArchimedesError::StateEncodingError(StateEncodingErrorKind::DiffOverflow))
+        ));
+    }
+}
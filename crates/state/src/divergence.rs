@@ -0,0 +1,164 @@
+use archimedes_core::{ArchimedesError, MerkleErrorKind};
+
+use crate::merkle::{CommitmentMerkleTree, MerkleHasher, MerkleNode, Sha256Hasher};
+
+type Result<T> = std::result::Result<T, ArchimedesError>;
+
+/// Locates the first leaf where a local tree and a remote one of the same
+/// leaf count differ, fetching both children of one remote node per level.
+pub struct DivergenceFinder<'a, H: MerkleHasher = Sha256Hasher> {
+    local: &'a CommitmentMerkleTree<H>,
+}
+
+impl<'a, H: MerkleHasher> DivergenceFinder<'a, H> {
+    pub fn new(local: &'a CommitmentMerkleTree<H>) -> Self {
+        Self { local }
+    }
+
+    /// `remote(level, index)` returns the counterpart's node, level 0 being
+    /// the leaves. Each remote parent on the way down must be the node its
+    /// two children hash to; otherwise the counterpart could steer the
+    /// search with a claimed child that its parent never committed to.
+    /// Descends into the left child when it differs and otherwise into the
+    /// right one, so the result is the leftmost differing leaf, or `None` if
+    /// the roots agree.
+    pub fn find<F>(&self, mut remote: F) -> Result<Option<usize>>
+    where
+        F: FnMut(usize, usize) -> Result<MerkleNode>,
+    {
+        let depth = self.local.depth();
+        let mut parent = remote(depth, 0)?;
+        if parent.hash == self.local.root_hash() {
+            return Ok(None);
+        }
+        let hasher = self.local.hasher();
+        let inconsistent = |index: usize, level: usize| ArchimedesError::MerkleTreeError {
+            index,
+            leaf_count: self.local.leaf_count(),
+            kind: MerkleErrorKind::InconsistentCounterpart { level },
+        };
+        let mut index = 0;
+        for level in (0..depth).rev() {
+            let left_index = 2 * index;
+            let left = remote(level, left_index)?;
+            let right = if left_index + 1 < self.local.level_len(level)? {
+                Some(remote(level, left_index + 1)?)
+            } else {
+                None
+            };
+            let expected = match &right {
+                Some(right) => MerkleNode::internal_with_hasher(hasher, &left, right)
+                    .map_err(|_| inconsistent(index, level + 1))?,
+                None => MerkleNode::padded_with_hasher(hasher, &left),
+            };
+            if expected.hash != parent.hash {
+                return Err(inconsistent(index, level + 1));
+            }
+            parent = if left.hash != self.local.node_hash(level, left_index)? {
+                index = left_index;
+                left
+            } else {
+                // The parent differs and hashes its children, so with the
+                // left one matching the right one exists and differs.
+                index = left_index + 1;
+                right.ok_or_else(|| inconsistent(index, level + 1))?
+            };
+        }
+        Ok(Some(index))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use archimedes_core::{Commitment, CommitmentChain, CommitmentParams};
+    use ark_ed_on_bls12_381::Fr as ScalarField;
+    use ark_std::test_rng;
+
+    fn commitments(n: u64) -> Vec<Commitment> {
+        let mut rng = test_rng();
+        let mut chain = CommitmentChain::new(CommitmentParams::setup(&mut rng).unwrap());
+        for i in 0..n {
+            chain.push(ScalarField::from(i), &mut rng).unwrap();
+        }
+        chain.commitments().to_vec()
+    }
+
+    fn find_against(local: &CommitmentMerkleTree, remote: &CommitmentMerkleTree) -> (Option<usize>, usize) {
+        let mut queries = 0;
+        let found = DivergenceFinder::new(local)
+            .find(|level, index| {
+                queries += 1;
+                remote.node(level, index).cloned()
+            })
+            .unwrap();
+        (found, queries)
+    }
+
+    #[test]
+    fn test_finds_first_differing_leaf() {
+        let honest = commitments(13);
+        let local = CommitmentMerkleTree::build(&honest).unwrap();
+        assert_eq!(find_against(&local, &local), (None, 1));
+
+        for bad in [0, 6, 12] {
+            let mut forged = honest.clone();
+            forged[bad] = honest[(bad + 1) % 13].clone();
+            let remote = CommitmentMerkleTree::build(&forged).unwrap();
+            let (found, queries) = find_against(&local, &remote);
+            assert_eq!(found, Some(bad));
+            assert!(queries <= 2 * local.depth() + 1);
+        }
+
+        let mut forged = honest.clone();
+        for bad in [9, 4, 11] {
+            forged[bad] = honest[0].clone();
+        }
+        let remote = CommitmentMerkleTree::build(&forged).unwrap();
+        assert_eq!(find_against(&local, &remote).0, Some(4));
+        assert_eq!(local.find_divergence(&remote), Some(4));
+    }
+
+    #[test]
+    fn test_rejects_inconsistent_counterpart() {
+        let local = CommitmentMerkleTree::build(&commitments(5)).unwrap();
+        // Claims a different root while agreeing on every node below it.
+        let forged_root = MerkleNode { hash: [0u8; 32], ..local.root().clone() };
+        let result = DivergenceFinder::new(&local)
+            .find(|level, index| if level == local.depth() { Ok(forged_root.clone()) } else { local.node(level, index).cloned() });
+        assert!(matches!(
+            result,
+            Err(ArchimedesError::MerkleTreeError { kind: MerkleErrorKind::InconsistentCounterpart { .. }, .. })
+        ));
+    }
+
+    #[test]
+    fn test_rejects_children_the_parent_does_not_hash() {
+        let honest = commitments(8);
+        let local = CommitmentMerkleTree::build(&honest).unwrap();
+        let mut forged = honest.clone();
+        forged[5] = honest[0].clone();
+        let remote = CommitmentMerkleTree::build(&forged).unwrap();
+        assert_eq!(find_against(&local, &remote), (Some(5), 7));
+
+        // Steering left: a differing left child the root does not hash.
+        let steered = DivergenceFinder::new(&local).find(|level, index| match (level, index) {
+            (2, 0) => Ok(remote.node(2, 1).unwrap().clone()),
+            _ => remote.node(level, index).cloned(),
+        });
+        assert!(matches!(
+            steered,
+            Err(ArchimedesError::MerkleTreeError { kind: MerkleErrorKind::InconsistentCounterpart { level: 3 }, .. })
+        ));
+
+        // Hiding the difference: honest children under a differing parent.
+        let hidden = DivergenceFinder::new(&local).find(|level, index| match level {
+            1 => local.node(level, index).cloned(),
+            _ => remote.node(level, index).cloned(),
+        });
+        assert!(matches!(
+            hidden,
+            Err(ArchimedesError::MerkleTreeError { index: 1, kind: MerkleErrorKind::InconsistentCounterpart { level: 2 }, .. })
+        ));
+    }
+}
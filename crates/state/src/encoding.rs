@@ -1,11 +1,95 @@
 use ark_ed_on_bls12_381::Fr as ScalarField;
-use ark_ff::PrimeField;
-use archimedes_core::ArchimedesError;
+use ark_ff::{BigInteger, PrimeField};
+use archimedes_core::{scalar_from_u128, ArchimedesError, SerializationErrorKind, StateEncodingErrorKind};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 
 type Result<T> = std::result::Result<T, ArchimedesError>;
 
+/// Version tag leading every canonical encoding below. Hashes are taken
+/// over these bytes, so bumping it changes every state commitment.
+pub const STATE_ENCODING_VERSION: u8 = 1;
+pub const ACCOUNT_STATE_ENCODED_LEN: usize = 1 + ACCOUNT_BODY_LEN;
+pub const STATE_TRANSITION_ENCODED_LEN: usize = 1 + 2 * ACCOUNT_BODY_LEN + 32;
+pub const TRANSFER_TRANSITION_ENCODED_LEN: usize = 1 + 4 * ACCOUNT_BODY_LEN + 16 + 32;
+
+const ACCOUNT_BODY_LEN: usize = 16 + 8 + 32 + 32;
+
+// Canonical layouts (integers big-endian, every field fixed-width, so no
+// length prefixes are needed):
+//   account:    version u8 | balance u128 | nonce u64 | code_hash[32] | storage_root[32]
+//   transition: version u8 | pre account without version | post account
+//               without version | tx_hash[32]
+//   transfer:   version u8 | sender pre | sender post | receiver pre |
+//               receiver post (accounts without version) | amount u128 |
+//               tx_hash[32]
+
+/// Leads the transfer hash preimage, keeping it apart from the other
+/// hashes taken over account encodings.
+const TRANSFER_DOMAIN: &[u8] = b"transfer_transition";
+
+/// How a 32-byte hash becomes a commitment value. `to_commitment_value`
+/// keeps `Truncated` so existing commitments still open; verifiers pick the
+/// version the commitments were made under.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CommitmentEncoding {
+    /// `bytes_to_field`: the first 31 bytes, little-endian.
+    #[default]
+    Truncated,
+    /// All 32 bytes as a little-endian field element. Hashes at or above
+    /// the field order are rejected rather than reduced, so the value maps
+    /// back to exactly one hash.
+    Full,
+}
+
+impl CommitmentEncoding {
+    pub fn hash_to_field(self, bytes: &[u8; 32]) -> Result<ScalarField> {
+        match self {
+            CommitmentEncoding::Truncated => Ok(bytes_to_field(bytes)),
+            CommitmentEncoding::Full => {
+                let value = ScalarField::from_le_bytes_mod_order(bytes);
+                if value.into_bigint().to_bytes_le() != bytes {
+                    return Err(ArchimedesError::StateEncodingError(StateEncodingErrorKind::NonCanonicalField));
+                }
+                Ok(value)
+            }
+        }
+    }
+}
+
+/// Hash a commitment value is anchored to. Every input starts with the
+/// scheme id: SHA-256 hashes the id byte followed by the canonical encoding,
+/// Poseidon absorbs it as the first element. Both variants exist in every
+/// build so serialized configs do not depend on features; hashing under
+/// Poseidon needs the `poseidon` feature.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HashScheme {
+    #[default]
+    Sha256,
+    Poseidon,
+}
+
+impl HashScheme {
+    pub const fn id(self) -> u8 {
+        match self {
+            HashScheme::Sha256 => 0,
+            HashScheme::Poseidon => 1,
+        }
+    }
+
+    fn sha256(self, preimage: &[u8]) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update([self.id()]);
+        hasher.update(preimage);
+        hasher.finalize().into()
+    }
+
+    #[cfg(not(feature = "poseidon"))]
+    fn unavailable(self) -> ArchimedesError {
+        ArchimedesError::StateEncodingError(StateEncodingErrorKind::HashSchemeUnavailable(self.id()))
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct AccountState {
     pub balance: u128,
@@ -21,6 +105,17 @@ pub struct StateTransition {
     pub tx_hash: [u8; 32],
 }
 
+/// A transfer of `amount` from one account to another, tracking both sides.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransferTransition {
+    pub sender_pre: AccountState,
+    pub sender_post: AccountState,
+    pub receiver_pre: AccountState,
+    pub receiver_post: AccountState,
+    pub amount: u128,
+    pub tx_hash: [u8; 32],
+}
+
 impl AccountState {
     pub fn new(balance: u128, nonce: u64) -> Self {
         Self {
@@ -32,26 +127,76 @@ impl AccountState {
     }
 
     pub fn to_field_elements(&self) -> Vec<ScalarField> {
-        let mut elements = Vec::with_capacity(4);
-        elements.push(ScalarField::from(self.balance as u64));
-        elements.push(ScalarField::from(self.nonce));
-        elements.push(bytes_to_field(&self.code_hash));
-        elements.push(bytes_to_field(&self.storage_root));
-        elements
+        vec![
+            scalar_from_u128(self.balance),
+            ScalarField::from(self.nonce),
+            bytes_to_field(&self.code_hash),
+            bytes_to_field(&self.storage_root),
+        ]
     }
 
+    /// SHA-256 of `encode()`.
     pub fn hash(&self) -> [u8; 32] {
-        let mut hasher = Sha256::new();
-        hasher.update(self.balance.to_be_bytes());
-        hasher.update(self.nonce.to_be_bytes());
-        hasher.update(self.code_hash);
-        hasher.update(self.storage_root);
-        hasher.finalize().into()
+        Sha256::digest(self.encode()).into()
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(ACCOUNT_STATE_ENCODED_LEN);
+        bytes.push(STATE_ENCODING_VERSION);
+        self.encode_body(&mut bytes);
+        bytes
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self> {
+        let body = check_encoding(bytes, ACCOUNT_STATE_ENCODED_LEN, "account state")?;
+        Ok(Self::decode_body(body))
+    }
+
+    fn encode_body(&self, bytes: &mut Vec<u8>) {
+        bytes.extend_from_slice(&self.balance.to_be_bytes());
+        bytes.extend_from_slice(&self.nonce.to_be_bytes());
+        bytes.extend_from_slice(&self.code_hash);
+        bytes.extend_from_slice(&self.storage_root);
+    }
+
+    /// `body` is exactly `ACCOUNT_BODY_LEN` bytes.
+    fn decode_body(body: &[u8]) -> Self {
+        Self {
+            balance: u128::from_be_bytes(body[..16].try_into().unwrap()),
+            nonce: u64::from_be_bytes(body[16..24].try_into().unwrap()),
+            code_hash: body[24..56].try_into().unwrap(),
+            storage_root: body[56..].try_into().unwrap(),
+        }
     }
 
     pub fn to_commitment_value(&self) -> ScalarField {
         bytes_to_field(&self.hash())
     }
+
+    pub fn commitment_value(&self, encoding: CommitmentEncoding) -> Result<ScalarField> {
+        encoding.hash_to_field(&self.hash())
+    }
+
+    /// The account hash under `scheme`. Unlike `hash`, which stays
+    /// untagged so existing commitments still open, this leads with the
+    /// scheme id.
+    pub fn hash_with(&self, scheme: HashScheme) -> Result<[u8; 32]> {
+        match scheme {
+            HashScheme::Sha256 => Ok(scheme.sha256(&self.encode())),
+            #[cfg(feature = "poseidon")]
+            HashScheme::Poseidon => Ok(self.poseidon_hash()),
+            #[cfg(not(feature = "poseidon"))]
+            HashScheme::Poseidon => Err(scheme.unavailable()),
+        }
+    }
+
+    /// `commitment_value` over `hash_with(scheme)`. Under `Full`, a
+    /// Poseidon value is the whole squeezed element, so a circuit can tie it
+    /// back to the sponge output; elements above the scalar field's order
+    /// are rejected.
+    pub fn commitment_value_with(&self, scheme: HashScheme, encoding: CommitmentEncoding) -> Result<ScalarField> {
+        encoding.hash_to_field(&self.hash_with(scheme)?)
+    }
 }
 
 impl StateTransition {
@@ -59,17 +204,146 @@ impl StateTransition {
         Self { pre_state, post_state, tx_hash }
     }
 
+    /// SHA-256 of `encode()`.
     pub fn transition_hash(&self) -> [u8; 32] {
-        let mut hasher = Sha256::new();
-        hasher.update(self.pre_state.hash());
-        hasher.update(self.post_state.hash());
-        hasher.update(self.tx_hash);
-        hasher.finalize().into()
+        Sha256::digest(self.encode()).into()
     }
 
     pub fn to_commitment_value(&self) -> ScalarField {
         bytes_to_field(&self.transition_hash())
     }
+
+    pub fn commitment_value(&self, encoding: CommitmentEncoding) -> Result<ScalarField> {
+        encoding.hash_to_field(&self.transition_hash())
+    }
+
+    /// The transition hash under `scheme`, tagged as in
+    /// `AccountState::hash_with`.
+    pub fn hash_with(&self, scheme: HashScheme) -> Result<[u8; 32]> {
+        match scheme {
+            HashScheme::Sha256 => Ok(scheme.sha256(&self.encode())),
+            #[cfg(feature = "poseidon")]
+            HashScheme::Poseidon => Ok(self.poseidon_transition_hash()),
+            #[cfg(not(feature = "poseidon"))]
+            HashScheme::Poseidon => Err(scheme.unavailable()),
+        }
+    }
+
+    pub fn commitment_value_with(&self, scheme: HashScheme, encoding: CommitmentEncoding) -> Result<ScalarField> {
+        encoding.hash_to_field(&self.hash_with(scheme)?)
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(STATE_TRANSITION_ENCODED_LEN);
+        bytes.push(STATE_ENCODING_VERSION);
+        self.pre_state.encode_body(&mut bytes);
+        self.post_state.encode_body(&mut bytes);
+        bytes.extend_from_slice(&self.tx_hash);
+        bytes
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self> {
+        let body = check_encoding(bytes, STATE_TRANSITION_ENCODED_LEN, "state transition")?;
+        let (pre, rest) = body.split_at(ACCOUNT_BODY_LEN);
+        let (post, tx_hash) = rest.split_at(ACCOUNT_BODY_LEN);
+        Ok(Self {
+            pre_state: AccountState::decode_body(pre),
+            post_state: AccountState::decode_body(post),
+            tx_hash: tx_hash.try_into().unwrap(),
+        })
+    }
+}
+
+impl TransferTransition {
+    /// SHA-256 of `TRANSFER_DOMAIN` followed by `encode()`.
+    pub fn transition_hash(&self) -> [u8; 32] {
+        Sha256::digest(self.preimage()).into()
+    }
+
+    /// The transfer hash under `scheme`, tagged as in
+    /// `AccountState::hash_with`.
+    pub fn hash_with(&self, scheme: HashScheme) -> Result<[u8; 32]> {
+        match scheme {
+            HashScheme::Sha256 => Ok(scheme.sha256(&self.preimage())),
+            #[cfg(feature = "poseidon")]
+            HashScheme::Poseidon => Ok(self.poseidon_transfer_hash()),
+            #[cfg(not(feature = "poseidon"))]
+            HashScheme::Poseidon => Err(scheme.unavailable()),
+        }
+    }
+
+    pub fn commitment_value_with(&self, scheme: HashScheme, encoding: CommitmentEncoding) -> Result<ScalarField> {
+        encoding.hash_to_field(&self.hash_with(scheme)?)
+    }
+
+    fn preimage(&self) -> Vec<u8> {
+        [TRANSFER_DOMAIN, &self.encode()].concat()
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(TRANSFER_TRANSITION_ENCODED_LEN);
+        bytes.push(STATE_ENCODING_VERSION);
+        for state in [&self.sender_pre, &self.sender_post, &self.receiver_pre, &self.receiver_post] {
+            state.encode_body(&mut bytes);
+        }
+        bytes.extend_from_slice(&self.amount.to_be_bytes());
+        bytes.extend_from_slice(&self.tx_hash);
+        bytes
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self> {
+        let body = check_encoding(bytes, TRANSFER_TRANSITION_ENCODED_LEN, "transfer transition")?;
+        let (accounts, rest) = body.split_at(4 * ACCOUNT_BODY_LEN);
+        let mut states = accounts.chunks(ACCOUNT_BODY_LEN).map(AccountState::decode_body);
+        let (amount, tx_hash) = rest.split_at(16);
+        Ok(Self {
+            sender_pre: states.next().unwrap(),
+            sender_post: states.next().unwrap(),
+            receiver_pre: states.next().unwrap(),
+            receiver_post: states.next().unwrap(),
+            amount: u128::from_be_bytes(amount.try_into().unwrap()),
+            tx_hash: tx_hash.try_into().unwrap(),
+        })
+    }
+
+    pub fn to_commitment_value(&self) -> ScalarField {
+        bytes_to_field(&self.transition_hash())
+    }
+
+    pub fn commitment_value(&self, encoding: CommitmentEncoding) -> Result<ScalarField> {
+        encoding.hash_to_field(&self.transition_hash())
+    }
+
+    /// The receiver's side as a single-account transition.
+    pub fn receiver_transition(&self) -> StateTransition {
+        StateTransition::new(self.receiver_pre.clone(), self.receiver_post.clone(), self.tx_hash)
+    }
+}
+
+/// The sender's side, which is what a single-account transition records for
+/// a transfer.
+impl From<TransferTransition> for StateTransition {
+    fn from(transfer: TransferTransition) -> Self {
+        StateTransition::new(transfer.sender_pre, transfer.sender_post, transfer.tx_hash)
+    }
+}
+
+/// Checks the version tag and exact length, returning the bytes after the tag.
+fn check_encoding<'a>(bytes: &'a [u8], len: usize, what: &'static str) -> Result<&'a [u8]> {
+    let (&version, body) = bytes
+        .split_first()
+        .ok_or(ArchimedesError::serialization(SerializationErrorKind::Truncated(what)))?;
+    if version != STATE_ENCODING_VERSION {
+        return Err(ArchimedesError::serialization(SerializationErrorKind::UnsupportedFormatVersion {
+            format: what,
+            version: version as u32,
+        }));
+    }
+    match bytes.len().cmp(&len) {
+        std::cmp::Ordering::Less => Err(ArchimedesError::serialization(SerializationErrorKind::Truncated(what))),
+        std::cmp::Ordering::Greater => Err(ArchimedesError::serialization(SerializationErrorKind::TrailingBytes(what))),
+        std::cmp::Ordering::Equal => Ok(body),
+    }
 }
 
 pub fn bytes_to_field(bytes: &[u8; 32]) -> ScalarField {
@@ -78,23 +352,83 @@ pub fn bytes_to_field(bytes: &[u8; 32]) -> ScalarField {
     ScalarField::from_le_bytes_mod_order(&truncated)
 }
 
+/// Splits `bytes` into two 16-byte little-endian limbs, low half first.
+/// Both are below 2^128, so no byte is lost and `fields_to_bytes` inverts it.
+pub fn bytes_to_fields(bytes: &[u8; 32]) -> [ScalarField; 2] {
+    [
+        ScalarField::from_le_bytes_mod_order(&bytes[..16]),
+        ScalarField::from_le_bytes_mod_order(&bytes[16..]),
+    ]
+}
+
+/// `None` if either limb is 2^128 or above, which `bytes_to_fields` never
+/// produces.
+pub fn fields_to_bytes(limbs: &[ScalarField; 2]) -> Option<[u8; 32]> {
+    let mut bytes = [0u8; 32];
+    for (limb, out) in limbs.iter().zip(bytes.chunks_exact_mut(16)) {
+        let words = limb.into_bigint().0;
+        if words[2] != 0 || words[3] != 0 {
+            return None;
+        }
+        out[..8].copy_from_slice(&words[0].to_le_bytes());
+        out[8..].copy_from_slice(&words[1].to_le_bytes());
+    }
+    Some(bytes)
+}
+
+/// Hashes each item, across rayon workers with the `parallel` feature. The
+/// output is in input order either way.
+fn map_ordered<T: Sync>(items: &[T], f: impl Fn(&T) -> ScalarField + Sync + Send) -> Vec<ScalarField> {
+    #[cfg(feature = "parallel")]
+    {
+        use rayon::prelude::*;
+        items.par_iter().map(f).collect()
+    }
+    #[cfg(not(feature = "parallel"))]
+    {
+        items.iter().map(f).collect()
+    }
+}
+
 pub fn encode_state_batch(states: &[AccountState]) -> Result<Vec<ScalarField>> {
     if states.is_empty() {
-        return Err(ArchimedesError::StateEncodingError("Empty state batch".to_string()));
+        return Err(ArchimedesError::StateEncodingError(StateEncodingErrorKind::EmptyStateBatch));
     }
-    Ok(states.iter().map(|s| s.to_commitment_value()).collect())
+    Ok(map_ordered(states, AccountState::to_commitment_value))
 }
 
 pub fn encode_transitions(transitions: &[StateTransition]) -> Result<Vec<ScalarField>> {
     if transitions.is_empty() {
-        return Err(ArchimedesError::StateEncodingError("Empty transitions".to_string()));
+        return Err(ArchimedesError::StateEncodingError(StateEncodingErrorKind::EmptyTransitions));
+    }
+    Ok(map_ordered(transitions, StateTransition::to_commitment_value))
+}
+
+/// `encode_transitions` split into consecutive chunks of `chunk_size` (the
+/// last may be shorter), so each can be committed while the next encodes.
+pub fn encode_transitions_chunked(transitions: &[StateTransition], chunk_size: usize) -> Result<Vec<Vec<ScalarField>>> {
+    if chunk_size == 0 {
+        return Err(ArchimedesError::StateEncodingError(StateEncodingErrorKind::ZeroChunkSize));
+    }
+    if transitions.is_empty() {
+        return Err(ArchimedesError::StateEncodingError(StateEncodingErrorKind::EmptyTransitions));
     }
-    Ok(transitions.iter().map(|t| t.to_commitment_value()).collect())
+    Ok(transitions
+        .chunks(chunk_size)
+        .map(|chunk| map_ordered(chunk, StateTransition::to_commitment_value))
+        .collect())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use rand::{Rng, SeedableRng};
+
+    const GOLDEN_ACCOUNT_HASH: &str = "9ec27348e788eebc0a6d8891fdd91d5c77b98b67760ba729d8c70a29b2a48b28";
+    const GOLDEN_TRANSITION_HASH: &str = "8416b609166565e7cd10b6d623f0deb2623efe2477b3f55909e67241bae5716e";
+    const TAGGED_ACCOUNT_HASH: &str = "7568c887d0e7797fa0f569177755f4f5f6777cc14c936a022ef556ea1679cebc";
+    const TAGGED_TRANSITION_HASH: &str = "c274a7452d7d95f5d975aa37e9ccc24a503cc4168bae0675ac8559d3f7bb0d58";
+    const GOLDEN_TRANSFER_HASH: &str = "5079ab484633eecb11b7f466ec8ecfa788b4bb34f1ad6822d70034b32f0d31ab";
 
     #[test]
     fn test_account_state_encoding() {
@@ -105,6 +439,43 @@ mod tests {
         assert_eq!(elements[1], ScalarField::from(5u64));
     }
 
+    #[test]
+    fn test_large_balance_not_truncated() {
+        let low = AccountState::new(5, 0);
+        let high = AccountState::new((1u128 << 64) + 5, 0);
+        assert_ne!(low.to_field_elements()[0], high.to_field_elements()[0]);
+
+        // Truncating to 64 bits would encode this as a zero balance.
+        let zero = AccountState::new(0, 0);
+        let wrapped = AccountState::new(u64::MAX as u128 + 1, 0);
+        assert_eq!(wrapped.to_field_elements()[0], scalar_from_u128(1u128 << 64));
+        assert_ne!(zero.to_field_elements(), wrapped.to_field_elements());
+        assert_ne!(zero.hash(), wrapped.hash());
+        assert_ne!(zero.to_commitment_value(), wrapped.to_commitment_value());
+    }
+
+    #[test]
+    fn test_transfer_transition_binds_receiver() {
+        let transfer = TransferTransition {
+            sender_pre: AccountState::new(1000, 0),
+            sender_post: AccountState::new(900, 1),
+            receiver_pre: AccountState::new(50, 3),
+            receiver_post: AccountState::new(150, 3),
+            amount: 100,
+            tx_hash: [4u8; 32],
+        };
+        let miscredited = TransferTransition {
+            receiver_post: AccountState::new(160, 3),
+            ..transfer.clone()
+        };
+        assert_ne!(transfer.to_commitment_value(), miscredited.to_commitment_value());
+
+        let sender = StateTransition::from(miscredited.clone());
+        assert_eq!(sender, StateTransition::new(AccountState::new(1000, 0), AccountState::new(900, 1), [4u8; 32]));
+        assert_eq!(sender, StateTransition::from(transfer.clone()));
+        assert_ne!(transfer.receiver_transition(), miscredited.receiver_transition());
+    }
+
     #[test]
     fn test_account_hash_determinism() {
         let s1 = AccountState::new(100, 1);
@@ -124,6 +495,196 @@ mod tests {
         assert_eq!(h1, h2);
     }
 
+    fn sample_transition() -> StateTransition {
+        let mut pre = AccountState::new((1u128 << 64) + 7, 3);
+        pre.code_hash = [0xc0; 32];
+        pre.storage_root = [0x5e; 32];
+        let post = AccountState { balance: 7, nonce: 4, ..pre.clone() };
+        StateTransition::new(pre, post, [0xab; 32])
+    }
+
+    #[test]
+    fn test_canonical_encoding_golden() {
+        let transition = sample_transition();
+        let account = transition.pre_state.encode();
+        assert_eq!(
+            hex::encode(&account),
+            concat!(
+                "01",
+                "00000000000000010000000000000007",
+                "0000000000000003",
+                "c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0",
+                "5e5e5e5e5e5e5e5e5e5e5e5e5e5e5e5e5e5e5e5e5e5e5e5e5e5e5e5e5e5e5e5e",
+            )
+        );
+        assert_eq!(hex::encode(transition.pre_state.hash()), GOLDEN_ACCOUNT_HASH);
+
+        let encoded = transition.encode();
+        assert_eq!(encoded.len(), STATE_TRANSITION_ENCODED_LEN);
+        assert_eq!(encoded[..ACCOUNT_STATE_ENCODED_LEN], account[..]);
+        assert_eq!(encoded[ACCOUNT_STATE_ENCODED_LEN..][..ACCOUNT_BODY_LEN], transition.post_state.encode()[1..]);
+        assert_eq!(encoded[STATE_TRANSITION_ENCODED_LEN - 32..], [0xab; 32]);
+        assert_eq!(hex::encode(transition.transition_hash()), GOLDEN_TRANSITION_HASH);
+
+        assert_eq!(AccountState::decode(&account).unwrap(), transition.pre_state);
+        assert_eq!(StateTransition::decode(&encoded).unwrap(), transition);
+    }
+
+    #[test]
+    fn test_transfer_encoding_golden() {
+        let transition = sample_transition();
+        let transfer = TransferTransition {
+            sender_pre: transition.pre_state.clone(),
+            sender_post: transition.post_state.clone(),
+            receiver_pre: AccountState::new(1, 0),
+            receiver_post: AccountState::new((1u128 << 64) + 1, 0),
+            amount: 1 << 64,
+            tx_hash: transition.tx_hash,
+        };
+        let encoded = transfer.encode();
+        assert_eq!(encoded.len(), TRANSFER_TRANSITION_ENCODED_LEN);
+        assert_eq!(encoded[..1 + 2 * ACCOUNT_BODY_LEN], transition.encode()[..1 + 2 * ACCOUNT_BODY_LEN]);
+        assert_eq!(encoded[TRANSFER_TRANSITION_ENCODED_LEN - 48..][..16], (1u128 << 64).to_be_bytes());
+        assert_eq!(hex::encode(transfer.transition_hash()), GOLDEN_TRANSFER_HASH);
+        assert_ne!(transfer.transition_hash(), <[u8; 32]>::from(Sha256::digest(&encoded)));
+
+        assert_eq!(TransferTransition::decode(&encoded).unwrap(), transfer);
+        for len in 0..encoded.len() {
+            assert!(TransferTransition::decode(&encoded[..len]).is_err());
+        }
+        assert!(TransferTransition::decode(&[encoded.as_slice(), &[0]].concat()).is_err());
+    }
+
+    #[test]
+    fn test_decode_rejects_malformed_input() {
+        let encoded = sample_transition().encode();
+        for len in 0..encoded.len() {
+            assert!(StateTransition::decode(&encoded[..len]).is_err());
+            assert!(AccountState::decode(&encoded[..len.min(ACCOUNT_STATE_ENCODED_LEN - 1)]).is_err());
+        }
+        let mut long = encoded.clone();
+        long.push(0);
+        assert!(matches!(
+            StateTransition::decode(&long),
+            Err(ArchimedesError::SerializationError { kind: SerializationErrorKind::TrailingBytes(_), .. })
+        ));
+        let mut bumped = encoded;
+        bumped[0] = STATE_ENCODING_VERSION + 1;
+        assert!(matches!(
+            StateTransition::decode(&bumped),
+            Err(ArchimedesError::SerializationError { kind: SerializationErrorKind::UnsupportedFormatVersion { .. }, .. })
+        ));
+
+        let mut rng = rand::rngs::StdRng::seed_from_u64(836);
+        for _ in 0..2000 {
+            let mut bytes = vec![0u8; rng.gen_range(0..2 * STATE_TRANSITION_ENCODED_LEN)];
+            rng.fill(&mut bytes[..]);
+            if let Some(tag) = bytes.first_mut() {
+                *tag = STATE_ENCODING_VERSION;
+            }
+            let _ = AccountState::decode(&bytes);
+            let _ = StateTransition::decode(&bytes);
+        }
+    }
+
+    #[test]
+    fn test_field_limbs_round_trip() {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(837);
+        for _ in 0..500 {
+            let bytes: [u8; 32] = rng.gen();
+            assert_eq!(fields_to_bytes(&bytes_to_fields(&bytes)), Some(bytes));
+        }
+        for bytes in [[0u8; 32], [0xff; 32]] {
+            assert_eq!(fields_to_bytes(&bytes_to_fields(&bytes)), Some(bytes));
+        }
+        assert_eq!(fields_to_bytes(&[ScalarField::from(1u64) - ScalarField::from(2u64), ScalarField::from(0u64)]), None);
+
+        // Hashes differing only in the last byte collide under the old encoding.
+        let mut other = [0x05; 32];
+        other[31] ^= 1;
+        assert_eq!(bytes_to_field(&[0x05; 32]), bytes_to_field(&other));
+        let full = |bytes: &[u8; 32]| CommitmentEncoding::Full.hash_to_field(bytes);
+        assert_ne!(full(&[0x05; 32]).unwrap(), full(&other).unwrap());
+        assert_eq!(full(&other).unwrap().into_bigint().to_bytes_le(), other);
+    }
+
+    #[test]
+    fn test_full_encoding_rejects_non_canonical() {
+        let max: [u8; 32] = (-ScalarField::from(1u64)).into_bigint().to_bytes_le().try_into().unwrap();
+        assert_eq!(CommitmentEncoding::Full.hash_to_field(&max).unwrap(), -ScalarField::from(1u64));
+        let modulus: [u8; 32] = ScalarField::MODULUS.to_bytes_le().try_into().unwrap();
+        for bytes in [modulus, [0xff; 32]] {
+            assert!(matches!(
+                CommitmentEncoding::Full.hash_to_field(&bytes),
+                Err(ArchimedesError::StateEncodingError(StateEncodingErrorKind::NonCanonicalField))
+            ));
+            assert!(CommitmentEncoding::Truncated.hash_to_field(&bytes).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_commitment_encodings_differ() {
+        // A tx hash chosen so the transition hash is below the field order.
+        let transition = StateTransition::new(AccountState::new(1000, 0), AccountState::new(900, 1), [7u8; 32]);
+        let hash = transition.transition_hash();
+        assert_eq!(transition.to_commitment_value(), transition.commitment_value(CommitmentEncoding::Truncated).unwrap());
+        assert_ne!(transition.commitment_value(CommitmentEncoding::Full).unwrap(), transition.to_commitment_value());
+        assert_ne!(CommitmentEncoding::Full.hash_to_field(&hash).unwrap(), bytes_to_field(&hash));
+        assert!(!bytes_to_fields(&hash).contains(&bytes_to_field(&hash)));
+    }
+
+    #[test]
+    fn test_scheme_tagged_hashes() {
+        let transition = sample_transition();
+        let account = &transition.pre_state;
+        let tagged = account.hash_with(HashScheme::Sha256).unwrap();
+        assert_eq!(hex::encode(tagged), TAGGED_ACCOUNT_HASH);
+        assert_eq!(hex::encode(transition.hash_with(HashScheme::Sha256).unwrap()), TAGGED_TRANSITION_HASH);
+        assert_ne!(tagged, account.hash());
+        assert_eq!(tagged, <[u8; 32]>::from(Sha256::digest([&[0u8][..], &account.encode()].concat())));
+        assert_eq!(
+            transition.commitment_value_with(HashScheme::Sha256, CommitmentEncoding::Truncated).unwrap(),
+            bytes_to_field(&transition.hash_with(HashScheme::Sha256).unwrap())
+        );
+        // TAGGED_TRANSITION_HASH is above the field order.
+        assert!(matches!(
+            transition.commitment_value_with(HashScheme::Sha256, CommitmentEncoding::Full),
+            Err(ArchimedesError::StateEncodingError(StateEncodingErrorKind::NonCanonicalField))
+        ));
+
+        assert_eq!(serde_json::to_string(&HashScheme::Poseidon).unwrap(), "\"Poseidon\"");
+        assert_eq!(serde_json::from_str::<HashScheme>("\"Poseidon\"").unwrap(), HashScheme::Poseidon);
+        #[cfg(not(feature = "poseidon"))]
+        assert!(matches!(
+            transition.hash_with(HashScheme::Poseidon),
+            Err(ArchimedesError::StateEncodingError(StateEncodingErrorKind::HashSchemeUnavailable(1)))
+        ));
+    }
+
+    #[test]
+    fn test_chunked_encoding_matches_serial() {
+        let transitions: Vec<StateTransition> = (0..1000u64)
+            .map(|i| StateTransition::new(AccountState::new(i as u128 * 7, i), AccountState::new(i as u128, i + 1), [i as u8; 32]))
+            .collect();
+        let serial: Vec<ScalarField> = transitions.iter().map(|t| t.to_commitment_value()).collect();
+        assert_eq!(encode_transitions(&transitions).unwrap(), serial);
+        for chunk_size in [1, 7, 256, 1000, 4096] {
+            let chunks = encode_transitions_chunked(&transitions, chunk_size).unwrap();
+            assert_eq!(chunks.len(), transitions.len().div_ceil(chunk_size));
+            assert!(chunks[..chunks.len() - 1].iter().all(|c| c.len() == chunk_size));
+            assert_eq!(chunks.concat(), serial);
+        }
+        assert!(encode_transitions_chunked(&[], 8).is_err());
+        assert!(matches!(
+            encode_transitions_chunked(&transitions, 0),
+            Err(ArchimedesError::StateEncodingError(StateEncodingErrorKind::ZeroChunkSize))
+        ));
+
+        let states: Vec<AccountState> = transitions.iter().map(|t| t.pre_state.clone()).collect();
+        let serial: Vec<ScalarField> = states.iter().map(|s| s.to_commitment_value()).collect();
+        assert_eq!(encode_state_batch(&states).unwrap(), serial);
+    }
+
     #[test]
     fn test_encode_batch() {
         let states = vec![AccountState::new(100, 0), AccountState::new(200, 1)];
@@ -0,0 +1,478 @@
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+
+use archimedes_core::{AggregateCommitment, ArchimedesError, Commitment, MerkleErrorKind, SerializationErrorKind};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use sha2::{Digest, Sha256};
+
+use crate::merkle::{committed_root, AggregatePathProof, CommitmentMerkleTree, MerkleNode, MERKLE_PADDING};
+
+type Result<T> = std::result::Result<T, ArchimedesError>;
+
+fn tree_levels(tree: &CommitmentMerkleTree) -> impl Iterator<Item = &[MerkleNode]> {
+    (0..=tree.depth()).map(|level| tree.level(level).expect("level within tree depth"))
+}
+
+pub const FROZEN_MAGIC: [u8; 8] = *b"ARCHFRZN";
+pub const FROZEN_VERSION: u32 = 3;
+
+const FLAG_AGGREGATES: u32 = 1;
+const HASH_LEN: usize = 32;
+const COUNT_LEN: usize = 8;
+// magic, version, flags, leaf_count, level_count, aggregate_width, root_hash
+const FIXED_HEADER_LEN: usize = 8 + 4 + 4 + 8 + 4 + 4 + HASH_LEN;
+
+// Layout (all integers little-endian):
+//   magic[8] | version u32 | flags u32 | leaf_count u64 | level_count u32 | aggregate_width u32
+//   | root_hash[32] | level_len u64 * level_count | header_checksum[32]
+//   | node hashes, level by level, 32 bytes each
+//   | (if FLAG_AGGREGATES) compressed aggregate point + u64 count per node, level by level
+
+enum Backing {
+    Owned(Vec<u8>),
+    #[cfg(feature = "mmap")]
+    Mapped(memmap2::Mmap),
+}
+
+impl Backing {
+    fn bytes(&self) -> &[u8] {
+        match self {
+            Backing::Owned(v) => v,
+            #[cfg(feature = "mmap")]
+            Backing::Mapped(m) => m,
+        }
+    }
+}
+
+/// Read-only snapshot of a `CommitmentMerkleTree` answering proof and hash
+/// queries directly from its flat byte layout.
+pub struct FrozenTree {
+    backing: Backing,
+    leaf_count: usize,
+    level_lens: Vec<usize>,
+    level_offsets: Vec<usize>,
+    aggregate_width: usize,
+    aggregate_offsets: Vec<usize>,
+    root_hash: [u8; 32],
+}
+
+impl CommitmentMerkleTree {
+    pub fn freeze_to<W: Write>(&self, writer: &mut W) -> Result<()> {
+        write_frozen(self, writer, false)
+    }
+
+    pub fn freeze_with_aggregates_to<W: Write>(&self, writer: &mut W) -> Result<()> {
+        write_frozen(self, writer, true)
+    }
+}
+
+fn write_frozen<W: Write>(tree: &CommitmentMerkleTree, writer: &mut W, with_aggregates: bool) -> Result<()> {
+    let aggregate_width = if with_aggregates {
+        <Commitment>::zero().compressed_size()
+    } else {
+        0
+    };
+    let mut header = Vec::with_capacity(FIXED_HEADER_LEN + (tree.depth() + 1) * 8);
+    header.extend_from_slice(&FROZEN_MAGIC);
+    header.extend_from_slice(&FROZEN_VERSION.to_le_bytes());
+    header.extend_from_slice(&(if with_aggregates { FLAG_AGGREGATES } else { 0 }).to_le_bytes());
+    header.extend_from_slice(&(tree.leaf_count() as u64).to_le_bytes());
+    header.extend_from_slice(&(tree.depth() as u32 + 1).to_le_bytes());
+    header.extend_from_slice(&(aggregate_width as u32).to_le_bytes());
+    header.extend_from_slice(&tree.root_hash());
+    for level in tree_levels(tree) {
+        header.extend_from_slice(&(level.len() as u64).to_le_bytes());
+    }
+    let checksum: [u8; 32] = Sha256::digest(&header).into();
+    writer.write_all(&header)?;
+    writer.write_all(&checksum)?;
+
+    for level in tree_levels(tree) {
+        for node in level {
+            writer.write_all(&node.hash)?;
+        }
+    }
+    if with_aggregates {
+        let mut buf = Vec::with_capacity(aggregate_width);
+        for level in tree_levels(tree) {
+            for node in level {
+                buf.clear();
+                node.aggregate.commitment.serialize_compressed(&mut buf)?;
+                writer.write_all(&buf)?;
+                writer.write_all(&(node.aggregate.count as u64).to_le_bytes())?;
+            }
+        }
+    }
+    Ok(writer.flush()?)
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap())
+}
+
+fn corrupt(reason: &'static str) -> ArchimedesError {
+    ArchimedesError::serialization(SerializationErrorKind::CorruptedFrozenTree(reason))
+}
+
+impl FrozenTree {
+    /// Opens a frozen tree file. With the `mmap` feature the file is memory
+    /// mapped; otherwise it is read into memory. Only the header is validated.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let file = File::open(path)?;
+        Self::from_backing(Self::load(file)?)
+    }
+
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<Self> {
+        Self::from_backing(Backing::Owned(bytes))
+    }
+
+    #[cfg(feature = "mmap")]
+    fn load(file: File) -> Result<Backing> {
+        // SAFETY: the mapping is read-only and frozen files are never modified
+        // in place; a concurrently truncated file is an operator error.
+        let map = unsafe { memmap2::Mmap::map(&file) }?;
+        Ok(Backing::Mapped(map))
+    }
+
+    #[cfg(not(feature = "mmap"))]
+    fn load(mut file: File) -> Result<Backing> {
+        let mut bytes = Vec::new();
+        std::io::Read::read_to_end(&mut file, &mut bytes)?;
+        Ok(Backing::Owned(bytes))
+    }
+
+    fn from_backing(backing: Backing) -> Result<Self> {
+        let bytes = backing.bytes();
+        if bytes.len() < FIXED_HEADER_LEN || bytes[..8] != FROZEN_MAGIC {
+            return Err(corrupt("bad magic"));
+        }
+        let version = read_u32(bytes, 8);
+        if version != FROZEN_VERSION {
+            return Err(ArchimedesError::serialization(SerializationErrorKind::UnsupportedFrozenVersion(version)));
+        }
+        let flags = read_u32(bytes, 12);
+        let leaf_count = read_u64(bytes, 16) as usize;
+        let level_count = read_u32(bytes, 24) as usize;
+        let aggregate_width = read_u32(bytes, 28) as usize;
+        let mut root_hash = [0u8; 32];
+        root_hash.copy_from_slice(&bytes[32..FIXED_HEADER_LEN]);
+
+        let table_end = level_count
+            .checked_mul(8)
+            .and_then(|t| t.checked_add(FIXED_HEADER_LEN))
+            .ok_or_else(|| corrupt("level table overflow"))?;
+        if bytes.len() < table_end.saturating_add(HASH_LEN) {
+            return Err(corrupt("truncated header"));
+        }
+        let checksum: [u8; 32] = Sha256::digest(&bytes[..table_end]).into();
+        if checksum[..] != bytes[table_end..table_end + HASH_LEN] {
+            return Err(corrupt("header checksum mismatch"));
+        }
+        if (flags & FLAG_AGGREGATES != 0) != (aggregate_width != 0) {
+            return Err(corrupt("aggregate flag mismatch"));
+        }
+
+        let level_lens: Vec<usize> = (0..level_count)
+            .map(|l| read_u64(bytes, FIXED_HEADER_LEN + l * 8) as usize)
+            .collect();
+        // Each level must halve the one below, or later reads run off a level.
+        if level_lens.first() != Some(&leaf_count)
+            || level_lens.last() != Some(&1)
+            || level_lens.windows(2).any(|pair| pair[1] != pair[0].div_ceil(2))
+        {
+            return Err(corrupt("inconsistent level sizes"));
+        }
+
+        // Lengths come from the file, so every step is checked.
+        let overflow = || corrupt("level size overflow");
+        let mut offset = table_end + HASH_LEN;
+        let mut level_offsets = Vec::with_capacity(level_count);
+        for len in &level_lens {
+            level_offsets.push(offset);
+            offset = len.checked_mul(HASH_LEN).and_then(|n| offset.checked_add(n)).ok_or_else(overflow)?;
+        }
+        let mut aggregate_offsets = Vec::new();
+        if aggregate_width != 0 {
+            let entry_len = aggregate_width.checked_add(COUNT_LEN).ok_or_else(overflow)?;
+            for len in &level_lens {
+                aggregate_offsets.push(offset);
+                offset = len.checked_mul(entry_len).and_then(|n| offset.checked_add(n)).ok_or_else(overflow)?;
+            }
+        }
+        if bytes.len() != offset {
+            return Err(corrupt("unexpected file length"));
+        }
+
+        let tree = Self {
+            backing,
+            leaf_count,
+            level_lens,
+            level_offsets,
+            aggregate_width,
+            aggregate_offsets,
+            root_hash,
+        };
+        if tree.hash_at(level_count - 1, 0) != root_hash {
+            return Err(corrupt("root hash mismatch"));
+        }
+        Ok(tree)
+    }
+
+    fn check_node(&self, level: usize, index: usize) -> Result<()> {
+        if level >= self.level_lens.len() || index >= self.level_lens[level] {
+            return Err(ArchimedesError::MerkleTreeError {
+                index,
+                leaf_count: self.leaf_count,
+                kind: MerkleErrorKind::NodeOutOfBounds { level },
+            });
+        }
+        Ok(())
+    }
+
+    fn hash_at(&self, level: usize, index: usize) -> [u8; 32] {
+        let start = self.level_offsets[level] + index * HASH_LEN;
+        let mut hash = [0u8; 32];
+        hash.copy_from_slice(&self.backing.bytes()[start..start + HASH_LEN]);
+        hash
+    }
+
+    pub fn root_hash(&self) -> [u8; 32] {
+        self.root_hash
+    }
+
+    pub fn committed_root(&self) -> [u8; 32] {
+        committed_root(self.root_hash, self.leaf_count)
+    }
+
+    pub fn leaf_count(&self) -> usize {
+        self.leaf_count
+    }
+
+    pub fn depth(&self) -> usize {
+        self.level_lens.len() - 1
+    }
+
+    pub fn has_aggregates(&self) -> bool {
+        self.aggregate_width != 0
+    }
+
+    pub fn node_hash(&self, level: usize, index: usize) -> Result<[u8; 32]> {
+        self.check_node(level, index)?;
+        Ok(self.hash_at(level, index))
+    }
+
+    pub fn hash_range(&self, level: usize, start: usize, end: usize) -> Result<Vec<[u8; 32]>> {
+        if level >= self.level_lens.len() || end > self.level_lens[level] || start > end {
+            return Err(ArchimedesError::MerkleTreeError {
+                index: start,
+                leaf_count: self.leaf_count,
+                kind: MerkleErrorKind::InvalidRange { end, level },
+            });
+        }
+        Ok((start..end).map(|i| self.hash_at(level, i)).collect())
+    }
+
+    pub fn node_aggregate(&self, level: usize, index: usize) -> Result<AggregateCommitment> {
+        if !self.has_aggregates() {
+            return Err(ArchimedesError::MerkleTreeError {
+                index,
+                leaf_count: self.leaf_count,
+                kind: MerkleErrorKind::NoAggregates,
+            });
+        }
+        self.check_node(level, index)?;
+        let start = self.aggregate_offsets[level] + index * (self.aggregate_width + COUNT_LEN);
+        let bytes = &self.backing.bytes()[start..start + self.aggregate_width + COUNT_LEN];
+        let commitment = Commitment::deserialize_compressed(&bytes[..self.aggregate_width])?;
+        let count = read_u64(bytes, self.aggregate_width) as usize;
+        Ok(AggregateCommitment { commitment, count })
+    }
+
+    /// Needs a tree frozen with its aggregates, which the proof carries.
+    pub fn generate_proof(&self, index: usize) -> Result<AggregatePathProof> {
+        if index >= self.leaf_count {
+            return Err(ArchimedesError::MerkleTreeError {
+                index,
+                leaf_count: self.leaf_count,
+                kind: MerkleErrorKind::IndexOutOfBounds,
+            });
+        }
+        let mut siblings = Vec::new();
+        let mut current_index = index;
+        for level in 0..self.level_lens.len() - 1 {
+            let sibling_index = current_index ^ 1;
+            let (hash, aggregate) = if sibling_index < self.level_lens[level] {
+                (self.hash_at(level, sibling_index), self.node_aggregate(level, sibling_index)?)
+            } else {
+                (MERKLE_PADDING, AggregateCommitment::empty())
+            };
+            siblings.push((hash, aggregate, current_index.is_multiple_of(2)));
+            current_index /= 2;
+        }
+        Ok(AggregatePathProof { index, siblings })
+    }
+
+    /// Recomputes every internal node from its children. Linear in the tree
+    /// size, so it is not run by `open`. Node hashes bind aggregates, so a
+    /// tree frozen without them cannot be checked and always fails.
+    pub fn verify_integrity(&self) -> bool {
+        self.has_aggregates() && self.internal_nodes_match().unwrap_or(false)
+    }
+
+    fn internal_nodes_match(&self) -> Result<bool> {
+        for level in 1..self.level_lens.len() {
+            for i in 0..self.level_lens[level] {
+                let left = self.node_at(level - 1, 2 * i)?;
+                let computed = if 2 * i + 1 < self.level_lens[level - 1] {
+                    MerkleNode::internal(&left, &self.node_at(level - 1, 2 * i + 1)?)?
+                } else {
+                    MerkleNode::padded(&left)
+                };
+                let stored = self.node_at(level, i)?;
+                if computed.hash != stored.hash
+                    || computed.aggregate.count != stored.aggregate.count
+                    || computed.aggregate.commitment != stored.aggregate.commitment
+                {
+                    return Ok(false);
+                }
+            }
+        }
+        Ok(true)
+    }
+
+    fn node_at(&self, level: usize, index: usize) -> Result<MerkleNode> {
+        Ok(MerkleNode {
+            hash: self.hash_at(level, index),
+            aggregate: self.node_aggregate(level, index)?,
+        })
+    }
+
+    pub fn verify_leaf(&self, commitment: &Commitment, index: usize) -> Result<bool> {
+        let proof = self.generate_proof(index)?;
+        Ok(proof.verify(commitment, self.root_hash))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use archimedes_core::{CommitmentChain, CommitmentParams};
+    use ark_ed_on_bls12_381::Fr as ScalarField;
+    use ark_std::rand::Rng;
+    use ark_std::test_rng;
+
+    fn build_tree(size: usize) -> (CommitmentMerkleTree, Vec<Commitment>) {
+        let mut rng = test_rng();
+        let params = CommitmentParams::setup(&mut rng).unwrap();
+        let mut chain = CommitmentChain::new(params);
+        for i in 1..=size {
+            chain.push(ScalarField::from(i as u64), &mut rng).unwrap();
+        }
+        (CommitmentMerkleTree::from_chain(&chain).unwrap(), chain.commitments().to_vec())
+    }
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("archimedes-{}-{}.frozen", name, std::process::id()))
+    }
+
+    #[test]
+    fn test_freeze_open_round_trip() {
+        let (tree, commitments) = build_tree(13);
+        let path = temp_path("round-trip");
+        let mut file = File::create(&path).unwrap();
+        tree.freeze_with_aggregates_to(&mut file).unwrap();
+        drop(file);
+
+        let frozen = FrozenTree::open(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(frozen.root_hash(), tree.root_hash());
+        assert_eq!(frozen.committed_root(), tree.committed_root());
+        assert_eq!(frozen.leaf_count(), 13);
+        assert_eq!(frozen.depth(), tree.depth());
+        assert!(frozen.verify_integrity());
+        assert!(frozen.verify_leaf(&commitments[7], 7).unwrap());
+        let root_agg = frozen.node_aggregate(frozen.depth(), 0).unwrap();
+        assert_eq!(root_agg.commitment, tree.aggregate().commitment);
+        assert_eq!(root_agg.count, 13);
+        let leaves = frozen.hash_range(0, 2, 5).unwrap();
+        assert_eq!(leaves[0], MerkleNode::leaf(&commitments[2], 2).hash);
+    }
+
+    #[test]
+    fn test_frozen_proofs_match_live_tree() {
+        let (tree, _) = build_tree(100);
+        let mut bytes = Vec::new();
+        tree.freeze_to(&mut bytes).unwrap();
+        let hashes_only = FrozenTree::from_bytes(bytes).unwrap();
+        assert!(!hashes_only.has_aggregates());
+        assert!(matches!(
+            hashes_only.generate_proof(3),
+            Err(ArchimedesError::MerkleTreeError { kind: MerkleErrorKind::NoAggregates, .. })
+        ));
+        assert!(!hashes_only.verify_integrity());
+
+        let mut bytes = Vec::new();
+        tree.freeze_with_aggregates_to(&mut bytes).unwrap();
+        let frozen = FrozenTree::from_bytes(bytes).unwrap();
+
+        let mut rng = test_rng();
+        for _ in 0..25 {
+            let index = rng.gen_range(0..100);
+            let live = tree.generate_proof(index).unwrap();
+            let cold = frozen.generate_proof(index).unwrap();
+            assert_eq!(live.to_bytes(), cold.to_bytes());
+        }
+    }
+
+    #[test]
+    fn test_corrupted_frozen_tree_detected() {
+        let (tree, _) = build_tree(9);
+        let mut bytes = Vec::new();
+        tree.freeze_with_aggregates_to(&mut bytes).unwrap();
+        assert!(FrozenTree::from_bytes(bytes.clone()).unwrap().verify_integrity());
+
+        let mut bad_header = bytes.clone();
+        bad_header[17] ^= 1;
+        assert!(FrozenTree::from_bytes(bad_header).is_err());
+
+        let mut truncated = bytes.clone();
+        truncated.truncate(bytes.len() - 1);
+        assert!(FrozenTree::from_bytes(truncated).is_err());
+
+        let mut bad_body = bytes.clone();
+        let leaf_offset = FIXED_HEADER_LEN + 8 * (tree.depth() + 1) + HASH_LEN;
+        bad_body[leaf_offset] ^= 1;
+        let frozen = FrozenTree::from_bytes(bad_body).unwrap();
+        assert!(!frozen.verify_integrity());
+    }
+
+    #[test]
+    fn test_inconsistent_level_sizes_rejected() {
+        // A checksummed header whose middle level is too wide for its leaves.
+        let level_lens = [2u64, 5, 1];
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&FROZEN_MAGIC);
+        bytes.extend_from_slice(&FROZEN_VERSION.to_le_bytes());
+        bytes.extend_from_slice(&0u32.to_le_bytes());
+        bytes.extend_from_slice(&2u64.to_le_bytes());
+        bytes.extend_from_slice(&(level_lens.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&0u32.to_le_bytes());
+        bytes.extend_from_slice(&[0u8; HASH_LEN]);
+        for len in level_lens {
+            bytes.extend_from_slice(&len.to_le_bytes());
+        }
+        let checksum: [u8; 32] = Sha256::digest(&bytes).into();
+        bytes.extend_from_slice(&checksum);
+        bytes.resize(bytes.len() + 8 * HASH_LEN, 0);
+
+        assert!(matches!(
+            FrozenTree::from_bytes(bytes),
+            Err(ArchimedesError::SerializationError { kind: SerializationErrorKind::CorruptedFrozenTree(_), .. })
+        ));
+    }
+}
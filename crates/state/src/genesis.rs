@@ -0,0 +1,229 @@
+use std::collections::BTreeMap;
+
+use archimedes_core::{ArchimedesError, SerializationErrorKind, StateEncodingErrorKind};
+use serde::Deserialize;
+
+use crate::encoding::AccountState;
+use crate::state_batch::{Address, StateBatch};
+use crate::storage_trie::StorageTrie;
+
+type Result<T> = std::result::Result<T, ArchimedesError>;
+
+/// Initial accounts for a chain. Accounts are kept in address order and
+/// allocations to one address add up, so the root depends only on what was
+/// allocated, not the order it was listed in. Setting code or a storage
+/// slot twice keeps the last value.
+#[derive(Clone, Debug, Default)]
+pub struct GenesisBuilder {
+    accounts: BTreeMap<Address, GenesisAccount>,
+}
+
+#[derive(Clone, Debug, Default)]
+struct GenesisAccount {
+    allocations: Vec<u128>,
+    code: Vec<u8>,
+    storage: StorageTrie,
+}
+
+// Allocation file layout. Balances are decimal strings, since JSON numbers
+// do not reliably carry 128 bits; everything else is hex.
+#[derive(Deserialize)]
+struct GenesisFile {
+    accounts: Vec<GenesisFileAccount>,
+}
+
+#[derive(Deserialize)]
+struct GenesisFileAccount {
+    address: String,
+    balance: String,
+    #[serde(default)]
+    code: String,
+    #[serde(default)]
+    storage: BTreeMap<String, String>,
+}
+
+impl GenesisBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn allocate(mut self, address: Address, balance: u128) -> Self {
+        self.accounts.entry(address).or_default().allocations.push(balance);
+        self
+    }
+
+    pub fn with_code(mut self, address: Address, code: &[u8]) -> Self {
+        self.accounts.entry(address).or_default().code = code.to_vec();
+        self
+    }
+
+    pub fn with_storage(mut self, address: Address, key: [u8; 32], value: [u8; 32]) -> Self {
+        self.accounts.entry(address).or_default().storage.insert(key, value);
+        self
+    }
+
+    /// Parses an allocation file:
+    ///
+    /// ```text
+    /// { "accounts": [ { "address": "<32-byte hex>", "balance": "1000",
+    ///                   "code": "<hex>", "storage": { "<32-byte hex>": "<32-byte hex>" } } ] }
+    /// ```
+    ///
+    /// `code` and `storage` are optional. An address may appear more than once;
+    /// its balances add up and an entry without `code` keeps the earlier code.
+    pub fn from_json(json: &str) -> Result<Self> {
+        let file: GenesisFile =
+            serde_json::from_str(json).map_err(|_| ArchimedesError::serialization(SerializationErrorKind::Encoding))?;
+        let mut builder = Self::new();
+        for account in file.accounts {
+            let address = Address(hex_array(&account.address)?);
+            let balance = account
+                .balance
+                .parse()
+                .map_err(|_| ArchimedesError::serialization(SerializationErrorKind::Encoding))?;
+            builder = builder.allocate(address, balance);
+            let code = hex_bytes(&account.code)?;
+            if !code.is_empty() {
+                builder = builder.with_code(address, &code);
+            }
+            for (key, value) in &account.storage {
+                builder = builder.with_storage(address, hex_array(key)?, hex_array(value)?);
+            }
+        }
+        Ok(builder)
+    }
+
+    /// The genesis accounts and their `StateBatch::state_root`.
+    pub fn build(&self) -> Result<(StateBatch, [u8; 32])> {
+        let batch = self
+            .accounts
+            .iter()
+            .map(|(address, account)| {
+                let balance = account
+                    .allocations
+                    .iter()
+                    .try_fold(0u128, |total, &b| total.checked_add(b))
+                    .ok_or(ArchimedesError::StateEncodingError(StateEncodingErrorKind::GenesisOverflow))?;
+                let mut state = AccountState::new(balance, 0);
+                state.set_code(&account.code);
+                state.storage_root = account.storage.root();
+                Ok((*address, state))
+            })
+            .collect::<Result<StateBatch>>()?;
+        let root = batch.state_root()?;
+        Ok((batch, root))
+    }
+}
+
+fn hex_bytes(s: &str) -> Result<Vec<u8>> {
+    let s = s.strip_prefix("0x").unwrap_or(s);
+    if !s.len().is_multiple_of(2) || !s.is_ascii() {
+        return Err(ArchimedesError::serialization(SerializationErrorKind::InvalidHex));
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16))
+        .collect::<std::result::Result<Vec<u8>, _>>()
+        .map_err(|_| ArchimedesError::serialization(SerializationErrorKind::InvalidHex))
+}
+
+fn hex_array(s: &str) -> Result<[u8; 32]> {
+    hex_bytes(s)?
+        .try_into()
+        .map_err(|_| ArchimedesError::serialization(SerializationErrorKind::InvalidHex))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_root_independent_of_allocation_order() {
+        let (a, b, c) = (Address([1; 32]), Address([2; 32]), Address([3; 32]));
+        let (batch, root) = GenesisBuilder::new()
+            .allocate(a, 100)
+            .with_code(b, b"code")
+            .allocate(b, 50)
+            .with_storage(c, [7; 32], [8; 32])
+            .allocate(a, 25)
+            .build()
+            .unwrap();
+        let (reordered, reordered_root) = GenesisBuilder::new()
+            .with_storage(c, [7; 32], [8; 32])
+            .allocate(a, 25)
+            .allocate(b, 50)
+            .allocate(a, 100)
+            .with_code(b, b"code")
+            .build()
+            .unwrap();
+        assert_eq!(root, reordered_root);
+        assert_eq!(batch, reordered);
+        assert_eq!(root, batch.state_root().unwrap());
+        assert_eq!(batch.get(&a).unwrap().balance, 125);
+        assert!(batch.get(&b).unwrap().verify_code(b"code"));
+        assert_eq!(batch.get(&c).unwrap().balance, 0);
+        assert_ne!(batch.get(&c).unwrap().storage_root, [0; 32]);
+
+        let (_, other) = GenesisBuilder::new().allocate(a, 125).allocate(b, 50).build().unwrap();
+        assert_ne!(other, root);
+        assert!(GenesisBuilder::new().build().is_err());
+        assert!(matches!(
+            GenesisBuilder::new().allocate(a, u128::MAX).allocate(a, 1).build(),
+            Err(ArchimedesError::StateEncodingError(StateEncodingErrorKind::GenesisOverflow))
+        ));
+    }
+
+    #[test]
+    fn test_from_json() {
+        let json = format!(
+            r#"{{"accounts": [
+                {{"address": "{b}", "balance": "50", "code": "0x636f6465"}},
+                {{"address": "{a}", "balance": "340282366920938463463374607431768211455"}},
+                {{"address": "{c}", "balance": "0", "storage": {{"{k}": "{v}"}}}}
+            ]}}"#,
+            a = "01".repeat(32),
+            b = "02".repeat(32),
+            c = "03".repeat(32),
+            k = "07".repeat(32),
+            v = "08".repeat(32),
+        );
+        let (batch, root) = GenesisBuilder::from_json(&json).unwrap().build().unwrap();
+        let (_, expected) = GenesisBuilder::new()
+            .allocate(Address([1; 32]), u128::MAX)
+            .allocate(Address([2; 32]), 50)
+            .with_code(Address([2; 32]), b"code")
+            .allocate(Address([3; 32]), 0)
+            .with_storage(Address([3; 32]), [7; 32], [8; 32])
+            .build()
+            .unwrap();
+        assert_eq!(root, expected);
+        assert_eq!(batch.len(), 3);
+
+        assert!(GenesisBuilder::from_json(r#"{"accounts": [{"address": "01", "balance": "1"}]}"#).is_err());
+        assert!(GenesisBuilder::from_json(r#"{"accounts": [{"address": "zz", "balance": "1"}]}"#).is_err());
+        assert!(GenesisBuilder::from_json(&json.replace("\"50\"", "\"-1\"")).is_err());
+        assert!(GenesisBuilder::from_json("[]").is_err());
+    }
+
+    #[test]
+    fn test_from_json_repeated_address() {
+        let json = format!(
+            r#"{{"accounts": [
+                {{"address": "{a}", "balance": "50", "code": "0x636f6465"}},
+                {{"address": "{a}", "balance": "7", "storage": {{"{k}": "{v}"}}}}
+            ]}}"#,
+            a = "02".repeat(32),
+            k = "07".repeat(32),
+            v = "08".repeat(32),
+        );
+        let (batch, root) = GenesisBuilder::from_json(&json).unwrap().build().unwrap();
+        let (_, expected) = GenesisBuilder::new()
+            .allocate(Address([2; 32]), 57)
+            .with_code(Address([2; 32]), b"code")
+            .with_storage(Address([2; 32]), [7; 32], [8; 32])
+            .build()
+            .unwrap();
+        assert_eq!(root, expected);
+        assert_eq!(batch.len(), 1);
+    }
+}
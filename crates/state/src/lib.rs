@@ -1,6 +1,95 @@
+pub mod account_proof;
+pub mod address;
+#[cfg(feature = "prover")]
+pub mod code;
+#[cfg(feature = "prover")]
+pub mod compact;
+#[cfg(feature = "prover")]
+pub mod diff;
+#[cfg(feature = "prover")]
+pub mod divergence;
 pub mod encoding;
+#[cfg(feature = "prover")]
+pub mod frozen;
+#[cfg(feature = "prover")]
+pub mod genesis;
+#[cfg(feature = "prover")]
 pub mod merkle;
+pub mod merkle_proof;
+#[cfg(feature = "prover")]
+pub mod operation;
+#[cfg(feature = "prover")]
+pub mod ordering;
+#[cfg(feature = "poseidon")]
+pub mod poseidon;
+#[cfg(feature = "prover")]
+pub mod persist;
+#[cfg(feature = "prover")]
+pub mod serialized;
+#[cfg(feature = "prover")]
+pub mod state_batch;
+#[cfg(feature = "prover")]
+pub mod state_commitment;
+#[cfg(feature = "prover")]
+pub mod state_manager;
+#[cfg(feature = "prover")]
+pub mod storage_trie;
+#[cfg(feature = "prover")]
+pub mod transition_batch;
+#[cfg(feature = "prover")]
+pub mod transition_chain;
+#[cfg(feature = "prover")]
+pub mod world_state;
 
-pub use encoding::{AccountState, StateTransition, bytes_to_field, encode_state_batch, encode_transitions};
-pub use merkle::{CommitmentMerkleTree, MerkleNode, MerkleProof};
-
+pub use account_proof::{account_leaf_hash, AccountNeighbor, AccountProof};
+pub use address::Address;
+#[cfg(feature = "prover")]
+pub use code::{code_hash, CodeStore, EMPTY_CODE_HASH};
+#[cfg(feature = "prover")]
+pub use compact::CompactCommitmentMerkleTree;
+#[cfg(feature = "prover")]
+pub use diff::{Delta, StateDiff};
+#[cfg(feature = "prover")]
+pub use divergence::DivergenceFinder;
+pub use encoding::{
+    AccountState, CommitmentEncoding, HashScheme, StateTransition, TransferTransition, bytes_to_field, bytes_to_fields,
+    encode_state_batch, encode_transitions, encode_transitions_chunked, fields_to_bytes,
+    ACCOUNT_STATE_ENCODED_LEN, STATE_ENCODING_VERSION, STATE_TRANSITION_ENCODED_LEN, TRANSFER_TRANSITION_ENCODED_LEN,
+};
+#[cfg(feature = "prover")]
+pub use frozen::FrozenTree;
+#[cfg(feature = "prover")]
+pub use genesis::GenesisBuilder;
+#[cfg(feature = "prover")]
+pub use merkle::{
+    CommitmentMerkleTree, ConsistencyProof, CoverNode, MerkleMultiProof, PedersenMerkleHasher, RangeAggregateProof,
+    StreamedRoot, SubtreeLinkProof, ToMerkleTree,
+};
+pub use merkle_proof::{
+    committed_root, AggregatePathProof, MerkleHasher, MerkleNode, MerkleProof, Sha256Hasher, MAX_PROOF_DEPTH,
+    MERKLE_FORMAT_VERSION, MERKLE_PADDING,
+};
+#[cfg(feature = "prover")]
+pub use operation::TransitionOperation;
+#[cfg(feature = "prover")]
+pub use ordering::{BatchOrdering, BatchTx};
+#[cfg(feature = "poseidon")]
+pub use poseidon::PoseidonMerkleHasher;
+#[cfg(feature = "prover")]
+pub use persist::{PERSIST_MANIFEST_MAGIC, PERSIST_PAGE_LEAVES, PERSIST_VERSION};
+#[cfg(feature = "prover")]
+pub use serialized::{SerializedTree, SERIALIZED_TREE_MAGIC, SERIALIZED_TREE_VERSION};
+#[cfg(feature = "prover")]
+pub use state_batch::{encode_addressed_state_batch, StateBatch};
+#[cfg(feature = "prover")]
+pub use state_commitment::{commit_state_with, verify_state_with, StateCommitment, StateOpening, STATE_COMMITMENT_WIDTH};
+#[cfg(feature = "prover")]
+pub use state_manager::{SnapshotId, StateManager};
+#[cfg(feature = "prover")]
+pub use storage_trie::{StorageProof, StorageTrie};
+#[cfg(feature = "prover")]
+pub use transition_batch::{TransitionBatch, TransitionViolation, TransitionViolationKind};
+#[cfg(feature = "prover")]
+pub use transition_chain::TransitionChain;
+#[cfg(feature = "prover")]
+pub use world_state::WorldState;
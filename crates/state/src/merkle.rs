@@ -1,69 +1,292 @@
-use archimedes_core::{AggregateCommitment, ArchimedesError, Commitment};
-use sha2::{Digest, Sha256};
+use archimedes_core::{
+    AggregateCommitment, ArchimedesError, Commitment, CommitmentChain, CommitmentParams, MerkleErrorKind,
+    PedersenHasher, PublicCommitmentChain, RangedAggregate,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::compact::CompactCommitmentMerkleTree;
+use crate::merkle_proof::{AGGREGATE_LEN, AGGREGATE_NODE_PREFIX, INTERNAL_PREFIX, LEAF_PREFIX};
+pub(crate) use crate::merkle_proof::LEAF_POINT_LEN;
+pub use crate::merkle_proof::{
+    committed_root, AggregatePathProof, MerkleHasher, MerkleNode, MerkleProof, Sha256Hasher, MERKLE_FORMAT_VERSION,
+    MERKLE_PADDING,
+};
+use subtle::ConstantTimeEq;
 
 type Result<T> = std::result::Result<T, ArchimedesError>;
 
+/// A Merkle tree over commitments whose nodes also carry aggregates. Node
+/// hashes come from `H`; the SHA-256 default is what committed roots in
+/// headers, persistence and the dispute protocol use.
 #[derive(Clone, Debug)]
-pub struct MerkleNode {
-    pub hash: [u8; 32],
-    pub aggregate: AggregateCommitment,
+pub struct CommitmentMerkleTree<H: MerkleHasher = Sha256Hasher> {
+    nodes: Vec<Vec<MerkleNode>>,
+    leaf_count: usize,
+    hasher: H,
+    sorted: bool,
 }
 
+/// Root and size of a tree built by `CommitmentMerkleTree::build_streaming`.
 #[derive(Clone, Debug)]
-pub struct CommitmentMerkleTree {
-    nodes: Vec<Vec<MerkleNode>>,
-    leaf_count: usize,
+pub struct StreamedRoot {
+    pub root: MerkleNode,
+    pub leaf_count: usize,
 }
 
-impl MerkleNode {
-    pub fn leaf(commitment: &Commitment, index: usize) -> Self {
-        let mut hasher = Sha256::new();
-        hasher.update(index.to_be_bytes());
-        let mut commitment_bytes = Vec::new();
-        ark_serialize::CanonicalSerialize::serialize_compressed(&commitment.0, &mut commitment_bytes).unwrap();
-        hasher.update(&commitment_bytes);
-        Self {
-            hash: hasher.finalize().into(),
-            aggregate: AggregateCommitment::from_commitments(&[commitment.clone()]),
-        }
+impl StreamedRoot {
+    pub fn root_hash(&self) -> [u8; 32] {
+        self.root.hash
     }
 
-    pub fn internal(left: &MerkleNode, right: &MerkleNode) -> Self {
-        let mut hasher = Sha256::new();
-        hasher.update(left.hash);
-        hasher.update(right.hash);
-        Self {
-            hash: hasher.finalize().into(),
-            aggregate: left.aggregate.merge(&right.aggregate),
-        }
+    pub fn committed_root(&self) -> [u8; 32] {
+        committed_root(self.root.hash, self.leaf_count)
+    }
+
+    pub fn aggregate(&self) -> &AggregateCommitment {
+        &self.root.aggregate
+    }
+}
+
+/// Pedersen node hashing, cheaper to open inside a circuit than SHA-256.
+/// Uses the same prefixes as `Sha256Hasher`.
+#[derive(Clone, Debug)]
+pub struct PedersenMerkleHasher(PedersenHasher);
+
+impl PedersenMerkleHasher {
+    const MAX_INPUT: usize = 1 + 2 * 32 + AGGREGATE_LEN;
+
+    pub fn new(params: &CommitmentParams) -> Result<Self> {
+        Ok(Self(PedersenHasher::new(params, Self::MAX_INPUT)?))
+    }
+}
+
+impl MerkleHasher for PedersenMerkleHasher {
+    fn hash_leaf(&self, index: usize, commitment: &[u8]) -> [u8; 32] {
+        let mut input = vec![LEAF_PREFIX];
+        input.extend_from_slice(&(index as u64).to_be_bytes());
+        input.extend_from_slice(commitment);
+        self.0.hash_bytes(&input).expect("leaf preimage fits hasher capacity")
+    }
+
+    fn hash_internal(&self, left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+        self.0.hash_bytes(&[&[INTERNAL_PREFIX][..], left, right].concat()).expect("node preimage fits hasher capacity")
+    }
+
+    fn hash_node(&self, left: &[u8; 32], right: &[u8; 32], aggregate: &[u8]) -> [u8; 32] {
+        self.0
+            .hash_bytes(&[&[AGGREGATE_NODE_PREFIX][..], left, right, aggregate].concat())
+            .expect("node preimage fits hasher capacity")
+    }
+}
+
+/// Builds the default Merkle tree over a chain's public commitments.
+pub trait ToMerkleTree {
+    fn to_merkle_tree(&self) -> Result<CommitmentMerkleTree>;
+}
+
+impl ToMerkleTree for PublicCommitmentChain {
+    fn to_merkle_tree(&self) -> Result<CommitmentMerkleTree> {
+        CommitmentMerkleTree::from_public_chain(self)
     }
 }
 
 impl CommitmentMerkleTree {
+    /// Builds over the chain's commitments and checks the root aggregate
+    /// against the chain's running aggregate.
+    pub fn from_chain(chain: &CommitmentChain) -> Result<Self> {
+        Self::build_checked(chain.commitments(), &chain.aggregate())
+    }
+
+    /// As `from_chain`, against the public chain's cached aggregate.
+    pub fn from_public_chain(chain: &PublicCommitmentChain) -> Result<Self> {
+        Self::build_checked(chain.commitments(), &chain.aggregate())
+    }
+
+    fn build_checked(commitments: &[Commitment], expected: &AggregateCommitment) -> Result<Self> {
+        let tree = Self::build(commitments)?;
+        if !tree.aggregate_matches(expected) {
+            return Err(ArchimedesError::MerkleTreeError {
+                index: 0,
+                leaf_count: tree.leaf_count,
+                kind: MerkleErrorKind::AggregateMismatch,
+            });
+        }
+        Ok(tree)
+    }
+
     pub fn build(commitments: &[Commitment]) -> Result<Self> {
+        Self::build_with_hasher(commitments, &Sha256Hasher)
+    }
+
+    /// Commits to the set of `commitments`: leaves are sorted by canonical
+    /// compressed encoding and deduplicated, so any ordering of the same set
+    /// gives the same root.
+    pub fn build_sorted(commitments: &[Commitment]) -> Result<Self> {
+        let mut leaves = commitments.to_vec();
+        leaves.sort_by_cached_key(Commitment::to_bytes);
+        leaves.dedup();
+        let mut tree = Self::build(&leaves)?;
+        tree.sorted = true;
+        Ok(tree)
+    }
+
+    /// Root of the tree `build` would produce over the same commitments,
+    /// holding at most one pending node per level instead of the whole tree.
+    /// `expected_len`, if given, must match the number of commitments.
+    pub fn build_streaming(commitments: impl Iterator<Item = Commitment>, expected_len: Option<usize>) -> Result<StreamedRoot> {
+        let mut pending: Vec<Option<MerkleNode>> =
+            Vec::with_capacity(expected_len.map_or(0, |n| n.next_power_of_two().trailing_zeros() as usize + 1));
+        let mut leaf_count = 0;
+        for commitment in commitments {
+            let mut carry = MerkleNode::leaf(&commitment, leaf_count);
+            leaf_count += 1;
+            let mut level = 0;
+            loop {
+                if level == pending.len() {
+                    pending.push(None);
+                }
+                match pending[level].take() {
+                    Some(left) => carry = MerkleNode::internal(&left, &carry)?,
+                    None => {
+                        pending[level] = Some(carry);
+                        break;
+                    }
+                }
+                level += 1;
+            }
+        }
+        if let Some(expected) = expected_len.filter(|&n| n != leaf_count) {
+            return Err(ArchimedesError::MerkleTreeError {
+                index: 0,
+                leaf_count,
+                kind: MerkleErrorKind::LeafCountMismatch { expected, found: leaf_count },
+            });
+        }
+        if leaf_count == 0 {
+            return Err(ArchimedesError::MerkleTreeError {
+                index: 0,
+                leaf_count: 0,
+                kind: MerkleErrorKind::EmptyTree,
+            });
+        }
+        // Below the root level every leftover node is the last on its level:
+        // it either absorbs the carry from below as its right child or is padded.
+        let depth = leaf_count.next_power_of_two().trailing_zeros() as usize;
+        pending.resize(depth + 1, None);
+        let mut carry: Option<MerkleNode> = None;
+        for slot in &mut pending[..depth] {
+            carry = match (slot.take(), carry) {
+                (Some(left), Some(right)) => Some(MerkleNode::internal(&left, &right)?),
+                (Some(lone), None) | (None, Some(lone)) => Some(MerkleNode::padded(&lone)),
+                (None, None) => None,
+            };
+        }
+        let root = carry.or_else(|| pending[depth].take()).expect("a non-empty stream leaves a root");
+        Ok(StreamedRoot { root, leaf_count })
+    }
+
+}
+
+impl<H: MerkleHasher> CommitmentMerkleTree<H> {
+    /// Builds with a non-default node hash, kept with the tree. Proofs from
+    /// such a tree must be checked with `AggregatePathProof::verify_with` and
+    /// the same hasher.
+    pub fn build_with_hasher(commitments: &[Commitment], hasher: &H) -> Result<Self> {
         if commitments.is_empty() {
-            return Err(ArchimedesError::MerkleTreeError("Cannot build empty tree".to_string()));
+            return Err(ArchimedesError::MerkleTreeError {
+                index: 0,
+                leaf_count: 0,
+                kind: MerkleErrorKind::EmptyTree,
+            });
         }
-        let leaf_count = commitments.len();
+        let affine = Commitment::batch_to_affine(commitments);
         let leaves: Vec<MerkleNode> = commitments
             .iter()
+            .zip(&affine)
             .enumerate()
-            .map(|(i, c)| MerkleNode::leaf(c, i))
+            .map(|(i, (c, a))| MerkleNode::leaf_with_hasher(hasher, c, a, i))
             .collect();
+        Self::from_leaves(leaves, hasher)
+    }
+
+    fn from_leaves(leaves: Vec<MerkleNode>, hasher: &H) -> Result<Self> {
+        let leaf_count = leaves.len();
         let mut nodes = vec![leaves];
         while nodes.last().unwrap().len() > 1 {
             let prev_level = nodes.last().unwrap();
-            let mut next_level = Vec::new();
-            for chunk in prev_level.chunks(2) {
-                if chunk.len() == 2 {
-                    next_level.push(MerkleNode::internal(&chunk[0], &chunk[1]));
-                } else {
-                    next_level.push(chunk[0].clone());
-                }
-            }
+            let aggregates = prev_level
+                .chunks(2)
+                .map(|chunk| match chunk {
+                    [left, right] => left.aggregate.merge(&right.aggregate),
+                    _ => Ok(chunk[0].aggregate.clone()),
+                })
+                .collect::<Result<Vec<_>>>()?;
+            // Node hashes take the aggregate's compressed form; normalize the
+            // whole level with one inversion.
+            let points: Vec<Commitment> = aggregates.iter().map(|aggregate| aggregate.commitment.clone()).collect();
+            let affine = Commitment::batch_to_affine(&points);
+            let next_level = prev_level
+                .chunks(2)
+                .zip(aggregates)
+                .zip(&affine)
+                .map(|((chunk, aggregate), affine)| {
+                    let right = chunk.get(1).map_or(&MERKLE_PADDING, |node| &node.hash);
+                    MerkleNode::parent(hasher, &chunk[0].hash, right, aggregate, affine)
+                })
+                .collect();
             nodes.push(next_level);
         }
-        Ok(Self { nodes, leaf_count })
+        Ok(Self {
+            nodes,
+            leaf_count,
+            hasher: hasher.clone(),
+            sorted: false,
+        })
+    }
+
+    pub fn hasher(&self) -> &H {
+        &self.hasher
+    }
+
+    /// Approximate heap and inline bytes held by the tree.
+    pub fn memory_estimate(&self) -> usize {
+        std::mem::size_of::<Self>()
+            + self.nodes.capacity() * std::mem::size_of::<Vec<MerkleNode>>()
+            + self.nodes.iter().map(|level| level.capacity() * std::mem::size_of::<MerkleNode>()).sum::<usize>()
+    }
+
+    /// Drops the internal levels, keeping the leaves and root.
+    pub fn into_compact(mut self) -> CompactCommitmentMerkleTree<H> {
+        let root = self.root().clone();
+        self.nodes.truncate(1);
+        let leaves = self.nodes.pop().expect("a tree always has a leaf level");
+        CompactCommitmentMerkleTree::from_parts(leaves, root, self.hasher)
+    }
+
+    /// Whether the root aggregate still matches `chain.aggregate()`.
+    pub fn is_consistent_with(&self, chain: &CommitmentChain) -> bool {
+        self.aggregate_matches(&chain.aggregate())
+    }
+
+    /// Constant-time comparison of the two raw roots.
+    pub fn same_root(&self, other: &Self) -> bool {
+        self.root_matches(other.root_hash())
+    }
+
+    /// Constant-time comparison against a raw root.
+    pub fn root_matches(&self, claimed: [u8; 32]) -> bool {
+        self.root_hash().ct_eq(&claimed).into()
+    }
+
+    /// Checks the count as well as the point, so a claim with the right sum
+    /// over the wrong number of leaves is rejected.
+    pub fn aggregate_matches(&self, claimed: &AggregateCommitment) -> bool {
+        aggregates_match(self.aggregate(), claimed)
+    }
+
+    /// `aggregate_matches` for leaves `start..end`.
+    pub fn range_aggregate_matches(&self, start: usize, end: usize, claimed: &AggregateCommitment) -> Result<bool> {
+        Ok(aggregates_match(&self.range_aggregate(start, end)?, claimed))
     }
 
     pub fn root(&self) -> &MerkleNode {
@@ -74,35 +297,339 @@ impl CommitmentMerkleTree {
         self.root().hash
     }
 
+    pub fn committed_root(&self) -> [u8; 32] {
+        committed_root(self.root_hash(), self.leaf_count)
+    }
+
     pub fn aggregate(&self) -> &AggregateCommitment {
         &self.root().aggregate
     }
 
+    /// Levels above the leaves; every proof path has this many siblings.
+    pub fn depth(&self) -> usize {
+        self.nodes.len() - 1
+    }
+
+    /// Nodes on `level`, leaves first at level 0.
+    pub fn level(&self, level: usize) -> Result<&[MerkleNode]> {
+        self.nodes.get(level).map(Vec::as_slice).ok_or(ArchimedesError::MerkleTreeError {
+            index: 0,
+            leaf_count: self.leaf_count,
+            kind: MerkleErrorKind::NodeOutOfBounds { level },
+        })
+    }
+
+    pub fn level_len(&self, level: usize) -> Result<usize> {
+        self.level(level).map(<[MerkleNode]>::len)
+    }
+
+    pub fn node(&self, level: usize, index: usize) -> Result<&MerkleNode> {
+        self.level(level)?.get(index).ok_or(ArchimedesError::MerkleTreeError {
+            index,
+            leaf_count: self.leaf_count,
+            kind: MerkleErrorKind::NodeOutOfBounds { level },
+        })
+    }
+
+    pub fn node_hash(&self, level: usize, index: usize) -> Result<[u8; 32]> {
+        self.node(level, index).map(|node| node.hash)
+    }
+
+    pub fn leaf_hash(&self, index: usize) -> Result<[u8; 32]> {
+        self.check_leaf_index(index)?;
+        Ok(self.nodes[0][index].hash)
+    }
+
+    pub fn leaf_aggregate(&self, index: usize) -> Result<&AggregateCommitment> {
+        self.check_leaf_index(index)?;
+        Ok(&self.nodes[0][index].aggregate)
+    }
+
     pub fn range_aggregate(&self, start: usize, end: usize) -> Result<AggregateCommitment> {
+        self.ranged_aggregate(start, end).map(|r| r.agg)
+    }
+
+    pub fn ranged_aggregate(&self, start: usize, end: usize) -> Result<RangedAggregate> {
         if end > self.leaf_count || start >= end {
-            return Err(ArchimedesError::MerkleTreeError("Invalid range".to_string()));
+            return Err(ArchimedesError::MerkleTreeError {
+                index: start,
+                leaf_count: self.leaf_count,
+                kind: MerkleErrorKind::InvalidRange { end, level: 0 },
+            });
         }
-        let mut agg = AggregateCommitment::empty();
-        for i in start..end {
-            agg = agg.merge(&self.nodes[0][i].aggregate);
+        // Bottom-up cover of [start, end) by whole subtrees: node `i` on level
+        // `l` spans leaves `i << l .. (i + 1) << l`, clipped to the leaf count,
+        // so at most two nodes per level are merged.
+        let mut commitment = Commitment::zero();
+        let (mut lo, mut hi) = (start, end);
+        for level in &self.nodes {
+            if lo >= hi {
+                break;
+            }
+            if !lo.is_multiple_of(2) {
+                commitment += &level[lo].aggregate.commitment;
+                lo += 1;
+            }
+            if !hi.is_multiple_of(2) && lo < hi {
+                hi -= 1;
+                commitment += &level[hi].aggregate.commitment;
+            }
+            lo /= 2;
+            hi /= 2;
         }
-        Ok(agg)
+        Ok(RangedAggregate {
+            start,
+            end,
+            agg: AggregateCommitment {
+                commitment,
+                count: end - start,
+            },
+        })
     }
 
-    pub fn generate_proof(&self, index: usize) -> Result<MerkleProof> {
+    /// Aggregate of leaves `from..`, taken as the whole-tree aggregate minus
+    /// the prefix.
+    pub fn suffix_aggregate(&self, from: usize) -> Result<AggregateCommitment> {
+        if from == 0 {
+            return Ok(self.aggregate().clone());
+        }
+        self.aggregate().checked_sub(&self.range_aggregate(0, from)?)
+    }
+
+    /// First leaf index where the two trees differ, found by descending only
+    /// into mismatching subtrees. With unequal leaf counts and an agreeing
+    /// prefix, the shorter count is returned. Both trees must use the same
+    /// node hasher.
+    pub fn find_divergence(&self, other: &CommitmentMerkleTree<H>) -> Option<usize> {
+        let shared = self.leaf_count.min(other.leaf_count);
+        let top = self.nodes.len().min(other.nodes.len()) - 1;
+        self.diverging_leaf(other, shared, top, 0)
+            .or_else(|| (self.leaf_count != other.leaf_count).then_some(shared))
+    }
+
+    /// Searches node `index` on `level`, which spans leaves
+    /// `index << level .. (index + 1) << level`, restricted to `..shared`.
+    fn diverging_leaf(&self, other: &CommitmentMerkleTree<H>, shared: usize, level: usize, index: usize) -> Option<usize> {
+        let start = index << level;
+        if start >= shared {
+            return None;
+        }
+        let fully_shared = (index + 1) << level <= shared;
+        if fully_shared && self.nodes[level][index].hash == other.nodes[level][index].hash {
+            return None;
+        }
+        if level == 0 {
+            return Some(index);
+        }
+        self.diverging_leaf(other, shared, level - 1, 2 * index)
+            .or_else(|| self.diverging_leaf(other, shared, level - 1, 2 * index + 1))
+    }
+
+    fn check_leaf_index(&self, index: usize) -> Result<()> {
         if index >= self.leaf_count {
-            return Err(ArchimedesError::MerkleTreeError("Index out of bounds".to_string()));
+            return Err(ArchimedesError::MerkleTreeError {
+                index,
+                leaf_count: self.leaf_count,
+                kind: MerkleErrorKind::IndexOutOfBounds,
+            });
+        }
+        Ok(())
+    }
+
+    /// Leaf position of `commitment` in a tree from `build_sorted`; `None` if
+    /// it is absent or the tree was not built sorted.
+    pub fn sorted_index_of(&self, commitment: &Commitment) -> Option<usize> {
+        self.sorted_search(commitment).ok()?.ok()
+    }
+
+    /// Inclusion proof for `commitment` in a tree from `build_sorted`. For an
+    /// absent commitment the error's index is where it would sort, so the
+    /// leaves either side of it bracket the gap. Fails with `NotSorted` on
+    /// any other tree.
+    pub fn prove_membership(&self, commitment: &Commitment) -> Result<AggregatePathProof> {
+        match self.sorted_search(commitment)? {
+            Ok(index) => self.generate_proof(index),
+            Err(index) => Err(ArchimedesError::MerkleTreeError {
+                index,
+                leaf_count: self.leaf_count,
+                kind: MerkleErrorKind::NotInSet,
+            }),
         }
+    }
+
+    /// `binary_search` over the sorted leaves.
+    fn sorted_search(&self, commitment: &Commitment) -> Result<std::result::Result<usize, usize>> {
+        if !self.sorted {
+            return Err(ArchimedesError::MerkleTreeError {
+                index: 0,
+                leaf_count: self.leaf_count,
+                kind: MerkleErrorKind::NotSorted,
+            });
+        }
+        let key = commitment.to_bytes();
+        Ok(self.nodes[0].binary_search_by(|leaf| leaf.aggregate.commitment.to_bytes().cmp(&key)))
+    }
+
+    pub fn generate_proof(&self, index: usize) -> Result<AggregatePathProof> {
+        self.check_leaf_index(index)?;
         let mut siblings = Vec::new();
         let mut current_index = index;
         for level in 0..self.nodes.len() - 1 {
-            let sibling_index = if current_index % 2 == 0 { current_index + 1 } else { current_index - 1 };
-            if sibling_index < self.nodes[level].len() {
-                siblings.push((self.nodes[level][sibling_index].hash, current_index % 2 == 0));
-            }
+            let (hash, aggregate) = self.sibling_of(level, current_index);
+            siblings.push((hash, aggregate, current_index.is_multiple_of(2)));
             current_index /= 2;
         }
-        Ok(MerkleProof { index, siblings })
+        Ok(AggregatePathProof { index, siblings })
+    }
+
+    /// Proofs for every leaf in index order, the same as calling
+    /// `generate_proof` on each.
+    pub fn generate_all_proofs(&self) -> Vec<AggregatePathProof> {
+        let indices: Vec<usize> = (0..self.leaf_count).collect();
+        self.proofs_for_checked(&indices)
+    }
+
+    /// Proofs for `indices`, in the order given.
+    pub fn generate_proofs_for(&self, indices: &[usize]) -> Result<Vec<AggregatePathProof>> {
+        for &index in indices {
+            self.check_leaf_index(index)?;
+        }
+        Ok(self.proofs_for_checked(indices))
+    }
+
+    fn proofs_for_checked(&self, indices: &[usize]) -> Vec<AggregatePathProof> {
+        let levels = 0..self.depth();
+        indices
+            .iter()
+            .map(|&index| {
+                let siblings = levels
+                    .clone()
+                    .map(|level| {
+                        let current = index >> level;
+                        let (hash, aggregate) = self.sibling_of(level, current);
+                        (hash, aggregate, current.is_multiple_of(2))
+                    })
+                    .collect();
+                AggregatePathProof { index, siblings }
+            })
+            .collect()
+    }
+
+    /// Hash and aggregate of the sibling of node `index` on `level`: the
+    /// padding constant with an empty aggregate past the end of the level.
+    fn sibling_of(&self, level: usize, index: usize) -> ([u8; 32], AggregateCommitment) {
+        match self.nodes[level].get(index ^ 1) {
+            Some(sibling) => (sibling.hash, sibling.aggregate.clone()),
+            None => (MERKLE_PADDING, AggregateCommitment::empty()),
+        }
+    }
+
+    /// One proof for several leaves, carrying each sibling only once.
+    /// Indices may be unsorted or repeated.
+    pub fn generate_multiproof(&self, indices: &[usize]) -> Result<MerkleMultiProof> {
+        let mut known: Vec<usize> = indices.to_vec();
+        known.sort_unstable();
+        known.dedup();
+        if let Some(&index) = known.iter().find(|&&i| i >= self.leaf_count) {
+            return Err(ArchimedesError::MerkleTreeError {
+                index,
+                leaf_count: self.leaf_count,
+                kind: MerkleErrorKind::IndexOutOfBounds,
+            });
+        }
+        let indices = known.clone();
+        let mut siblings = Vec::new();
+        for level in &self.nodes[..self.nodes.len() - 1] {
+            let mut parents = Vec::with_capacity(known.len());
+            let mut i = 0;
+            while i < known.len() {
+                let current = known[i];
+                let sibling = current ^ 1;
+                if current.is_multiple_of(2) && known.get(i + 1) == Some(&sibling) {
+                    i += 1;
+                } else if sibling < level.len() {
+                    // A missing sibling is `MERKLE_PADDING` and is not carried.
+                    siblings.push(level[sibling].clone());
+                }
+                parents.push(current / 2);
+                i += 1;
+            }
+            known = parents;
+        }
+        Ok(MerkleMultiProof { indices,
+            siblings,
+        })
+    }
+
+    pub fn prove_range_aggregate(&self, start: usize, end: usize) -> Result<RangeAggregateProof> {
+        self.range_aggregate(start, end)?;
+        let cover = range_cover(start, end, self.leaf_count)
+            .into_iter()
+            .map(|(level, index)| {
+                let node = &self.nodes[level][index];
+                if level == 0 {
+                    return CoverNode::Leaf(node.aggregate.commitment.clone());
+                }
+                let child = |i: usize| self.nodes[level - 1].get(i).map_or(MERKLE_PADDING, |n| n.hash);
+                CoverNode::Internal {
+                    left: child(2 * index),
+                    right: child(2 * index + 1),
+                    aggregate: node.aggregate.clone(),
+                }
+            })
+            .collect();
+        let outside = old_frontier(start)
+            .into_iter()
+            .chain(range_cover(end, self.leaf_count, self.leaf_count))
+            .map(|(level, index)| self.nodes[level][index].clone())
+            .collect();
+        Ok(RangeAggregateProof {
+            leaf_count: self.leaf_count,
+            cover,
+            outside,
+        })
+    }
+
+    /// Proves this tree extends its own first `old_leaf_count` leaves.
+    pub fn consistency_proof(&self, old_leaf_count: usize) -> Result<ConsistencyProof> {
+        if old_leaf_count == 0 || old_leaf_count > self.leaf_count {
+            return Err(ArchimedesError::MerkleTreeError {
+                index: old_leaf_count,
+                leaf_count: self.leaf_count,
+                kind: MerkleErrorKind::InvalidRange { end: old_leaf_count, level: 0 },
+            });
+        }
+        let nodes = old_frontier(old_leaf_count)
+            .into_iter()
+            .chain(range_cover(old_leaf_count, self.leaf_count, self.leaf_count))
+            .map(|(level, index)| self.nodes[level][index].clone())
+            .collect();
+        let delta = if old_leaf_count == self.leaf_count {
+            AggregateCommitment::empty()
+        } else {
+            self.range_aggregate(old_leaf_count, self.leaf_count)?
+        };
+        Ok(ConsistencyProof { nodes, delta })
+    }
+
+    /// The tree over leaves `start..end` alone, with a proof that it sits in
+    /// this one. Its leaves are indexed from 0, so it is the tree that
+    /// `build_with_hasher` gives for the same commitments.
+    pub fn extract_subtree(&self, start: usize, end: usize) -> Result<(Self, SubtreeLinkProof)> {
+        self.range_aggregate(start, end)?;
+        let leaves: Vec<Commitment> = self.nodes[0][start..end].iter().map(|n| n.aggregate.commitment.clone()).collect();
+        let outside = old_frontier(start)
+            .into_iter()
+            .chain(range_cover(end, self.leaf_count, self.leaf_count))
+            .map(|(level, index)| self.nodes[level][index].clone())
+            .collect();
+        let subtree = Self::build_with_hasher(&leaves, &self.hasher)?;
+        let link = SubtreeLinkProof {
+            leaf_count: self.leaf_count,
+            leaves,
+            outside,
+        };
+        Ok((subtree, link))
     }
 
     pub fn leaf_count(&self) -> usize {
@@ -110,36 +637,485 @@ impl CommitmentMerkleTree {
     }
 }
 
+/// Inclusion proof for a set of leaves. `siblings` holds the nodes not
+/// derivable from the proven leaves, level by level in ascending index order.
 #[derive(Clone, Debug)]
-pub struct MerkleProof {
-    pub index: usize,
-    pub siblings: Vec<([u8; 32], bool)>,
-}
-
-impl MerkleProof {
-    pub fn verify(&self, leaf_hash: [u8; 32], root_hash: [u8; 32]) -> bool {
-        let mut current = leaf_hash;
-        for (sibling, is_left) in &self.siblings {
-            let mut hasher = Sha256::new();
-            if *is_left {
-                hasher.update(current);
-                hasher.update(sibling);
-            } else {
-                hasher.update(sibling);
-                hasher.update(current);
+pub struct MerkleMultiProof {
+    pub indices: Vec<usize>,
+    pub siblings: Vec<MerkleNode>,
+}
+
+impl MerkleMultiProof {
+    /// Number of siblings carried, comparable to the summed
+    /// `siblings.len()` of single proofs.
+    pub fn proof_size(&self) -> usize {
+        self.siblings.len()
+    }
+
+    pub fn verify(&self, leaves: &[(usize, Commitment)], committed: [u8; 32], leaf_count: usize) -> bool {
+        self.verify_with(&Sha256Hasher, leaves, committed, leaf_count)
+    }
+
+    /// Verifies against a committed root in a tree of `leaf_count` leaves,
+    /// both from a trusted source. `leaves` must cover exactly the proven
+    /// indices; repeats are accepted only with identical commitments.
+    pub fn verify_with<H: MerkleHasher>(
+        &self,
+        hasher: &H,
+        leaves: &[(usize, Commitment)],
+        committed: [u8; 32],
+        leaf_count: usize,
+    ) -> bool {
+        self.fold_root(hasher, leaves, leaf_count)
+            .is_some_and(|root| committed_root(root.hash, leaf_count) == committed)
+    }
+
+    fn fold_root<H: MerkleHasher>(&self, hasher: &H, leaves: &[(usize, Commitment)], leaf_count: usize) -> Option<MerkleNode> {
+        let mut leaves: Vec<&(usize, Commitment)> = leaves.iter().collect();
+        leaves.sort_unstable_by_key(|(index, _)| *index);
+        if leaves.windows(2).any(|pair| pair[0].0 == pair[1].0 && pair[0].1 != pair[1].1) {
+            return None;
+        }
+        leaves.dedup_by_key(|(index, _)| *index);
+        if leaves.is_empty() || !leaves.iter().map(|(index, _)| *index).eq(self.indices.iter().copied()) {
+            return None;
+        }
+        if self.indices.last().is_some_and(|&last| last >= leaf_count) {
+            return None;
+        }
+        let mut current: Vec<(usize, MerkleNode)> = leaves
+            .into_iter()
+            .map(|(index, commitment)| (*index, MerkleNode::leaf_with_hasher(hasher, commitment, &commitment.to_affine(), *index)))
+            .collect();
+
+        let mut siblings = self.siblings.iter();
+        let mut width = leaf_count;
+        while width > 1 {
+            let mut parents = Vec::with_capacity(current.len());
+            let mut i = 0;
+            while i < current.len() {
+                let index = current[i].0;
+                let node = &current[i].1;
+                let sibling = index ^ 1;
+                let parent = if index.is_multiple_of(2) && current.get(i + 1).map(|(j, _)| *j) == Some(sibling) {
+                    i += 1;
+                    MerkleNode::internal_with_hasher(hasher, node, &current[i].1).ok()?
+                } else if sibling < width {
+                    let sibling_node = siblings.next().filter(|node| hasher.is_canonical(&node.hash))?;
+                    if index.is_multiple_of(2) {
+                        MerkleNode::internal_with_hasher(hasher, node, sibling_node).ok()?
+                    } else {
+                        MerkleNode::internal_with_hasher(hasher, sibling_node, node).ok()?
+                    }
+                } else {
+                    MerkleNode::padded_with_hasher(hasher, node)
+                };
+                parents.push((index / 2, parent));
+                i += 1;
             }
-            current = hasher.finalize().into();
+            current = parents;
+            width = width.div_ceil(2);
+        }
+        match &current[..] {
+            [(0, _)] if siblings.next().is_none() => current.pop().map(|(_, root)| root),
+            _ => None,
         }
-        current == root_hash
+    }
+}
+
+/// Evidence that an aggregate covers leaves `start..end` of a committed tree,
+/// in O(log n) nodes. `cover` holds the nodes covering the range, left to
+/// right, and `outside` those covering every other leaf. A parent's hash
+/// binds only the sum of its children's aggregates, so each covering node
+/// comes with what its own hash is computed from.
+#[derive(Clone, Debug)]
+pub struct RangeAggregateProof {
+    pub leaf_count: usize,
+    pub cover: Vec<CoverNode>,
+    pub outside: Vec<MerkleNode>,
+}
+
+/// A node covering part of a proven range, opened far enough to recompute
+/// its hash from its aggregate.
+#[derive(Clone, Debug)]
+pub enum CoverNode {
+    Leaf(Commitment),
+    Internal {
+        left: [u8; 32],
+        right: [u8; 32],
+        aggregate: AggregateCommitment,
+    },
+}
+
+impl CoverNode {
+    fn node(&self, level: usize, index: usize) -> Option<MerkleNode> {
+        match (self, level) {
+            (CoverNode::Leaf(commitment), 0) if commitment.is_valid() => Some(MerkleNode::leaf(commitment, index)),
+            (CoverNode::Internal { left, right, aggregate }, 1..) if aggregate.commitment.is_valid() => {
+                let affine = aggregate.commitment.to_affine();
+                Some(MerkleNode::parent(&Sha256Hasher, left, right, aggregate.clone(), &affine))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl RangeAggregateProof {
+    /// `root_hash` is the tree's `committed_root`, which binds `leaf_count`.
+    pub fn verify(&self, root_hash: [u8; 32], start: usize, end: usize, claimed: &AggregateCommitment) -> bool {
+        let leaf_count = self.leaf_count;
+        if start >= end || end > leaf_count || claimed.count != end - start {
+            return false;
+        }
+        let cover = range_cover(start, end, leaf_count);
+        let outside: Vec<(usize, usize)> =
+            old_frontier(start).into_iter().chain(range_cover(end, leaf_count, leaf_count)).collect();
+        if cover.len() != self.cover.len() || outside.len() != self.outside.len() {
+            return false;
+        }
+        let Some(cover_nodes) = cover
+            .iter()
+            .zip(&self.cover)
+            .map(|(&(level, index), node)| node.node(level, index))
+            .collect::<Option<Vec<MerkleNode>>>()
+        else {
+            return false;
+        };
+        let sum = cover_nodes
+            .iter()
+            .try_fold(AggregateCommitment::empty(), |sum, node| sum.merge(&node.aggregate));
+        if !sum.is_ok_and(|sum| aggregates_match(&sum, claimed)) {
+            return false;
+        }
+        let nodes = cover
+            .iter()
+            .zip(&cover_nodes)
+            .map(|(&(level, index), node)| (level, index, node))
+            .chain(outside.iter().zip(&self.outside).map(|(&(level, index), node)| (level, index, node)))
+            .collect();
+        fold_cover(&Sha256Hasher, nodes, leaf_count).is_some_and(|root| committed_root(root.hash, leaf_count) == root_hash)
+    }
+}
+
+/// Shows that a tree of `new_count` leaves extends one of `old_count`, in
+/// the manner of RFC 6962 but over this tree's padded shape. `nodes` holds
+/// the old tree's complete subtrees, one per set bit of `old_count`, which
+/// fold to the old root and reappear unchanged in the new tree, followed by
+/// the aligned subtrees covering the appended leaves.
+///
+/// `delta` is the appended leaves' aggregate. Node hashes bind aggregates and
+/// both roots are checked, so `verify` holds it to the merged aggregates of
+/// the covering subtrees.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ConsistencyProof {
+    pub nodes: Vec<MerkleNode>,
+    pub delta: AggregateCommitment,
+}
+
+impl ConsistencyProof {
+    /// Both roots are committed roots.
+    pub fn verify(&self, old_root: [u8; 32], old_count: usize, new_root: [u8; 32], new_count: usize) -> bool {
+        if old_count == 0 || old_count > new_count || self.delta.count != new_count - old_count {
+            return false;
+        }
+        let frontier = old_frontier(old_count);
+        let cover = range_cover(old_count, new_count, new_count);
+        if self.nodes.len() != frontier.len() + cover.len() {
+            return false;
+        }
+        let appended = self.nodes[frontier.len()..]
+            .iter()
+            .try_fold(AggregateCommitment::empty(), |sum, node| sum.merge(&node.aggregate));
+        if !appended.is_ok_and(|sum| sum.count == self.delta.count && sum.commitment == self.delta.commitment) {
+            return false;
+        }
+        let old_nodes: Vec<(usize, usize, &MerkleNode)> =
+            frontier.iter().zip(&self.nodes).map(|(&(level, index), node)| (level, index, node)).collect();
+        let new_nodes = old_nodes
+            .iter()
+            .copied()
+            .chain(cover.iter().zip(&self.nodes[frontier.len()..]).map(|(&(level, index), node)| (level, index, node)))
+            .collect();
+        fold_cover(&Sha256Hasher, old_nodes, old_count).is_some_and(|root| committed_root(root.hash, old_count) == old_root)
+            && fold_cover(&Sha256Hasher, new_nodes, new_count)
+                .is_some_and(|root| committed_root(root.hash, new_count) == new_root)
+    }
+
+    /// The new tree's aggregate, given the old one from a trusted source.
+    pub fn extend_aggregate(&self, old_aggregate: &AggregateCommitment) -> Result<AggregateCommitment> {
+        old_aggregate.merge(&self.delta)
+    }
+}
+
+/// Links a tree from `extract_subtree` to the tree it was taken from.
+/// Leaf hashes bind positions, which differ between the two trees, so the
+/// range's commitments are carried and hashed into both. `outside` holds the
+/// nodes covering every leaf before and after the range, left to right.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(try_from = "SubtreeLinkRepr", into = "SubtreeLinkRepr")]
+pub struct SubtreeLinkProof {
+    pub leaf_count: usize,
+    pub leaves: Vec<Commitment>,
+    pub outside: Vec<MerkleNode>,
+}
+
+/// Wire form: hex-encoded compressed leaves.
+#[derive(Serialize, Deserialize)]
+struct SubtreeLinkRepr {
+    leaf_count: usize,
+    leaves: Vec<String>,
+    outside: Vec<MerkleNode>,
+}
+
+impl From<SubtreeLinkProof> for SubtreeLinkRepr {
+    fn from(proof: SubtreeLinkProof) -> Self {
+        Self {
+            leaf_count: proof.leaf_count,
+            leaves: proof.leaves.iter().map(Commitment::to_hex).collect(),
+            outside: proof.outside,
+        }
+    }
+}
+
+impl TryFrom<SubtreeLinkRepr> for SubtreeLinkProof {
+    type Error = ArchimedesError;
+
+    fn try_from(repr: SubtreeLinkRepr) -> Result<Self> {
+        Ok(Self {
+            leaf_count: repr.leaf_count,
+            leaves: repr.leaves.iter().map(|l| Commitment::from_hex(l)).collect::<Result<_>>()?,
+            outside: repr.outside,
+        })
+    }
+}
+
+impl SubtreeLinkProof {
+    /// `subtree_root` is the extracted tree's root node; `main_root` is the
+    /// main tree's committed root, which binds `leaf_count`.
+    pub fn verify(&self, subtree_root: &MerkleNode, main_root: [u8; 32], start: usize, end: usize) -> bool {
+        self.verify_with(&Sha256Hasher, subtree_root, main_root, start, end)
+    }
+
+    /// For trees built with `build_with_hasher`.
+    pub fn verify_with<H: MerkleHasher>(
+        &self,
+        hasher: &H,
+        subtree_root: &MerkleNode,
+        main_root: [u8; 32],
+        start: usize,
+        end: usize,
+    ) -> bool {
+        if start >= end || end > self.leaf_count || self.leaves.len() != end - start {
+            return false;
+        }
+        if !self.leaves.iter().all(Commitment::is_valid) || !self.outside.iter().all(|node| hasher.is_canonical(&node.hash)) {
+            return false;
+        }
+        let affine = Commitment::batch_to_affine(&self.leaves);
+        let leaves_at = |offset: usize| -> Vec<MerkleNode> {
+            self.leaves
+                .iter()
+                .zip(&affine)
+                .enumerate()
+                .map(|(i, (c, a))| MerkleNode::leaf_with_hasher(hasher, c, a, offset + i))
+                .collect()
+        };
+        let rounds = (end - start).next_power_of_two().trailing_zeros() as usize;
+        match hash_up(hasher, leaves_at(0), rounds) {
+            Some(root) if root[0].hash == subtree_root.hash && aggregates_match(&root[0].aggregate, &subtree_root.aggregate) => {}
+            _ => return false,
+        }
+
+        let main_leaves = leaves_at(start);
+        let cover = range_cover(start, end, self.leaf_count);
+        let Some(cover_nodes) = cover
+            .iter()
+            .map(|&(level, index)| {
+                let lo = (index << level) - start;
+                let hi = ((index + 1) << level).min(self.leaf_count) - start;
+                hash_up(hasher, main_leaves[lo..hi].to_vec(), level).map(|mut nodes| nodes.remove(0))
+            })
+            .collect::<Option<Vec<MerkleNode>>>()
+        else {
+            return false;
+        };
+        let outside: Vec<(usize, usize)> =
+            old_frontier(start).into_iter().chain(range_cover(end, self.leaf_count, self.leaf_count)).collect();
+        if outside.len() != self.outside.len() {
+            return false;
+        }
+        let nodes = cover
+            .iter()
+            .zip(&cover_nodes)
+            .map(|(&(level, index), node)| (level, index, node))
+            .chain(outside.iter().zip(&self.outside).map(|(&(level, index), node)| (level, index, node)))
+            .collect();
+        fold_cover(hasher, nodes, self.leaf_count)
+            .is_some_and(|root| committed_root(root.hash, self.leaf_count) == main_root)
+    }
+}
+
+fn aggregates_match(a: &AggregateCommitment, b: &AggregateCommitment) -> bool {
+    a.count == b.count && bool::from(a.commitment.ct_eq(&b.commitment))
+}
+
+/// Complete subtrees covering leaves `0..leaf_count`, as `(level, index)`
+/// from left to right: one per set bit of `leaf_count`.
+fn old_frontier(leaf_count: usize) -> Vec<(usize, usize)> {
+    (0..usize::BITS as usize)
+        .rev()
+        .filter(|&level| leaf_count >> level & 1 == 1)
+        .map(|level| (level, (leaf_count >> level) - 1))
+        .collect()
+}
+
+/// Largest aligned nodes of a `leaf_count`-leaf tree covering `start..end`,
+/// from left to right. A node ending at `leaf_count` may be clipped there;
+/// of a clipped node and its lone left child, the child is taken.
+fn range_cover(start: usize, end: usize, leaf_count: usize) -> Vec<(usize, usize)> {
+    let depth = leaf_count.next_power_of_two().trailing_zeros();
+    let mut cover = Vec::new();
+    let mut pos = start;
+    while pos < end {
+        let mut level = pos.trailing_zeros().min(depth) as usize;
+        while (pos + (1 << level)).min(leaf_count) > end || (level > 0 && pos + (1 << (level - 1)) >= leaf_count) {
+            level -= 1;
+        }
+        cover.push((level, pos >> level));
+        pos = (pos + (1 << level)).min(leaf_count);
+    }
+    cover
+}
+
+/// Hashes `level` up `rounds` levels, padding a lone last node as the tree
+/// does; `None` if an aggregate overflows.
+fn hash_up<H: MerkleHasher>(hasher: &H, mut level: Vec<MerkleNode>, rounds: usize) -> Option<Vec<MerkleNode>> {
+    for _ in 0..rounds {
+        level = level
+            .chunks(2)
+            .map(|pair| match pair {
+                [left, right] => MerkleNode::internal_with_hasher(hasher, left, right).ok(),
+                _ => Some(MerkleNode::padded_with_hasher(hasher, &pair[0])),
+            })
+            .collect::<Option<_>>()?;
+    }
+    Some(level)
+}
+
+/// Root of a `leaf_count`-leaf tree from disjoint nodes covering all of its
+/// leaves, or `None` if they do not fit together.
+fn fold_cover<H: MerkleHasher>(hasher: &H, mut nodes: Vec<(usize, usize, &MerkleNode)>, leaf_count: usize) -> Option<MerkleNode> {
+    nodes.sort_unstable_by_key(|&(level, index, _)| (level, index));
+    let mut pending = nodes.into_iter().peekable();
+    let mut current: Vec<(usize, MerkleNode)> = Vec::new();
+    let (mut level, mut width) = (0, leaf_count);
+    loop {
+        while let Some((_, index, node)) = pending.next_if(|&(l, _, _)| l == level) {
+            current.push((index, node.clone()));
+        }
+        current.sort_unstable_by_key(|&(index, _)| index);
+        if width == 1 {
+            return match &current[..] {
+                [(0, _)] if pending.peek().is_none() => current.pop().map(|(_, root)| root),
+                _ => None,
+            };
+        }
+        let mut parents = Vec::with_capacity(current.len().div_ceil(2));
+        let mut entries = current.iter().peekable();
+        while let Some((index, node)) = entries.next() {
+            if !index.is_multiple_of(2) {
+                return None;
+            }
+            let parent = match entries.next_if(|(next, _)| *next == index + 1) {
+                Some((_, right)) => MerkleNode::internal_with_hasher(hasher, node, right).ok()?,
+                None if index + 1 == width => MerkleNode::padded_with_hasher(hasher, node),
+                None => return None,
+            };
+            parents.push((index / 2, parent));
+        }
+        current = parents;
+        level += 1;
+        width = width.div_ceil(2);
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use archimedes_core::{CommitmentChain, CommitmentParams};
+    use archimedes_core::{CommitmentChain, MerkleVerifyError};
     use ark_ed_on_bls12_381::Fr as ScalarField;
     use ark_std::test_rng;
+    use sha2::{Digest, Sha256};
+
+    #[test]
+    fn test_public_chain_to_merkle_tree() {
+        let mut rng = test_rng();
+        let params = CommitmentParams::setup(&mut rng).unwrap();
+        let mut chain = CommitmentChain::new(params);
+        for i in 1..=5 {
+            chain.push(ScalarField::from(i as u64), &mut rng).unwrap();
+        }
+        let tree = chain.to_public().to_merkle_tree().unwrap();
+        assert_eq!(tree.root_hash(), CommitmentMerkleTree::from_chain(&chain).unwrap().root_hash());
+        assert!(PublicCommitmentChain::from_commitments(chain.params.clone(), Vec::new()).to_merkle_tree().is_err());
+    }
+
+    #[test]
+    fn test_find_divergence_single_leaf() {
+        use archimedes_core::find_first_divergence;
+
+        let mut rng = test_rng();
+        let params = CommitmentParams::setup(&mut rng).unwrap();
+        let mut chain = CommitmentChain::new(params);
+        for i in 0..13 {
+            chain.push(ScalarField::from(i as u64), &mut rng).unwrap();
+        }
+        let honest = CommitmentMerkleTree::from_chain(&chain).unwrap();
+        assert_eq!(honest.find_divergence(&honest.clone()), None);
+        for bad in 0..13 {
+            let mut forged = chain.commitments().to_vec();
+            forged[bad] = &forged[bad] + &chain.commitments()[0];
+            let tree = CommitmentMerkleTree::build(&forged).unwrap();
+            assert_eq!(honest.find_divergence(&tree), Some(bad));
+            assert_eq!(tree.find_divergence(&honest), Some(bad));
+            assert_eq!(find_first_divergence(chain.commitments(), &forged), Some(bad));
+        }
+
+        for len in [1, 5, 8, 12] {
+            let prefix = CommitmentMerkleTree::build(&chain.commitments()[..len]).unwrap();
+            assert_eq!(honest.find_divergence(&prefix), Some(len));
+            assert_eq!(prefix.find_divergence(&honest), Some(len));
+        }
+        let mut forged = chain.commitments()[..9].to_vec();
+        forged[6] = forged[7].clone();
+        let short = CommitmentMerkleTree::build(&forged).unwrap();
+        assert_eq!(honest.find_divergence(&short), Some(6));
+    }
+
+    #[test]
+    fn test_suffix_aggregate_random_splits() {
+        use ark_std::rand::Rng;
+
+        let mut rng = test_rng();
+        let params = CommitmentParams::setup(&mut rng).unwrap();
+        let mut chain = CommitmentChain::new(params);
+        for i in 0..37 {
+            chain.push(ScalarField::from(i as u64), &mut rng).unwrap();
+        }
+        let tree = CommitmentMerkleTree::from_chain(&chain).unwrap();
+        let full = tree.aggregate();
+        for _ in 0..32 {
+            let split = rng.gen_range(1..37);
+            let prefix = tree.range_aggregate(0, split).unwrap();
+            let suffix = tree.suffix_aggregate(split).unwrap();
+            assert_eq!(suffix.commitment, tree.range_aggregate(split, 37).unwrap().commitment);
+            let merged = prefix.merge(&suffix).unwrap();
+            assert_eq!((merged.commitment, merged.count), (full.commitment.clone(), full.count));
+        }
+        assert_eq!(tree.suffix_aggregate(0).unwrap().count, 37);
+        assert_eq!(tree.suffix_aggregate(37).unwrap().count, 0);
+        assert!(tree.suffix_aggregate(38).is_err());
+    }
 
     #[test]
     fn test_merkle_tree_build() {
@@ -149,7 +1125,7 @@ mod tests {
         for i in 1..=8 {
             chain.push(ScalarField::from(i as u64), &mut rng).unwrap();
         }
-        let tree = CommitmentMerkleTree::build(&chain.commitments).unwrap();
+        let tree = CommitmentMerkleTree::from_chain(&chain).unwrap();
         assert_eq!(tree.leaf_count(), 8);
         assert_eq!(tree.aggregate().count, 8);
     }
@@ -162,10 +1138,623 @@ mod tests {
         for i in 1..=4 {
             chain.push(ScalarField::from(i as u64), &mut rng).unwrap();
         }
-        let tree = CommitmentMerkleTree::build(&chain.commitments).unwrap();
+        let tree = CommitmentMerkleTree::from_chain(&chain).unwrap();
         let proof = tree.generate_proof(2).unwrap();
-        let leaf_hash = tree.nodes[0][2].hash;
-        assert!(proof.verify(leaf_hash, tree.root_hash()));
+        assert!(proof.verify(&chain.commitments()[2], tree.root_hash()));
     }
-}
 
+    #[test]
+    fn test_aggregate_path_proof() {
+        let mut rng = test_rng();
+        let mut chain = CommitmentChain::new(CommitmentParams::setup(&mut rng).unwrap());
+        for i in 0..11u64 {
+            chain.push(ScalarField::from(i), &mut rng).unwrap();
+        }
+        let tree = CommitmentMerkleTree::from_chain(&chain).unwrap();
+        let root = tree.committed_root();
+        for index in 0..11 {
+            let proof = tree.generate_proof(index).unwrap();
+            assert!(proof.verify_aggregate(&chain.commitments()[index], root, 11, tree.aggregate()));
+        }
+        assert!(tree.generate_proof(11).is_err());
+
+        let proof = tree.generate_proof(4).unwrap();
+        let leaf = &chain.commitments()[4];
+        assert!(!proof.verify_aggregate(leaf, root, 12, tree.aggregate()));
+        assert!(!proof.verify_aggregate(&chain.commitments()[5], root, 11, tree.aggregate()));
+        let wrong_root = tree.aggregate().add(leaf).unwrap();
+        assert!(!proof.verify_aggregate(leaf, root, 11, &wrong_root));
+
+        // Moving a commitment from one sibling subtree to another keeps every
+        // count and the root aggregate, but not the node hashes.
+        let moved = &chain.commitments()[0];
+        let mut shifted = proof.clone();
+        shifted.siblings[1].1.commitment += moved;
+        shifted.siblings[2].1.commitment = shifted.siblings[2].1.commitment.sub(moved);
+        assert!(!shifted.verify_aggregate(leaf, root, 11, tree.aggregate()));
+        let mut recounted = proof.clone();
+        recounted.siblings[0].1.count += 1;
+        recounted.siblings[1].1.count -= 1;
+        assert!(!recounted.verify_aggregate(leaf, root, 11, tree.aggregate()));
+        let mut truncated = proof;
+        truncated.siblings.pop();
+        assert!(!truncated.verify_aggregate(leaf, root, 11, tree.aggregate()));
+    }
+
+    #[test]
+    fn test_consistency_proofs() {
+        let tree = golden_tree(13);
+        let commitments: Vec<Commitment> = tree.level(0).unwrap().iter().map(|n| n.aggregate.commitment.clone()).collect();
+        for new_count in 1..=13 {
+            let new = CommitmentMerkleTree::build(&commitments[..new_count]).unwrap();
+            // Powers of two and ragged sizes alike.
+            for old_count in 1..=new_count {
+                let old = CommitmentMerkleTree::build(&commitments[..old_count]).unwrap();
+                let proof = new.consistency_proof(old_count).unwrap();
+                assert!(proof.verify(old.committed_root(), old_count, new.committed_root(), new_count));
+                assert_eq!(proof.extend_aggregate(old.aggregate()).unwrap().commitment, new.aggregate().commitment);
+                if old_count > 1 {
+                    assert!(!proof.verify(old.committed_root(), old_count - 1, new.committed_root(), new_count));
+                }
+            }
+        }
+        assert!(tree.consistency_proof(0).is_err());
+        assert!(tree.consistency_proof(14).is_err());
+    }
+
+    #[test]
+    fn test_consistency_proof_rejects_rewritten_history() {
+        let honest = golden_tree(11);
+        let mut commitments: Vec<Commitment> = honest.level(0).unwrap().iter().map(|n| n.aggregate.commitment.clone()).collect();
+        let old = CommitmentMerkleTree::build(&commitments[..6]).unwrap();
+        commitments[2] = commitments[9].clone();
+        let rewritten = CommitmentMerkleTree::build(&commitments).unwrap();
+        let proof = rewritten.consistency_proof(6).unwrap();
+        assert!(!proof.verify(old.committed_root(), 6, rewritten.committed_root(), 11));
+
+        let mut spliced = honest.consistency_proof(6).unwrap();
+        assert!(spliced.verify(old.committed_root(), 6, honest.committed_root(), 11));
+        spliced.nodes.swap(0, 1);
+        assert!(!spliced.verify(old.committed_root(), 6, honest.committed_root(), 11));
+    }
+
+    #[test]
+    fn test_consistency_proof_rejects_tampered_delta() {
+        let tree = golden_tree(11);
+        let old = CommitmentMerkleTree::build(
+            &tree.level(0).unwrap()[..6].iter().map(|n| n.aggregate.commitment.clone()).collect::<Vec<_>>(),
+        )
+        .unwrap();
+        let proof = tree.consistency_proof(6).unwrap();
+        assert!(proof.verify(old.committed_root(), 6, tree.committed_root(), 11));
+
+        // Same count, different commitment: the appended leaves with one of
+        // them swapped for an old leaf.
+        let mut tampered = proof.clone();
+        let swapped = tree.range_aggregate(6, 10).unwrap().merge(&tree.range_aggregate(0, 1).unwrap()).unwrap();
+        assert_eq!(swapped.count, tampered.delta.count);
+        tampered.delta = swapped;
+        assert!(!tampered.verify(old.committed_root(), 6, tree.committed_root(), 11));
+
+        // Moving aggregate weight between the covering nodes keeps their sum
+        // but changes the hashes folded into the new root.
+        let mut shifted = proof;
+        let cover = shifted.nodes.len() - 2;
+        let moved = tree.level(0).unwrap()[0].aggregate.clone();
+        shifted.nodes[cover].aggregate = shifted.nodes[cover].aggregate.merge(&moved).unwrap();
+        shifted.nodes[cover + 1].aggregate = shifted.nodes[cover + 1].aggregate.difference(&moved).unwrap();
+        shifted.delta = shifted.nodes[cover].aggregate.merge(&shifted.nodes[cover + 1].aggregate).unwrap();
+        assert!(!shifted.verify(old.committed_root(), 6, tree.committed_root(), 11));
+    }
+
+    #[test]
+    fn test_extract_aligned_subtree() {
+        let tree = golden_tree(11);
+        let root = tree.committed_root();
+        for (start, end) in [(0, 11), (4, 8), (8, 11), (10, 11)] {
+            let (subtree, link) = tree.extract_subtree(start, end).unwrap();
+            assert_eq!(link.leaves.len(), end - start);
+            assert_eq!(subtree.aggregate().commitment, tree.range_aggregate(start, end).unwrap().commitment);
+            assert!(link.verify(subtree.root(), root, start, end));
+            assert!(!link.verify(subtree.root(), tree.root_hash(), start, end));
+        }
+        let (subtree, link) = tree.extract_subtree(4, 8).unwrap();
+        assert!(!link.verify(subtree.root(), root, 0, 4));
+        assert!(!link.verify(tree.node(2, 0).unwrap(), root, 4, 8));
+        assert!(tree.extract_subtree(3, 3).is_err());
+        assert!(tree.extract_subtree(5, 12).is_err());
+    }
+
+    #[test]
+    fn test_extract_unaligned_subtree() {
+        let tree = golden_tree(11);
+        let root = tree.committed_root();
+        for start in 0..11 {
+            for end in start + 1..=11 {
+                let (subtree, link) = tree.extract_subtree(start, end).unwrap();
+                assert_eq!(subtree.leaf_count(), end - start);
+                let rebuilt = CommitmentMerkleTree::build(&link.leaves).unwrap();
+                assert_eq!(subtree.root_hash(), rebuilt.root_hash());
+                assert_eq!(subtree.generate_proof(0).unwrap().index, 0);
+                assert!(link.verify(subtree.root(), root, start, end), "{start}..{end}");
+            }
+        }
+
+        let (subtree, link) = tree.extract_subtree(1, 5).unwrap();
+        assert_eq!(link.leaves.len(), 4);
+        assert!(!link.verify(subtree.root(), root, 2, 6));
+        let mut forged = link.clone();
+        forged.leaves[2] = tree.leaf_aggregate(7).unwrap().commitment.clone();
+        assert!(!forged.verify(subtree.root(), root, 1, 5));
+        let other = CommitmentMerkleTree::build(&vec![tree.leaf_aggregate(1).unwrap().commitment.clone(); 4]).unwrap();
+        assert!(!link.verify(other.root(), root, 1, 5));
+        let decoded: SubtreeLinkProof = serde_json::from_str(&serde_json::to_string(&link).unwrap()).unwrap();
+        assert!(decoded.verify(subtree.root(), root, 1, 5));
+        let mut moved = link;
+        moved.outside.swap(0, 1);
+        assert!(!moved.verify(subtree.root(), root, 1, 5));
+    }
+
+    #[test]
+    fn test_extracted_subtree_proves_its_own_leaves() {
+        let tree = golden_tree(11);
+        let (subtree, _) = tree.extract_subtree(3, 9).unwrap();
+        for i in 0..6 {
+            let leaf = &tree.level(0).unwrap()[3 + i].aggregate.commitment;
+            assert!(subtree.generate_proof(i).unwrap().verify(leaf, subtree.root_hash()));
+        }
+    }
+
+    #[test]
+    fn test_build_streaming_matches_build() {
+        let tree = golden_tree(33);
+        let commitments: Vec<Commitment> = tree.level(0).unwrap().iter().map(|n| n.aggregate.commitment.clone()).collect();
+        for n in [1, 2, 3, 5, 6, 7, 8, 11, 16, 17, 33] {
+            let batch = CommitmentMerkleTree::build(&commitments[..n]).unwrap();
+            let streamed = CommitmentMerkleTree::build_streaming(commitments[..n].iter().cloned(), None).unwrap();
+            assert_eq!(streamed.committed_root(), batch.committed_root());
+            assert_eq!(streamed.aggregate().commitment, batch.aggregate().commitment);
+            assert_eq!(streamed.aggregate().count, n);
+            let sized = CommitmentMerkleTree::build_streaming(commitments[..n].iter().cloned(), Some(n)).unwrap();
+            assert_eq!(sized.root_hash(), batch.root_hash());
+        }
+        assert!(CommitmentMerkleTree::build_streaming(std::iter::empty(), None).is_err());
+        assert!(matches!(
+            CommitmentMerkleTree::build_streaming(commitments[..4].iter().cloned(), Some(5)),
+            Err(ArchimedesError::MerkleTreeError { kind: MerkleErrorKind::LeafCountMismatch { expected: 5, found: 4 }, .. })
+        ));
+    }
+
+    #[test]
+    fn test_from_chain_checks_aggregate() {
+        let mut rng = test_rng();
+        let mut chain = CommitmentChain::new(CommitmentParams::setup(&mut rng).unwrap());
+        for i in 0..6u64 {
+            chain.push(ScalarField::from(i), &mut rng).unwrap();
+        }
+        let tree = CommitmentMerkleTree::from_chain(&chain).unwrap();
+        assert!(tree.is_consistent_with(&chain));
+        assert_eq!(CommitmentMerkleTree::from_public_chain(&chain.to_public()).unwrap().root_hash(), tree.root_hash());
+        let mut extended = chain.clone();
+        extended.push(ScalarField::from(6u64), &mut rng).unwrap();
+        assert!(!tree.is_consistent_with(&extended));
+    }
+
+    #[test]
+    fn test_sorted_set_commitment() {
+        let commitments: Vec<Commitment> =
+            golden_tree(6).level(0).unwrap().iter().map(|leaf| leaf.aggregate.commitment.clone()).collect();
+        let mut shuffled: Vec<Commitment> = commitments.iter().rev().cloned().collect();
+        shuffled.swap(1, 4);
+        shuffled.push(commitments[2].clone());
+        let tree = CommitmentMerkleTree::build_sorted(&commitments[..5]).unwrap();
+        let other = CommitmentMerkleTree::build_sorted(&shuffled[1..]).unwrap();
+        assert_eq!(tree.leaf_count(), 5);
+        assert!(tree.same_root(&other));
+
+        for commitment in &commitments[..5] {
+            let index = tree.sorted_index_of(commitment).unwrap();
+            let proof = tree.prove_membership(commitment).unwrap();
+            assert_eq!(proof.index, index);
+            assert!(proof.verify(commitment, tree.root_hash()));
+        }
+        let unsorted = CommitmentMerkleTree::build(&commitments).unwrap();
+        assert!(unsorted.sorted_index_of(&commitments[0]).is_none());
+        assert!(matches!(
+            unsorted.prove_membership(&commitments[0]),
+            Err(ArchimedesError::MerkleTreeError { kind: MerkleErrorKind::NotSorted, .. })
+        ));
+
+        // The absent commitment's neighbours are adjacent leaves that sort
+        // either side of it.
+        let absent = &commitments[5];
+        assert_eq!(tree.sorted_index_of(absent), None);
+        let Err(ArchimedesError::MerkleTreeError { index, kind: MerkleErrorKind::NotInSet, .. }) = tree.prove_membership(absent)
+        else {
+            panic!("absent commitment proved");
+        };
+        if index > 0 {
+            assert!(tree.leaf_aggregate(index - 1).unwrap().commitment.to_bytes() < absent.to_bytes());
+        }
+        if index < tree.leaf_count() {
+            assert!(tree.leaf_aggregate(index).unwrap().commitment.to_bytes() > absent.to_bytes());
+        }
+    }
+
+    #[test]
+    fn test_root_and_aggregate_comparisons() {
+        let tree = golden_tree(6);
+        assert!(tree.same_root(&golden_tree(6)));
+        assert!(!tree.same_root(&golden_tree(5)));
+        assert!(tree.root_matches(tree.root_hash()));
+        assert!(!tree.root_matches(tree.committed_root()));
+
+        let mut claimed = tree.aggregate().clone();
+        assert!(tree.aggregate_matches(&claimed));
+        claimed.count += 1;
+        assert!(!tree.aggregate_matches(&claimed));
+        let mut range = tree.range_aggregate(1, 4).unwrap();
+        assert!(tree.range_aggregate_matches(1, 4, &range).unwrap());
+        range.count = 2;
+        assert!(!tree.range_aggregate_matches(1, 4, &range).unwrap());
+        assert!(tree.range_aggregate_matches(4, 7, &range).is_err());
+    }
+
+    #[test]
+    fn test_batch_proofs_match_single() {
+        for n in [1, 2, 5, 8, 13] {
+            let tree = golden_tree(n);
+            let single: Vec<Vec<u8>> = (0..n as usize).map(|i| tree.generate_proof(i).unwrap().to_bytes()).collect();
+            assert_eq!(tree.generate_all_proofs().iter().map(AggregatePathProof::to_bytes).collect::<Vec<_>>(), single);
+        }
+        let tree = golden_tree(13);
+        let picked: Vec<Vec<u8>> = tree.generate_proofs_for(&[12, 3, 3, 0]).unwrap().iter().map(AggregatePathProof::to_bytes).collect();
+        let expected: Vec<Vec<u8>> = [12, 3, 3, 0].iter().map(|&i| tree.generate_proof(i).unwrap().to_bytes()).collect();
+        assert_eq!(picked, expected);
+        assert!(tree.generate_proofs_for(&[2, 13]).is_err());
+    }
+
+    #[test]
+    fn test_verify_for_commitment_binds_index() {
+        let tree = golden_tree(8);
+        let commitment = &tree.leaf_aggregate(5).unwrap().commitment;
+        let proof = tree.generate_proof(5).unwrap();
+        assert!(proof.verify(commitment, tree.root_hash()));
+        assert!(!proof.verify(&tree.leaf_aggregate(3).unwrap().commitment, tree.root_hash()));
+
+        let relabelled = AggregatePathProof { index: 3, ..proof.clone() };
+        assert!(!relabelled.verify(commitment, tree.root_hash()));
+        let beyond = AggregatePathProof { index: 13, ..proof };
+        assert!(!beyond.verify(commitment, tree.root_hash()));
+    }
+
+    #[test]
+    fn test_verify_detailed_reports_failure_kind() {
+        let tree = golden_tree(6);
+        let leaf = &tree.leaf_aggregate(5).unwrap().commitment;
+        let proof = tree.generate_proof(5).unwrap();
+        assert_eq!(proof.verify_detailed(leaf, tree.root_hash()), Ok(()));
+
+        let short = AggregatePathProof { siblings: proof.siblings[..2].to_vec(), ..proof.clone() };
+        assert_eq!(short.verify_detailed(leaf, tree.root_hash()), Err(MerkleVerifyError::WrongDepth { index: 5, depth: 2 }));
+        let mut flipped = proof.clone();
+        flipped.siblings[1].2 ^= true;
+        assert_eq!(flipped.verify_detailed(leaf, tree.root_hash()), Err(MerkleVerifyError::WrongDirection { index: 5, level: 1 }));
+        let other = &tree.leaf_aggregate(4).unwrap().commitment;
+        assert_eq!(
+            proof.verify_detailed(other, tree.root_hash()),
+            Err(MerkleVerifyError::RootMismatch {
+                computed: proof.compute_root(other).unwrap().hash,
+                expected: tree.root_hash(),
+            })
+        );
+        assert!(!proof.verify(leaf, [0u8; 32]));
+    }
+
+    #[test]
+    fn test_accessors_and_path_metadata() {
+        let tree = golden_tree(5);
+        assert_eq!(tree.depth(), 3);
+        let lens: Vec<usize> = (0..=tree.depth()).map(|l| tree.level_len(l).unwrap()).collect();
+        assert_eq!(lens, vec![5, 3, 2, 1]);
+        assert!(tree.level_len(4).is_err());
+        assert!(tree.node(1, 3).is_err());
+        assert_eq!(tree.node(3, 0).unwrap().hash, tree.root_hash());
+        assert_eq!(tree.leaf_aggregate(4).unwrap().count, 1);
+        assert!(tree.leaf_hash(5).is_err());
+        assert!(tree.leaf_aggregate(5).is_err());
+        for i in 0..5 {
+            let proof = tree.generate_proof(i).unwrap();
+            assert_eq!(proof.path_len(), tree.depth());
+            assert_eq!(proof.compute_root(&tree.leaf_aggregate(i).unwrap().commitment).unwrap().hash, tree.root_hash());
+        }
+    }
+
+    #[test]
+    fn test_pedersen_hasher_tree() {
+        let mut rng = test_rng();
+        let params = CommitmentParams::setup(&mut rng).unwrap();
+        let hasher = PedersenMerkleHasher::new(&params).unwrap();
+        let mut chain = CommitmentChain::new(params.clone());
+        for i in 1..=5 {
+            chain.push(ScalarField::from(i as u64), &mut rng).unwrap();
+        }
+        let tree = CommitmentMerkleTree::build_with_hasher(chain.commitments(), &hasher).unwrap();
+        let sha_tree = CommitmentMerkleTree::from_chain(&chain).unwrap();
+        assert_ne!(tree.root_hash(), sha_tree.root_hash());
+        assert_eq!(tree.aggregate().commitment, sha_tree.aggregate().commitment);
+
+        let again = CommitmentMerkleTree::build_with_hasher(chain.commitments(), &PedersenMerkleHasher::new(&params).unwrap());
+        assert_eq!(again.unwrap().root_hash(), tree.root_hash());
+
+        for i in 0..5 {
+            let proof = tree.generate_proof(i).unwrap();
+            assert!(proof.verify_with(&hasher, &chain.commitments()[i], tree.root_hash()));
+            assert!(!proof.verify(&chain.commitments()[i], tree.root_hash()));
+        }
+    }
+
+    #[test]
+    fn test_range_aggregate_matches_leaf_sum() {
+        let mut rng = test_rng();
+        let params = CommitmentParams::setup(&mut rng).unwrap();
+        let mut chain = CommitmentChain::new(params);
+        for i in 1..=11 {
+            chain.push(ScalarField::from(i as u64), &mut rng).unwrap();
+        }
+        for n in 1..=11 {
+            let tree = CommitmentMerkleTree::build(&chain.commitments()[..n]).unwrap();
+            for start in 0..n {
+                for end in start + 1..=n {
+                    let ranged = tree.ranged_aggregate(start, end).unwrap();
+                    assert!(ranged.covers(start, end));
+                    assert!(ranged.matches(&chain.ranged_aggregate(start, end).unwrap()));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_multiproof_matches_single_proofs() {
+        let mut rng = test_rng();
+        let params = CommitmentParams::setup(&mut rng).unwrap();
+        let mut chain = CommitmentChain::new(params);
+        for i in 0..13 {
+            chain.push(ScalarField::from(i as u64), &mut rng).unwrap();
+        }
+        let leaf = |i: usize| chain.commitments()[i].clone();
+        for n in [1, 2, 7, 13] {
+            let tree = CommitmentMerkleTree::build(&chain.commitments()[..n]).unwrap();
+            let root = tree.root_hash();
+            let committed = tree.committed_root();
+            for indices in [vec![0], vec![n - 1], vec![n - 1, 0, n / 2, 0], (0..n).collect()] {
+                let proof = tree.generate_multiproof(&indices).unwrap();
+                let leaves: Vec<(usize, Commitment)> = indices.iter().map(|&i| (i, leaf(i))).collect();
+                assert!(proof.verify(&leaves, committed, n));
+                assert!(!proof.verify(&leaves, root, n));
+                for &i in &indices {
+                    assert!(tree.generate_proof(i).unwrap().verify(&leaf(i), root));
+                }
+                let single: usize = proof.indices.iter().map(|&i| tree.generate_proof(i).unwrap().siblings.len()).sum();
+                assert!(proof.proof_size() <= single);
+            }
+        }
+
+        let tree = CommitmentMerkleTree::from_chain(&chain).unwrap();
+        let root = tree.committed_root();
+        let indices = [9, 2, 3, 8, 12];
+        let proof = tree.generate_multiproof(&indices).unwrap();
+        let single: usize = indices.iter().map(|&i| tree.generate_proof(i).unwrap().siblings.len()).sum();
+        assert!(proof.proof_size() < single);
+        let mut leaves: Vec<(usize, Commitment)> = indices.iter().map(|&i| (i, leaf(i))).collect();
+        assert!(proof.verify(&leaves, root, 13));
+        // The same siblings folded as a 16-leaf tree give another root.
+        assert!(!proof.verify(&leaves, root, 16));
+        assert!(!proof.verify(&leaves[..4], root, 13));
+        leaves.push((3, leaf(4)));
+        assert!(!proof.verify(&leaves, root, 13));
+        leaves.pop();
+        leaves[1].1 = leaf(1);
+        assert!(!proof.verify(&leaves, root, 13));
+        assert!(tree.generate_multiproof(&[2, 13]).is_err());
+    }
+
+    #[test]
+    fn test_range_aggregate_proofs() {
+        let mut rng = test_rng();
+        let params = CommitmentParams::setup(&mut rng).unwrap();
+        let mut chain = CommitmentChain::new(params);
+        for i in 0..13 {
+            chain.push(ScalarField::from(i as u64), &mut rng).unwrap();
+        }
+        let tree = CommitmentMerkleTree::from_chain(&chain).unwrap();
+        let root = tree.committed_root();
+        for (start, end) in [(3, 6), (5, 12), (7, 8), (12, 13), (1, 12), (0, 13)] {
+            let proof = tree.prove_range_aggregate(start, end).unwrap();
+            let claimed = tree.range_aggregate(start, end).unwrap();
+            assert!(proof.cover.len() + proof.outside.len() <= 2 * 4);
+            assert!(proof.verify(root, start, end, &claimed));
+            let mut wrong = claimed.clone();
+            wrong.commitment += &chain.commitments()[0];
+            assert!(!proof.verify(root, start, end, &wrong));
+            if end < 13 {
+                assert!(!proof.verify(root, start + 1, end + 1, &tree.range_aggregate(start + 1, end + 1).unwrap()));
+            }
+        }
+
+        let proof = tree.prove_range_aggregate(1, 12).unwrap();
+        let claimed = tree.range_aggregate(1, 12).unwrap();
+        assert!(!proof.verify(tree.root_hash(), 1, 12, &claimed));
+
+        let mut forged = tree.prove_range_aggregate(3, 6).unwrap();
+        forged.cover[0] = CoverNode::Leaf(chain.commitments()[0].clone());
+        let mut claimed = tree.range_aggregate(3, 6).unwrap();
+        claimed.commitment = &claimed.commitment - &chain.commitments()[3];
+        claimed.commitment += &chain.commitments()[0];
+        assert!(!forged.verify(root, 3, 6, &claimed));
+        assert!(tree.prove_range_aggregate(4, 4).is_err());
+    }
+
+    #[test]
+    fn test_range_aggregate_proof_rejects_shifted_aggregates() {
+        // Leaves 4..8 are node (2, 1), whose sibling (2, 0) lies outside the
+        // range. Their parent binds only the sum of the two aggregates.
+        let tree = golden_tree(16);
+        let root = tree.committed_root();
+        let proof = tree.prove_range_aggregate(4, 8).unwrap();
+        let claimed = tree.range_aggregate(4, 8).unwrap();
+        assert!(proof.verify(root, 4, 8, &claimed));
+
+        let moved = tree.level(0).unwrap()[0].aggregate.commitment.clone();
+        let mut shifted = proof.clone();
+        let CoverNode::Internal { aggregate, .. } = &mut shifted.cover[0] else {
+            panic!("leaves 4..8 are covered by one internal node");
+        };
+        aggregate.commitment += &moved;
+        shifted.outside[0].aggregate.commitment = &shifted.outside[0].aggregate.commitment - &moved;
+        let mut inflated = claimed.clone();
+        inflated.commitment += &moved;
+        assert!(!shifted.verify(root, 4, 8, &inflated));
+        assert!(!shifted.verify(root, 4, 8, &claimed));
+    }
+
+    #[test]
+    fn test_duplicate_commitments_keep_positional_identity() {
+        let mut rng = test_rng();
+        let params = CommitmentParams::setup(&mut rng).unwrap();
+        let (c, _) = params.commit(&ScalarField::from(7u64), &mut rng).unwrap();
+        let (other, _) = params.commit(&ScalarField::from(8u64), &mut rng).unwrap();
+        let commitments = vec![c.clone(), other, c.clone(), c.clone()];
+        let tree = CommitmentMerkleTree::build(&commitments).unwrap();
+
+        let leaf_hashes: Vec<[u8; 32]> = tree.level(0).unwrap().iter().map(|n| n.hash).collect();
+        assert_ne!(leaf_hashes[0], leaf_hashes[2]);
+        assert_ne!(leaf_hashes[2], leaf_hashes[3]);
+
+        for &i in &[0usize, 2, 3] {
+            let proof = tree.generate_proof(i).unwrap();
+            assert_eq!(proof.index, i);
+            assert!(proof.verify(&c, tree.root_hash()));
+            for &j in &[0usize, 2, 3] {
+                if i != j {
+                    let moved = AggregatePathProof { index: j, ..proof.clone() };
+                    assert!(!moved.verify(&c, tree.root_hash()));
+                }
+            }
+        }
+        assert_eq!(tree.range_aggregate(2, 4).unwrap().count, 2);
+    }
+
+    #[test]
+    fn test_range_aggregate_difference() {
+        let mut rng = test_rng();
+        let params = CommitmentParams::setup(&mut rng).unwrap();
+        let mut chain = CommitmentChain::new(params);
+        for i in 1..=7 {
+            chain.push(ScalarField::from(i as u64), &mut rng).unwrap();
+        }
+        let tree = CommitmentMerkleTree::from_chain(&chain).unwrap();
+        let n = tree.leaf_count();
+        let full = tree.range_aggregate(0, n).unwrap();
+        for k in 1..n {
+            let prefix = tree.range_aggregate(0, k).unwrap();
+            let suffix = tree.range_aggregate(k, n).unwrap();
+            let diff = full.difference(&prefix).unwrap();
+            assert_eq!(diff.commitment, suffix.commitment);
+            assert_eq!(diff.count, suffix.count);
+            assert_eq!(&full.commitment - &prefix.commitment, suffix.commitment);
+        }
+    }
+
+    fn golden_tree(n: u64) -> CommitmentMerkleTree {
+        use ark_ec::PrimeGroup;
+        use archimedes_core::Randomness;
+        let g = ark_ed_on_bls12_381::EdwardsProjective::generator();
+        let params = CommitmentParams::from_generators(g, g * ScalarField::from(7u64));
+        let commitments: Vec<Commitment> = (0..n)
+            .map(|i| params.commit_with_randomness(&ScalarField::from(i), &Randomness(ScalarField::from(i + 1))).unwrap())
+            .collect();
+        CommitmentMerkleTree::build(&commitments).unwrap()
+    }
+
+    #[test]
+    fn test_committed_root_resolves_leaf_count_ambiguity() {
+        // Every level of a padded path has a sibling, so leaf 4 of a 5-leaf
+        // tree carries three and cannot pass for a shorter or wider tree.
+        let tree = golden_tree(5);
+        let leaf = &tree.level(0).unwrap()[4].aggregate.commitment;
+        let proof = tree.generate_proof(4).unwrap();
+        assert_eq!(proof.siblings.len(), 3);
+        assert_eq!((proof.siblings[0].0, proof.siblings[0].1.count, proof.siblings[0].2), (MERKLE_PADDING, 0, true));
+        let relabelled = AggregatePathProof { index: 0, siblings: proof.siblings.clone() };
+
+        assert_ne!(committed_root(tree.root_hash(), 5), committed_root(tree.root_hash(), 6));
+        assert!(proof.verify_committed(leaf, tree.committed_root(), 5));
+        assert!(!relabelled.verify_committed(leaf, tree.committed_root(), 5));
+        assert!(!proof.verify_committed(leaf, tree.committed_root(), 6));
+
+        for n in 1..=9u64 {
+            let tree = golden_tree(n);
+            for i in 0..n as usize {
+                let proof = tree.generate_proof(i).unwrap();
+                let leaf = &tree.level(0).unwrap()[i].aggregate.commitment;
+                assert!(proof.verify_committed(leaf, tree.committed_root(), n as usize));
+            }
+        }
+    }
+
+    /// Raw root over level-0 nodes under the current rules.
+    fn fold_levels(mut level: Vec<MerkleNode>) -> [u8; 32] {
+        while level.len() > 1 {
+            level = level
+                .chunks(2)
+                .map(|pair| match pair {
+                    [lone] => MerkleNode::padded(lone),
+                    [l, r] => MerkleNode::internal(l, r).unwrap(),
+                    _ => unreachable!(),
+                })
+                .collect();
+        }
+        level[0].hash
+    }
+
+    /// Raw root under the old unprefixed hashing with odd-node promotion.
+    fn fold_legacy(mut level: Vec<[u8; 32]>) -> [u8; 32] {
+        while level.len() > 1 {
+            level = level
+                .chunks(2)
+                .map(|pair| match pair {
+                    [lone] => *lone,
+                    [l, r] => Sha256::new().chain_update(l).chain_update(r).finalize().into(),
+                    _ => unreachable!(),
+                })
+                .collect();
+        }
+        level[0]
+    }
+
+    #[test]
+    fn test_five_and_six_leaf_trees_cannot_share_root() {
+        // Crafted 5-leaf input: the first four leaves of a 6-leaf tree plus
+        // its lone level-1 node H(e, f) in place of the fifth leaf.
+        let six = golden_tree(6);
+        let mut crafted = six.level(0).unwrap()[..4].to_vec();
+        crafted.push(six.node(1, 2).unwrap().clone());
+        assert_eq!(fold_levels(six.level(0).unwrap().to_vec()), six.root_hash());
+        assert_ne!(fold_levels(crafted), six.root_hash());
+
+        // Under promotion and unprefixed hashing the two roots coincided.
+        let legacy_six: Vec<[u8; 32]> = six.level(0).unwrap().iter().map(|n| n.hash).collect();
+        let legacy_lone: [u8; 32] = Sha256::new().chain_update(legacy_six[4]).chain_update(legacy_six[5]).finalize().into();
+        let mut crafted = legacy_six[..4].to_vec();
+        crafted.push(legacy_lone);
+        assert_eq!(fold_legacy(crafted), fold_legacy(legacy_six));
+    }
+
+    #[test]
+    fn test_committed_root_golden_vectors() {
+        let hex = |bytes: [u8; 32]| bytes.iter().map(|b| format!("{:02x}", b)).collect::<String>();
+        assert_eq!(hex(golden_tree(1).committed_root()), "cf31bb47bb343593c4501363fd215fa0072cfb90362ca5ea529fd0ee524df73e");
+        assert_eq!(hex(golden_tree(5).committed_root()), "86ecab0d845c5fdbd870c58df9ee06c99ce0c51727e73a8e67db85a67f55b761");
+    }
+}
@@ -0,0 +1,503 @@
+use archimedes_core::{
+    AffineCommitment, AggregateCommitment, ArchimedesError, Commitment, MerkleVerifyError, SerializationErrorKind,
+};
+use ark_serialize::CanonicalSerialize;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+type Result<T> = std::result::Result<T, ArchimedesError>;
+
+/// Version mixed into committed roots. Bump whenever node hashing or the
+/// committed-root preimage changes.
+pub const MERKLE_FORMAT_VERSION: u32 = 4;
+const COMMITTED_ROOT_DOMAIN: &[u8] = b"archimedes-committed-root";
+pub(crate) const LEAF_POINT_LEN: usize = 32;
+const HASH_LEN: usize = 32;
+const AGGREGATE_SIBLING_LEN: usize = HASH_LEN + 8 + LEAF_POINT_LEN;
+pub(crate) const LEAF_PREFIX: u8 = 0x00;
+pub(crate) const INTERNAL_PREFIX: u8 = 0x01;
+pub(crate) const AGGREGATE_NODE_PREFIX: u8 = 0x02;
+/// Bytes of `aggregate_bytes`: the count, then the compressed point.
+pub(crate) const AGGREGATE_LEN: usize = 8 + LEAF_POINT_LEN;
+
+/// Right sibling of the last node on an odd-width level. Lone nodes are
+/// hashed with it rather than promoted, so every level of every path has a
+/// sibling and trees of different shapes cannot share interior nodes.
+pub const MERKLE_PADDING: [u8; 32] = [0u8; 32];
+
+/// Deepest proof `MerkleProof::from_bytes` accepts: one sibling per bit of a
+/// leaf index.
+pub const MAX_PROOF_DEPTH: usize = 64;
+
+/// Binds a raw top hash to the tree's leaf count, so one root cannot be
+/// claimed for trees of different sizes. Headers and evidence carry this;
+/// the raw root is only for internal path checks.
+pub fn committed_root(raw_root: [u8; 32], leaf_count: usize) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(COMMITTED_ROOT_DOMAIN);
+    hasher.update(raw_root);
+    hasher.update((leaf_count as u64).to_be_bytes());
+    hasher.update(MERKLE_FORMAT_VERSION.to_be_bytes());
+    hasher.finalize().into()
+}
+
+fn truncated(what: &'static str) -> ArchimedesError {
+    ArchimedesError::serialization(SerializationErrorKind::Truncated(what))
+}
+
+fn trailing(what: &'static str) -> ArchimedesError {
+    ArchimedesError::serialization(SerializationErrorKind::TrailingBytes(what))
+}
+
+/// Hash function used for tree nodes. `Sha256Hasher` is the default and is
+/// what committed roots in headers use. Leaf, internal and aggregate node
+/// preimages must be domain-separated.
+pub trait MerkleHasher: Clone {
+    fn hash_leaf(&self, index: usize, commitment: &[u8]) -> [u8; 32];
+    fn hash_internal(&self, left: &[u8; 32], right: &[u8; 32]) -> [u8; 32];
+    /// Internal node of a commitment tree; `aggregate` is the node's
+    /// `aggregate_bytes`, so the hash fixes how the aggregate splits between
+    /// the two subtrees.
+    fn hash_node(&self, left: &[u8; 32], right: &[u8; 32], aggregate: &[u8]) -> [u8; 32];
+    /// Whether `hash` could be an output of this hasher. Verifiers reject
+    /// proof siblings that are not, so every node has one encoding.
+    fn is_canonical(&self, _hash: &[u8; 32]) -> bool {
+        true
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Sha256Hasher;
+
+impl MerkleHasher for Sha256Hasher {
+    fn hash_leaf(&self, index: usize, commitment: &[u8]) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update([LEAF_PREFIX]);
+        hasher.update(index.to_be_bytes());
+        hasher.update(commitment);
+        hasher.finalize().into()
+    }
+
+    fn hash_internal(&self, left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update([INTERNAL_PREFIX]);
+        hasher.update(left);
+        hasher.update(right);
+        hasher.finalize().into()
+    }
+
+    fn hash_node(&self, left: &[u8; 32], right: &[u8; 32], aggregate: &[u8]) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update([AGGREGATE_NODE_PREFIX]);
+        hasher.update(left);
+        hasher.update(right);
+        hasher.update(aggregate);
+        hasher.finalize().into()
+    }
+}
+
+/// Hash of leaf `index` holding the compressed form of `affine`.
+pub(crate) fn leaf_hash_with<H: MerkleHasher>(hasher: &H, affine: &AffineCommitment, index: usize) -> [u8; 32] {
+    let mut bytes = [0u8; LEAF_POINT_LEN];
+    affine.0.serialize_compressed(&mut bytes[..]).expect("compressed point fits its buffer");
+    hasher.hash_leaf(index, &bytes)
+}
+
+/// `aggregate.count` as a big-endian u64, then `affine`, the compressed form
+/// of `aggregate.commitment`.
+pub(crate) fn aggregate_bytes(aggregate: &AggregateCommitment, affine: &AffineCommitment) -> [u8; AGGREGATE_LEN] {
+    let mut bytes = [0u8; AGGREGATE_LEN];
+    bytes[..8].copy_from_slice(&(aggregate.count as u64).to_be_bytes());
+    affine.0.serialize_compressed(&mut bytes[8..]).expect("compressed point fits its buffer");
+    bytes
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MerkleNode {
+    pub hash: [u8; 32],
+    pub aggregate: AggregateCommitment,
+}
+
+impl MerkleNode {
+    pub fn leaf(commitment: &Commitment, index: usize) -> Self {
+        Self::leaf_with_affine(commitment, &commitment.to_affine(), index)
+    }
+
+    /// Same as `leaf`, reusing an affine form computed in a batch.
+    pub fn leaf_with_affine(commitment: &Commitment, affine: &AffineCommitment, index: usize) -> Self {
+        Self::leaf_with_hasher(&Sha256Hasher, commitment, affine, index)
+    }
+
+    pub fn leaf_with_hasher<H: MerkleHasher>(
+        hasher: &H,
+        commitment: &Commitment,
+        affine: &AffineCommitment,
+        index: usize,
+    ) -> Self {
+        Self {
+            hash: leaf_hash_with(hasher, affine, index),
+            // A one-element aggregate is the commitment itself; no addition.
+            aggregate: AggregateCommitment {
+                commitment: commitment.clone(),
+                count: 1,
+            },
+        }
+    }
+
+    pub fn internal(left: &MerkleNode, right: &MerkleNode) -> Result<Self> {
+        Self::internal_with_hasher(&Sha256Hasher, left, right)
+    }
+
+    pub fn internal_with_hasher<H: MerkleHasher>(hasher: &H, left: &MerkleNode, right: &MerkleNode) -> Result<Self> {
+        let aggregate = left.aggregate.merge(&right.aggregate)?;
+        let affine = aggregate.commitment.to_affine();
+        Ok(Self::parent(hasher, &left.hash, &right.hash, aggregate, &affine))
+    }
+
+    /// Parent of the lone last node on an odd-width level.
+    pub fn padded(lone: &MerkleNode) -> Self {
+        Self::padded_with_hasher(&Sha256Hasher, lone)
+    }
+
+    pub fn padded_with_hasher<H: MerkleHasher>(hasher: &H, lone: &MerkleNode) -> Self {
+        let affine = lone.aggregate.commitment.to_affine();
+        Self::parent(hasher, &lone.hash, &MERKLE_PADDING, lone.aggregate.clone(), &affine)
+    }
+
+    /// Node over children `left` and `right` holding `aggregate`, whose
+    /// commitment has the affine form `affine`.
+    pub(crate) fn parent<H: MerkleHasher>(
+        hasher: &H,
+        left: &[u8; 32],
+        right: &[u8; 32],
+        aggregate: AggregateCommitment,
+        affine: &AffineCommitment,
+    ) -> Self {
+        Self {
+            hash: hasher.hash_node(left, right, &aggregate_bytes(&aggregate, affine)),
+            aggregate,
+        }
+    }
+}
+
+/// Checks that a path with sibling sides `directions` fits `index` and that
+/// each sibling sits on the side that index puts it.
+fn check_path(index: usize, directions: impl ExactSizeIterator<Item = bool>) -> std::result::Result<(), MerkleVerifyError> {
+    let depth = directions.len();
+    if index.checked_shr(depth as u32).unwrap_or(0) != 0 {
+        return Err(MerkleVerifyError::WrongDepth { index, depth });
+    }
+    for (level, is_left) in directions.enumerate() {
+        if is_left != (index >> level).is_multiple_of(2) {
+            return Err(MerkleVerifyError::WrongDirection { index, level });
+        }
+    }
+    Ok(())
+}
+
+/// Sibling directions a proof for `index` must have in a tree of
+/// `leaf_count` leaves: one per level, padding included.
+fn expected_directions(index: usize, leaf_count: usize) -> Vec<bool> {
+    let mut directions = Vec::new();
+    let mut current = index;
+    let mut width = leaf_count;
+    while width > 1 {
+        directions.push(current.is_multiple_of(2));
+        current /= 2;
+        width = width.div_ceil(2);
+    }
+    directions
+}
+
+/// Inclusion proof in a tree of plain hashes, such as a `StateBatch`.
+/// Commitment trees bind each node's aggregate and prove with
+/// `AggregatePathProof`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MerkleProof {
+    pub index: usize,
+    pub siblings: Vec<([u8; 32], bool)>,
+}
+
+impl MerkleProof {
+    /// `verify_detailed` without the reason for a failure.
+    pub fn verify(&self, leaf_hash: [u8; 32], root_hash: [u8; 32]) -> bool {
+        self.verify_detailed(leaf_hash, root_hash).is_ok()
+    }
+
+    pub fn verify_with<H: MerkleHasher>(&self, hasher: &H, leaf_hash: [u8; 32], root_hash: [u8; 32]) -> bool {
+        self.check_shape().is_ok()
+            && self.siblings.iter().all(|(sibling, _)| hasher.is_canonical(sibling))
+            && self.fold_root(hasher, leaf_hash) == root_hash
+    }
+
+    /// Checks that the path fits `self.index` and that each sibling sits on
+    /// the side that index puts it, then that the walk reaches `root_hash`.
+    pub fn verify_detailed(&self, leaf_hash: [u8; 32], root_hash: [u8; 32]) -> std::result::Result<(), MerkleVerifyError> {
+        self.check_shape()?;
+        let computed = self.compute_root(leaf_hash);
+        if computed != root_hash {
+            return Err(MerkleVerifyError::RootMismatch {
+                computed,
+                expected: root_hash,
+            });
+        }
+        Ok(())
+    }
+
+    pub fn path_len(&self) -> usize {
+        self.siblings.len()
+    }
+
+    /// Root this path yields for `leaf_hash` under the default hasher.
+    pub fn compute_root(&self, leaf_hash: [u8; 32]) -> [u8; 32] {
+        self.fold_root(&Sha256Hasher, leaf_hash)
+    }
+
+    fn check_shape(&self) -> std::result::Result<(), MerkleVerifyError> {
+        check_path(self.index, self.siblings.iter().map(|(_, is_left)| *is_left))
+    }
+
+    fn fold_root<H: MerkleHasher>(&self, hasher: &H, leaf_hash: [u8; 32]) -> [u8; 32] {
+        self.siblings.iter().fold(leaf_hash, |current, (sibling, is_left)| {
+            if *is_left {
+                hasher.hash_internal(&current, sibling)
+            } else {
+                hasher.hash_internal(sibling, &current)
+            }
+        })
+    }
+
+    /// Verifies against a committed root, checking that the path shape matches
+    /// `index` in a tree of exactly `leaf_count` leaves.
+    pub fn verify_committed(&self, leaf_hash: [u8; 32], committed: [u8; 32], leaf_count: usize) -> bool {
+        if self.index >= leaf_count {
+            return false;
+        }
+        if !self.siblings.iter().map(|(_, is_left)| *is_left).eq(expected_directions(self.index, leaf_count)) {
+            return false;
+        }
+        let raw_root = self.fold_root(&Sha256Hasher, leaf_hash);
+        committed_root(raw_root, leaf_count) == committed
+    }
+}
+
+// Proof layouts (integers little-endian): index u64 | depth u8 |
+// ceil(depth / 8) bytes of is_left bits, LSB first | depth siblings, each a
+// hash[32] for `MerkleProof` and hash[32] | count u64 | point[32] for
+// `AggregatePathProof`.
+
+fn encode_path(index: usize, directions: impl ExactSizeIterator<Item = bool>, sibling_len: usize) -> Vec<u8> {
+    let depth = directions.len();
+    let mut bytes = Vec::with_capacity(9 + depth.div_ceil(8) + depth * sibling_len);
+    bytes.extend_from_slice(&(index as u64).to_le_bytes());
+    bytes.push(depth as u8);
+    let mut bits = vec![0u8; depth.div_ceil(8)];
+    for (i, is_left) in directions.enumerate() {
+        bits[i / 8] |= (is_left as u8) << (i % 8);
+    }
+    bytes.extend_from_slice(&bits);
+    bytes
+}
+
+/// Splits an encoded path into its index, its directions and the sibling
+/// bytes, one `sibling_len` chunk per level. Rejects paths deeper than
+/// `MAX_PROOF_DEPTH` before allocating.
+fn decode_path(bytes: &[u8], sibling_len: usize) -> Result<(usize, Vec<bool>, &[u8])> {
+    let header = bytes.get(..9).ok_or_else(|| truncated("Merkle proof header"))?;
+    let index = usize::try_from(u64::from_le_bytes(header[..8].try_into().unwrap()))
+        .map_err(|_| ArchimedesError::serialization(SerializationErrorKind::Encoding))?;
+    let depth = header[8] as usize;
+    if depth > MAX_PROOF_DEPTH {
+        return Err(ArchimedesError::serialization(SerializationErrorKind::ProofTooDeep {
+            depth,
+            max: MAX_PROOF_DEPTH,
+        }));
+    }
+    let (bits, siblings) = bytes[9..]
+        .split_at_checked(depth.div_ceil(8))
+        .ok_or_else(|| truncated("Merkle proof directions"))?;
+    // Bits past `depth` must be clear, so each proof has one encoding.
+    if !depth.is_multiple_of(8) && bits[depth / 8] >> (depth % 8) != 0 {
+        return Err(ArchimedesError::serialization(SerializationErrorKind::Encoding));
+    }
+    if siblings.len() < depth * sibling_len {
+        return Err(truncated("Merkle proof siblings"));
+    }
+    if siblings.len() > depth * sibling_len {
+        return Err(trailing("Merkle proof"));
+    }
+    let directions = (0..depth).map(|i| bits[i / 8] >> (i % 8) & 1 == 1).collect();
+    Ok((index, directions, siblings))
+}
+
+impl MerkleProof {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = encode_path(self.index, self.siblings.iter().map(|(_, is_left)| *is_left), HASH_LEN);
+        for (hash, _) in &self.siblings {
+            bytes.extend_from_slice(hash);
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let (index, directions, siblings) = decode_path(bytes, HASH_LEN)?;
+        let siblings = siblings.chunks_exact(HASH_LEN).zip(directions).map(|(hash, is_left)| (hash.try_into().unwrap(), is_left)).collect();
+        Ok(Self { index, siblings })
+    }
+}
+
+/// Inclusion proof in a commitment tree. Each sibling carries its aggregate
+/// alongside its hash; node hashes bind aggregates, so the path fixes every
+/// sibling's aggregate and the leaf's share of each one above it.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AggregatePathProof {
+    pub index: usize,
+    pub siblings: Vec<([u8; 32], AggregateCommitment, bool)>,
+}
+
+impl AggregatePathProof {
+    /// `verify_detailed` without the reason for a failure.
+    pub fn verify(&self, leaf: &Commitment, root_hash: [u8; 32]) -> bool {
+        self.verify_detailed(leaf, root_hash).is_ok()
+    }
+
+    pub fn verify_with<H: MerkleHasher>(&self, hasher: &H, leaf: &Commitment, root_hash: [u8; 32]) -> bool {
+        self.check_shape().is_ok() && self.fold_root(hasher, leaf).is_ok_and(|root| root.hash == root_hash)
+    }
+
+    /// Verifies `leaf` as leaf `self.index` against a raw root. The leaf hash
+    /// is recomputed from the index, so a valid proof cannot be relabelled.
+    pub fn verify_detailed(&self, leaf: &Commitment, root_hash: [u8; 32]) -> std::result::Result<(), MerkleVerifyError> {
+        self.check_shape()?;
+        let computed = self.compute_root(leaf)?.hash;
+        if computed != root_hash {
+            return Err(MerkleVerifyError::RootMismatch {
+                computed,
+                expected: root_hash,
+            });
+        }
+        Ok(())
+    }
+
+    /// Verifies against a committed root, checking that the path shape matches
+    /// `index` in a tree of exactly `leaf_count` leaves.
+    pub fn verify_committed(&self, leaf: &Commitment, committed: [u8; 32], leaf_count: usize) -> bool {
+        self.committed_node(leaf, committed, leaf_count).is_ok()
+    }
+
+    /// `verify_committed` with the reason for a failure. A mismatch reports
+    /// the committed root the path yields.
+    pub fn verify_committed_detailed(
+        &self,
+        leaf: &Commitment,
+        committed: [u8; 32],
+        leaf_count: usize,
+    ) -> std::result::Result<(), MerkleVerifyError> {
+        self.committed_node(leaf, committed, leaf_count).map(|_| ())
+    }
+
+    /// `verify_committed`, also requiring the root aggregate to equal
+    /// `root_aggregate`, which must come from the same trusted source as
+    /// `committed`.
+    pub fn verify_aggregate(
+        &self,
+        leaf: &Commitment,
+        committed: [u8; 32],
+        leaf_count: usize,
+        root_aggregate: &AggregateCommitment,
+    ) -> bool {
+        self.committed_node(leaf, committed, leaf_count).is_ok_and(|root| {
+            root.aggregate.count == root_aggregate.count && root.aggregate.commitment == root_aggregate.commitment
+        })
+    }
+
+    pub fn path_len(&self) -> usize {
+        self.siblings.len()
+    }
+
+    /// Root node this path yields for `leaf` under the default hasher.
+    pub fn compute_root(&self, leaf: &Commitment) -> std::result::Result<MerkleNode, MerkleVerifyError> {
+        self.fold_root(&Sha256Hasher, leaf)
+    }
+
+    fn committed_node(
+        &self,
+        leaf: &Commitment,
+        committed: [u8; 32],
+        leaf_count: usize,
+    ) -> std::result::Result<MerkleNode, MerkleVerifyError> {
+        let expected = expected_directions(self.index, leaf_count);
+        if self.index >= leaf_count || self.siblings.len() != expected.len() {
+            return Err(MerkleVerifyError::WrongDepth {
+                index: self.index,
+                depth: self.siblings.len(),
+            });
+        }
+        if let Some(level) = self.siblings.iter().zip(&expected).position(|((_, _, is_left), expected)| is_left != expected) {
+            return Err(MerkleVerifyError::WrongDirection { index: self.index, level });
+        }
+        let root = self.compute_root(leaf)?;
+        let computed = committed_root(root.hash, leaf_count);
+        if computed != committed {
+            return Err(MerkleVerifyError::RootMismatch {
+                computed,
+                expected: committed,
+            });
+        }
+        Ok(root)
+    }
+
+    fn check_shape(&self) -> std::result::Result<(), MerkleVerifyError> {
+        check_path(self.index, self.siblings.iter().map(|(_, _, is_left)| *is_left))
+    }
+
+    fn fold_root<H: MerkleHasher>(&self, hasher: &H, leaf: &Commitment) -> std::result::Result<MerkleNode, MerkleVerifyError> {
+        let mut node = MerkleNode::leaf_with_hasher(hasher, leaf, &leaf.to_affine(), self.index);
+        for (level, (hash, aggregate, is_left)) in self.siblings.iter().enumerate() {
+            if !hasher.is_canonical(hash) {
+                return Err(MerkleVerifyError::NonCanonicalSibling { level });
+            }
+            let sibling = MerkleNode {
+                hash: *hash,
+                aggregate: aggregate.clone(),
+            };
+            let (left, right) = if *is_left { (&node, &sibling) } else { (&sibling, &node) };
+            node = MerkleNode::internal_with_hasher(hasher, left, right)
+                .map_err(|_| MerkleVerifyError::CountOverflow { level })?;
+        }
+        Ok(node)
+    }
+}
+
+impl AggregatePathProof {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = encode_path(self.index, self.siblings.iter().map(|(_, _, is_left)| *is_left), AGGREGATE_SIBLING_LEN);
+        for (hash, aggregate, _) in &self.siblings {
+            bytes.extend_from_slice(hash);
+            bytes.extend_from_slice(&(aggregate.count as u64).to_le_bytes());
+            bytes.extend_from_slice(&aggregate.commitment.to_bytes());
+        }
+        bytes
+    }
+
+    /// Rejects aggregate points outside the prime-order subgroup.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let (index, directions, siblings) = decode_path(bytes, AGGREGATE_SIBLING_LEN)?;
+        let siblings = siblings
+            .chunks_exact(AGGREGATE_SIBLING_LEN)
+            .zip(directions)
+            .map(|(sibling, is_left)| {
+                let (hash, rest) = sibling.split_at(HASH_LEN);
+                let (count, point) = rest.split_at(8);
+                let count = usize::try_from(u64::from_le_bytes(count.try_into().unwrap()))
+                    .map_err(|_| ArchimedesError::serialization(SerializationErrorKind::Encoding))?;
+                let aggregate = AggregateCommitment {
+                    commitment: Commitment::from_bytes(point)?,
+                    count,
+                };
+                Ok((hash.try_into().unwrap(), aggregate, is_left))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self { index, siblings })
+    }
+}
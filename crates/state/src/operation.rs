@@ -0,0 +1,151 @@
+use archimedes_core::{ArchimedesError, StateEncodingErrorKind};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::encoding::AccountState;
+use crate::storage_trie::StorageProof;
+
+type Result<T> = std::result::Result<T, ArchimedesError>;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum TransitionOperation {
+    Transfer { amount: u128 },
+    NonceIncrement,
+    StorageWrite { key: [u8; 32], value: [u8; 32] },
+    CodeDeploy { code_hash: [u8; 32] },
+}
+
+impl TransitionOperation {
+    pub fn hash(&self) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        match self {
+            TransitionOperation::Transfer { amount } => {
+                hasher.update(b"transfer");
+                hasher.update(amount.to_le_bytes());
+            }
+            TransitionOperation::NonceIncrement => {
+                hasher.update(b"nonce_inc");
+            }
+            TransitionOperation::StorageWrite { key, value } => {
+                hasher.update(b"storage_write");
+                hasher.update(key);
+                hasher.update(value);
+            }
+            TransitionOperation::CodeDeploy { code_hash } => {
+                hasher.update(b"code_deploy");
+                hasher.update(code_hash);
+            }
+        }
+        let result = hasher.finalize();
+        let mut hash = [0u8; 32];
+        hash.copy_from_slice(&result);
+        hash
+    }
+}
+
+impl AccountState {
+    /// The state after `op`: a transfer debits the sender and bumps its
+    /// nonce, a code deploy sets the code and bumps the nonce, and a nonce
+    /// increment does just that. Storage writes need the trie and go
+    /// through `apply_with`.
+    pub fn apply(&self, op: &TransitionOperation) -> Result<AccountState> {
+        self.apply_with(op, None)
+    }
+
+    /// `apply`, with `storage` opening the written key against this
+    /// state's `storage_root` for a `StorageWrite`. Only the storage root
+    /// changes on a write.
+    pub fn apply_with(&self, op: &TransitionOperation, storage: Option<&StorageProof>) -> Result<AccountState> {
+        let error = ArchimedesError::StateEncodingError;
+        let bump_nonce = || self.nonce.checked_add(1).ok_or(error(StateEncodingErrorKind::NonceOverflow));
+        match op {
+            TransitionOperation::Transfer { amount } => Ok(AccountState {
+                balance: self.balance.checked_sub(*amount).ok_or(error(
+                    StateEncodingErrorKind::InsufficientBalance {
+                        balance: self.balance,
+                        amount: *amount,
+                    },
+                ))?,
+                nonce: bump_nonce()?,
+                ..self.clone()
+            }),
+            TransitionOperation::NonceIncrement => Ok(AccountState {
+                nonce: bump_nonce()?,
+                ..self.clone()
+            }),
+            TransitionOperation::StorageWrite { key, value } => {
+                let proof = storage
+                    .filter(|p| p.key == *key && p.verify(self.storage_root))
+                    .ok_or(error(StateEncodingErrorKind::MissingStorageProof))?;
+                Ok(AccountState {
+                    storage_root: proof.root_with(Some(*value)).expect("a verified proof is well-formed"),
+                    ..self.clone()
+                })
+            }
+            TransitionOperation::CodeDeploy { code_hash } => Ok(AccountState {
+                nonce: bump_nonce()?,
+                code_hash: *code_hash,
+                ..self.clone()
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::code::code_hash;
+    use crate::storage_trie::StorageTrie;
+
+    #[test]
+    fn test_operation_hash_domain_separation() {
+        let transfer = TransitionOperation::Transfer { amount: 7 };
+        assert_eq!(transfer.hash(), TransitionOperation::Transfer { amount: 7 }.hash());
+        assert_ne!(transfer.hash(), TransitionOperation::Transfer { amount: 8 }.hash());
+        assert_ne!(transfer.hash(), TransitionOperation::NonceIncrement.hash());
+    }
+
+    #[test]
+    fn test_apply_operations() {
+        let state = AccountState::new(100, 4);
+        let debited = state.apply(&TransitionOperation::Transfer { amount: 40 }).unwrap();
+        assert_eq!(debited, AccountState::new(60, 5));
+        assert_eq!(state.apply(&TransitionOperation::NonceIncrement).unwrap(), AccountState::new(100, 5));
+        let deployed = state.apply(&TransitionOperation::CodeDeploy { code_hash: code_hash(b"code") }).unwrap();
+        assert!(deployed.verify_code(b"code"));
+        assert_eq!(deployed.nonce, 5);
+
+        assert!(matches!(
+            state.apply(&TransitionOperation::Transfer { amount: 101 }),
+            Err(ArchimedesError::StateEncodingError(StateEncodingErrorKind::InsufficientBalance {
+                balance: 100,
+                amount: 101
+            }))
+        ));
+        assert!(matches!(
+            AccountState::new(100, u64::MAX).apply(&TransitionOperation::NonceIncrement),
+            Err(ArchimedesError::StateEncodingError(StateEncodingErrorKind::NonceOverflow))
+        ));
+    }
+
+    #[test]
+    fn test_apply_storage_write() {
+        let mut trie = StorageTrie::new();
+        let mut state = AccountState::new(100, 4);
+        state.apply_storage_write([1; 32], [2; 32], &mut trie).unwrap();
+        let write = TransitionOperation::StorageWrite { key: [3; 32], value: [4; 32] };
+        let proof = trie.prove(&[3; 32]);
+
+        let post = state.apply_with(&write, Some(&proof)).unwrap();
+        let mut expected = state.clone();
+        expected.apply_storage_write([3; 32], [4; 32], &mut trie).unwrap();
+        assert_eq!(post, expected);
+
+        for storage in [None, Some(&trie.prove(&[3; 32])), Some(&trie.prove(&[5; 32]))] {
+            assert!(matches!(
+                state.apply_with(&write, storage),
+                Err(ArchimedesError::StateEncodingError(StateEncodingErrorKind::MissingStorageProof))
+            ));
+        }
+    }
+}
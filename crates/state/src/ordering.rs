@@ -0,0 +1,87 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+const ORDERING_DOMAIN: &[u8] = b"archimedes-batch-ordering-v1";
+
+/// The fields of a published transaction that determine its execution slot.
+/// Field order matters: the derived `Ord` is the canonical ordering rule.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct BatchTx {
+    pub sender: [u8; 32],
+    pub nonce: u64,
+    pub tx_hash: [u8; 32],
+}
+
+/// Canonical execution order for a batch, shared by proposers and re-executors.
+///
+/// Transactions are sorted by `(sender, nonce)` ascending, comparing sender keys
+/// bytewise. Two transactions with the same sender and nonce are ordered by
+/// ascending `tx_hash`, so the result never depends on arrival order.
+pub struct BatchOrdering;
+
+impl BatchOrdering {
+    pub fn canonical_order(txs: &[BatchTx]) -> Vec<BatchTx> {
+        let mut ordered = txs.to_vec();
+        ordered.sort();
+        ordered
+    }
+
+    pub fn is_canonical(txs: &[BatchTx]) -> bool {
+        txs.windows(2).all(|w| w[0] <= w[1])
+    }
+
+    pub fn ordering_digest(tx_hashes: &[[u8; 32]]) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update(ORDERING_DOMAIN);
+        hasher.update((tx_hashes.len() as u64).to_be_bytes());
+        for hash in tx_hashes {
+            hasher.update(hash);
+        }
+        hasher.finalize().into()
+    }
+
+    pub fn canonical_digest(txs: &[BatchTx]) -> [u8; 32] {
+        let hashes: Vec<[u8; 32]> = Self::canonical_order(txs).into_iter().map(|tx| tx.tx_hash).collect();
+        Self::ordering_digest(&hashes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::seq::SliceRandom;
+    use rand::SeedableRng;
+
+    fn tx(sender: u8, nonce: u64, hash: u8) -> BatchTx {
+        BatchTx {
+            sender: [sender; 32],
+            nonce,
+            tx_hash: [hash; 32],
+        }
+    }
+
+    #[test]
+    fn test_shuffled_inputs_order_identically() {
+        let txs = vec![tx(2, 0, 10), tx(1, 1, 11), tx(1, 0, 12), tx(3, 5, 13), tx(2, 1, 14), tx(1, 0, 9)];
+        let expected = BatchOrdering::canonical_order(&txs);
+        assert!(BatchOrdering::is_canonical(&expected));
+        assert_eq!(expected[0], tx(1, 0, 9));
+        assert_eq!(expected[1], tx(1, 0, 12));
+
+        let mut rng = rand::rngs::StdRng::seed_from_u64(7);
+        for _ in 0..20 {
+            let mut shuffled = txs.clone();
+            shuffled.shuffle(&mut rng);
+            assert_eq!(BatchOrdering::canonical_order(&shuffled), expected);
+            assert_eq!(BatchOrdering::canonical_digest(&shuffled), BatchOrdering::canonical_digest(&txs));
+        }
+    }
+
+    #[test]
+    fn test_ordering_digest_is_order_sensitive() {
+        let a = [[1u8; 32], [2u8; 32]];
+        let b = [[2u8; 32], [1u8; 32]];
+        assert_ne!(BatchOrdering::ordering_digest(&a), BatchOrdering::ordering_digest(&b));
+        assert_ne!(BatchOrdering::ordering_digest(&a[..1]), BatchOrdering::ordering_digest(&a));
+    }
+}
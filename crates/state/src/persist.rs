@@ -0,0 +1,165 @@
+use archimedes_availability::storage::StorageError;
+use archimedes_availability::{ContentAddressedStorage, ContentId};
+use archimedes_core::{ArchimedesError, Commitment, MerkleErrorKind, SerializationErrorKind};
+
+use crate::merkle::{CommitmentMerkleTree, LEAF_POINT_LEN};
+
+type Result<T> = std::result::Result<T, ArchimedesError>;
+
+pub const PERSIST_MANIFEST_MAGIC: [u8; 8] = *b"ARCHMTMF";
+pub const PERSIST_VERSION: u32 = 1;
+/// Leaves per stored page.
+pub const PERSIST_PAGE_LEAVES: usize = 1024;
+
+const ID_LEN: usize = 32;
+const MANIFEST_HEADER_LEN: usize = 8 + 4 + 8 + 4 + 32 + 4;
+
+// Manifest layout (integers little-endian):
+//   magic[8] | version u32 | leaf_count u64 | page_leaves u32 | root_hash[32]
+//   | page_count u32 | page_count × page id[32]
+// Each page holds up to `page_leaves` compressed leaf commitments. Internal
+// nodes are not stored; `load` rebuilds them and checks the root.
+
+fn storage_error(err: StorageError, index: usize, leaf_count: usize) -> ArchimedesError {
+    let kind = match err {
+        StorageError::NotFound(id) => MerkleErrorKind::MissingChunk(id),
+        other => MerkleErrorKind::StorageFailed(other.to_string()),
+    };
+    ArchimedesError::MerkleTreeError { index, leaf_count, kind }
+}
+
+fn corrupt(what: &'static str) -> ArchimedesError {
+    ArchimedesError::serialization(SerializationErrorKind::Truncated(what))
+}
+
+impl CommitmentMerkleTree {
+    /// Writes the leaves in pages of `PERSIST_PAGE_LEAVES` plus a manifest,
+    /// returning the manifest's id. Blobs are stored with timestamp 0.
+    pub fn persist(&self, store: &mut ContentAddressedStorage) -> Result<ContentId> {
+        let leaves = self.level(0)?;
+        let mut manifest = Vec::with_capacity(MANIFEST_HEADER_LEN + leaves.len().div_ceil(PERSIST_PAGE_LEAVES) * ID_LEN);
+        manifest.extend_from_slice(&PERSIST_MANIFEST_MAGIC);
+        manifest.extend_from_slice(&PERSIST_VERSION.to_le_bytes());
+        manifest.extend_from_slice(&(self.leaf_count() as u64).to_le_bytes());
+        manifest.extend_from_slice(&(PERSIST_PAGE_LEAVES as u32).to_le_bytes());
+        manifest.extend_from_slice(&self.root_hash());
+        manifest.extend_from_slice(&(leaves.len().div_ceil(PERSIST_PAGE_LEAVES) as u32).to_le_bytes());
+        for (page, chunk) in leaves.chunks(PERSIST_PAGE_LEAVES).enumerate() {
+            let mut bytes = Vec::with_capacity(chunk.len() * LEAF_POINT_LEN);
+            for leaf in chunk {
+                bytes.extend_from_slice(&leaf.aggregate.commitment.to_bytes());
+            }
+            let id = store
+                .store(bytes, 0)
+                .map_err(|e| storage_error(e, page * PERSIST_PAGE_LEAVES, self.leaf_count()))?;
+            manifest.extend_from_slice(&id.0);
+        }
+        store.store(manifest, 0).map_err(|e| storage_error(e, 0, self.leaf_count()))
+    }
+
+    /// Rebuilds a tree written by `persist`, failing with `TreeRootMismatch`
+    /// if the rebuilt root differs from the manifest's.
+    pub fn load(store: &ContentAddressedStorage, manifest_id: &ContentId) -> Result<Self> {
+        let manifest = store.retrieve(manifest_id).map_err(|e| storage_error(e, 0, 0))?;
+        let header = manifest.get(..MANIFEST_HEADER_LEN).ok_or_else(|| corrupt("tree manifest header"))?;
+        if header[..8] != PERSIST_MANIFEST_MAGIC {
+            return Err(ArchimedesError::serialization(SerializationErrorKind::WrongMagic("tree manifest")));
+        }
+        let version = u32::from_le_bytes(header[8..12].try_into().unwrap());
+        if version != PERSIST_VERSION {
+            return Err(ArchimedesError::serialization(SerializationErrorKind::UnsupportedFormatVersion {
+                format: "tree manifest",
+                version,
+            }));
+        }
+        let leaf_count = usize::try_from(u64::from_le_bytes(header[12..20].try_into().unwrap()))
+            .map_err(|_| ArchimedesError::serialization(SerializationErrorKind::Encoding))?;
+        let page_leaves = u32::from_le_bytes(header[20..24].try_into().unwrap()) as usize;
+        let root_hash: [u8; 32] = header[24..56].try_into().unwrap();
+        let page_count = u32::from_le_bytes(header[56..60].try_into().unwrap()) as usize;
+        if page_leaves == 0 || page_count != leaf_count.div_ceil(page_leaves) {
+            return Err(ArchimedesError::serialization(SerializationErrorKind::Encoding));
+        }
+        let ids = &manifest[MANIFEST_HEADER_LEN..];
+        if ids.len() < page_count * ID_LEN {
+            return Err(corrupt("tree manifest page ids"));
+        }
+        if ids.len() > page_count * ID_LEN {
+            return Err(ArchimedesError::serialization(SerializationErrorKind::TrailingBytes("tree manifest")));
+        }
+
+        let mut commitments = Vec::with_capacity(leaf_count);
+        for (page, id) in ids.chunks_exact(ID_LEN).enumerate() {
+            let id = ContentId(id.try_into().unwrap());
+            let bytes = store
+                .retrieve(&id)
+                .map_err(|e| storage_error(e, page * page_leaves, leaf_count))?;
+            let expected = page_leaves.min(leaf_count - page * page_leaves);
+            if bytes.len() != expected * LEAF_POINT_LEN {
+                return Err(corrupt("tree page"));
+            }
+            for leaf in bytes.chunks_exact(LEAF_POINT_LEN) {
+                commitments.push(Commitment::from_bytes(leaf)?);
+            }
+        }
+        let tree = Self::build(&commitments)?;
+        if tree.root_hash() != root_hash {
+            return Err(ArchimedesError::serialization(SerializationErrorKind::TreeRootMismatch));
+        }
+        Ok(tree)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use archimedes_core::RetentionRegistry;
+    use ark_ec::PrimeGroup;
+    use ark_ed_on_bls12_381::EdwardsProjective;
+
+    // Synthetic commitments i·g, cheap enough for thousands of leaves.
+    fn tree_of(n: usize) -> CommitmentMerkleTree {
+        let g = EdwardsProjective::generator();
+        let commitments: Vec<Commitment> = std::iter::successors(Some(g), |p| Some(*p + g)).take(n).map(Commitment).collect();
+        CommitmentMerkleTree::build(&commitments).unwrap()
+    }
+
+    #[test]
+    fn test_round_trip_with_proofs() {
+        let tree = tree_of(4000);
+        let mut store = ContentAddressedStorage::new(1 << 20);
+        let id = tree.persist(&mut store).unwrap();
+        let loaded = CommitmentMerkleTree::load(&store, &id).unwrap();
+        assert_eq!(loaded.committed_root(), tree.committed_root());
+        assert_eq!(loaded.aggregate().commitment, tree.aggregate().commitment);
+        for index in [0, 1023, 1024, 3999] {
+            let proof = loaded.generate_proof(index).unwrap();
+            let leaf = &tree.level(0).unwrap()[index].aggregate.commitment;
+            assert!(proof.verify_committed(leaf, tree.committed_root(), 4000));
+        }
+    }
+
+    #[test]
+    fn test_missing_and_reordered_pages() {
+        let tree = tree_of(2500);
+        let mut store = ContentAddressedStorage::new(1 << 20);
+        let id = tree.persist(&mut store).unwrap();
+        let manifest = store.retrieve(&id).unwrap().to_vec();
+        let page = |i: usize| ContentId(manifest[MANIFEST_HEADER_LEN + i * ID_LEN..][..ID_LEN].try_into().unwrap());
+
+        let mut reordered = manifest.clone();
+        reordered[MANIFEST_HEADER_LEN..MANIFEST_HEADER_LEN + 2 * ID_LEN].rotate_left(ID_LEN);
+        let reordered_id = store.store(reordered, 0).unwrap();
+        assert!(matches!(
+            CommitmentMerkleTree::load(&store, &reordered_id),
+            Err(ArchimedesError::SerializationError { kind: SerializationErrorKind::TreeRootMismatch, .. })
+        ));
+
+        store.remove(&page(1), &RetentionRegistry::new(0)).unwrap();
+        assert!(matches!(
+            CommitmentMerkleTree::load(&store, &id),
+            Err(ArchimedesError::MerkleTreeError { index: 1024, kind: MerkleErrorKind::MissingChunk(_), .. })
+        ));
+        assert!(CommitmentMerkleTree::load(&store, &page(0)).is_err());
+    }
+}
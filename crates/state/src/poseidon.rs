@@ -0,0 +1,345 @@
+use ark_crypto_primitives::sponge::poseidon::{find_poseidon_ark_and_mds, PoseidonConfig, PoseidonSponge};
+use ark_crypto_primitives::sponge::{CryptographicSponge, FieldBasedCryptographicSponge};
+use ark_ed_on_bls12_381::Fq;
+use ark_ff::{BigInteger, PrimeField};
+
+use std::sync::OnceLock;
+
+use crate::encoding::{AccountState, HashScheme, StateTransition, TransferTransition};
+use crate::merkle_proof::MerkleHasher;
+
+const LEAF_TAG: u64 = 0;
+const INTERNAL_TAG: u64 = 1;
+const ACCOUNT_TAG: u64 = 2;
+const TRANSITION_TAG: u64 = 3;
+const AGGREGATE_NODE_TAG: u64 = 4;
+const TRANSFER_TAG: u64 = 5;
+/// Absorbed first by the state hashes.
+const POSEIDON_SCHEME: u64 = HashScheme::Poseidon.id() as u64;
+
+// Width-3 Poseidon (rate 2, capacity 1) with x^5 S-boxes, the usual
+// parameters for the 255-bit BLS12-381 scalar field.
+const RATE: usize = 2;
+const FULL_ROUNDS: usize = 8;
+const PARTIAL_ROUNDS: usize = 57;
+const ALPHA: u64 = 5;
+
+/// Poseidon node hashing over the BLS12-381 scalar field, which the
+/// transition circuit works in. Hashes are the little-endian encoding of the
+/// squeezed field element. Leaves absorb the 32-byte commitment as two
+/// 16-byte limbs; child hashes are read back as field elements, and proof
+/// siblings that are not canonical encodings of one are rejected.
+#[derive(Clone, Debug)]
+pub struct PoseidonMerkleHasher {
+    config: PoseidonConfig<Fq>,
+}
+
+impl Default for PoseidonMerkleHasher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PoseidonMerkleHasher {
+    pub fn new() -> Self {
+        let (ark, mds) = find_poseidon_ark_and_mds::<Fq>(
+            Fq::MODULUS_BIT_SIZE as u64,
+            RATE,
+            FULL_ROUNDS as u64,
+            PARTIAL_ROUNDS as u64,
+            0,
+        );
+        Self {
+            config: PoseidonConfig::new(FULL_ROUNDS, PARTIAL_ROUNDS, ALPHA, mds, ark, RATE, 1),
+        }
+    }
+
+    /// Parameter generation is slow, so the state hashes share one hasher.
+    fn shared() -> &'static Self {
+        static HASHER: OnceLock<PoseidonMerkleHasher> = OnceLock::new();
+        HASHER.get_or_init(Self::new)
+    }
+
+    fn hash(&self, inputs: &[Fq]) -> [u8; 32] {
+        let mut sponge = PoseidonSponge::new(&self.config);
+        sponge.absorb(&inputs);
+        let out = sponge.squeeze_native_field_elements(1)[0];
+        let mut bytes = [0u8; 32];
+        bytes.copy_from_slice(&out.into_bigint().to_bytes_le());
+        bytes
+    }
+}
+
+impl MerkleHasher for PoseidonMerkleHasher {
+    fn hash_leaf(&self, index: usize, commitment: &[u8]) -> [u8; 32] {
+        let (lo, hi) = commitment.split_at(commitment.len() / 2);
+        self.hash(&[
+            Fq::from(LEAF_TAG),
+            Fq::from(index as u64),
+            Fq::from_le_bytes_mod_order(lo),
+            Fq::from_le_bytes_mod_order(hi),
+        ])
+    }
+
+    fn hash_internal(&self, left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+        self.hash(&[
+            Fq::from(INTERNAL_TAG),
+            Fq::from_le_bytes_mod_order(left),
+            Fq::from_le_bytes_mod_order(right),
+        ])
+    }
+
+    /// The aggregate's count, then its compressed point as two 16-byte limbs.
+    fn is_canonical(&self, hash: &[u8; 32]) -> bool {
+        Fq::from_le_bytes_mod_order(hash).into_bigint().to_bytes_le() == hash
+    }
+
+    fn hash_node(&self, left: &[u8; 32], right: &[u8; 32], aggregate: &[u8]) -> [u8; 32] {
+        let (count, point) = aggregate.split_at(8);
+        let (lo, hi) = point.split_at(point.len() / 2);
+        self.hash(&[
+            Fq::from(AGGREGATE_NODE_TAG),
+            Fq::from_le_bytes_mod_order(left),
+            Fq::from_le_bytes_mod_order(right),
+            Fq::from(u64::from_be_bytes(count.try_into().expect("aggregate starts with a u64 count"))),
+            Fq::from_le_bytes_mod_order(lo),
+            Fq::from_le_bytes_mod_order(hi),
+        ])
+    }
+}
+
+fn hash_limbs(bytes: &[u8; 32]) -> [Fq; 2] {
+    [Fq::from_le_bytes_mod_order(&bytes[..16]), Fq::from_le_bytes_mod_order(&bytes[16..])]
+}
+
+impl AccountState {
+    /// Poseidon over `[scheme, tag, balance lo, balance hi, nonce, code_hash
+    /// limbs, storage_root limbs]`, each hash split into 16-byte limbs.
+    pub fn poseidon_hash(&self) -> [u8; 32] {
+        let [code_lo, code_hi] = hash_limbs(&self.code_hash);
+        let [storage_lo, storage_hi] = hash_limbs(&self.storage_root);
+        PoseidonMerkleHasher::shared().hash(&[
+            Fq::from(POSEIDON_SCHEME),
+            Fq::from(ACCOUNT_TAG),
+            Fq::from(self.balance as u64),
+            Fq::from((self.balance >> 64) as u64),
+            Fq::from(self.nonce),
+            code_lo,
+            code_hi,
+            storage_lo,
+            storage_hi,
+        ])
+    }
+}
+
+impl StateTransition {
+    /// Poseidon over `[scheme, tag, pre hash, post hash, tx_hash limbs]`,
+    /// reading the state hashes back as field elements.
+    pub fn poseidon_transition_hash(&self) -> [u8; 32] {
+        let [tx_lo, tx_hi] = hash_limbs(&self.tx_hash);
+        PoseidonMerkleHasher::shared().hash(&[
+            Fq::from(POSEIDON_SCHEME),
+            Fq::from(TRANSITION_TAG),
+            Fq::from_le_bytes_mod_order(&self.pre_state.poseidon_hash()),
+            Fq::from_le_bytes_mod_order(&self.post_state.poseidon_hash()),
+            tx_lo,
+            tx_hi,
+        ])
+    }
+}
+
+impl TransferTransition {
+    /// Poseidon over `[scheme, tag, sender pre, sender post, receiver pre,
+    /// receiver post, amount lo, amount hi, tx_hash limbs]`, reading the
+    /// state hashes back as field elements.
+    pub fn poseidon_transfer_hash(&self) -> [u8; 32] {
+        let [tx_lo, tx_hi] = hash_limbs(&self.tx_hash);
+        let state = |account: &AccountState| Fq::from_le_bytes_mod_order(&account.poseidon_hash());
+        PoseidonMerkleHasher::shared().hash(&[
+            Fq::from(POSEIDON_SCHEME),
+            Fq::from(TRANSFER_TAG),
+            state(&self.sender_pre),
+            state(&self.sender_post),
+            state(&self.receiver_pre),
+            state(&self.receiver_post),
+            Fq::from(self.amount as u64),
+            Fq::from((self.amount >> 64) as u64),
+            tx_lo,
+            tx_hi,
+        ])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encoding::CommitmentEncoding;
+    use crate::merkle_proof::MerkleProof;
+    #[cfg(feature = "prover")]
+    use crate::merkle::CommitmentMerkleTree;
+    #[cfg(feature = "prover")]
+    use archimedes_core::{CommitmentChain, CommitmentParams};
+    use ark_ed_on_bls12_381::Fr as ScalarField;
+    #[cfg(feature = "prover")]
+    use ark_std::test_rng;
+
+    const POSEIDON_ACCOUNT_HASH: &str = "5ae8f049472ad56827155412de8dd2c97f21d1b4e88545e8bee3ec2ecea8d038";
+    const POSEIDON_TRANSITION_HASH: &str = "5a4157151d7f330aff05159dd8e4d0b97c10ae2541dbe81e5d40517932698508";
+    const POSEIDON_EMPTY_ACCOUNT_HASH: &str = "f585c7d76a7d2c6052a2ead5ae0ffac3125db02fe465be789716ce336c21056a";
+
+    #[test]
+    #[cfg(feature = "prover")]
+    fn test_poseidon_tree_proofs() {
+        let mut rng = test_rng();
+        let mut chain = CommitmentChain::new(CommitmentParams::setup(&mut rng).unwrap());
+        for i in 0..7u64 {
+            chain.push(ScalarField::from(i), &mut rng).unwrap();
+        }
+        let hasher = PoseidonMerkleHasher::new();
+        let tree = CommitmentMerkleTree::build_with_hasher(chain.commitments(), &hasher).unwrap();
+        let sha_tree = CommitmentMerkleTree::from_chain(&chain).unwrap();
+        assert_ne!(tree.root_hash(), sha_tree.root_hash());
+        assert_eq!(tree.aggregate().commitment, sha_tree.aggregate().commitment);
+
+        // Each hash is a canonical field element.
+        assert!(Fq::from_le_bytes_mod_order(&tree.root_hash()).into_bigint().to_bytes_le() == tree.root_hash());
+
+        for i in 0..7 {
+            let proof = tree.generate_proof(i).unwrap();
+            assert!(proof.verify_with(tree.hasher(), &chain.commitments()[i], tree.root_hash()));
+            assert!(!proof.verify(&chain.commitments()[i], tree.root_hash()));
+            let sha_proof = sha_tree.generate_proof(i).unwrap();
+            assert!(!sha_proof.verify_with(&hasher, &chain.commitments()[i], sha_tree.root_hash()));
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "prover")]
+    fn test_poseidon_subtree_link() {
+        let mut rng = test_rng();
+        let mut chain = CommitmentChain::new(CommitmentParams::setup(&mut rng).unwrap());
+        for i in 0..11u64 {
+            chain.push(ScalarField::from(i), &mut rng).unwrap();
+        }
+        let hasher = PoseidonMerkleHasher::new();
+        let tree = CommitmentMerkleTree::build_with_hasher(chain.commitments(), &hasher).unwrap();
+        let (subtree, link) = tree.extract_subtree(2, 9).unwrap();
+        let rebuilt = CommitmentMerkleTree::build_with_hasher(&chain.commitments()[2..9], &hasher).unwrap();
+        assert_eq!(subtree.root_hash(), rebuilt.root_hash());
+        assert!(link.verify_with(&hasher, subtree.root(), tree.committed_root(), 2, 9));
+        assert!(!link.verify(subtree.root(), tree.committed_root(), 2, 9));
+    }
+
+    /// `hash` plus the field order: the same element, encoded differently.
+    fn alias(hash: &[u8; 32]) -> [u8; 32] {
+        let mut value = Fq::from_le_bytes_mod_order(hash).into_bigint();
+        assert!(!value.add_with_carry(&Fq::MODULUS));
+        value.to_bytes_le().try_into().unwrap()
+    }
+
+    #[test]
+    fn test_non_canonical_siblings_rejected() {
+        let hasher = PoseidonMerkleHasher::new();
+        let (leaf, sibling) = (hasher.hash_leaf(0, &[1; 32]), hasher.hash_leaf(1, &[2; 32]));
+        let root = hasher.hash_internal(&leaf, &sibling);
+        assert_eq!(hasher.hash_internal(&leaf, &alias(&sibling)), root);
+        assert!(!hasher.is_canonical(&alias(&sibling)));
+
+        let proof = MerkleProof { index: 0, siblings: vec![(sibling, true)] };
+        assert!(proof.verify_with(&hasher, leaf, root));
+        let aliased = MerkleProof { index: 0, siblings: vec![(alias(&sibling), true)] };
+        assert!(!aliased.verify_with(&hasher, leaf, root));
+    }
+
+    #[test]
+    #[cfg(feature = "prover")]
+    fn test_non_canonical_tree_siblings_rejected() {
+        let mut rng = test_rng();
+        let mut chain = CommitmentChain::new(CommitmentParams::setup(&mut rng).unwrap());
+        for i in 0..6u64 {
+            chain.push(ScalarField::from(i), &mut rng).unwrap();
+        }
+        let hasher = PoseidonMerkleHasher::new();
+        let tree = CommitmentMerkleTree::build_with_hasher(chain.commitments(), &hasher).unwrap();
+        let leaf = &chain.commitments()[2];
+
+        let mut proof = tree.generate_proof(2).unwrap();
+        assert!(proof.verify_with(&hasher, leaf, tree.root_hash()));
+        proof.siblings[1].0 = alias(&proof.siblings[1].0);
+        assert!(!proof.verify_with(&hasher, leaf, tree.root_hash()));
+
+        let leaves = [(2, leaf.clone())];
+        let mut multi = tree.generate_multiproof(&[2]).unwrap();
+        assert!(multi.verify_with(&hasher, &leaves, tree.committed_root(), 6));
+        multi.siblings[0].hash = alias(&multi.siblings[0].hash);
+        assert!(!multi.verify_with(&hasher, &leaves, tree.committed_root(), 6));
+    }
+
+    fn sample_transition() -> StateTransition {
+        let mut pre = AccountState::new((1u128 << 64) + 7, 3);
+        pre.code_hash = [0xc0; 32];
+        pre.storage_root = [0x5e; 32];
+        let post = AccountState { balance: 7, nonce: 4, ..pre.clone() };
+        StateTransition::new(pre, post, [0xab; 32])
+    }
+
+    #[test]
+    fn test_poseidon_state_hash_vectors() {
+        let transition = sample_transition();
+        assert_eq!(hex::encode(transition.pre_state.poseidon_hash()), POSEIDON_ACCOUNT_HASH);
+        assert_eq!(hex::encode(transition.poseidon_transition_hash()), POSEIDON_TRANSITION_HASH);
+        assert_eq!(hex::encode(AccountState::new(0, 0).poseidon_hash()), POSEIDON_EMPTY_ACCOUNT_HASH);
+
+        let mut bumped = transition.clone();
+        bumped.post_state.balance += 1 << 64;
+        assert_ne!(bumped.poseidon_transition_hash(), transition.poseidon_transition_hash());
+    }
+
+    #[test]
+    fn test_hash_schemes_are_separated() {
+        let transition = sample_transition();
+        let account = &transition.pre_state;
+        let (sha, poseidon) = (HashScheme::Sha256, HashScheme::Poseidon);
+        assert_ne!(account.hash_with(sha).unwrap(), account.hash_with(poseidon).unwrap());
+        assert_ne!(transition.hash_with(sha).unwrap(), transition.hash_with(poseidon).unwrap());
+        assert_eq!(account.hash_with(poseidon).unwrap(), account.poseidon_hash());
+        assert_eq!(transition.hash_with(poseidon).unwrap(), transition.poseidon_transition_hash());
+        // Under `Full` the value is the whole squeezed element, or an error
+        // when that element is above the scalar field's order.
+        let (mut canonical, mut rejected) = (0, 0);
+        for b in 0..32u8 {
+            let transition = StateTransition { tx_hash: [b; 32], ..transition.clone() };
+            let hash = transition.poseidon_transition_hash();
+            match transition.commitment_value_with(poseidon, CommitmentEncoding::Full) {
+                Ok(value) => {
+                    assert_eq!(value.into_bigint().to_bytes_le(), hash);
+                    assert_ne!(value, transition.commitment_value_with(poseidon, CommitmentEncoding::Truncated).unwrap());
+                    canonical += 1;
+                }
+                Err(_) => {
+                    assert_ne!(ScalarField::from_le_bytes_mod_order(&hash).into_bigint().to_bytes_le(), hash);
+                    rejected += 1;
+                }
+            }
+        }
+        assert!(canonical > 0 && rejected > 0);
+
+        let transfer = TransferTransition {
+            sender_pre: transition.pre_state.clone(),
+            sender_post: transition.post_state.clone(),
+            receiver_pre: AccountState::new(1, 0),
+            receiver_post: AccountState::new(2, 0),
+            amount: 1,
+            tx_hash: transition.tx_hash,
+        };
+        assert_ne!(transfer.hash_with(sha).unwrap(), transfer.hash_with(poseidon).unwrap());
+        let moved = TransferTransition { amount: 1 << 64, ..transfer.clone() };
+        assert_ne!(transfer.poseidon_transfer_hash(), moved.poseidon_transfer_hash());
+
+        // The scheme and type tags keep state hashes apart from tree nodes
+        // over the same field elements.
+        let hasher = PoseidonMerkleHasher::new();
+        let node = hasher.hash(&[Fq::from(INTERNAL_TAG), Fq::from(POSEIDON_SCHEME), Fq::from(ACCOUNT_TAG)]);
+        assert_ne!(node, AccountState::new(0, 0).poseidon_hash());
+    }
+}
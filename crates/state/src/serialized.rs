@@ -0,0 +1,257 @@
+use archimedes_core::{AggregateCommitment, ArchimedesError, Commitment, SerializationErrorKind};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use serde::{Deserialize, Serialize};
+
+use crate::merkle::{CommitmentMerkleTree, MerkleHasher, MerkleNode, Sha256Hasher, LEAF_POINT_LEN};
+
+type Result<T> = std::result::Result<T, ArchimedesError>;
+
+pub const SERIALIZED_TREE_MAGIC: [u8; 8] = *b"ARCHMTRE";
+pub const SERIALIZED_TREE_VERSION: u32 = 1;
+
+const HASH_LEN: usize = 32;
+const TREE_HEADER_LEN: usize = 8 + 4 + 8 + HASH_LEN;
+
+// Layouts (integers little-endian; proofs are laid out in `merkle_proof`):
+//   node:  hash[32] | aggregate (compressed commitment, count u64)
+//   tree:  magic[8] | version u32 | leaf_count u64 | root_hash[32] | leaf_count × compressed commitment
+
+fn truncated(what: &'static str) -> ArchimedesError {
+    ArchimedesError::serialization(SerializationErrorKind::Truncated(what))
+}
+
+fn trailing(what: &'static str) -> ArchimedesError {
+    ArchimedesError::serialization(SerializationErrorKind::TrailingBytes(what))
+}
+
+impl MerkleNode {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.hash.to_vec();
+        self.aggregate.serialize_compressed(&mut bytes).expect("serializing to a Vec cannot fail");
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let hash = bytes.get(..HASH_LEN).ok_or_else(|| truncated("Merkle node hash"))?;
+        let mut body = &bytes[HASH_LEN..];
+        let aggregate = AggregateCommitment::deserialize_compressed(&mut body)?;
+        if !body.is_empty() {
+            return Err(trailing("Merkle node"));
+        }
+        Ok(Self {
+            hash: hash.try_into().unwrap(),
+            aggregate,
+        })
+    }
+}
+
+/// A tree reduced to its leaves and root hash. Internal nodes are rebuilt on
+/// load and the rebuilt root must match the stored one.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "SerializedTreeRepr", into = "SerializedTreeRepr")]
+pub struct SerializedTree {
+    pub leaves: Vec<Commitment>,
+    pub root_hash: [u8; 32],
+}
+
+/// Wire form: hex-encoded compressed leaves and root hash.
+#[derive(Serialize, Deserialize)]
+struct SerializedTreeRepr {
+    leaves: Vec<String>,
+    root_hash: String,
+}
+
+impl From<SerializedTree> for SerializedTreeRepr {
+    fn from(tree: SerializedTree) -> Self {
+        Self {
+            leaves: tree.leaves.iter().map(Commitment::to_hex).collect(),
+            root_hash: tree.root_hash.iter().map(|b| format!("{:02x}", b)).collect(),
+        }
+    }
+}
+
+impl TryFrom<SerializedTreeRepr> for SerializedTree {
+    type Error = ArchimedesError;
+
+    fn try_from(repr: SerializedTreeRepr) -> Result<Self> {
+        let invalid_hex = || ArchimedesError::serialization(SerializationErrorKind::InvalidHex);
+        if repr.root_hash.len() != 2 * HASH_LEN || !repr.root_hash.is_ascii() {
+            return Err(invalid_hex());
+        }
+        let mut root_hash = [0u8; 32];
+        for (i, byte) in root_hash.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&repr.root_hash[2 * i..2 * i + 2], 16).map_err(|_| invalid_hex())?;
+        }
+        Ok(Self {
+            leaves: repr.leaves.iter().map(|l| Commitment::from_hex(l)).collect::<Result<_>>()?,
+            root_hash,
+        })
+    }
+}
+
+impl SerializedTree {
+    pub fn into_tree(self) -> Result<CommitmentMerkleTree> {
+        self.into_tree_with_hasher(&Sha256Hasher)
+    }
+
+    /// For trees built with `build_with_hasher`; the same hasher must be used.
+    pub fn into_tree_with_hasher<H: MerkleHasher>(self, hasher: &H) -> Result<CommitmentMerkleTree<H>> {
+        let tree = CommitmentMerkleTree::build_with_hasher(&self.leaves, hasher)?;
+        if tree.root_hash() != self.root_hash {
+            return Err(ArchimedesError::serialization(SerializationErrorKind::TreeRootMismatch));
+        }
+        Ok(tree)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(TREE_HEADER_LEN + self.leaves.len() * LEAF_POINT_LEN);
+        bytes.extend_from_slice(&SERIALIZED_TREE_MAGIC);
+        bytes.extend_from_slice(&SERIALIZED_TREE_VERSION.to_le_bytes());
+        bytes.extend_from_slice(&(self.leaves.len() as u64).to_le_bytes());
+        bytes.extend_from_slice(&self.root_hash);
+        for leaf in &self.leaves {
+            bytes.extend_from_slice(&leaf.to_bytes());
+        }
+        bytes
+    }
+
+    /// Decodes and subgroup-checks the leaves; the root is checked by
+    /// `into_tree`.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let header = bytes.get(..TREE_HEADER_LEN).ok_or_else(|| truncated("Merkle tree header"))?;
+        if header[..8] != SERIALIZED_TREE_MAGIC {
+            return Err(ArchimedesError::serialization(SerializationErrorKind::WrongMagic("Merkle tree")));
+        }
+        let version = u32::from_le_bytes(header[8..12].try_into().unwrap());
+        if version != SERIALIZED_TREE_VERSION {
+            return Err(ArchimedesError::serialization(SerializationErrorKind::UnsupportedFormatVersion {
+                format: "Merkle tree",
+                version,
+            }));
+        }
+        let leaf_count = u64::from_le_bytes(header[12..20].try_into().unwrap());
+        let body = &bytes[TREE_HEADER_LEN..];
+        match (body.len() as u128).cmp(&(leaf_count as u128 * LEAF_POINT_LEN as u128)) {
+            std::cmp::Ordering::Less => return Err(truncated("Merkle tree leaves")),
+            std::cmp::Ordering::Greater => return Err(trailing("Merkle tree")),
+            std::cmp::Ordering::Equal => {}
+        }
+        Ok(Self {
+            leaves: body.chunks_exact(LEAF_POINT_LEN).map(Commitment::from_bytes).collect::<Result<_>>()?,
+            root_hash: header[20..].try_into().unwrap(),
+        })
+    }
+}
+
+impl CommitmentMerkleTree {
+    pub fn to_serialized(&self) -> SerializedTree {
+        SerializedTree {
+            leaves: self.level(0).expect("a tree always has a leaf level").iter().map(|leaf| leaf.aggregate.commitment.clone()).collect(),
+            root_hash: self.root_hash(),
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.to_serialized().to_bytes()
+    }
+
+    /// Loads a tree written by `to_bytes`, rebuilding internal nodes.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        SerializedTree::from_bytes(bytes)?.into_tree()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::merkle::AggregatePathProof;
+    use crate::merkle_proof::MAX_PROOF_DEPTH;
+    use archimedes_core::{CommitmentChain, CommitmentParams};
+    use ark_ed_on_bls12_381::Fr as ScalarField;
+    use ark_std::test_rng;
+
+    fn tree_of(n: u64) -> CommitmentMerkleTree {
+        let mut rng = test_rng();
+        let mut chain = CommitmentChain::new(CommitmentParams::setup(&mut rng).unwrap());
+        for i in 0..n {
+            chain.push(ScalarField::from(i), &mut rng).unwrap();
+        }
+        CommitmentMerkleTree::from_chain(&chain).unwrap()
+    }
+
+    #[test]
+    fn test_round_trips() {
+        let tree = tree_of(11);
+        let loaded = CommitmentMerkleTree::from_bytes(&tree.to_bytes()).unwrap();
+        assert_eq!(loaded.root_hash(), tree.root_hash());
+        assert_eq!(loaded.aggregate().commitment, tree.aggregate().commitment);
+
+        let json = serde_json::to_string(&tree.to_serialized()).unwrap();
+        let decoded: SerializedTree = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, tree.to_serialized());
+        assert_eq!(decoded.into_tree().unwrap().committed_root(), tree.committed_root());
+
+        let proof = tree.generate_proof(6).unwrap();
+        assert_eq!(AggregatePathProof::from_bytes(&proof.to_bytes()).unwrap().to_bytes(), proof.to_bytes());
+        let proof_json: AggregatePathProof = serde_json::from_str(&serde_json::to_string(&proof).unwrap()).unwrap();
+        assert_eq!(proof_json.to_bytes(), proof.to_bytes());
+
+        let node = tree.root().clone();
+        let decoded = MerkleNode::from_bytes(&node.to_bytes()).unwrap();
+        assert_eq!((decoded.hash, decoded.aggregate.count), (node.hash, 11));
+        let decoded: MerkleNode = serde_json::from_str(&serde_json::to_string(&node).unwrap()).unwrap();
+        assert_eq!(decoded.aggregate.commitment, node.aggregate.commitment);
+    }
+
+    #[test]
+    fn test_compact_proof_encoding() {
+        let tree = tree_of(11);
+        let proof = tree.generate_proof(6).unwrap();
+        let bytes = proof.to_bytes();
+        assert_eq!(bytes.len(), 8 + 1 + 1 + 4 * (HASH_LEN + 8 + LEAF_POINT_LEN));
+        assert_eq!(AggregatePathProof::from_bytes(&bytes).unwrap().to_bytes(), bytes);
+        assert!(AggregatePathProof::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(AggregatePathProof::from_bytes(&[bytes.as_slice(), &[0]].concat()).is_err());
+
+        let leaf = &tree.level(0).unwrap()[6].aggregate.commitment;
+        assert!(AggregatePathProof::from_bytes(&bytes).unwrap().verify(leaf, tree.root_hash()));
+        let mut flipped = bytes.clone();
+        flipped[9] ^= 0b10;
+        assert!(!AggregatePathProof::from_bytes(&flipped).unwrap().verify(leaf, tree.root_hash()));
+        let mut stray = bytes.clone();
+        stray[9] |= 0x80;
+        assert!(AggregatePathProof::from_bytes(&stray).is_err());
+
+        let mut deep = bytes;
+        deep[8] = (MAX_PROOF_DEPTH + 1) as u8;
+        assert!(matches!(
+            AggregatePathProof::from_bytes(&deep),
+            Err(ArchimedesError::SerializationError { kind: SerializationErrorKind::ProofTooDeep { .. }, .. })
+        ));
+    }
+
+    #[test]
+    fn test_corrupted_leaf_detected_on_load() {
+        let tree = tree_of(8);
+        let other = tree_of(9);
+        let mut serialized = tree.to_serialized();
+        serialized.leaves[3] = other.to_serialized().leaves[8].clone();
+        let bytes = serialized.to_bytes();
+        assert!(matches!(
+            CommitmentMerkleTree::from_bytes(&bytes),
+            Err(ArchimedesError::SerializationError { kind: SerializationErrorKind::TreeRootMismatch, .. })
+        ));
+
+        let bytes = tree.to_bytes();
+        assert!(CommitmentMerkleTree::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        let mut extended = bytes.clone();
+        extended.push(0);
+        assert!(CommitmentMerkleTree::from_bytes(&extended).is_err());
+        let mut future = bytes;
+        future[8..12].copy_from_slice(&(SERIALIZED_TREE_VERSION + 1).to_le_bytes());
+        assert!(matches!(
+            SerializedTree::from_bytes(&future),
+            Err(ArchimedesError::SerializationError { kind: SerializationErrorKind::UnsupportedFormatVersion { .. }, .. })
+        ));
+    }
+}
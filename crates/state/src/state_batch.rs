@@ -0,0 +1,182 @@
+use std::collections::BTreeMap;
+
+use ark_ed_on_bls12_381::Fr as ScalarField;
+use archimedes_core::{ArchimedesError, StateEncodingErrorKind};
+
+use crate::account_proof::account_leaf_hash;
+pub use crate::address::Address;
+use crate::encoding::{bytes_to_field, AccountState, StateTransition};
+use crate::merkle::{MerkleHasher, MerkleProof, Sha256Hasher, MERKLE_PADDING};
+
+type Result<T> = std::result::Result<T, ArchimedesError>;
+
+/// The accounts a block touches, kept in address order so the root does
+/// not depend on the order they were inserted in. Leaves are
+/// `SHA256(address || account.hash())`, paired and padded like the
+/// commitment tree, so `MerkleProof::verify` checks inclusion proofs.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StateBatch {
+    accounts: BTreeMap<Address, AccountState>,
+}
+
+impl StateBatch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the state previously held at `address`, if any.
+    pub fn insert(&mut self, address: Address, state: AccountState) -> Option<AccountState> {
+        self.accounts.insert(address, state)
+    }
+
+    pub fn get(&self, address: &Address) -> Option<&AccountState> {
+        self.accounts.get(address)
+    }
+
+    pub fn len(&self) -> usize {
+        self.accounts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.accounts.is_empty()
+    }
+
+    /// Accounts in address order, which is leaf order.
+    pub fn iter(&self) -> impl Iterator<Item = (&Address, &AccountState)> {
+        self.accounts.iter()
+    }
+
+    /// Moves `address` to `transition.post_state`, provided it currently
+    /// holds `transition.pre_state`.
+    pub fn apply_transition(&mut self, address: &Address, transition: &StateTransition) -> Result<()> {
+        let state = self
+            .accounts
+            .get_mut(address)
+            .ok_or(ArchimedesError::StateEncodingError(StateEncodingErrorKind::UnknownAddress))?;
+        if *state != transition.pre_state {
+            return Err(ArchimedesError::StateEncodingError(StateEncodingErrorKind::PreStateMismatch));
+        }
+        *state = transition.post_state.clone();
+        Ok(())
+    }
+
+    pub fn leaf_hash(address: &Address, state: &AccountState) -> [u8; 32] {
+        Self::leaf_hash_of(address, state.hash())
+    }
+
+    /// `leaf_hash` from an already computed `AccountState::hash`.
+    pub fn leaf_hash_of(address: &Address, account_hash: [u8; 32]) -> [u8; 32] {
+        account_leaf_hash(address, account_hash)
+    }
+
+    pub fn state_root(&self) -> Result<[u8; 32]> {
+        Ok(self.levels()?.last().expect("a tree always has a leaf level")[0])
+    }
+
+    pub fn inclusion_proof(&self, address: &Address) -> Result<MerkleProof> {
+        let index = self
+            .accounts
+            .keys()
+            .position(|a| a == address)
+            .ok_or(ArchimedesError::StateEncodingError(StateEncodingErrorKind::UnknownAddress))?;
+        let levels = self.levels()?;
+        let siblings = levels[..levels.len() - 1]
+            .iter()
+            .enumerate()
+            .map(|(level, hashes)| {
+                let position = index >> level;
+                let sibling = hashes.get(position ^ 1).copied().unwrap_or(MERKLE_PADDING);
+                (sibling, position.is_multiple_of(2))
+            })
+            .collect();
+        Ok(MerkleProof { index, siblings })
+    }
+
+    fn levels(&self) -> Result<Vec<Vec<[u8; 32]>>> {
+        if self.accounts.is_empty() {
+            return Err(ArchimedesError::StateEncodingError(StateEncodingErrorKind::EmptyStateBatch));
+        }
+        Ok(build_levels(self.iter().map(|(address, state)| Self::leaf_hash(address, state)).collect()))
+    }
+}
+
+/// Levels of the addressed tree from non-empty `leaves` up to the root.
+pub(crate) fn build_levels(leaves: Vec<[u8; 32]>) -> Vec<Vec<[u8; 32]>> {
+    let mut levels = vec![leaves];
+    while levels.last().unwrap().len() > 1 {
+        let next = levels.last().unwrap().chunks(2).map(parent_hash).collect();
+        levels.push(next);
+    }
+    levels
+}
+
+/// Hash of a node whose children are `pair`, padding a lone left child.
+pub(crate) fn parent_hash(pair: &[[u8; 32]]) -> [u8; 32] {
+    Sha256Hasher.hash_internal(&pair[0], pair.get(1).unwrap_or(&MERKLE_PADDING))
+}
+
+impl FromIterator<(Address, AccountState)> for StateBatch {
+    fn from_iter<I: IntoIterator<Item = (Address, AccountState)>>(iter: I) -> Self {
+        Self {
+            accounts: iter.into_iter().collect(),
+        }
+    }
+}
+
+/// `encode_state_batch` with each value bound to its address, in address order.
+pub fn encode_addressed_state_batch(batch: &StateBatch) -> Result<Vec<ScalarField>> {
+    if batch.is_empty() {
+        return Err(ArchimedesError::StateEncodingError(StateEncodingErrorKind::EmptyStateBatch));
+    }
+    Ok(batch.iter().map(|(address, state)| bytes_to_field(&StateBatch::leaf_hash(address, state))).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn accounts() -> Vec<(Address, AccountState)> {
+        (0..5u8).map(|i| (Address([i * 37; 32]), AccountState::new(1000 + i as u128, i as u64))).collect()
+    }
+
+    #[test]
+    fn test_root_independent_of_insertion_order() {
+        let forward: StateBatch = accounts().into_iter().collect();
+        let mut reversed = StateBatch::new();
+        for (address, state) in accounts().into_iter().rev() {
+            reversed.insert(address, state);
+        }
+        assert_eq!(forward.state_root().unwrap(), reversed.state_root().unwrap());
+        assert_eq!(encode_addressed_state_batch(&forward).unwrap(), encode_addressed_state_batch(&reversed).unwrap());
+
+        let root = forward.state_root().unwrap();
+        for (address, state) in accounts() {
+            let proof = forward.inclusion_proof(&address).unwrap();
+            assert!(proof.verify(StateBatch::leaf_hash(&address, &state), root));
+            assert!(!proof.verify(StateBatch::leaf_hash(&Address([1; 32]), &state), root));
+        }
+        assert!(forward.inclusion_proof(&Address([1; 32])).is_err());
+        assert!(StateBatch::new().state_root().is_err());
+    }
+
+    #[test]
+    fn test_apply_transition() {
+        let mut batch: StateBatch = accounts().into_iter().collect();
+        let (address, pre) = accounts()[2].clone();
+        let root = batch.state_root().unwrap();
+        let post = AccountState::new(900, pre.nonce + 1);
+        let transition = StateTransition::new(pre.clone(), post.clone(), [3u8; 32]);
+        batch.apply_transition(&address, &transition).unwrap();
+        assert_eq!(batch.get(&address), Some(&post));
+        assert_ne!(batch.state_root().unwrap(), root);
+
+        assert!(matches!(
+            batch.apply_transition(&address, &transition),
+            Err(ArchimedesError::StateEncodingError(StateEncodingErrorKind::PreStateMismatch))
+        ));
+        assert!(matches!(
+            batch.apply_transition(&Address([1; 32]), &transition),
+            Err(ArchimedesError::StateEncodingError(StateEncodingErrorKind::UnknownAddress))
+        ));
+    }
+}
@@ -0,0 +1,125 @@
+use ark_ed_on_bls12_381::Fr as ScalarField;
+use ark_std::rand::Rng;
+use archimedes_core::{ArchimedesError, Commitment, CommitmentParams, Randomness, VectorCommitmentKey};
+
+use crate::encoding::{bytes_to_fields, AccountState};
+
+type Result<T> = std::result::Result<T, ArchimedesError>;
+
+/// Field elements per state: balance as two 64-bit limbs, the nonce, and each
+/// 32-byte hash as two 16-byte limbs so every byte is bound.
+pub const STATE_COMMITMENT_WIDTH: usize = 7;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StateOpening {
+    pub state: AccountState,
+    pub randomness: Randomness,
+}
+
+impl AccountState {
+    pub fn to_commitment_limbs(&self) -> [ScalarField; STATE_COMMITMENT_WIDTH] {
+        let [code_lo, code_hi] = bytes_to_fields(&self.code_hash);
+        let [storage_lo, storage_hi] = bytes_to_fields(&self.storage_root);
+        [
+            ScalarField::from(self.balance as u64),
+            ScalarField::from((self.balance >> 64) as u64),
+            ScalarField::from(self.nonce),
+            code_lo,
+            code_hi,
+            storage_lo,
+            storage_hi,
+        ]
+    }
+}
+
+/// Field-wise commitments to `AccountState`, keeping the structure the
+/// dispute game needs instead of committing to a hash of the state.
+/// Deriving the key hashes to the curve once per limb; callers handling
+/// several states should take `state_key` once and use `commit_state_with`
+/// and `verify_state_with`.
+pub trait StateCommitment {
+    fn state_key(&self) -> Result<VectorCommitmentKey>;
+
+    fn commit_state<R: Rng>(&self, state: &AccountState, rng: &mut R) -> Result<(Commitment, StateOpening)> {
+        commit_state_with(&self.state_key()?, state, rng)
+    }
+
+    fn verify_state(&self, commitment: &Commitment, opening: &StateOpening) -> Result<bool> {
+        verify_state_with(&self.state_key()?, commitment, opening)
+    }
+}
+
+pub fn commit_state_with<R: Rng>(
+    key: &VectorCommitmentKey,
+    state: &AccountState,
+    rng: &mut R,
+) -> Result<(Commitment, StateOpening)> {
+    let (commitment, randomness) = key.commit(&state.to_commitment_limbs(), rng)?;
+    Ok((commitment, StateOpening { state: state.clone(), randomness }))
+}
+
+pub fn verify_state_with(key: &VectorCommitmentKey, commitment: &Commitment, opening: &StateOpening) -> Result<bool> {
+    key.verify(commitment, &opening.state.to_commitment_limbs(), &opening.randomness)
+}
+
+impl StateCommitment for CommitmentParams {
+    fn state_key(&self) -> Result<VectorCommitmentKey> {
+        self.vector_key(STATE_COMMITMENT_WIDTH)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_std::test_rng;
+
+    fn sample_state() -> AccountState {
+        AccountState {
+            balance: u128::from(u64::MAX) + 5,
+            nonce: 3,
+            code_hash: [0xab; 32],
+            storage_root: [0xcd; 32],
+        }
+    }
+
+    #[test]
+    fn test_commit_state_round_trip() {
+        let mut rng = test_rng();
+        let params = CommitmentParams::setup(&mut rng).unwrap();
+        let (commitment, opening) = params.commit_state(&sample_state(), &mut rng).unwrap();
+        assert!(params.verify_state(&commitment, &opening).unwrap());
+
+        let other_params = CommitmentParams::setup(&mut rng).unwrap();
+        assert!(!other_params.verify_state(&commitment, &opening).unwrap());
+
+        let key = params.state_key().unwrap();
+        let (keyed, keyed_opening) = commit_state_with(&key, &sample_state(), &mut rng).unwrap();
+        assert!(params.verify_state(&keyed, &keyed_opening).unwrap());
+        assert!(verify_state_with(&key, &commitment, &opening).unwrap());
+        assert!(verify_state_with(&params.vector_key(STATE_COMMITMENT_WIDTH - 1).unwrap(), &commitment, &opening).is_err());
+    }
+
+    #[test]
+    fn test_every_field_is_bound() {
+        let mut rng = test_rng();
+        let params = CommitmentParams::setup(&mut rng).unwrap();
+        let (commitment, opening) = params.commit_state(&sample_state(), &mut rng).unwrap();
+
+        let mut tampered = Vec::new();
+        for edit in [
+            (|s: &mut AccountState| s.balance += 1) as fn(&mut AccountState),
+            |s| s.balance += 1 << 64,
+            |s| s.nonce += 1,
+            |s| s.code_hash[31] ^= 1,
+            |s| s.storage_root[0] ^= 1,
+            |s| std::mem::swap(&mut s.code_hash, &mut s.storage_root),
+        ] {
+            let mut state = sample_state();
+            edit(&mut state);
+            tampered.push(StateOpening { state, randomness: opening.randomness.clone() });
+        }
+        for forged in &tampered {
+            assert!(!params.verify_state(&commitment, forged).unwrap());
+        }
+    }
+}
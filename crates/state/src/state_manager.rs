@@ -0,0 +1,268 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use archimedes_core::{ArchimedesError, StateEncodingErrorKind};
+
+use crate::encoding::{AccountState, StateTransition};
+use crate::state_batch::{build_levels, parent_hash, Address, StateBatch};
+
+type Result<T> = std::result::Result<T, ArchimedesError>;
+
+/// A point `StateManager::rollback` can return to. Ids are never reused, so
+/// an id closed by a rollback or commit, directly or through an enclosing
+/// snapshot, stays invalid after new snapshots are taken.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SnapshotId(u64);
+
+/// Account states under speculative execution. Snapshots cost nothing up
+/// front: each write while a snapshot is open journals the value it
+/// replaces, and rolling back replays the journal in reverse.
+///
+/// `root` matches `StateBatch::state_root` over the same accounts. Writes
+/// to existing accounts only rehash their paths; adding or removing an
+/// account shifts leaf positions, so the next `root` rebuilds the tree.
+#[derive(Clone, Debug, Default)]
+pub struct StateManager {
+    accounts: BTreeMap<Address, AccountState>,
+    journal: Vec<(Address, Option<AccountState>)>,
+    /// Open snapshots, innermost last, with the journal length when each
+    /// was taken.
+    snapshots: Vec<(SnapshotId, usize)>,
+    next_snapshot: u64,
+    tree: Option<CachedTree>,
+    dirty: BTreeSet<Address>,
+}
+
+#[derive(Clone, Debug)]
+struct CachedTree {
+    addresses: Vec<Address>,
+    levels: Vec<Vec<[u8; 32]>>,
+}
+
+impl StateManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, address: &Address) -> Option<&AccountState> {
+        self.accounts.get(address)
+    }
+
+    pub fn len(&self) -> usize {
+        self.accounts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.accounts.is_empty()
+    }
+
+    /// Returns the state previously held at `address`, if any.
+    pub fn insert(&mut self, address: Address, state: AccountState) -> Option<AccountState> {
+        let previous = self.accounts.insert(address, state);
+        if !self.snapshots.is_empty() {
+            self.journal.push((address, previous.clone()));
+        }
+        self.touch(address, previous.is_some());
+        previous
+    }
+
+    /// `StateBatch::apply_transition` against the current state.
+    pub fn apply_transition(&mut self, address: &Address, transition: &StateTransition) -> Result<()> {
+        let state = self
+            .accounts
+            .get(address)
+            .ok_or(ArchimedesError::StateEncodingError(StateEncodingErrorKind::UnknownAddress))?;
+        if *state != transition.pre_state {
+            return Err(ArchimedesError::StateEncodingError(StateEncodingErrorKind::PreStateMismatch));
+        }
+        self.insert(*address, transition.post_state.clone());
+        Ok(())
+    }
+
+    pub fn snapshot(&mut self) -> SnapshotId {
+        let id = SnapshotId(self.next_snapshot);
+        self.next_snapshot += 1;
+        self.snapshots.push((id, self.journal.len()));
+        id
+    }
+
+    /// Undoes every write since `id` was taken, discarding `id` and any
+    /// snapshot nested inside it.
+    pub fn rollback(&mut self, id: SnapshotId) -> Result<()> {
+        let mark = self.close(id)?;
+        let undone: Vec<_> = self.journal.drain(mark..).rev().collect();
+        for (address, previous) in undone {
+            match previous {
+                Some(state) => {
+                    self.accounts.insert(address, state);
+                    self.touch(address, true);
+                }
+                None => {
+                    self.accounts.remove(&address);
+                    self.touch(address, false);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Keeps the writes since `id`. They stay journaled while an enclosing
+    /// snapshot is open, so rolling that one back still undoes them.
+    pub fn commit(&mut self, id: SnapshotId) -> Result<()> {
+        self.close(id)?;
+        if self.snapshots.is_empty() {
+            self.journal.clear();
+        }
+        Ok(())
+    }
+
+    pub fn root(&mut self) -> Result<[u8; 32]> {
+        if self.accounts.is_empty() {
+            return Err(ArchimedesError::StateEncodingError(StateEncodingErrorKind::EmptyStateBatch));
+        }
+        let dirty = std::mem::take(&mut self.dirty);
+        let accounts = &self.accounts;
+        let tree = self.tree.get_or_insert_with(|| CachedTree {
+            addresses: accounts.keys().copied().collect(),
+            levels: build_levels(accounts.iter().map(|(a, s)| StateBatch::leaf_hash(a, s)).collect()),
+        });
+        let mut positions: Vec<usize> = Vec::with_capacity(dirty.len());
+        for address in dirty {
+            let index = tree.addresses.binary_search(&address).expect("dirty accounts are in the cached tree");
+            tree.levels[0][index] = StateBatch::leaf_hash(&address, &accounts[&address]);
+            positions.push(index);
+        }
+        for level in 1..tree.levels.len() {
+            positions = positions.into_iter().map(|p| p / 2).collect();
+            positions.dedup();
+            for &p in &positions {
+                let (below, above) = tree.levels.split_at_mut(level);
+                let children = &below[level - 1];
+                above[0][p] = parent_hash(&children[2 * p..(2 * p + 2).min(children.len())]);
+            }
+        }
+        Ok(tree.levels.last().expect("a tree always has a leaf level")[0])
+    }
+
+    /// Drops snapshot `id` and everything nested in it, returning the
+    /// journal length when it was taken.
+    fn close(&mut self, id: SnapshotId) -> Result<usize> {
+        let position = self
+            .snapshots
+            .binary_search_by_key(&id, |(open, _)| *open)
+            .map_err(|_| ArchimedesError::StateEncodingError(StateEncodingErrorKind::UnknownSnapshot { id: id.0 }))?;
+        let mark = self.snapshots[position].1;
+        self.snapshots.truncate(position);
+        Ok(mark)
+    }
+
+    /// Records a change at `address`; `existed` is false when the account
+    /// set itself changed.
+    fn touch(&mut self, address: Address, existed: bool) {
+        if existed && self.tree.is_some() {
+            self.dirty.insert(address);
+        } else {
+            self.tree = None;
+            self.dirty.clear();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{Rng, SeedableRng};
+
+    fn rebuilt_root(manager: &StateManager) -> [u8; 32] {
+        manager.accounts.iter().map(|(a, s)| (*a, s.clone())).collect::<StateBatch>().state_root().unwrap()
+    }
+
+    #[test]
+    fn test_nested_snapshots_roll_back() {
+        let mut manager = StateManager::new();
+        for i in 0..5u8 {
+            manager.insert(Address([i; 32]), AccountState::new(100, 0));
+        }
+        let base = manager.root().unwrap();
+
+        let outer = manager.snapshot();
+        manager.insert(Address([1; 32]), AccountState::new(50, 1));
+        let after_outer = manager.root().unwrap();
+        let inner = manager.snapshot();
+        manager.insert(Address([9; 32]), AccountState::new(7, 0));
+        manager.insert(Address([2; 32]), AccountState::new(60, 1));
+        assert_eq!(manager.root().unwrap(), rebuilt_root(&manager));
+
+        manager.rollback(inner).unwrap();
+        assert_eq!(manager.get(&Address([9; 32])), None);
+        assert_eq!(manager.root().unwrap(), after_outer);
+        assert!(manager.rollback(inner).is_err());
+
+        let inner = manager.snapshot();
+        manager.insert(Address([3; 32]), AccountState::new(1, 1));
+        manager.commit(inner).unwrap();
+        manager.rollback(outer).unwrap();
+        assert_eq!(manager.root().unwrap(), base);
+        assert_eq!(manager.get(&Address([3; 32])), Some(&AccountState::new(100, 0)));
+        assert!(manager.commit(outer).is_err());
+    }
+
+    #[test]
+    fn test_stale_snapshot_ids_rejected() {
+        let mut manager = StateManager::new();
+        manager.insert(Address([1; 32]), AccountState::new(100, 0));
+        let outer = manager.snapshot();
+        let inner = manager.snapshot();
+        manager.rollback(outer).unwrap();
+
+        // The new snapshots sit where `outer` and `inner` did.
+        let reopened = manager.snapshot();
+        manager.insert(Address([1; 32]), AccountState::new(50, 1));
+        let nested = manager.snapshot();
+        manager.insert(Address([2; 32]), AccountState::new(7, 0));
+        for stale in [outer, inner] {
+            assert!(matches!(
+                manager.rollback(stale),
+                Err(ArchimedesError::StateEncodingError(StateEncodingErrorKind::UnknownSnapshot { .. }))
+            ));
+            assert!(manager.commit(stale).is_err());
+        }
+        assert_eq!(manager.get(&Address([2; 32])), Some(&AccountState::new(7, 0)));
+
+        manager.commit(nested).unwrap();
+        assert!(manager.rollback(nested).is_err());
+        manager.rollback(reopened).unwrap();
+        assert_eq!(manager.get(&Address([1; 32])), Some(&AccountState::new(100, 0)));
+        assert_eq!(manager.get(&Address([2; 32])), None);
+    }
+
+    #[test]
+    fn test_random_interleaving_matches_rebuild() {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(840);
+        let mut manager = StateManager::new();
+        manager.insert(Address([0; 32]), AccountState::new(1, 0));
+        let mut open = Vec::new();
+        for step in 0..400 {
+            match rng.gen_range(0..10) {
+                0 => open.push(manager.snapshot()),
+                1 if !open.is_empty() => {
+                    let id = open.remove(rng.gen_range(0..open.len()));
+                    let keep = open.iter().filter(|o| o.0 < id.0).count();
+                    open.truncate(keep);
+                    if rng.gen() {
+                        manager.rollback(id).unwrap();
+                    } else {
+                        manager.commit(id).unwrap();
+                    }
+                }
+                _ => {
+                    let address = Address([rng.gen_range(0..24u8); 32]);
+                    manager.insert(address, AccountState::new(rng.gen_range(0..1000), step));
+                }
+            }
+            if step % 7 == 0 {
+                assert_eq!(manager.root().unwrap(), rebuilt_root(&manager));
+            }
+        }
+        assert_eq!(manager.root().unwrap(), rebuilt_root(&manager));
+    }
+}
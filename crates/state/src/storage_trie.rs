@@ -0,0 +1,227 @@
+use std::collections::BTreeMap;
+
+use archimedes_core::{ArchimedesError, StateEncodingErrorKind};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::encoding::AccountState;
+
+type Result<T> = std::result::Result<T, ArchimedesError>;
+
+const KEY_BITS: usize = 256;
+const LEAF_PREFIX: u8 = 0x00;
+const INTERNAL_PREFIX: u8 = 0x01;
+/// Hash of every empty subtree, so an account with no storage keeps the
+/// zero `storage_root` it starts with.
+const EMPTY: [u8; 32] = [0u8; 32];
+
+/// Sparse Merkle tree over 256-bit keys: key bits, most significant first,
+/// pick the path from the root. Roots are recomputed on demand.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StorageTrie {
+    entries: BTreeMap<[u8; 32], [u8; 32]>,
+}
+
+/// Shows that `key` holds `value`, or is empty when `value` is `None`.
+/// `siblings` holds the non-empty siblings from the leaf up, and bit `i` of
+/// `occupied` (LSB first) marks whether level `i` has one.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StorageProof {
+    pub key: [u8; 32],
+    pub value: Option<[u8; 32]>,
+    pub occupied: [u8; 32],
+    pub siblings: Vec<[u8; 32]>,
+}
+
+impl StorageTrie {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the previous value at `key`, if any.
+    pub fn insert(&mut self, key: [u8; 32], value: [u8; 32]) -> Option<[u8; 32]> {
+        self.entries.insert(key, value)
+    }
+
+    pub fn get(&self, key: &[u8; 32]) -> Option<&[u8; 32]> {
+        self.entries.get(key)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn root(&self) -> [u8; 32] {
+        let entries: Vec<_> = self.entries.iter().map(|(k, v)| (*k, *v)).collect();
+        subtree_hash(&entries, 0)
+    }
+
+    pub fn prove(&self, key: &[u8; 32]) -> StorageProof {
+        let entries: Vec<_> = self.entries.iter().map(|(k, v)| (*k, *v)).collect();
+        let mut path = &entries[..];
+        let mut from_root = Vec::with_capacity(KEY_BITS);
+        for depth in 0..KEY_BITS {
+            let split = path.partition_point(|(k, _)| !bit(k, depth));
+            let (left, right) = path.split_at(split);
+            let (own, other) = if bit(key, depth) { (right, left) } else { (left, right) };
+            from_root.push(subtree_hash(other, depth + 1));
+            path = own;
+        }
+        let mut occupied = [0u8; 32];
+        let mut siblings = Vec::new();
+        for (level, sibling) in from_root.into_iter().rev().enumerate() {
+            if sibling != EMPTY {
+                occupied[level / 8] |= 1 << (level % 8);
+                siblings.push(sibling);
+            }
+        }
+        StorageProof {
+            key: *key,
+            value: self.entries.get(key).copied(),
+            occupied,
+            siblings,
+        }
+    }
+}
+
+impl StorageProof {
+    pub fn verify(&self, root: [u8; 32]) -> bool {
+        self.root_with(self.value) == Some(root)
+    }
+
+    /// Root of the same trie with `key` set to `value`, or cleared for
+    /// `None`, everything else unchanged. `None` if the proof is malformed.
+    pub fn root_with(&self, value: Option<[u8; 32]>) -> Option<[u8; 32]> {
+        let occupied_count: u32 = self.occupied.iter().map(|b| b.count_ones()).sum();
+        if occupied_count as usize != self.siblings.len() {
+            return None;
+        }
+        let mut siblings = self.siblings.iter();
+        let mut current = value.map_or(EMPTY, |v| leaf_hash(&self.key, &v));
+        for level in 0..KEY_BITS {
+            let sibling = if self.occupied[level / 8] >> (level % 8) & 1 == 1 {
+                *siblings.next()?
+            } else {
+                EMPTY
+            };
+            current = if bit(&self.key, KEY_BITS - 1 - level) {
+                node_hash(&sibling, &current)
+            } else {
+                node_hash(&current, &sibling)
+            };
+        }
+        Some(current)
+    }
+}
+
+impl AccountState {
+    /// Writes `value` at `key` in `storage`, which must be the trie this
+    /// account's `storage_root` commits to, and updates the root.
+    pub fn apply_storage_write(&mut self, key: [u8; 32], value: [u8; 32], storage: &mut StorageTrie) -> Result<()> {
+        if storage.root() != self.storage_root {
+            return Err(ArchimedesError::StateEncodingError(StateEncodingErrorKind::StorageRootMismatch));
+        }
+        storage.insert(key, value);
+        self.storage_root = storage.root();
+        Ok(())
+    }
+}
+
+fn bit(key: &[u8; 32], depth: usize) -> bool {
+    key[depth / 8] >> (7 - depth % 8) & 1 == 1
+}
+
+fn leaf_hash(key: &[u8; 32], value: &[u8; 32]) -> [u8; 32] {
+    Sha256::new().chain_update([LEAF_PREFIX]).chain_update(key).chain_update(value).finalize().into()
+}
+
+fn node_hash(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    if *left == EMPTY && *right == EMPTY {
+        return EMPTY;
+    }
+    Sha256::new()
+        .chain_update([INTERNAL_PREFIX])
+        .chain_update(left)
+        .chain_update(right)
+        .finalize()
+        .into()
+}
+
+/// Hash of the subtree at `depth` holding `entries`, which are sorted and
+/// share their first `depth` key bits.
+fn subtree_hash(entries: &[([u8; 32], [u8; 32])], depth: usize) -> [u8; 32] {
+    match entries {
+        [] => EMPTY,
+        [(key, value)] if depth == KEY_BITS => leaf_hash(key, value),
+        _ => {
+            let split = entries.partition_point(|(k, _)| !bit(k, depth));
+            node_hash(&subtree_hash(&entries[..split], depth + 1), &subtree_hash(&entries[split..], depth + 1))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(i: u8) -> [u8; 32] {
+        let mut key = [i; 32];
+        key[0] = i.wrapping_mul(97);
+        key
+    }
+
+    #[test]
+    fn test_membership_and_non_membership() {
+        let mut trie = StorageTrie::new();
+        assert_eq!(trie.root(), EMPTY);
+        assert!(trie.prove(&key(1)).verify(EMPTY));
+        for i in 0..6 {
+            trie.insert(key(i), [i + 100; 32]);
+        }
+        let root = trie.root();
+        let mut reordered = StorageTrie::new();
+        for i in (0..6).rev() {
+            reordered.insert(key(i), [i + 100; 32]);
+        }
+        assert_eq!(reordered.root(), root);
+
+        for i in 0..6 {
+            let proof = trie.prove(&key(i));
+            assert_eq!(proof.value, Some([i + 100; 32]));
+            assert!(proof.verify(root));
+            assert!(!StorageProof { value: Some([0; 32]), ..proof.clone() }.verify(root));
+        }
+        let absent = trie.prove(&key(9));
+        assert_eq!(absent.value, None);
+        assert!(absent.verify(root));
+        assert!(!StorageProof { value: Some([1; 32]), ..absent.clone() }.verify(root));
+        let mut truncated = absent;
+        truncated.siblings.pop();
+        assert!(!truncated.verify(root));
+    }
+
+    #[test]
+    fn test_storage_write_updates_root() {
+        let mut storage = StorageTrie::new();
+        let mut account = AccountState::new(10, 0);
+        account.apply_storage_write(key(1), [7; 32], &mut storage).unwrap();
+        assert_eq!(account.storage_root, storage.root());
+
+        let proof = storage.prove(&key(2));
+        let pre_root = account.storage_root;
+        account.apply_storage_write(key(2), [8; 32], &mut storage).unwrap();
+        assert!(proof.verify(pre_root));
+        assert_eq!(proof.root_with(Some([8; 32])), Some(account.storage_root));
+        assert_eq!(proof.root_with(None), Some(pre_root));
+
+        let mut stale = StorageTrie::new();
+        assert!(matches!(
+            account.apply_storage_write(key(3), [9; 32], &mut stale),
+            Err(ArchimedesError::StateEncodingError(StateEncodingErrorKind::StorageRootMismatch))
+        ));
+    }
+}
@@ -0,0 +1,162 @@
+use std::collections::HashMap;
+
+use ark_ed_on_bls12_381::Fr as ScalarField;
+use archimedes_core::{ArchimedesError, StateEncodingErrorKind};
+use serde::{Deserialize, Serialize};
+
+use crate::encoding::{encode_transitions, StateTransition};
+use crate::state_batch::Address;
+
+type Result<T> = std::result::Result<T, ArchimedesError>;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TransitionViolationKind {
+    /// The nonce goes down within the transition, or below the account's
+    /// previous post-state.
+    NonceDecreased,
+    /// The pre-state is not the post-state of `previous`, the account's last
+    /// transition in the batch.
+    BrokenChain { previous: usize },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransitionViolation {
+    pub index: usize,
+    pub kind: TransitionViolationKind,
+}
+
+/// Transitions in execution order, each tagged with the account it moves.
+/// Balances are unsigned, so a transition cannot take one below zero; an
+/// overdraw surfaces as a post-state that the next transition does not
+/// chain from.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransitionBatch {
+    transitions: Vec<(Address, StateTransition)>,
+}
+
+impl TransitionBatch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, address: Address, transition: StateTransition) {
+        self.transitions.push((address, transition));
+    }
+
+    pub fn len(&self) -> usize {
+        self.transitions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.transitions.is_empty()
+    }
+
+    pub fn get(&self, index: usize) -> Option<&(Address, StateTransition)> {
+        self.transitions.get(index)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &(Address, StateTransition)> {
+        self.transitions.iter()
+    }
+
+    /// Every violation, in index order. A transition can appear twice if it
+    /// both breaks the chain and lowers the nonce.
+    pub fn validate(&self) -> std::result::Result<(), Vec<TransitionViolation>> {
+        let mut last: HashMap<Address, usize> = HashMap::new();
+        let mut violations = Vec::new();
+        for (index, (address, transition)) in self.transitions.iter().enumerate() {
+            let mut violation = |kind| violations.push(TransitionViolation { index, kind });
+            if let Some(&previous) = last.get(address) {
+                let previous_post = &self.transitions[previous].1.post_state;
+                if transition.pre_state != *previous_post {
+                    violation(TransitionViolationKind::BrokenChain { previous });
+                }
+                if transition.pre_state.nonce < previous_post.nonce {
+                    violation(TransitionViolationKind::NonceDecreased);
+                }
+            }
+            if transition.post_state.nonce < transition.pre_state.nonce {
+                violation(TransitionViolationKind::NonceDecreased);
+            }
+            last.insert(*address, index);
+        }
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(violations)
+        }
+    }
+
+    /// `encode_transitions` over a batch that passes `validate`.
+    pub fn to_field_elements(&self) -> Result<Vec<ScalarField>> {
+        if let Err(violations) = self.validate() {
+            return Err(ArchimedesError::StateEncodingError(StateEncodingErrorKind::InvalidTransitionBatch {
+                index: violations[0].index,
+            }));
+        }
+        self.encode_unchecked()
+    }
+
+    /// Encodes without validating, for tests and tooling that need a faulty
+    /// batch on purpose.
+    pub fn encode_unchecked(&self) -> Result<Vec<ScalarField>> {
+        let transitions: Vec<StateTransition> = self.transitions.iter().map(|(_, t)| t.clone()).collect();
+        encode_transitions(&transitions)
+    }
+}
+
+impl FromIterator<(Address, StateTransition)> for TransitionBatch {
+    fn from_iter<I: IntoIterator<Item = (Address, StateTransition)>>(iter: I) -> Self {
+        Self {
+            transitions: iter.into_iter().collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encoding::AccountState;
+
+    /// Two accounts alternating, each paying 10 per step.
+    fn honest() -> TransitionBatch {
+        (0..6u64)
+            .map(|i| {
+                let (account, step) = (i % 2, i / 2);
+                let pre = AccountState::new(1000 - 10 * step as u128, step);
+                let post = AccountState::new(990 - 10 * step as u128, step + 1);
+                (Address([account as u8; 32]), StateTransition::new(pre, post, [i as u8; 32]))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_valid_batch_encodes() {
+        let batch = honest();
+        assert_eq!(batch.validate(), Ok(()));
+        let transitions: Vec<_> = batch.iter().map(|(_, t)| t.clone()).collect();
+        assert_eq!(batch.to_field_elements().unwrap(), encode_transitions(&transitions).unwrap());
+        assert!(TransitionBatch::new().to_field_elements().is_err());
+    }
+
+    #[test]
+    fn test_violations_reported_by_index() {
+        let mut transitions: Vec<_> = honest().iter().cloned().collect();
+        transitions[2].1.post_state.balance += 1_000;
+        transitions[3].1.post_state.nonce = 0;
+        let batch: TransitionBatch = transitions.into_iter().collect();
+        assert_eq!(
+            batch.validate(),
+            Err(vec![
+                TransitionViolation { index: 3, kind: TransitionViolationKind::NonceDecreased },
+                TransitionViolation { index: 4, kind: TransitionViolationKind::BrokenChain { previous: 2 } },
+                TransitionViolation { index: 5, kind: TransitionViolationKind::BrokenChain { previous: 3 } },
+            ])
+        );
+        assert!(matches!(
+            batch.to_field_elements(),
+            Err(ArchimedesError::StateEncodingError(StateEncodingErrorKind::InvalidTransitionBatch { index: 3 }))
+        ));
+        assert_eq!(batch.encode_unchecked().unwrap().len(), 6);
+    }
+}
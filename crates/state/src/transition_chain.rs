@@ -0,0 +1,63 @@
+use ark_std::rand::Rng;
+use archimedes_core::{ArchimedesError, Commitment, CommitmentChain, EntryMeta};
+
+use crate::encoding::StateTransition;
+
+type Result<T> = std::result::Result<T, ArchimedesError>;
+
+/// Commits state transitions straight into a chain, keeping each `tx_hash`
+/// in the entry metadata so it stays aligned through pops and dedup.
+pub trait TransitionChain {
+    fn push_transition<R: Rng>(&mut self, transition: &StateTransition, rng: &mut R) -> Result<&Commitment>;
+
+    fn transition_hash_at(&self, index: usize) -> Option<[u8; 32]>;
+}
+
+impl TransitionChain for CommitmentChain {
+    fn push_transition<R: Rng>(&mut self, transition: &StateTransition, rng: &mut R) -> Result<&Commitment> {
+        let meta = EntryMeta {
+            tx_hash: transition.tx_hash,
+            ..Default::default()
+        };
+        self.push_with_meta(transition.to_commitment_value(), rng, meta)
+    }
+
+    fn transition_hash_at(&self, index: usize) -> Option<[u8; 32]> {
+        self.provenance(index).map(|meta| meta.tx_hash)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encoding::AccountState;
+    use archimedes_core::{CommitmentParams, Opening};
+    use ark_std::test_rng;
+
+    fn transition(i: u64) -> StateTransition {
+        let mut tx_hash = [0u8; 32];
+        tx_hash[..8].copy_from_slice(&i.to_le_bytes());
+        StateTransition::new(AccountState::new(1_000 + i as u128, i), AccountState::new(990 + i as u128, i + 1), tx_hash)
+    }
+
+    #[test]
+    fn test_push_transitions() {
+        let mut rng = test_rng();
+        let mut chain = CommitmentChain::new(CommitmentParams::setup(&mut rng).unwrap());
+        let transitions: Vec<StateTransition> = (0..100).map(transition).collect();
+        for t in &transitions {
+            chain.push_transition(t, &mut rng).unwrap();
+        }
+
+        assert_eq!(chain.len(), 100);
+        for (i, t) in transitions.iter().enumerate() {
+            assert_eq!(chain.transition_hash_at(i), Some(t.tx_hash));
+            assert_eq!(chain.values()[i], t.to_commitment_value());
+            assert_eq!(chain.find_by_tx_hash(&t.tx_hash), vec![i]);
+        }
+        assert_eq!(chain.transition_hash_at(100), None);
+        assert!(chain.verify_aggregate(&chain.aggregate()).unwrap());
+        let opening = Opening { value: chain.values()[42], randomness: chain.randomness()[42].clone() };
+        assert!(chain.params.verify(&chain.commitments()[42], &opening).unwrap());
+    }
+}
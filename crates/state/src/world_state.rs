@@ -0,0 +1,257 @@
+use std::collections::BTreeMap;
+
+use archimedes_core::{ArchimedesError, StateEncodingErrorKind};
+
+use crate::encoding::{AccountState, StateTransition};
+use crate::operation::TransitionOperation;
+use crate::state_batch::Address;
+use crate::state_manager::StateManager;
+use crate::storage_trie::StorageTrie;
+
+type Result<T> = std::result::Result<T, ArchimedesError>;
+
+/// Accounts together with their storage tries. Each account's
+/// `storage_root` is the root of its trie; accounts without storage have
+/// an empty trie.
+///
+/// `root` is `StateBatch::state_root` over the accounts, and after k
+/// updates to existing accounts it rehashes only their k paths.
+#[derive(Clone, Debug, Default)]
+pub struct WorldState {
+    accounts: StateManager,
+    storage: BTreeMap<Address, StorageTrie>,
+}
+
+impl WorldState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, address: &Address) -> Option<&AccountState> {
+        self.accounts.get(address)
+    }
+
+    /// The storage trie of `address`, if it has written any storage.
+    pub fn storage(&self, address: &Address) -> Option<&StorageTrie> {
+        self.storage.get(address)
+    }
+
+    pub fn len(&self) -> usize {
+        self.accounts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.accounts.is_empty()
+    }
+
+    /// Sets the account at `address`, dropping storage that no longer
+    /// matches its `storage_root`. Returns the state previously held there.
+    pub fn insert(&mut self, address: Address, state: AccountState) -> Option<AccountState> {
+        if self.storage.get(&address).is_some_and(|trie| trie.root() != state.storage_root) {
+            self.storage.remove(&address);
+        }
+        self.accounts.insert(address, state)
+    }
+
+    /// Changes the account at `address` in place. Storage must go through
+    /// `execute`, so a `storage_root` change is rejected.
+    pub fn update(&mut self, address: &Address, f: impl FnOnce(&mut AccountState)) -> Result<()> {
+        let mut state = self.account(address)?.clone();
+        f(&mut state);
+        if state.storage_root != self.account(address)?.storage_root {
+            return Err(ArchimedesError::StateEncodingError(StateEncodingErrorKind::StorageRootMismatch));
+        }
+        self.accounts.insert(*address, state);
+        Ok(())
+    }
+
+    /// Runs `op` from `sender`, returning the sender's transition and, for a
+    /// transfer, the receiver's. Both accounts must exist; `receiver` is
+    /// only read for transfers. Transitions carry `op.hash()` as their
+    /// transaction hash. Nothing is written if the operation fails.
+    pub fn execute(
+        &mut self,
+        op: &TransitionOperation,
+        sender: &Address,
+        receiver: Option<&Address>,
+    ) -> Result<Vec<StateTransition>> {
+        let tx_hash = op.hash();
+        let pre = self.account(sender)?.clone();
+        let post = match op {
+            TransitionOperation::StorageWrite { key, value } => {
+                let mut post = pre.clone();
+                match self.storage.get_mut(sender) {
+                    Some(trie) => post.apply_storage_write(*key, *value, trie)?,
+                    None => {
+                        let mut trie = StorageTrie::new();
+                        post.apply_storage_write(*key, *value, &mut trie)?;
+                        self.storage.insert(*sender, trie);
+                    }
+                }
+                post
+            }
+            _ => pre.apply(op)?,
+        };
+        let mut transitions = vec![StateTransition::new(pre, post.clone(), tx_hash)];
+
+        if let TransitionOperation::Transfer { amount } = op {
+            let receiver = receiver.ok_or(ArchimedesError::StateEncodingError(StateEncodingErrorKind::UnknownAddress))?;
+            let receiver_pre = if receiver == sender { post.clone() } else { self.account(receiver)?.clone() };
+            let balance = receiver_pre.balance.checked_add(*amount).ok_or(ArchimedesError::StateEncodingError(
+                StateEncodingErrorKind::BalanceOverflow {
+                    balance: receiver_pre.balance,
+                    amount: *amount,
+                },
+            ))?;
+            let receiver_post = AccountState { balance, ..receiver_pre.clone() };
+            transitions.push(StateTransition::new(receiver_pre, receiver_post.clone(), tx_hash));
+            self.accounts.insert(*sender, post);
+            self.accounts.insert(*receiver, receiver_post);
+        } else {
+            self.accounts.insert(*sender, post);
+        }
+        Ok(transitions)
+    }
+
+    pub fn root(&mut self) -> Result<[u8; 32]> {
+        self.accounts.root()
+    }
+
+    fn account(&self, address: &Address) -> Result<&AccountState> {
+        self.accounts
+            .get(address)
+            .ok_or(ArchimedesError::StateEncodingError(StateEncodingErrorKind::UnknownAddress))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state_batch::StateBatch;
+    use rand::{Rng, SeedableRng};
+
+    fn address(i: u32) -> Address {
+        let mut a = [0u8; 32];
+        a[..4].copy_from_slice(&i.to_be_bytes());
+        Address(a)
+    }
+
+    #[test]
+    fn test_execute_operations() {
+        let (alice, bob) = (address(1), address(2));
+        let mut world = WorldState::new();
+        world.insert(alice, AccountState::new(100, 0));
+        world.insert(bob, AccountState::new(5, 3));
+
+        let transfer = TransitionOperation::Transfer { amount: 40 };
+        let transitions = world.execute(&transfer, &alice, Some(&bob)).unwrap();
+        assert_eq!(transitions.len(), 2);
+        assert_eq!(transitions[0].post_state, AccountState::new(60, 1));
+        assert_eq!(transitions[1].post_state, AccountState::new(45, 3));
+        assert_eq!(transitions[1].tx_hash, transfer.hash());
+        assert_eq!(world.get(&bob), Some(&AccountState::new(45, 3)));
+
+        let write = TransitionOperation::StorageWrite { key: [7; 32], value: [8; 32] };
+        let [written] = &world.execute(&write, &bob, None).unwrap()[..] else {
+            panic!("a storage write moves one account");
+        };
+        assert_eq!(written.post_state.storage_root, world.storage(&bob).unwrap().root());
+        assert_eq!(world.storage(&bob).unwrap().get(&[7; 32]), Some(&[8; 32]));
+        assert!(world.update(&bob, |state| state.storage_root = [0; 32]).is_err());
+
+        // Failed operations leave the world untouched.
+        let root = world.root().unwrap();
+        for (op, receiver) in [
+            (TransitionOperation::Transfer { amount: 61 }, Some(&bob)),
+            (TransitionOperation::Transfer { amount: 1 }, Some(&address(9))),
+            (TransitionOperation::Transfer { amount: 1 }, None),
+        ] {
+            assert!(world.execute(&op, &alice, receiver).is_err());
+        }
+        world.update(&bob, |state| state.balance = u128::MAX).unwrap();
+        assert!(matches!(
+            world.execute(&TransitionOperation::Transfer { amount: 1 }, &alice, Some(&bob)),
+            Err(ArchimedesError::StateEncodingError(StateEncodingErrorKind::BalanceOverflow { .. }))
+        ));
+        world.update(&bob, |state| state.balance = 45).unwrap();
+        assert_eq!(world.root().unwrap(), root);
+        assert_eq!(world.get(&alice), Some(&AccountState::new(60, 1)));
+
+        world.execute(&transfer, &alice, Some(&alice)).unwrap();
+        assert_eq!(world.get(&alice), Some(&AccountState::new(60, 2)));
+
+        // A write the account's storage root does not allow creates no trie.
+        let carol = address(3);
+        world.insert(carol, AccountState { storage_root: [1; 32], ..AccountState::new(0, 0) });
+        assert!(matches!(
+            world.execute(&write, &carol, None),
+            Err(ArchimedesError::StateEncodingError(StateEncodingErrorKind::StorageRootMismatch))
+        ));
+        assert!(world.storage(&carol).is_none());
+    }
+
+    /// Checks `WorldState` against plain maps whose root is rebuilt from
+    /// scratch every time.
+    #[test]
+    fn test_random_updates_match_full_rebuild() {
+        const ACCOUNTS: u32 = 10_000;
+        let mut rng = rand::rngs::StdRng::seed_from_u64(847);
+        let mut world = WorldState::new();
+        let mut accounts: BTreeMap<Address, AccountState> = BTreeMap::new();
+        let mut storage: BTreeMap<Address, StorageTrie> = BTreeMap::new();
+        for i in 0..ACCOUNTS {
+            let state = AccountState::new(rng.gen_range(0..1_000), 0);
+            world.insert(address(i), state.clone());
+            accounts.insert(address(i), state);
+        }
+        let rebuilt = |accounts: &BTreeMap<Address, AccountState>| {
+            accounts.iter().map(|(a, s)| (*a, s.clone())).collect::<StateBatch>().state_root().unwrap()
+        };
+
+        for step in 0..3_000 {
+            let sender = address(rng.gen_range(0..ACCOUNTS + 10));
+            let receiver = address(rng.gen_range(0..ACCOUNTS));
+            let op = match rng.gen_range(0..4) {
+                0 => TransitionOperation::NonceIncrement,
+                1 => TransitionOperation::StorageWrite { key: [rng.gen_range(0..4); 32], value: rng.gen() },
+                _ => TransitionOperation::Transfer { amount: rng.gen_range(0..600) },
+            };
+            let result = world.execute(&op, &sender, Some(&receiver));
+
+            let Some(pre) = accounts.get(&sender).cloned() else {
+                assert!(result.is_err());
+                continue;
+            };
+            match &op {
+                TransitionOperation::Transfer { amount } if pre.balance < *amount => {
+                    assert!(result.is_err());
+                    continue;
+                }
+                TransitionOperation::Transfer { amount } => {
+                    let sender_post = accounts.get_mut(&sender).unwrap();
+                    sender_post.balance -= amount;
+                    sender_post.nonce += 1;
+                    accounts.get_mut(&receiver).unwrap().balance += amount;
+                }
+                TransitionOperation::StorageWrite { key, value } => {
+                    let trie = storage.entry(sender).or_default();
+                    trie.insert(*key, *value);
+                    accounts.get_mut(&sender).unwrap().storage_root = trie.root();
+                }
+                _ => accounts.get_mut(&sender).unwrap().nonce += 1,
+            }
+            let transitions = result.unwrap();
+            assert_eq!(transitions[0].pre_state, pre);
+            assert_eq!(world.get(&sender), accounts.get(&sender));
+            assert_eq!(world.get(&receiver), accounts.get(&receiver));
+
+            if step % 250 == 0 {
+                assert_eq!(world.root().unwrap(), rebuilt(&accounts));
+            }
+        }
+        assert_eq!(world.root().unwrap(), rebuilt(&accounts));
+        for (address, trie) in &storage {
+            assert_eq!(world.storage(address).unwrap().root(), trie.root());
+        }
+    }
+}
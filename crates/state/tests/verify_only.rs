@@ -0,0 +1,70 @@
+//! Checks pinned proofs through the verify-only surface, the one light
+//! clients build:
+//!
+//! ```text
+//! cargo test -p archimedes-state --no-default-features --test verify_only
+//! ```
+//!
+//! Only items available without the `prover` feature are used here, so the
+//! test also shows that surface compiles on its own.
+
+use archimedes_core::{CommitmentParams, Opening};
+use archimedes_state::{committed_root, AccountProof, AccountState, Address, AggregatePathProof};
+use ark_ed_on_bls12_381::Fr as ScalarField;
+
+const PARAMS_DOMAIN: &str = "archimedes-verify-only";
+const LEAF_COUNT: usize = 5;
+const TREE_ROOT: &str = "e2b143a17588bc03af0dd5976edb190c18f5fd9e744aec3e0949394db0aa8826";
+const TREE_COMMITTED_ROOT: &str = "2c529a06fb93fa986794e338ef531423c8c5a74dfcee49998ff0f9216a58fa05";
+const LEAF_3_PROOF: &str = "0300000000000000030475a46178109015b3db45c9259fb4a9fe76abb7829dca200a67727c30628301a701000000000000007dae8357db1cf5b4423e2838546bba9594ec858b0c78e7576ba4d181f278be3dda90bbef0ea451c85f625a9c9456e1d767232c39ef1525b43c3e44f6da5013cf02000000000000005cf963e6259c0c174c6851c0ab7939547bd46b7c5b10c3fb70698d2efd5e27393fdac3c57b0aa1e5872c5486fa1cde5c8ddf3ad396a4599c119fc9533cca24660100000000000000eab7e32d8416445dfe816b8507f96f59928c3d382a0e6965bd8de6697a8c6594";
+
+const BATCH_ROOT: &str = "825c154b3ce4674556e6d4dee296e22b095e0b951d5dca90f77a7052be193ee6";
+const PRESENT_PROOF: &str = "00010000000000000002026323a9c74621a2713f51c54fc3233a535960b07e0d26878dc028f2fc42d76473d5e17085c9ed734bbf24470b186a15b614b4c2d4414fd0f17445d266a85ddf64";
+const ABSENT_PROOF: &str = "01030404040404040404040404040404040404040404040404040404040404040404db6bd76ccfd4f0d5f472bdbe492bce1233105f817f8f658c976e4d019ca8ed91010000000000000002026323a9c74621a2713f51c54fc3233a535960b07e0d26878dc028f2fc42d76473d5e17085c9ed734bbf24470b186a15b614b4c2d4414fd0f17445d266a85ddf640606060606060606060606060606060606060606060606060606060606060606a813c3698cca1dd7d009d1b6050be19b00e2990d7cd687b8c30e6acd47b7e653020000000000000002010000000000000000000000000000000000000000000000000000000000000000fdbf55ee41f6224f453566c5ace04fb85b4d86ac719d857fc2c7d092509d47de";
+
+fn root(hex_root: &str) -> [u8; 32] {
+    hex::decode(hex_root).unwrap().try_into().unwrap()
+}
+
+/// Leaf 3 of the pinned tree: the value 3 committed under seed `[3; 32]`.
+#[test]
+fn test_commitment_inclusion() {
+    let params = CommitmentParams::setup_deterministic_for_domain(PARAMS_DOMAIN).unwrap();
+    let (commitment, randomness) = params.commit_deterministic(&ScalarField::from(3u64), &[3; 32]).unwrap();
+    let opening = Opening { value: ScalarField::from(3u64), randomness };
+    assert!(params.verify(&commitment, &opening).unwrap());
+    let wrong = Opening { value: ScalarField::from(4u64), randomness: opening.randomness.clone() };
+    assert!(!params.verify(&commitment, &wrong).unwrap());
+
+    let proof = AggregatePathProof::from_bytes(&hex::decode(LEAF_3_PROOF).unwrap()).unwrap();
+    assert_eq!(proof.index, 3);
+    assert!(proof.verify(&commitment, root(TREE_ROOT)));
+    assert_eq!(committed_root(root(TREE_ROOT), LEAF_COUNT), root(TREE_COMMITTED_ROOT));
+    assert!(proof.verify_committed(&commitment, root(TREE_COMMITTED_ROOT), LEAF_COUNT));
+    assert!(!proof.verify_committed(&commitment, root(TREE_COMMITTED_ROOT), LEAF_COUNT + 1));
+    assert_eq!(proof.to_bytes(), hex::decode(LEAF_3_PROOF).unwrap());
+
+    let (other, _) = params.commit_deterministic(&ScalarField::from(2u64), &[2; 32]).unwrap();
+    assert!(!proof.verify(&other, root(TREE_ROOT)));
+    let relabelled = AggregatePathProof { index: 2, ..proof.clone() };
+    assert!(!relabelled.verify(&commitment, root(TREE_ROOT)));
+    assert!(!proof.verify(&commitment, root(BATCH_ROOT)));
+}
+
+/// Accounts `[2; 32]`, `[4; 32]` and `[6; 32]` hold `(100, 1)`, `(7, 0)`
+/// and `(0, 9)`.
+#[test]
+fn test_account_proofs() {
+    let batch_root = root(BATCH_ROOT);
+    let present = AccountProof::from_bytes(&hex::decode(PRESENT_PROOF).unwrap()).unwrap();
+    assert!(present.verify(batch_root, &Address([4; 32]), AccountState::new(7, 0).hash()));
+    assert!(!present.verify(batch_root, &Address([4; 32]), AccountState::new(8, 0).hash()));
+    assert!(!present.verify(batch_root, &Address([2; 32]), AccountState::new(7, 0).hash()));
+    assert!(!present.verify_absent(batch_root, &Address([4; 32])));
+
+    let absent = AccountProof::from_bytes(&hex::decode(ABSENT_PROOF).unwrap()).unwrap();
+    assert!(absent.verify_absent(batch_root, &Address([5; 32])));
+    assert!(!absent.verify_absent(batch_root, &Address([3; 32])));
+    assert!(!absent.verify_absent(root(TREE_ROOT), &Address([5; 32])));
+    assert_eq!(absent.to_bytes(), hex::decode(ABSENT_PROOF).unwrap());
+}